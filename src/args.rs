@@ -0,0 +1,231 @@
+//! Command-line options, which set up the receiver for one run on top of
+//! the config file, for the TUI and the subcommands alike.
+
+use std::path::Path;
+
+use rf_rust::dsp::AudioMode;
+use rf_rust::engine::Engine;
+use rf_rust::net::remote::RemoteSource;
+
+pub const OPTIONS_USAGE: &str = "\
+options, anywhere on the command line:
+  --freq HZ               center frequency, such as 145.5M
+  --rate HZ               sample rate, such as 2.4M
+  --gain DB
+  --mode fm|am|usb|lsb    demodulator of the first VFO
+  --driver demo|rtl_tcp   sample source, else the last session's or the demo
+  --device HOST:PORT      rtl_tcp server for --driver rtl_tcp
+  --play FILE             play an IQ recording instead of a live source
+  --script FILE           run a receiver script alongside, see `rf_rust run`
+  --fps N                 TUI redraws a second, lower for slow links
+  --fresh                 start the TUI as configured, not where it was left";
+
+/// Sample source given with `--driver` or `--device`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Demo,
+    /// An rtl_tcp server as `HOST:PORT`
+    RtlTcp(String),
+}
+
+/// Settings given on the command line, `None` where the defaults stand
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub frequency: Option<f64>,
+    pub sample_rate: Option<f64>,
+    pub gain: Option<f64>,
+    pub mode: Option<AudioMode>,
+    /// Sample source, over that of the last session
+    pub source: Option<Source>,
+    /// IQ recording to play, over the sample source
+    pub play: Option<String>,
+    /// Receiver script to run
+    pub script: Option<String>,
+    /// TUI redraws a second
+    pub fps: Option<f64>,
+    /// Skip restoring the last session
+    pub fresh: bool,
+}
+
+impl Options {
+    /// Set up `engine` as given, over the config file and the last session
+    pub fn apply(&self, engine: &mut Engine) {
+        if let Some(hz) = self.frequency {
+            engine.frequency = hz.clamp(1e6, 6e9);
+        }
+        if let Some(rate) = self.sample_rate {
+            engine.sample_rate = rate.clamp(0.1e6, 10e6);
+        }
+        if let Some(db) = self.gain {
+            engine.gain = db.clamp(0.0, 60.0);
+        }
+        if let Some(mode) = self.mode {
+            engine.vfos[engine.active_vfo].demod.set_mode(mode);
+        }
+        match &self.source {
+            Some(Source::Demo) => engine.set_remote(None),
+            Some(Source::RtlTcp(server)) => engine.set_remote(Some(RemoteSource::connect(server))),
+            None => {}
+        }
+        if let Some(path) = &self.play
+            && let Err(e) = engine.open_playback(Path::new(path))
+        {
+            log::error!("{}", e);
+        }
+        if let Some(path) = &self.script
+            && let Err(e) = engine.load_script(Path::new(path))
+        {
+            log::error!("{}", e);
+        }
+    }
+}
+
+/// Split `args` into options and the words of the subcommand. Options take
+/// their value as the next argument or after `=`, except for the flag
+/// `--fresh`.
+pub fn parse(args: &[String]) -> Result<(Options, Vec<String>), String> {
+    let mut options = Options::default();
+    let mut driver = None;
+    let mut device = None;
+    let mut words = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(option) = arg.strip_prefix("--") else {
+            words.push(arg.clone());
+            continue;
+        };
+        if option == "fresh" {
+            options.fresh = true;
+            continue;
+        }
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => (option, args.next().ok_or(format!("--{} needs a value", option))?.clone()),
+        };
+        match name {
+            "freq" => options.frequency = Some(parse_hz(&value)?),
+            "rate" => options.sample_rate = Some(parse_hz(&value)?),
+            "gain" => {
+                options.gain = Some(value.parse().ok().filter(|g: &f64| g.is_finite()).ok_or(format!("--gain `{}` is not a number", value))?)
+            }
+            "mode" => options.mode = Some(AudioMode::parse(&value).ok_or(format!("--mode must be fm, am, usb or lsb, not `{}`", value))?),
+            "driver" => driver = Some(value),
+            "device" => device = Some(value),
+            "play" => options.play = Some(value),
+            "script" => options.script = Some(value),
+            "fps" => {
+                options.fps = Some(value.parse().ok().filter(|fps| crate::tui::FPS.contains(fps)).ok_or(format!(
+                    "--fps must be {} to {}, not `{}`",
+                    crate::tui::FPS.start(),
+                    crate::tui::FPS.end(),
+                    value
+                ))?)
+            }
+            _ => return Err(format!("no option --{}", name)),
+        }
+    }
+    match (driver.as_deref(), device) {
+        (None, None) => {}
+        (Some("demo"), None) => options.source = Some(Source::Demo),
+        (None | Some("rtl_tcp"), Some(server)) => options.source = Some(Source::RtlTcp(server)),
+        (Some("rtl_tcp"), None) => return Err("--driver rtl_tcp needs --device HOST:PORT".to_string()),
+        (Some("demo"), Some(_)) => return Err("the demo driver takes no --device".to_string()),
+        (Some(other), _) => return Err(format!("no driver `{}`, there are demo and rtl_tcp", other)),
+    }
+    Ok((options, words))
+}
+
+/// Hertz from a number with an optional `k`, `M` or `G` suffix in either
+/// case, and `Hz` after it if wanted
+pub fn parse_hz(text: &str) -> Result<f64, String> {
+    let trimmed = text.strip_suffix("Hz").or_else(|| text.strip_suffix("hz")).unwrap_or(text);
+    let (number, scale) = match trimmed.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&trimmed[..i], 1e3),
+        Some((i, 'M' | 'm')) => (&trimmed[..i], 1e6),
+        Some((i, 'G' | 'g')) => (&trimmed[..i], 1e9),
+        _ => (trimmed, 1.0),
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(n * scale),
+        _ => Err(format!("`{}` is not a frequency", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn frequencies_take_suffixes() {
+        assert_eq!(parse_hz("145.5M"), Ok(145.5e6));
+        assert_eq!(parse_hz("12.5k"), Ok(12.5e3));
+        assert_eq!(parse_hz("1.2G"), Ok(1.2e9));
+        assert_eq!(parse_hz("2.4mHz"), Ok(2.4e6));
+        assert_eq!(parse_hz("100KHz"), Ok(100e3));
+        assert_eq!(parse_hz("433920000"), Ok(433.92e6));
+        for bad in ["", "M", "abc", "1.2.3M", "-5M", "infM", "NaN", "12 k"] {
+            assert!(parse_hz(bad).is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn options_mix_with_the_subcommand() {
+        let (options, words) = parse(&args("scan --freq=100M 88M --gain 30 108M --mode usb 100k --fresh")).unwrap();
+        assert_eq!(words, ["scan", "88M", "108M", "100k"]);
+        assert_eq!(options.frequency, Some(100e6));
+        assert_eq!(options.gain, Some(30.0));
+        assert_eq!(options.mode, Some(AudioMode::Usb));
+        assert!(options.fresh);
+        assert_eq!(options.source, None);
+    }
+
+    #[test]
+    fn drivers_and_devices_agree() {
+        let source = |line: &str| parse(&args(line)).unwrap().0.source;
+        let server = Some(Source::RtlTcp("localhost:1234".to_string()));
+        assert_eq!(source("--driver rtl_tcp --device localhost:1234"), server);
+        assert_eq!(source("--device localhost:1234"), server);
+        assert_eq!(source("--driver demo"), Some(Source::Demo));
+        assert!(parse(&args("--driver rtl_tcp")).is_err());
+        assert!(parse(&args("--driver demo --device localhost:1234")).is_err());
+        assert!(parse(&args("--driver uhd")).is_err());
+    }
+
+    #[test]
+    fn play_takes_over_the_source() {
+        let path = std::env::temp_dir().join(format!("rf_rust_play_{}_100000000Hz_48000sps.cs16", std::process::id()));
+        std::fs::write(&path, vec![0u8; 4800 * 4]).unwrap();
+        let (options, _) = parse(&[format!("--play={}", path.display()), "--freq".to_string(), "90M".to_string()]).unwrap();
+        let mut engine = Engine::new();
+        options.apply(&mut engine);
+        std::fs::remove_file(&path).unwrap();
+        assert!(engine.player.is_some());
+        assert_eq!((engine.frequency, engine.sample_rate), (100e6, 48e3));
+    }
+
+    #[test]
+    fn bad_options_are_refused() {
+        let error = |line: &str| parse(&args(line)).unwrap_err();
+        assert_eq!(error("--freq"), "--freq needs a value");
+        assert_eq!(error("info --gain"), "--gain needs a value");
+        assert_eq!(error("--bogus 1"), "no option --bogus");
+        assert_eq!(error("--gain loud"), "--gain `loud` is not a number");
+        assert_eq!(error("--rate 2.4X"), "`2.4X` is not a frequency");
+        assert!(error("--mode cw").starts_with("--mode must be"));
+        assert!(error("--fps 0").starts_with("--fps must be"));
+    }
+
+    #[test]
+    fn subcommands_are_dispatched_by_their_words() {
+        let options = Options::default();
+        let usage = |words: &[&str]| crate::tui::headless::run(words, &options).unwrap_err().to_string();
+        for words in [&["bogus"][..], &["scan", "88M"], &["decode"], &["serve", "now"]] {
+            assert!(usage(words).contains(crate::tui::headless::USAGE), "{:?}", words);
+        }
+        assert!(crate::tui::headless::run(&["info"], &options).is_ok());
+    }
+}
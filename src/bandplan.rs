@@ -0,0 +1,181 @@
+//! What a frequency is allocated to: broadcast, amateur and the better
+//! known services from the ITU Radio Regulations and the IARU band plans,
+//! for the three ITU regions. Region 1 is Europe, Africa and the Middle
+//! East, region 2 the Americas and region 3 Asia and the Pacific.
+//!
+//! Allocations overlap, such as an ISM band inside an amateur band, and a
+//! lookup gives the narrowest one containing the frequency.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Region {
+    #[default]
+    One,
+    Two,
+    Three,
+}
+
+impl Region {
+    /// `1`, `2` or `3`
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "1" => Some(Region::One),
+            "2" => Some(Region::Two),
+            "3" => Some(Region::Three),
+            _ => None,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        match self {
+            Region::One => R1,
+            Region::Two => R2,
+            Region::Three => R3,
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Region::One => "ITU region 1",
+            Region::Two => "ITU region 2",
+            Region::Three => "ITU region 3",
+        })
+    }
+}
+
+const R1: u8 = 1;
+const R2: u8 = 2;
+const R3: u8 = 4;
+const ALL: u8 = R1 | R2 | R3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Band {
+    /// Edges in Hz
+    pub start: f64,
+    pub stop: f64,
+    pub name: &'static str,
+    regions: u8,
+}
+
+impl Band {
+    pub fn width(&self) -> f64 {
+        self.stop - self.start
+    }
+}
+
+const fn band(start_khz: f64, stop_khz: f64, regions: u8, name: &'static str) -> Band {
+    Band { start: start_khz * 1e3, stop: stop_khz * 1e3, name, regions }
+}
+
+/// Edges in kHz, as the Radio Regulations give them
+const BANDS: &[Band] = &[
+    // Time and navigation below the broadcast bands
+    band(59.5, 60.5, R2, "WWVB time signal"),
+    band(77.0, 78.0, R1, "DCF77 time signal"),
+    band(135.7, 137.8, ALL, "2200m amateur"),
+    band(148.5, 283.5, R1, "LW broadcast"),
+    band(190.0, 535.0, ALL, "Aeronautical NDB"),
+    band(472.0, 479.0, ALL, "630m amateur"),
+    band(489.0, 491.0, ALL, "NAVTEX national"),
+    band(517.0, 519.0, ALL, "NAVTEX"),
+    band(526.5, 1606.5, R1 | R3, "MW broadcast"),
+    band(525.0, 1705.0, R2, "MW broadcast"),
+    // HF
+    band(1810.0, 2000.0, R1, "160m amateur"),
+    band(1800.0, 2000.0, R2 | R3, "160m amateur"),
+    band(2300.0, 2495.0, ALL, "120m broadcast"),
+    band(2495.0, 2505.0, ALL, "Standard frequency and time"),
+    band(3200.0, 3400.0, ALL, "90m broadcast"),
+    band(3500.0, 3800.0, R1, "80m amateur"),
+    band(3500.0, 4000.0, R2, "80m amateur"),
+    band(3500.0, 3900.0, R3, "80m amateur"),
+    band(3900.0, 4000.0, R1 | R3, "75m broadcast"),
+    band(4750.0, 5060.0, ALL, "60m broadcast"),
+    band(4995.0, 5005.0, ALL, "Standard frequency and time"),
+    band(5351.5, 5366.5, ALL, "60m amateur"),
+    band(5900.0, 6200.0, ALL, "49m broadcast"),
+    band(7000.0, 7200.0, R1 | R3, "40m amateur"),
+    band(7000.0, 7300.0, R2, "40m amateur"),
+    band(7200.0, 7450.0, R1 | R3, "41m broadcast"),
+    band(7300.0, 7450.0, R2, "41m broadcast"),
+    band(9400.0, 9900.0, ALL, "31m broadcast"),
+    band(9995.0, 10005.0, ALL, "Standard frequency and time"),
+    band(10100.0, 10150.0, ALL, "30m amateur"),
+    band(11600.0, 12100.0, ALL, "25m broadcast"),
+    band(13553.0, 13567.0, ALL, "ISM 13.56 MHz"),
+    band(13570.0, 13870.0, ALL, "22m broadcast"),
+    band(14000.0, 14350.0, ALL, "20m amateur"),
+    band(14995.0, 15005.0, ALL, "Standard frequency and time"),
+    band(15100.0, 15800.0, ALL, "19m broadcast"),
+    band(17480.0, 17900.0, ALL, "16m broadcast"),
+    band(18068.0, 18168.0, ALL, "17m amateur"),
+    band(18900.0, 19020.0, ALL, "15m broadcast"),
+    band(19990.0, 20010.0, ALL, "Standard frequency and time"),
+    band(21000.0, 21450.0, ALL, "15m amateur"),
+    band(21450.0, 21850.0, ALL, "13m broadcast"),
+    band(24890.0, 24990.0, ALL, "12m amateur"),
+    band(25670.0, 26100.0, ALL, "11m broadcast"),
+    band(26957.0, 27283.0, ALL, "ISM 27 MHz"),
+    band(26965.0, 27405.0, ALL, "CB radio"),
+    band(28000.0, 29700.0, ALL, "10m amateur"),
+    // VHF
+    band(40660.0, 40700.0, ALL, "ISM 40 MHz"),
+    band(50000.0, 52000.0, R1, "6m amateur"),
+    band(50000.0, 54000.0, R2 | R3, "6m amateur"),
+    band(70000.0, 70500.0, R1, "4m amateur"),
+    band(87500.0, 108000.0, R1, "FM broadcast"),
+    band(88000.0, 108000.0, R2, "FM broadcast"),
+    band(87000.0, 108000.0, R3, "FM broadcast"),
+    band(108000.0, 117975.0, ALL, "Airband navigation (VOR/ILS)"),
+    band(117975.0, 137000.0, ALL, "Airband voice"),
+    band(137000.0, 138000.0, ALL, "Weather satellites"),
+    band(144000.0, 146000.0, R1, "2m amateur"),
+    band(144000.0, 148000.0, R2 | R3, "2m amateur"),
+    band(156000.0, 162025.0, ALL, "Marine VHF"),
+    band(161962.5, 162037.5, ALL, "AIS"),
+    band(162400.0, 162550.0, R2, "NOAA weather radio"),
+    band(174000.0, 230000.0, R1, "DAB / TV band III"),
+    band(174000.0, 216000.0, R2, "TV band III"),
+    band(174000.0, 230000.0, R3, "TV band III"),
+    band(222000.0, 225000.0, R2, "1.25m amateur"),
+    band(225000.0, 400000.0, ALL, "Military airband"),
+    // UHF
+    band(406000.0, 406100.0, ALL, "COSPAS-SARSAT beacons"),
+    band(430000.0, 440000.0, R1 | R3, "70cm amateur"),
+    band(420000.0, 450000.0, R2, "70cm amateur"),
+    band(433050.0, 434790.0, R1, "ISM 433 MHz"),
+    band(446000.0, 446200.0, R1, "PMR446"),
+    band(462550.0, 467725.0, R2, "FRS / GMRS"),
+    band(470000.0, 694000.0, R1, "UHF TV"),
+    band(470000.0, 608000.0, R2, "UHF TV"),
+    band(470000.0, 698000.0, R3, "UHF TV"),
+    band(824000.0, 849000.0, R2, "Cellular 850 uplink"),
+    band(869000.0, 894000.0, R2, "Cellular 850 downlink"),
+    band(863000.0, 870000.0, R1, "SRD 868 MHz"),
+    band(880000.0, 915000.0, R1 | R3, "GSM 900 uplink"),
+    band(925000.0, 960000.0, R1 | R3, "GSM 900 downlink"),
+    band(902000.0, 928000.0, R2, "33cm amateur / ISM 915 MHz"),
+    band(1029000.0, 1031000.0, ALL, "SSR interrogation"),
+    band(1089000.0, 1091000.0, ALL, "ADS-B / SSR replies"),
+    band(1164000.0, 1215000.0, ALL, "GNSS L5/E5"),
+    band(1215000.0, 1240000.0, ALL, "GNSS L2"),
+    band(1240000.0, 1300000.0, ALL, "23cm amateur"),
+    band(1400000.0, 1427000.0, ALL, "Radio astronomy (hydrogen line)"),
+    band(1525000.0, 1559000.0, ALL, "Mobile satellite (Inmarsat)"),
+    band(1559000.0, 1610000.0, ALL, "GNSS L1/E1"),
+    band(1616000.0, 1626500.0, ALL, "Iridium"),
+    band(2300000.0, 2450000.0, ALL, "13cm amateur"),
+    band(2400000.0, 2500000.0, ALL, "ISM 2.4 GHz"),
+    band(5725000.0, 5875000.0, ALL, "ISM 5.8 GHz"),
+];
+
+/// The narrowest allocation containing `hz` in `region`
+pub fn lookup(hz: f64, region: Region) -> Option<&'static Band> {
+    BANDS
+        .iter()
+        .filter(|band| band.regions & region.bit() != 0 && (band.start..band.stop).contains(&hz))
+        .min_by(|a, b| a.width().total_cmp(&b.width()))
+}
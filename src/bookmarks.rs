@@ -0,0 +1,103 @@
+//! Frequency bookmarks kept in `bookmarks.toml` next to the config file,
+//! one `[name]` section each, such as
+//!
+//! ```text
+//! [Marine 16]
+//! frequency = 156.8e6
+//! mode = "fm"
+//! bandwidth = 12.5e3
+//! tags = "marine, distress"
+//! ```
+//!
+//! Unlike a preset, a bookmark is a channel rather than a whole receiver
+//! setup: tuning to it moves the active VFO and leaves the rest alone.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::dsp::AudioMode;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub frequency: f64,
+    pub mode: AudioMode,
+    /// Occupied bandwidth of the channel in Hz
+    pub bandwidth: f64,
+    pub tags: Vec<String>,
+}
+
+/// `bookmarks.toml` beside the config file
+pub fn path() -> Option<PathBuf> {
+    Some(Config::path()?.with_file_name("bookmarks.toml"))
+}
+
+/// Bookmarks in file order, none when the file does not exist
+pub fn load(path: &Path) -> io::Result<Vec<Bookmark>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+/// Bookmarks from the file's text. The mode defaults to FM and the
+/// bandwidth to the mode's passband.
+pub fn parse(text: &str) -> Result<Vec<Bookmark>, String> {
+    let config = Config::parse(text)?;
+    config
+        .sections()
+        .iter()
+        .map(|name| {
+            let get = |key: &str| config.get(&format!("{}.{}", name, key));
+            let frequency = get("frequency")
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .ok_or(format!("[{}] needs a frequency", name))?;
+            let mode = match get("mode") {
+                Some(mode) => AudioMode::parse(mode).ok_or(format!("[{}] mode must be fm, am, usb or lsb", name))?,
+                None => AudioMode::Fm,
+            };
+            let bandwidth = match get("bandwidth") {
+                Some(bw) => bw
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .ok_or(format!("[{}] bandwidth must be a positive number", name))?,
+                None => mode.passband(),
+            };
+            let tags = get("tags")
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect();
+            Ok(Bookmark { name: name.clone(), frequency, mode, bandwidth, tags })
+        })
+        .collect()
+}
+
+/// Write `bookmarks` over the file at `path`, creating its directory
+pub fn save(path: &Path, bookmarks: &[Bookmark]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let sections: Vec<String> = bookmarks
+        .iter()
+        .map(|b| {
+            format!(
+                "[{}]\nfrequency = {}\nmode = \"{}\"\nbandwidth = {}\ntags = \"{}\"\n",
+                b.name,
+                b.frequency,
+                b.mode.to_string().to_lowercase(),
+                b.bandwidth,
+                b.tags.join(", ")
+            )
+        })
+        .collect();
+    fs::write(path, sections.join("\n"))
+}
@@ -0,0 +1,104 @@
+//! User settings read from a small TOML-style file of `[section]` headers and
+//! `key = value` lines.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+/// Overrides the config file location
+pub const CONFIG_ENV: &str = "SDR_CONFIG";
+
+/// Settings keyed as `section.key`, values with any quotes removed
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    values: HashMap<String, String>,
+    /// Section names in the order they appear
+    sections: Vec<String>,
+}
+
+impl Config {
+    /// [`CONFIG_ENV`], else `rf_rust/config.toml` under `$XDG_CONFIG_HOME` or `~/.config`
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("rf_rust").join("config.toml"))
+    }
+
+    /// Read the user's config file, empty when there is none
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        let mut sections = Vec::new();
+        let mut section = String::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                if !sections.contains(&section) {
+                    sections.push(section.clone());
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected `key = value`", number + 1));
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            let key = key.trim();
+            let key = if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) };
+            values.insert(key, value.to_string());
+        }
+        Ok(Self { values, sections })
+    }
+
+    /// Value of `section.key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Names of the `[section]` headers in file order
+    pub fn sections(&self) -> &[String] {
+        &self.sections
+    }
+
+    /// Keys present under `section`, without the section prefix
+    pub fn keys<'a>(&'a self, section: &'a str) -> impl Iterator<Item = &'a str> {
+        self.values
+            .keys()
+            .filter_map(move |key| key.strip_prefix(section)?.strip_prefix('.'))
+    }
+}
+
+/// Drop a `#` comment that is not inside a quoted string
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
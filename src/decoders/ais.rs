@@ -0,0 +1,506 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::Instant;
+
+use num_complex::Complex32;
+
+use super::{bits_to_i32, bits_to_u32};
+use crate::dsp::{FskConfig, FskDemod};
+
+/// AIS channel A and B centre frequencies
+pub const AIS_CHANNELS: [(char, f64); 2] = [('A', 161.975e6), ('B', 162.025e6)];
+
+const AIS_BAUD: f64 = 9600.0;
+/// GMSK with modulation index 0.5
+const AIS_DEVIATION: f64 = 2400.0;
+const MAX_NMEA_LINES: usize = 200;
+const NMEA_PAYLOAD_CHARS: usize = 60;
+
+/// Last known state of a vessel
+#[derive(Clone, Debug)]
+pub struct Vessel {
+    pub mmsi: u32,
+    pub name: Option<String>,
+    pub sog: Option<f32>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub last_seen: Instant,
+    pub messages: u32,
+}
+
+impl Vessel {
+    fn new(mmsi: u32) -> Self {
+        Self {
+            mmsi,
+            name: None,
+            sog: None,
+            lat: None,
+            lon: None,
+            last_seen: Instant::now(),
+            messages: 0,
+        }
+    }
+}
+
+/// HDLC deframer working on NRZI-decoded bits
+struct Hdlc {
+    shift: u8,
+    ones: u32,
+    in_frame: bool,
+    bits: Vec<bool>,
+}
+
+impl Hdlc {
+    fn new() -> Self {
+        Self {
+            shift: 0,
+            ones: 0,
+            in_frame: false,
+            bits: Vec::with_capacity(1024),
+        }
+    }
+
+    /// Push one bit, returning the frame bits (without flags) when a frame closes
+    fn push(&mut self, bit: bool) -> Option<Vec<bool>> {
+        self.shift = (self.shift >> 1) | ((bit as u8) << 7);
+
+        if self.shift == 0x7E {
+            let mut frame = None;
+            // The first seven flag bits have already been pushed into the buffer
+            if self.in_frame && self.bits.len() >= 7 + 184 {
+                self.bits.truncate(self.bits.len() - 7);
+                frame = Some(std::mem::take(&mut self.bits));
+            }
+            self.bits.clear();
+            self.ones = 0;
+            self.in_frame = true;
+            return frame;
+        }
+
+        if !self.in_frame {
+            return None;
+        }
+
+        if bit {
+            self.ones += 1;
+            if self.ones > 6 {
+                self.in_frame = false;
+                self.bits.clear();
+                return None;
+            }
+            self.bits.push(true);
+        } else {
+            if self.ones != 5 {
+                self.bits.push(false);
+            }
+            self.ones = 0;
+        }
+
+        if self.bits.len() > 1024 {
+            self.in_frame = false;
+            self.bits.clear();
+        }
+        None
+    }
+}
+
+/// Demodulation chain for a single AIS channel
+struct AisChannel {
+    name: char,
+    demod: FskDemod,
+    last_raw: bool,
+    hdlc: Hdlc,
+}
+
+impl AisChannel {
+    fn new(name: char, offset_hz: f64, sample_rate: f64) -> Self {
+        let config = FskConfig {
+            offset_hz,
+            ..FskConfig::new(AIS_DEVIATION, AIS_BAUD)
+        };
+        Self {
+            name,
+            demod: FskDemod::new(config, sample_rate),
+            last_raw: false,
+            hdlc: Hdlc::new(),
+        }
+    }
+
+    fn process(&mut self, samples: &[Complex32], frames: &mut Vec<(char, Vec<bool>)>) {
+        for &s in samples {
+            let Some(raw) = self.demod.push(s).map(|bit| bit.value) else {
+                continue;
+            };
+            // NRZI: no transition is a one
+            let bit = raw == self.last_raw;
+            self.last_raw = raw;
+
+            if let Some(frame) = self.hdlc.push(bit) {
+                frames.push((self.name, frame));
+            }
+        }
+    }
+}
+
+/// Two-channel AIS receiver keeping a table of heard vessels
+pub struct AisDecoder {
+    channels: Vec<AisChannel>,
+    tuned: (f64, f64),
+    pub vessels: BTreeMap<u32, Vessel>,
+    pub nmea: Vec<String>,
+    /// Sentences decoded so far, including those dropped from `nmea`
+    pub sentences: u64,
+    pub frames_ok: u32,
+    pub frames_bad: u32,
+    sequence: u8,
+    nmea_log: Option<File>,
+}
+
+impl AisDecoder {
+    pub fn new() -> Self {
+        Self {
+            channels: Vec::new(),
+            tuned: (0.0, 0.0),
+            vessels: BTreeMap::new(),
+            nmea: Vec::new(),
+            sentences: 0,
+            frames_ok: 0,
+            frames_bad: 0,
+            sequence: 0,
+            nmea_log: None,
+        }
+    }
+
+    /// Append every decoded sentence to `path`, or stop logging with `None`
+    pub fn set_nmea_log(&mut self, path: Option<&str>) -> io::Result<()> {
+        self.nmea_log = match path {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn is_logging_nmea(&self) -> bool {
+        self.nmea_log.is_some()
+    }
+
+    /// Number of AIS channels inside the current passband
+    pub fn active_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn retune(&mut self, center_freq: f64, sample_rate: f64) {
+        self.tuned = (center_freq, sample_rate);
+        let bandwidth = FskConfig::new(AIS_DEVIATION, AIS_BAUD).bandwidth();
+        self.channels = AIS_CHANNELS
+            .iter()
+            .filter(|(_, freq)| (freq - center_freq).abs() < sample_rate / 2.0 - bandwidth / 2.0)
+            .map(|&(name, freq)| AisChannel::new(name, freq - center_freq, sample_rate))
+            .collect();
+    }
+
+    /// Feed a block of IQ samples captured at `center_freq` / `sample_rate`
+    pub fn process(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        if self.tuned != (center_freq, sample_rate) {
+            self.retune(center_freq, sample_rate);
+        }
+
+        let mut frames = Vec::new();
+        for channel in &mut self.channels {
+            channel.process(samples, &mut frames);
+        }
+
+        for (channel, frame) in frames {
+            match check_frame(&frame) {
+                Some(payload) => {
+                    self.frames_ok += 1;
+                    self.handle_message(channel, &payload);
+                }
+                None => self.frames_bad += 1,
+            }
+        }
+    }
+
+    fn handle_message(&mut self, channel: char, bits: &[bool]) {
+        for sentence in to_nmea(bits, channel, self.sequence) {
+            if let Some(log) = &mut self.nmea_log
+                && writeln!(log, "{}", sentence).is_err()
+            {
+                self.nmea_log = None;
+            }
+            self.nmea.push(sentence);
+            self.sentences += 1;
+        }
+        self.sequence = (self.sequence + 1) % 10;
+        if self.nmea.len() > MAX_NMEA_LINES {
+            let excess = self.nmea.len() - MAX_NMEA_LINES;
+            self.nmea.drain(..excess);
+        }
+
+        if bits.len() < 38 {
+            return;
+        }
+        let msg_type = bits_to_u32(bits, 0, 6);
+        let mmsi = bits_to_u32(bits, 8, 30);
+        let vessel = self.vessels.entry(mmsi).or_insert_with(|| Vessel::new(mmsi));
+        vessel.last_seen = Instant::now();
+        vessel.messages += 1;
+
+        match msg_type {
+            1..=3 if bits.len() >= 168 => {
+                vessel.sog = decode_sog(bits_to_u32(bits, 50, 10));
+                vessel.lon = decode_coord(bits_to_i32(bits, 61, 28), 181.0);
+                vessel.lat = decode_coord(bits_to_i32(bits, 89, 27), 91.0);
+            }
+            18 if bits.len() >= 168 => {
+                vessel.sog = decode_sog(bits_to_u32(bits, 46, 10));
+                vessel.lon = decode_coord(bits_to_i32(bits, 57, 28), 181.0);
+                vessel.lat = decode_coord(bits_to_i32(bits, 85, 27), 91.0);
+            }
+            5 if bits.len() >= 424 => {
+                vessel.name = decode_text(bits, 112, 20);
+            }
+            24 if bits.len() >= 160 && bits_to_u32(bits, 38, 2) == 0 => {
+                vessel.name = decode_text(bits, 40, 20);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for AisDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Verify the HDLC FCS and return the message bits in AIS (MSB-first) order
+fn check_frame(frame: &[bool]) -> Option<Vec<bool>> {
+    if !frame.len().is_multiple_of(8) || frame.len() < 8 * 4 {
+        return None;
+    }
+
+    // HDLC transmits each byte LSB first
+    let bytes: Vec<u8> = frame
+        .chunks(8)
+        .map(|c| c.iter().enumerate().fold(0u8, |acc, (i, &b)| acc | ((b as u8) << i)))
+        .collect();
+
+    let mut crc: u16 = 0xFFFF;
+    for &byte in &bytes {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+        }
+    }
+    if crc != 0xF0B8 {
+        return None;
+    }
+
+    let data = &bytes[..bytes.len() - 2];
+    Some(
+        data.iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| byte & (1 << i) != 0))
+            .collect(),
+    )
+}
+
+fn decode_sog(raw: u32) -> Option<f32> {
+    (raw != 1023).then(|| raw as f32 / 10.0)
+}
+
+fn decode_coord(raw: i32, not_available: f64) -> Option<f64> {
+    let deg = raw as f64 / 600_000.0;
+    (deg.abs() <= not_available - 1.0).then_some(deg)
+}
+
+/// Decode AIS 6-bit text, dropping the `@` padding
+fn decode_text(bits: &[bool], start: usize, chars: usize) -> Option<String> {
+    let text: String = (0..chars)
+        .map(|i| {
+            let v = bits_to_u32(bits, start + i * 6, 6) as u8;
+            if v < 32 { (v + 64) as char } else { v as char }
+        })
+        .collect();
+    let text = text.trim_end_matches(['@', ' ']).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Wrap message bits into one or more `!AIVDM` sentences
+pub fn to_nmea(bits: &[bool], channel: char, sequence: u8) -> Vec<String> {
+    let fill = (6 - bits.len() % 6) % 6;
+    let payload: String = bits
+        .chunks(6)
+        .map(|c| {
+            let v = c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8) << (6 - c.len());
+            let ch = v + 48;
+            (if ch > 87 { ch + 8 } else { ch }) as char
+        })
+        .collect();
+
+    let parts: Vec<&str> = payload
+        .as_bytes()
+        .chunks(NMEA_PAYLOAD_CHARS)
+        .map(|c| std::str::from_utf8(c).unwrap_or_default())
+        .collect();
+    let total = parts.len();
+    let seq = if total > 1 { sequence.to_string() } else { String::new() };
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let part_fill = if i + 1 == total { fill } else { 0 };
+            let body = format!("AIVDM,{},{},{},{},{},{}", total, i + 1, seq, channel, part, part_fill);
+            let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+            format!("!{}*{:02X}", body, checksum)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Position report of MMSI 477553000, moored at 47.5828 N 122.3458 W
+    const POSITION: &str = "!AIVDM,1,1,,B,177KQJ5000G?tO`K>RA1wUbN0TKH,0*5C";
+    const FLAG: [bool; 8] = [false, true, true, true, true, true, true, false];
+
+    /// Message bits of an armored AIVDM payload
+    fn unarmor(payload: &str) -> Vec<bool> {
+        payload
+            .bytes()
+            .flat_map(|c| {
+                let v = if c > 87 { c - 56 } else { c - 48 };
+                (0..6).rev().map(move |i| v >> i & 1 == 1)
+            })
+            .collect()
+    }
+
+    fn position_bits() -> Vec<bool> {
+        unarmor(POSITION.split(',').nth(5).unwrap())
+    }
+
+    /// `chars` of 6-bit text, padded with `@`
+    fn text_bits(text: &str, chars: usize) -> Vec<bool> {
+        let mut padded = text.to_string();
+        padded.extend(std::iter::repeat_n('@', chars - text.len()));
+        padded.bytes().flat_map(|c| (0..6).rev().map(move |i| (c & 63) >> i & 1 == 1)).collect()
+    }
+
+    /// The message with its FCS, each byte sent LSB first
+    fn frame(message: &[bool]) -> Vec<bool> {
+        let mut bytes: Vec<u8> = message.chunks(8).map(|c| c.iter().fold(0, |acc, &b| acc << 1 | b as u8)).collect();
+        let mut crc: u16 = 0xFFFF;
+        for &byte in &bytes {
+            crc ^= byte as u16;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+            }
+        }
+        bytes.extend_from_slice(&(!crc).to_le_bytes());
+        bytes.iter().flat_map(|&byte| (0..8).map(move |i| byte >> i & 1 == 1)).collect()
+    }
+
+    /// A zero after every five ones, so the frame cannot hold a flag
+    fn stuff(frame: &[bool]) -> Vec<bool> {
+        let mut out = Vec::new();
+        let mut ones = 0;
+        for &bit in frame {
+            out.push(bit);
+            ones = if bit { ones + 1 } else { 0 };
+            if ones == 5 {
+                out.push(false);
+                ones = 0;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn armors_a_known_sentence() {
+        let bits = position_bits();
+        assert_eq!(bits.len(), 168);
+        assert_eq!((bits_to_u32(&bits, 0, 6), bits_to_u32(&bits, 8, 30)), (1, 477_553_000));
+        assert_eq!(to_nmea(&bits, 'B', 3), [POSITION]);
+    }
+
+    #[test]
+    fn long_messages_split_across_sentences() {
+        let mut bits: Vec<bool> = (0..112).map(|i| i % 3 == 0).collect();
+        bits.extend(text_bits("EVER GIVEN", 20));
+        bits.resize(424, false);
+        assert_eq!(decode_text(&bits, 112, 20).as_deref(), Some("EVER GIVEN"));
+
+        let sentences = to_nmea(&bits, 'A', 7);
+        assert_eq!(sentences.len(), 2);
+        let mut payload = String::new();
+        for (i, sentence) in sentences.iter().enumerate() {
+            let (body, checksum) = sentence[1..].split_once('*').unwrap();
+            assert_eq!(u8::from_str_radix(checksum, 16), Ok(body.bytes().fold(0, |acc, b| acc ^ b)));
+            let fields: Vec<&str> = body.split(',').collect();
+            assert_eq!(fields[..5], ["AIVDM", "2", &(i + 1).to_string(), "7", "A"]);
+            payload.push_str(fields[5]);
+            // 424 bits fill 71 characters with 2 bits over
+            assert_eq!(fields[6], if i == 0 { "0" } else { "2" });
+        }
+        assert_eq!(payload.len(), 71);
+        assert_eq!(unarmor(&payload)[..424], bits[..]);
+    }
+
+    #[test]
+    fn frame_check_sequence_guards_the_message() {
+        let bits = position_bits();
+        let mut frame = frame(&bits);
+        assert_eq!(check_frame(&frame), Some(bits));
+        frame[40] = !frame[40];
+        assert_eq!(check_frame(&frame), None);
+        assert_eq!(check_frame(&frame[..frame.len() - 1]), None);
+    }
+
+    #[test]
+    fn hdlc_finds_frames_between_flags_and_drops_stuffing() {
+        let frame = frame(&position_bits());
+        let stuffed = stuff(&frame);
+        assert!(stuffed.len() > frame.len());
+
+        let mut hdlc = Hdlc::new();
+        let line = [&[true, false, true][..], &FLAG, &FLAG, &stuffed, &FLAG];
+        let frames: Vec<Vec<bool>> = line.concat().into_iter().filter_map(|bit| hdlc.push(bit)).collect();
+        assert_eq!(frames, [frame]);
+
+        // Seven ones in a row abort the frame
+        let mut hdlc = Hdlc::new();
+        let aborted = [&FLAG[..], &stuffed[..100], &[true; 7], &stuffed[100..], &FLAG].concat();
+        assert!(aborted.into_iter().all(|bit| hdlc.push(bit).is_none()));
+    }
+
+    #[test]
+    fn decodes_a_position_report_over_the_air() {
+        let (rate, center) = (96e3, AIS_CHANNELS[0].1);
+        let mut line: Vec<bool> = (0..48).map(|i| i % 2 == 0).collect();
+        line.extend([&FLAG[..], &stuff(&frame(&position_bits())), &FLAG, &[false; 24]].concat());
+
+        let mut iq = Vec::new();
+        let (mut level, mut phase) = (false, 0.0f64);
+        for (i, bit) in line.into_iter().enumerate() {
+            // NRZI: a zero is a change of tone
+            level ^= !bit;
+            let freq = if level { AIS_DEVIATION } else { -AIS_DEVIATION };
+            while (iq.len() as f64) < (i + 1) as f64 * rate / AIS_BAUD {
+                phase += 2.0 * std::f64::consts::PI * freq / rate;
+                iq.push(Complex32::from_polar(0.5, phase as f32));
+            }
+        }
+        let mut decoder = AisDecoder::new();
+        for block in iq.chunks(1024) {
+            decoder.process(block, center, rate);
+        }
+        assert_eq!(decoder.active_channels(), 1);
+        assert_eq!(decoder.nmea, [POSITION.replace(",B,", ",A,").replace("*5C", "*5F")]);
+        let vessel = &decoder.vessels[&477_553_000];
+        assert_eq!(vessel.sog, Some(0.0));
+        assert!((vessel.lat.unwrap() - 47.582833).abs() < 1e-5 && (vessel.lon.unwrap() + 122.345833).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,26 @@
+//! BCH(31,21) plus even parity, as used by POCSAG and FLEX codewords.
+
+const GENERATOR: u32 = 0x769;
+
+/// Remainder of the 31-bit codeword (bits 31..1) divided by the generator
+fn syndrome(word: u32) -> u32 {
+    let mut rem = word >> 1;
+    for bit in (10..31).rev() {
+        if rem & (1 << bit) != 0 {
+            rem ^= GENERATOR << (bit - 10);
+        }
+    }
+    rem
+}
+
+fn is_valid(word: u32) -> bool {
+    syndrome(word) == 0 && word.count_ones().is_multiple_of(2)
+}
+
+/// Return the codeword with up to one bit error corrected, or `None`
+pub fn bch3121_correct(word: u32) -> Option<u32> {
+    if is_valid(word) {
+        return Some(word);
+    }
+    (0..32).map(|i| word ^ (1 << i)).find(|&w| is_valid(w))
+}
@@ -0,0 +1,176 @@
+use num_complex::Complex32;
+
+use crate::dsp::DecimatingFir;
+
+const ENVELOPE_RATE: f64 = 1e3;
+const FILTER_BANDWIDTH: f64 = 300.0;
+const MIN_WPM: f64 = 5.0;
+const MAX_WPM: f64 = 40.0;
+const MAX_TEXT: usize = 8000;
+
+const MORSE: [(&str, char); 54] = [
+    (".-", 'A'), ("-...", 'B'), ("-.-.", 'C'), ("-..", 'D'), (".", 'E'), ("..-.", 'F'),
+    ("--.", 'G'), ("....", 'H'), ("..", 'I'), (".---", 'J'), ("-.-", 'K'), (".-..", 'L'),
+    ("--", 'M'), ("-.", 'N'), ("---", 'O'), (".--.", 'P'), ("--.-", 'Q'), (".-.", 'R'),
+    ("...", 'S'), ("-", 'T'), ("..-", 'U'), ("...-", 'V'), (".--", 'W'), ("-..-", 'X'),
+    ("-.--", 'Y'), ("--..", 'Z'), ("-----", '0'), (".----", '1'), ("..---", '2'),
+    ("...--", '3'), ("....-", '4'), (".....", '5'), ("-....", '6'), ("--...", '7'),
+    ("---..", '8'), ("----.", '9'), (".-.-.-", '.'), ("--..--", ','), ("..--..", '?'),
+    ("-..-.", '/'), ("-...-", '='), (".-.-.", '+'), ("-....-", '-'), ("-.--.", '('),
+    ("-.--.-", ')'), (".----.", '\''), ("---...", ':'), ("-.-.-.", ';'), (".-..-.", '"'),
+    (".--.-.", '@'), ("-.-.--", '!'), ("..--.-", '_'), ("...-..-", '$'), ("...-.-", '*'),
+];
+
+/// Milliseconds per dot at a given speed (PARIS timing)
+fn dot_ms(wpm: f64) -> f64 {
+    1200.0 / wpm
+}
+
+/// Morse-to-text decoder with adaptive speed tracking on the tuned centre frequency
+pub struct CwDecoder {
+    sample_rate: f64,
+    fir: DecimatingFir,
+    rate: f64,
+    envelope: f32,
+    peak: f32,
+    noise: f32,
+    /// Whether the key is currently down
+    pub key_down: bool,
+    /// Duration of the current mark or space in milliseconds
+    run_ms: f64,
+    unit_ms: f64,
+    symbols: String,
+    word_gap_sent: bool,
+    pub text: String,
+}
+
+impl CwDecoder {
+    pub fn new() -> Self {
+        let mut decoder = Self {
+            sample_rate: 0.0,
+            fir: DecimatingFir::new(vec![1.0], 1),
+            rate: ENVELOPE_RATE,
+            envelope: 0.0,
+            peak: 0.0,
+            noise: 0.0,
+            key_down: false,
+            run_ms: 0.0,
+            unit_ms: dot_ms(20.0),
+            symbols: String::new(),
+            word_gap_sent: true,
+            text: String::new(),
+        };
+        decoder.retune(1e6);
+        decoder
+    }
+
+    /// Current speed estimate in words per minute
+    pub fn wpm(&self) -> f64 {
+        1200.0 / self.unit_ms
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, ENVELOPE_RATE, FILTER_BANDWIDTH);
+        self.fir = fir;
+        self.rate = rate;
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+
+        let step_ms = 1000.0 / self.rate;
+        // Envelope smoothing of roughly 5 ms
+        let alpha = (step_ms / 5.0).min(1.0) as f32;
+        for &s in samples {
+            let Some(x) = self.fir.push(s) else {
+                continue;
+            };
+            self.envelope += alpha * (x.norm() - self.envelope);
+
+            // Fast attack, slow decay trackers for the key-down and key-up levels
+            let e = self.envelope;
+            self.peak = if e > self.peak { e } else { self.peak * 0.9995 };
+            self.noise = if e < self.noise { e } else { self.noise + (e - self.noise) * 0.0005 };
+
+            // Hysteresis around the midpoint
+            let span = self.peak - self.noise;
+            let key_down = if self.key_down {
+                e > self.noise + 0.4 * span
+            } else {
+                e > self.noise + 0.6 * span
+            };
+
+            if key_down != self.key_down {
+                if self.key_down {
+                    self.end_mark();
+                } else {
+                    self.end_space();
+                }
+                self.key_down = key_down;
+                self.run_ms = 0.0;
+            }
+            self.run_ms += step_ms;
+
+            // Flush characters and words without waiting for the next mark
+            if !self.key_down {
+                if self.run_ms > 2.0 * self.unit_ms && !self.symbols.is_empty() {
+                    self.flush_char();
+                }
+                if self.run_ms > 5.0 * self.unit_ms && !self.word_gap_sent {
+                    self.push_char(' ');
+                    self.word_gap_sent = true;
+                }
+            }
+        }
+    }
+
+    fn end_mark(&mut self) {
+        let d = self.run_ms;
+        // Ignore glitches far shorter than a dot at the top speed
+        if d < dot_ms(MAX_WPM) * 0.3 {
+            return;
+        }
+        let estimate = if d < 2.0 * self.unit_ms {
+            self.symbols.push('.');
+            d
+        } else {
+            self.symbols.push('-');
+            d / 3.0
+        };
+        self.unit_ms = (0.8 * self.unit_ms + 0.2 * estimate).clamp(dot_ms(MAX_WPM), dot_ms(MIN_WPM));
+        self.word_gap_sent = false;
+    }
+
+    fn end_space(&mut self) {
+        if self.run_ms > 2.0 * self.unit_ms && !self.symbols.is_empty() {
+            self.flush_char();
+        }
+    }
+
+    fn flush_char(&mut self) {
+        let ch = MORSE
+            .iter()
+            .find(|(code, _)| *code == self.symbols)
+            .map(|&(_, ch)| ch)
+            .unwrap_or('*');
+        self.symbols.clear();
+        self.push_char(ch);
+    }
+
+    fn push_char(&mut self, ch: char) {
+        self.text.push(ch);
+        if self.text.len() > MAX_TEXT {
+            let cut = self.text.len() - MAX_TEXT;
+            self.text.drain(..cut);
+        }
+    }
+}
+
+impl Default for CwDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
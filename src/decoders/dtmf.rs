@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant, SystemTime};
+
+const ROWS: [f64; 4] = [697.0, 770.0, 852.0, 941.0];
+const COLUMNS: [f64; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+const KEYS: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+/// Goertzel block length in seconds (205 samples at 8 kHz)
+const BLOCK_SECS: f64 = 0.0256;
+/// Each tone must carry this fraction of the block energy
+const MIN_TONE_SHARE: f32 = 0.25;
+/// Row and column together must dominate the block
+const MIN_PAIR_SHARE: f32 = 0.7;
+const MAX_TWIST_DB: f32 = 8.0;
+const SEQUENCE_GAP: Duration = Duration::from_millis(1500);
+const MAX_SEQUENCES: usize = 200;
+
+/// Digits received without a long pause between them
+#[derive(Clone, Debug)]
+pub struct DtmfSequence {
+    pub started: SystemTime,
+    pub digits: String,
+}
+
+/// Goertzel-based DTMF detector working on demodulated audio
+pub struct DtmfDetector {
+    rate: f64,
+    block: Vec<f32>,
+    block_len: usize,
+    candidate: Option<char>,
+    /// Digit currently held down, cleared once the tones stop
+    held: Option<char>,
+    last_digit: Option<Instant>,
+    pub sequences: Vec<DtmfSequence>,
+}
+
+impl DtmfDetector {
+    pub fn new() -> Self {
+        Self {
+            rate: 0.0,
+            block: Vec::new(),
+            block_len: 0,
+            candidate: None,
+            held: None,
+            last_digit: None,
+            sequences: Vec::new(),
+        }
+    }
+
+    pub fn process(&mut self, audio: &[f32], rate: f64) {
+        if self.rate != rate {
+            self.rate = rate;
+            self.block_len = (rate * BLOCK_SECS).round() as usize;
+            self.block.clear();
+        }
+
+        for &s in audio {
+            self.block.push(s);
+            if self.block.len() == self.block_len {
+                let digit = self.detect();
+                // Half-block hop so a 40 ms tone spans two full windows
+                self.block.drain(..self.block_len / 2);
+                self.update(digit);
+            }
+        }
+    }
+
+    fn goertzel(&self, freq: f64) -> f32 {
+        let coeff = (2.0 * (2.0 * std::f64::consts::PI * freq / self.rate).cos()) as f32;
+        let (mut s1, mut s2) = (0.0f32, 0.0f32);
+        for &x in &self.block {
+            let s0 = x + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        s1 * s1 + s2 * s2 - coeff * s1 * s2
+    }
+
+    fn detect(&self) -> Option<char> {
+        let energy: f32 = self.block.iter().map(|x| x * x).sum();
+        if energy <= f32::EPSILON {
+            return None;
+        }
+        // A pure tone yields a share of one
+        let norm = energy * self.block.len() as f32 / 2.0;
+        let strongest = |freqs: &[f64; 4]| {
+            freqs
+                .iter()
+                .map(|&f| self.goertzel(f) / norm)
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap_or((0, 0.0))
+        };
+        let (row, row_share) = strongest(&ROWS);
+        let (col, col_share) = strongest(&COLUMNS);
+
+        let twist_db = 10.0 * (row_share / col_share).log10().abs();
+        (row_share > MIN_TONE_SHARE
+            && col_share > MIN_TONE_SHARE
+            && row_share + col_share > MIN_PAIR_SHARE
+            && twist_db < MAX_TWIST_DB)
+            .then_some(KEYS[row][col])
+    }
+
+    /// Require two consecutive blocks to agree and a gap before repeating a digit
+    fn update(&mut self, digit: Option<char>) {
+        let confirmed = digit.is_some() && digit == self.candidate;
+        self.candidate = digit;
+
+        match digit {
+            None => self.held = None,
+            Some(d) if confirmed && self.held != Some(d) => {
+                self.held = Some(d);
+                let now = Instant::now();
+                let continues = self.last_digit.is_some_and(|t| now - t < SEQUENCE_GAP);
+                self.last_digit = Some(now);
+                match self.sequences.last_mut() {
+                    Some(sequence) if continues => sequence.digits.push(d),
+                    _ => {
+                        self.sequences.push(DtmfSequence {
+                            started: SystemTime::now(),
+                            digits: d.to_string(),
+                        });
+                        if self.sequences.len() > MAX_SEQUENCES {
+                            self.sequences.remove(0);
+                        }
+                    }
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+impl Default for DtmfDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,133 @@
+//! Decoders in shared libraries, loaded at run time through a C ABI so that
+//! they can be built separately, with any Rust version or another language.
+//!
+//! A library exports `rf_rust_decoder`, a function returning a pointer to a
+//! static [`PluginApi`]. In Rust:
+//!
+//! ```text
+//! #[unsafe(no_mangle)]
+//! pub extern "C" fn rf_rust_decoder() -> *const PluginApi {
+//!     &API
+//! }
+//! ```
+//!
+//! with `PluginApi` copied from this file, as the library does not link
+//! the crate. Libraries are never unloaded.
+
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::path::Path;
+
+use num_complex::Complex32;
+
+use super::plugin::{Decoder, Input};
+use crate::error::{Result, RfError};
+
+/// Version of [`PluginApi`] this build takes
+pub const ABI_VERSION: u32 = 1;
+const ENTRY_SYMBOL: &CStr = c"rf_rust_decoder";
+/// Longest message taken from a library, longer ones are cut short
+const MESSAGE_BYTES: usize = 4096;
+const RTLD_NOW: c_int = 2;
+
+#[repr(C)]
+pub struct PluginApi {
+    /// [`ABI_VERSION`] of the library
+    pub abi_version: u32,
+    /// NUL-terminated name of the decoder
+    pub name: *const c_char,
+    /// A new decoder instance, passed back to the other functions
+    pub create: extern "C" fn() -> *mut c_void,
+    /// `len` complex samples as interleaved I and Q floats
+    pub process: extern "C" fn(state: *mut c_void, iq: *const f32, len: usize, center_freq: f64, sample_rate: f64),
+    /// Copy the next decoded message as UTF-8 into `buf`, at most `cap`
+    /// bytes, returning its length, or a negative number when none is waiting
+    pub next_message: extern "C" fn(state: *mut c_void, buf: *mut u8, cap: usize) -> isize,
+    pub destroy: extern "C" fn(state: *mut c_void),
+}
+
+unsafe extern "C" {
+    fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlerror() -> *const c_char;
+}
+
+/// Text of the latest dynamic loader error
+fn loader_error() -> String {
+    // SAFETY: dlerror returns NULL or a NUL-terminated string valid until the next call
+    unsafe {
+        let e = dlerror();
+        if e.is_null() { "unknown error".to_string() } else { CStr::from_ptr(e).to_string_lossy().into_owned() }
+    }
+}
+
+pub struct DynamicDecoder {
+    api: &'static PluginApi,
+    state: *mut c_void,
+    name: String,
+}
+
+impl DynamicDecoder {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = CString::new(path.as_os_str().as_encoded_bytes()).map_err(|_| RfError::Dsp(format!("{}: invalid path", path.display())))?;
+        // SAFETY: loading a library runs its initialisers, which the user
+        // trusts by naming it in their config file. The entry point must
+        // have the documented signature and return a static PluginApi.
+        let api = unsafe {
+            let handle = dlopen(file.as_ptr(), RTLD_NOW);
+            if handle.is_null() {
+                return Err(RfError::Dsp(loader_error()));
+            }
+            let entry = dlsym(handle, ENTRY_SYMBOL.as_ptr());
+            if entry.is_null() {
+                return Err(RfError::Dsp(format!("{}: no {} function", path.display(), ENTRY_SYMBOL.to_string_lossy())));
+            }
+            let entry: extern "C" fn() -> *const PluginApi = std::mem::transmute(entry);
+            entry()
+                .as_ref()
+                .ok_or_else(|| RfError::Dsp(format!("{}: {} returned NULL", path.display(), ENTRY_SYMBOL.to_string_lossy())))?
+        };
+        if api.abi_version != ABI_VERSION {
+            return Err(RfError::Dsp(format!("{}: plugin ABI {}, this build takes {}", path.display(), api.abi_version, ABI_VERSION)));
+        }
+        let name = match api.name.is_null() {
+            true => path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(),
+            // SAFETY: the API documents `name` as NUL-terminated
+            false => unsafe { CStr::from_ptr(api.name) }.to_string_lossy().into_owned(),
+        };
+        let state = (api.create)();
+        Ok(Self { api, state, name })
+    }
+}
+
+impl Decoder for DynamicDecoder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn process(&mut self, input: &Input) {
+        // Complex32 is repr(C), a pair of f32
+        let iq = input.iq.as_ptr() as *const f32;
+        (self.api.process)(self.state, iq, input.iq.len(), input.center_freq, input.sample_rate);
+    }
+
+    fn take_messages(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+        let mut buf = vec![0u8; MESSAGE_BYTES];
+        loop {
+            let len = (self.api.next_message)(self.state, buf.as_mut_ptr(), buf.len());
+            if len < 0 {
+                return messages;
+            }
+            messages.push(String::from_utf8_lossy(&buf[..(len as usize).min(buf.len())]).into_owned());
+        }
+    }
+}
+
+impl Drop for DynamicDecoder {
+    fn drop(&mut self) {
+        (self.api.destroy)(self.state);
+    }
+}
+
+// The layout `process` relies on
+const _: () = assert!(std::mem::size_of::<Complex32>() == 2 * std::mem::size_of::<f32>());
@@ -0,0 +1,151 @@
+use super::bch::bch3121_correct;
+use super::pager::{PagerMessage, Protocol};
+
+/// Sync 1 pattern for 1600 bps 2-FSK: A word, fixed B pattern, inverted A word
+const SYNC_1600_2: u64 = 0x870C_A6C6_AAAA_78F3;
+const FIW_BITS: usize = 32;
+/// Sync 2 lasts 25 ms
+const SYNC2_BITS: usize = 40;
+const BLOCKS: usize = 11;
+const WORDS_PER_BLOCK: usize = 8;
+const FRAME_BITS: usize = BLOCKS * WORDS_PER_BLOCK * 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Hunt,
+    FrameInfo,
+    Sync2,
+    Data,
+}
+
+/// FLEX 1600 bps 2-FSK frame decoder
+pub struct Flex {
+    shift: u64,
+    state: State,
+    inverted: bool,
+    bits: Vec<bool>,
+}
+
+impl Flex {
+    pub fn new() -> Self {
+        Self {
+            shift: 0,
+            state: State::Hunt,
+            inverted: false,
+            bits: Vec::with_capacity(FRAME_BITS),
+        }
+    }
+
+    pub fn push_bit(&mut self, bit: bool, out: &mut Vec<PagerMessage>) {
+        match self.state {
+            State::Hunt => {
+                self.shift = (self.shift << 1) | bit as u64;
+                if self.shift == SYNC_1600_2 || self.shift == !SYNC_1600_2 {
+                    self.inverted = self.shift != SYNC_1600_2;
+                    self.state = State::FrameInfo;
+                    self.bits.clear();
+                }
+            }
+            state => {
+                self.bits.push(bit != self.inverted);
+                let (needed, next) = match state {
+                    State::FrameInfo => (FIW_BITS, State::Sync2),
+                    State::Sync2 => (SYNC2_BITS, State::Data),
+                    _ => (FRAME_BITS, State::Hunt),
+                };
+                if self.bits.len() < needed {
+                    return;
+                }
+
+                if state == State::FrameInfo && read_word(&self.bits).is_none() {
+                    self.state = State::Hunt;
+                } else {
+                    if state == State::Data {
+                        decode_frame(&self.bits, out);
+                    }
+                    self.state = next;
+                }
+                self.shift = 0;
+                self.bits.clear();
+            }
+        }
+    }
+}
+
+/// Assemble an LSB-first word and strip the BCH check bits
+fn read_word(bits: &[bool]) -> Option<u32> {
+    let raw = bits
+        .iter()
+        .take(32)
+        .enumerate()
+        .fold(0u32, |acc, (i, &b)| acc | ((b as u32) << i));
+    bch3121_correct(raw.reverse_bits()).map(|w| w.reverse_bits() & 0x1F_FFFF)
+}
+
+/// Undo the per-block bit interleaving and decode the words of one frame
+fn decode_frame(bits: &[bool], out: &mut Vec<PagerMessage>) {
+    let mut words = Vec::with_capacity(BLOCKS * WORDS_PER_BLOCK);
+    for block in bits.chunks(WORDS_PER_BLOCK * 32) {
+        for word in 0..WORDS_PER_BLOCK {
+            let word_bits: Vec<bool> = (0..32).map(|i| block[i * WORDS_PER_BLOCK + word]).collect();
+            words.push(read_word(&word_bits));
+        }
+    }
+
+    let Some(biw) = words[0] else {
+        return;
+    };
+    let address_start = (((biw >> 8) & 0x3) + 1) as usize;
+    let vector_start = ((biw >> 10) & 0x3F) as usize;
+    if vector_start <= address_start || vector_start >= words.len() {
+        return;
+    }
+
+    for (i, address) in (address_start..vector_start).enumerate() {
+        let (Some(address), Some(vector)) = (words[address], words.get(vector_start + i).copied().flatten())
+        else {
+            continue;
+        };
+        // Only short addresses are handled
+        if !(0x8001..=0x1E_0000).contains(&address) {
+            continue;
+        }
+        let capcode = address - 0x8000;
+
+        let kind = (vector >> 4) & 0x7;
+        let start = ((vector >> 7) & 0x7F) as usize;
+        let len = ((vector >> 14) & 0x7F) as usize;
+
+        let text = match kind {
+            // Alphanumeric: a fragment header word, then three 7-bit characters per word
+            5 => words
+                .iter()
+                .skip(start + 1)
+                .take(len.saturating_sub(1))
+                .flatten()
+                .enumerate()
+                .flat_map(|(n, &w)| {
+                    let skip = if n == 0 { 1 } else { 0 };
+                    (0..3).skip(skip).map(move |c| ((w >> (7 * c)) & 0x7F) as u8)
+                })
+                .filter(|c| (0x20..0x7F).contains(c))
+                .map(|c| c as char)
+                .collect(),
+            // Numeric: 4-bit digits packed five to a word
+            3 | 4 => words
+                .iter()
+                .skip(start)
+                .take(((vector >> 14) & 0x7) as usize + 1)
+                .flatten()
+                .flat_map(|&w| (0..5).map(move |d| ((w >> (4 * d)) & 0xF) as usize))
+                .map(|d| b"0123456789 U -][".get(d).copied().unwrap_or(b' ') as char)
+                .collect::<String>()
+                .trim()
+                .to_string(),
+            2 => "<tone only>".to_string(),
+            _ => continue,
+        };
+
+        out.push(PagerMessage::new(Protocol::Flex, capcode, kind as u8, text));
+    }
+}
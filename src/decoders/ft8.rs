@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex32;
+
+use crate::dsp::{DecimatingFir, Nco};
+use crate::recording::wav;
+
+/// Standard FT8 dial frequencies (USB), signals occupy 200-3000 Hz above
+pub const DIAL_FREQUENCIES: [f64; 12] = [
+    1.840e6, 3.573e6, 5.357e6, 7.074e6, 10.136e6, 14.074e6, 18.100e6, 21.074e6, 24.915e6,
+    28.074e6, 50.313e6, 144.174e6,
+];
+
+const AUDIO_RATE: f64 = 12e3;
+const AUDIO_CENTER: f64 = 1500.0;
+const AUDIO_BANDWIDTH: f64 = 3000.0;
+const CYCLE_SECS: u64 = 15;
+/// Transmissions last 12.64 s, shorter recordings cannot be decoded
+const MIN_CYCLE_SECS: f64 = 13.0;
+const MAX_DECODES: usize = 500;
+
+/// External decoder unless another is set with `ft8.decoder` in the config
+const DEFAULT_DECODER: &str = "jt9";
+
+/// One decoded FT8 message
+#[derive(Clone, Debug)]
+pub struct Ft8Decode {
+    /// Start of the 15 s cycle, seconds since the epoch
+    pub cycle_start: u64,
+    pub snr: i32,
+    pub dt: f32,
+    /// Audio offset above the dial frequency in Hz
+    pub audio_hz: u32,
+    pub message: String,
+    pub callsign: Option<String>,
+    pub grid: Option<String>,
+}
+
+/// 15 s cycle recorder handing each cycle to an external FT8 decoder
+pub struct Ft8Decoder {
+    tuned: (f64, f64),
+    /// Dial frequency of the FT8 sub-band inside the current passband
+    pub dial: Option<f64>,
+    nco: Nco,
+    fir: DecimatingFir,
+    upmix: Nco,
+    cycle_start: u64,
+    audio: Vec<f32>,
+    work_dir: PathBuf,
+    results: Receiver<Result<Vec<Ft8Decode>, String>>,
+    results_tx: Sender<Result<Vec<Ft8Decode>, String>>,
+    /// Program run on each cycle, taking `jt9` arguments
+    pub decoder: String,
+    pub decoding: bool,
+    pub decodes: Vec<Ft8Decode>,
+    pub last_error: Option<String>,
+}
+
+impl Ft8Decoder {
+    pub fn new() -> Self {
+        let (results_tx, results) = mpsc::channel();
+        Self {
+            tuned: (0.0, 0.0),
+            dial: None,
+            nco: Nco::new(0.0, 1.0),
+            fir: DecimatingFir::new(vec![1.0], 1),
+            upmix: Nco::new(0.0, 1.0),
+            cycle_start: 0,
+            audio: Vec::new(),
+            work_dir: std::env::temp_dir().join("rf_rust_ft8"),
+            results,
+            results_tx,
+            decoder: DEFAULT_DECODER.to_string(),
+            decoding: false,
+            decodes: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    /// Fraction of the current cycle that has been recorded
+    pub fn cycle_progress(&self) -> f32 {
+        (self.audio.len() as f32 / (CYCLE_SECS as f32 * AUDIO_RATE as f32)).min(1.0)
+    }
+
+    fn retune(&mut self, center_freq: f64, sample_rate: f64) {
+        self.tuned = (center_freq, sample_rate);
+        self.audio.clear();
+        self.dial = DIAL_FREQUENCIES
+            .iter()
+            .copied()
+            .find(|dial| (dial + AUDIO_CENTER - center_freq).abs() < (sample_rate - AUDIO_BANDWIDTH) / 2.0);
+        if let Some(dial) = self.dial {
+            self.nco = Nco::new(dial + AUDIO_CENTER - center_freq, sample_rate);
+            let (fir, rate) = DecimatingFir::for_rates(sample_rate, AUDIO_RATE, AUDIO_BANDWIDTH);
+            self.fir = fir;
+            // Shift the passband back up so the dial frequency lands at 0 Hz audio
+            self.upmix = Nco::new(-AUDIO_CENTER, rate);
+        }
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        if let Ok(result) = self.results.try_recv() {
+            self.decoding = false;
+            match result {
+                Ok(decodes) => {
+                    self.last_error = None;
+                    self.decodes.extend(decodes);
+                    if self.decodes.len() > MAX_DECODES {
+                        let excess = self.decodes.len() - MAX_DECODES;
+                        self.decodes.drain(..excess);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("FT8 decode failed: {}", e);
+                    self.last_error = Some(e);
+                }
+            }
+        }
+
+        if self.tuned != (center_freq, sample_rate) {
+            self.retune(center_freq, sample_rate);
+        }
+        if self.dial.is_none() {
+            return;
+        }
+
+        // Cycles start at 0, 15, 30 and 45 seconds past the minute
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let cycle = now - now % CYCLE_SECS;
+        if cycle != self.cycle_start {
+            if self.audio.len() as f64 >= MIN_CYCLE_SECS * AUDIO_RATE && !self.decoding {
+                self.start_decode();
+            }
+            self.audio.clear();
+            self.cycle_start = cycle;
+        }
+
+        for &s in samples {
+            if let Some(x) = self.fir.push(self.nco.mix(s)) {
+                self.audio.push(self.upmix.mix(x).re);
+            }
+        }
+    }
+
+    fn start_decode(&mut self) {
+        let audio = std::mem::take(&mut self.audio);
+        let cycle_start = self.cycle_start;
+        let work_dir = self.work_dir.clone();
+        let decoder = self.decoder.clone();
+        let tx = self.results_tx.clone();
+        self.decoding = true;
+        thread::spawn(move || {
+            let _ = tx.send(run_decoder(&decoder, &audio, cycle_start, &work_dir));
+        });
+    }
+}
+
+impl Default for Ft8Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write the cycle to a WAV file and run the external `decoder` on it
+fn run_decoder(decoder: &str, audio: &[f32], cycle_start: u64, work_dir: &Path) -> Result<Vec<Ft8Decode>, String> {
+    std::fs::create_dir_all(work_dir).map_err(|e| e.to_string())?;
+    let wav = work_dir.join(format!("{}.wav", cycle_start));
+    write_wav(&wav, audio).map_err(|e| format!("cannot write {}: {}", wav.display(), e))?;
+
+    let output = Command::new(decoder)
+        .arg("-8")
+        .arg("-d")
+        .arg("3")
+        .arg("-a")
+        .arg(work_dir)
+        .arg("-t")
+        .arg(work_dir)
+        .arg(&wav)
+        .output()
+        .map_err(|e| format!("cannot run {}: {}", decoder, e));
+    let _ = std::fs::remove_file(&wav);
+
+    let output = output?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| parse_decode(line, cycle_start))
+        .collect())
+}
+
+/// Parse a `jt9` result line: `hhmmss snr dt freq ~ message`
+fn parse_decode(line: &str, cycle_start: u64) -> Option<Ft8Decode> {
+    let (fields, message) = line.split_once('~')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let [.., snr, dt, freq] = fields.as_slice() else {
+        return None;
+    };
+    let message = message.split("  ").next()?.trim().to_string();
+    if message.is_empty() {
+        return None;
+    }
+
+    let words: Vec<&str> = message.split_whitespace().collect();
+    let callsign = match words.as_slice() {
+        // CQ with an optional directed-call modifier
+        ["CQ", modifier, call, ..] if modifier.len() <= 4 && !modifier.chars().any(|c| c.is_ascii_digit()) => {
+            Some(call.to_string())
+        }
+        ["CQ", call, ..] => Some(call.to_string()),
+        [_, from, ..] => Some(from.to_string()),
+        _ => None,
+    };
+    let grid = words.last().filter(|w| is_grid(w)).map(|w| w.to_string());
+
+    Some(Ft8Decode {
+        cycle_start,
+        snr: snr.parse().ok()?,
+        dt: dt.parse().ok()?,
+        audio_hz: freq.parse().ok()?,
+        message,
+        callsign,
+        grid,
+    })
+}
+
+/// Four-character Maidenhead locator, excluding the `RR73` sign-off
+fn is_grid(word: &str) -> bool {
+    let b = word.as_bytes();
+    b.len() == 4
+        && (b'A'..=b'R').contains(&b[0])
+        && (b'A'..=b'R').contains(&b[1])
+        && b[2].is_ascii_digit()
+        && b[3].is_ascii_digit()
+        && word != "RR73"
+}
+
+/// 16-bit mono PCM WAV at the audio rate
+fn write_wav(path: &Path, audio: &[f32]) -> io::Result<()> {
+    let peak = audio.iter().fold(1e-9f32, |m, s| m.max(s.abs()));
+    let scale = 0.5 * i16::MAX as f32 / peak;
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(&wav::audio_header(AUDIO_RATE as u32, audio.len() as u64))?;
+    for &s in audio {
+        out.write_all(&((s * scale) as i16).to_le_bytes())?;
+    }
+    out.flush()
+}
@@ -0,0 +1,334 @@
+//! rtl_433-style pulse analysis for 433/868/915 MHz OOK and FSK devices.
+
+mod protocols;
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use num_complex::Complex32;
+
+use super::utc_date_time;
+use crate::dsp::{DecimatingFir, FmDiscriminator};
+
+const PULSE_RATE: f64 = 250e3;
+const CHANNEL_BANDWIDTH: f64 = 200e3;
+/// A gap this long ends the packet
+const PACKET_GAP_US: f64 = 10_000.0;
+const MIN_PULSES: usize = 8;
+const MAX_PULSES: usize = 2048;
+const MAX_RECORDS: usize = 500;
+
+/// How a pulse train was demodulated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modulation {
+    Ook,
+    Fsk,
+}
+
+/// Mark/space durations of one packet in microseconds
+#[derive(Clone, Debug)]
+pub struct PulseTrain {
+    pub modulation: Modulation,
+    pub pulses: Vec<(f64, f64)>,
+}
+
+impl PulseTrain {
+    /// Split into rows at spaces longer than `gap_us`, slicing each pulse with `bit`
+    pub fn rows(&self, gap_us: f64, bit: impl Fn(f64, f64) -> bool) -> Vec<Vec<bool>> {
+        let mut rows = vec![Vec::new()];
+        for &(mark, space) in &self.pulses {
+            if let Some(row) = rows.last_mut() {
+                row.push(bit(mark, space));
+            }
+            if space > gap_us {
+                rows.push(Vec::new());
+            }
+        }
+        rows.retain(|row| !row.is_empty());
+        rows
+    }
+
+    /// Pulse width modulation: a long mark is a one
+    pub fn slice_pwm(&self, threshold_us: f64, gap_us: f64) -> Vec<Vec<bool>> {
+        self.rows(gap_us, |mark, _| mark > threshold_us)
+    }
+
+    /// Pulse position modulation: a long space is a one. The space ending a
+    /// row carries no bit.
+    pub fn slice_ppm(&self, threshold_us: f64, gap_us: f64) -> Vec<Vec<bool>> {
+        let mut rows = vec![Vec::new()];
+        for &(_, space) in &self.pulses {
+            match rows.last_mut() {
+                Some(row) if space <= gap_us => row.push(space > threshold_us),
+                _ => rows.push(Vec::new()),
+            }
+        }
+        rows.retain(|row| !row.is_empty());
+        rows
+    }
+
+    /// Manchester (IEEE 802.3, rising edge is a one) with a known half-bit time
+    pub fn slice_manchester(&self, half_bit_us: f64) -> Vec<bool> {
+        let mut levels = Vec::new();
+        for &(mark, space) in &self.pulses {
+            let marks = (mark / half_bit_us).round().max(1.0) as usize;
+            levels.extend(std::iter::repeat_n(true, marks));
+            if space < PACKET_GAP_US {
+                let spaces = (space / half_bit_us).round().max(1.0) as usize;
+                levels.extend(std::iter::repeat_n(false, spaces));
+            }
+        }
+        // Align on the first mid-bit transition: a packet starts with a mark
+        levels
+            .chunks_exact(2)
+            .map_while(|pair| match pair {
+                [false, true] => Some(true),
+                [true, false] => Some(false),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Value of one field in a decoded record
+#[derive(Clone, Debug)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{:.1}", v),
+            Value::Str(v) => write!(f, "\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")),
+        }
+    }
+}
+
+/// A decoded device transmission
+#[derive(Clone, Debug)]
+pub struct IsmRecord {
+    pub received: SystemTime,
+    pub model: &'static str,
+    pub fields: Vec<(&'static str, Value)>,
+}
+
+impl IsmRecord {
+    /// One-line JSON in the style of `rtl_433 -F json`
+    pub fn to_json(&self) -> String {
+        let secs = self
+            .received
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (date, time) = utc_date_time(secs);
+        let mut json = format!("{{\"time\" : \"{} {}\", \"model\" : \"{}\"", date, time, self.model);
+        for (key, value) in &self.fields {
+            json.push_str(&format!(", \"{}\" : {}", key, value));
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// A device protocol recognised from a pulse train
+pub trait IsmProtocol: Send {
+    fn name(&self) -> &'static str;
+    fn decode(&self, train: &PulseTrain) -> Option<Vec<(&'static str, Value)>>;
+}
+
+/// Pulse detector on the tuned centre frequency feeding the protocol plugins
+pub struct IsmDecoder {
+    sample_rate: f64,
+    fir: DecimatingFir,
+    disc: FmDiscriminator,
+    rate: f64,
+    envelope: f32,
+    noise: f32,
+    peak: f32,
+    carrier: bool,
+    fsk_high: bool,
+    run_us: f64,
+    mark_us: f64,
+    ook: Vec<(f64, f64)>,
+    fsk: Vec<(f64, f64)>,
+    fsk_run_us: f64,
+    fsk_mark_us: f64,
+    protocols: Vec<Box<dyn IsmProtocol>>,
+    pub records: Vec<IsmRecord>,
+    pub packets: u32,
+    log: Option<File>,
+}
+
+impl IsmDecoder {
+    pub fn new() -> Self {
+        let mut decoder = Self {
+            sample_rate: 0.0,
+            fir: DecimatingFir::new(vec![1.0], 1),
+            disc: FmDiscriminator::new(),
+            rate: PULSE_RATE,
+            envelope: 0.0,
+            noise: 0.0,
+            peak: 0.0,
+            carrier: false,
+            fsk_high: false,
+            run_us: 0.0,
+            mark_us: 0.0,
+            ook: Vec::new(),
+            fsk: Vec::new(),
+            fsk_run_us: 0.0,
+            fsk_mark_us: 0.0,
+            protocols: protocols::all(),
+            records: Vec::new(),
+            packets: 0,
+            log: None,
+        };
+        decoder.retune(1e6);
+        decoder
+    }
+
+    pub fn protocol_names(&self) -> Vec<&'static str> {
+        self.protocols.iter().map(|p| p.name()).collect()
+    }
+
+    /// Append records as JSON lines to `path`, or stop with `None`
+    pub fn set_log(&mut self, path: Option<&str>) -> io::Result<()> {
+        self.log = match path {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, PULSE_RATE, CHANNEL_BANDWIDTH);
+        self.fir = fir;
+        self.rate = rate;
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+
+        let step_us = 1e6 / self.rate;
+        for &s in samples {
+            let Some(x) = self.fir.push(s) else {
+                continue;
+            };
+            let freq = self.disc.push(x);
+            self.envelope += 0.3 * (x.norm() - self.envelope);
+            let e = self.envelope;
+            self.peak = if e > self.peak { e } else { self.peak * 0.9999 };
+            self.noise = if e < self.noise || self.noise == 0.0 {
+                e
+            } else {
+                self.noise + (e - self.noise) * 0.0001
+            };
+
+            let threshold = self.noise + 0.5 * (self.peak - self.noise);
+            let carrier = e > threshold && e > self.noise * 3.0;
+
+            // OOK: carrier on/off durations
+            if carrier != self.carrier {
+                if carrier {
+                    if self.mark_us > 0.0 {
+                        self.ook.push((self.mark_us, self.run_us));
+                    }
+                } else {
+                    self.mark_us = self.run_us;
+                }
+                self.carrier = carrier;
+                self.run_us = 0.0;
+            }
+            self.run_us += step_us;
+
+            // FSK: frequency sign durations while the carrier is up
+            if carrier {
+                let high = freq > 0.0;
+                if high != self.fsk_high {
+                    if high {
+                        if self.fsk_mark_us > 0.0 {
+                            self.fsk.push((self.fsk_mark_us, self.fsk_run_us));
+                        }
+                    } else {
+                        self.fsk_mark_us = self.fsk_run_us;
+                    }
+                    self.fsk_high = high;
+                    self.fsk_run_us = 0.0;
+                }
+                self.fsk_run_us += step_us;
+            }
+
+            if !carrier && self.run_us > PACKET_GAP_US && self.mark_us > 0.0 {
+                self.ook.push((self.mark_us, self.run_us));
+                self.end_packet();
+            }
+            if self.ook.len() > MAX_PULSES {
+                self.ook.clear();
+                self.fsk.clear();
+            }
+        }
+    }
+
+    fn end_packet(&mut self) {
+        self.mark_us = 0.0;
+        if self.fsk_mark_us > 0.0 {
+            self.fsk.push((self.fsk_mark_us, PACKET_GAP_US * 2.0));
+        }
+        self.fsk_mark_us = 0.0;
+        self.fsk_run_us = 0.0;
+
+        let ook = PulseTrain {
+            modulation: Modulation::Ook,
+            pulses: std::mem::take(&mut self.ook),
+        };
+        let fsk = PulseTrain {
+            modulation: Modulation::Fsk,
+            pulses: std::mem::take(&mut self.fsk),
+        };
+
+        for train in [ook, fsk] {
+            if train.pulses.len() < MIN_PULSES {
+                continue;
+            }
+            self.packets += 1;
+            for protocol in &self.protocols {
+                if let Some(fields) = protocol.decode(&train) {
+                    let record = IsmRecord {
+                        received: SystemTime::now(),
+                        model: protocol.name(),
+                        fields,
+                    };
+                    if let Some(log) = &mut self.log
+                        && writeln!(log, "{}", record.to_json()).is_err()
+                    {
+                        self.log = None;
+                    }
+                    self.records.push(record);
+                }
+            }
+        }
+
+        if self.records.len() > MAX_RECORDS {
+            let excess = self.records.len() - MAX_RECORDS;
+            self.records.drain(..excess);
+        }
+    }
+}
+
+impl Default for IsmDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
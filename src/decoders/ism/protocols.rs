@@ -0,0 +1,246 @@
+use super::{IsmProtocol, Modulation, PulseTrain, Value};
+
+pub fn all() -> Vec<Box<dyn IsmProtocol>> {
+    vec![Box::new(GenericRemote), Box::new(NexusTh), Box::new(FordTpms)]
+}
+
+fn to_u32(bits: &[bool]) -> u32 {
+    bits.iter().fold(0, |acc, &b| (acc << 1) | b as u32)
+}
+
+/// Threshold halfway between the shortest and longest durations
+fn midpoint(durations: impl Iterator<Item = f64>) -> f64 {
+    let (min, max) = durations.fold((f64::MAX, 0.0f64), |(lo, hi), d| (lo.min(d), hi.max(d)));
+    (min + max) / 2.0
+}
+
+/// EV1527 / PT2262 style remotes and doorbells: 24 PWM bits then a sync pulse
+struct GenericRemote;
+
+impl IsmProtocol for GenericRemote {
+    fn name(&self) -> &'static str {
+        "Generic-Remote"
+    }
+
+    fn decode(&self, train: &PulseTrain) -> Option<Vec<(&'static str, Value)>> {
+        if train.modulation != Modulation::Ook || train.pulses.len() < 25 {
+            return None;
+        }
+        let bits = &train.pulses[..24];
+        // Every bit period is about four units: 1+3 for a zero, 3+1 for a one
+        let threshold = midpoint(bits.iter().map(|p| p.0));
+        let unit = bits.iter().map(|p| p.0 + p.1).sum::<f64>() / 24.0 / 4.0;
+        if bits.iter().any(|&(m, s)| ((m + s) / unit - 4.0).abs() > 1.0) {
+            return None;
+        }
+        let rows = train.slice_pwm(threshold, unit * 8.0);
+        let row = rows.first().filter(|r| r.len() >= 24)?;
+
+        let code = to_u32(&row[..24]);
+        if code == 0 || code == 0xFF_FFFF {
+            return None;
+        }
+        Some(vec![
+            ("id", Value::Int((code >> 4) as i64)),
+            ("cmd", Value::Int((code & 0xF) as i64)),
+        ])
+    }
+}
+
+/// Nexus / Sencor temperature-humidity sensors: 36-bit PPM rows repeated
+struct NexusTh;
+
+impl IsmProtocol for NexusTh {
+    fn name(&self) -> &'static str {
+        "Nexus-TH"
+    }
+
+    fn decode(&self, train: &PulseTrain) -> Option<Vec<(&'static str, Value)>> {
+        if train.modulation != Modulation::Ook {
+            return None;
+        }
+        // 1000 us gap for a zero, 2000 us for a one, 4000 us between rows
+        let rows = train.slice_ppm(1500.0, 3000.0);
+        let row = rows
+            .iter()
+            .filter(|r| r.len() == 36)
+            .find(|r| rows.iter().filter(|other| other == r).count() >= 2)?;
+
+        if to_u32(&row[24..28]) != 0xF {
+            return None;
+        }
+        let humidity = to_u32(&row[28..36]);
+        if humidity > 100 {
+            return None;
+        }
+        let raw_temp = to_u32(&row[12..24]) as i32;
+        let temp = ((raw_temp << 20) >> 20) as f64 / 10.0;
+
+        Some(vec![
+            ("id", Value::Int(to_u32(&row[0..8]) as i64)),
+            ("channel", Value::Int(to_u32(&row[10..12]) as i64 + 1)),
+            ("battery_ok", Value::Int(row[8] as i64)),
+            ("temperature_C", Value::Float(temp)),
+            ("humidity", Value::Int(humidity as i64)),
+        ])
+    }
+}
+
+/// Ford tyre pressure sensors: FSK Manchester, 8 bytes with an additive checksum
+struct FordTpms;
+
+impl IsmProtocol for FordTpms {
+    fn name(&self) -> &'static str {
+        "Ford-TPMS"
+    }
+
+    fn decode(&self, train: &PulseTrain) -> Option<Vec<(&'static str, Value)>> {
+        if train.modulation != Modulation::Fsk {
+            return None;
+        }
+        let bits = train.slice_manchester(52.0);
+        if bits.len() < 64 {
+            return None;
+        }
+        // The preamble length varies, so search for a checksum-valid alignment
+        (0..=bits.len() - 64).find_map(|offset| {
+            let b: Vec<u8> = bits[offset..offset + 64]
+                .chunks(8)
+                .map(|c| to_u32(c) as u8)
+                .collect();
+            let sum = b[..7].iter().fold(0u8, |acc, &x| acc.wrapping_add(x));
+            if sum != b[7] || b[..4].iter().all(|&x| x == 0) {
+                return None;
+            }
+            let id = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+            let pressure = (((b[6] as u32 & 0x20) << 3) | b[4] as u32) as f64 * 0.25;
+            Some(vec![
+                ("type", Value::Str("TPMS".to_string())),
+                ("id", Value::Str(format!("{:08x}", id))),
+                ("pressure_PSI", Value::Float(pressure)),
+                ("temperature_C", Value::Int(b[5] as i64 - 56)),
+            ])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(value: u64, count: usize) -> Vec<bool> {
+        (0..count).rev().map(|i| value >> i & 1 == 1).collect()
+    }
+
+    fn field<'a>(fields: &'a [(&'static str, Value)], name: &str) -> &'a Value {
+        &fields.iter().find(|(n, _)| *n == name).expect("field decoded").1
+    }
+
+    fn int(value: &Value) -> i64 {
+        match value {
+            Value::Int(v) => *v,
+            other => panic!("{:?} is not an integer", other),
+        }
+    }
+
+    fn float(value: &Value) -> f64 {
+        match value {
+            Value::Float(v) => *v,
+            other => panic!("{:?} is not a float", other),
+        }
+    }
+
+    /// 350 us units, a one 3 on 1 off, a zero 1 on 3 off, then the sync pulse
+    fn remote(code: u32) -> PulseTrain {
+        let unit = 350.0;
+        let mut pulses: Vec<(f64, f64)> = bits(code as u64, 24)
+            .into_iter()
+            .map(|one| if one { (3.0 * unit, unit) } else { (unit, 3.0 * unit) })
+            .collect();
+        pulses.push((unit, 31.0 * unit));
+        PulseTrain { modulation: Modulation::Ook, pulses }
+    }
+
+    #[test]
+    fn generic_remote_decodes_id_and_command() {
+        let fields = GenericRemote.decode(&remote(0x5A_3C91)).unwrap();
+        assert_eq!(int(field(&fields, "id")), 0x5_A3C9);
+        assert_eq!(int(field(&fields, "cmd")), 1);
+        assert!(GenericRemote.decode(&remote(0)).is_none());
+        let fsk = PulseTrain { modulation: Modulation::Fsk, ..remote(0x5A_3C91) };
+        assert!(GenericRemote.decode(&fsk).is_none());
+    }
+
+    /// Three rows of 500 us pulses, 1000 us after a zero and 2000 us after a
+    /// one, each row ended by a pulse and a 4000 us gap
+    fn nexus(id: u8, channel: u8, tenths: i16, humidity: u8) -> PulseTrain {
+        let mut row = bits(id as u64, 8);
+        row.extend([true, false]);
+        row.extend(bits(channel as u64 - 1, 2));
+        row.extend(bits(tenths as u16 as u64 & 0xFFF, 12));
+        row.extend(bits(0xF, 4));
+        row.extend(bits(humidity as u64, 8));
+        let mut pulses = Vec::new();
+        for _ in 0..3 {
+            pulses.extend(row.iter().map(|&one| (500.0, if one { 2000.0 } else { 1000.0 })));
+            pulses.push((500.0, 4000.0));
+        }
+        PulseTrain { modulation: Modulation::Ook, pulses }
+    }
+
+    #[test]
+    fn nexus_decodes_temperature_and_humidity() {
+        let fields = NexusTh.decode(&nexus(0x5B, 2, -73, 64)).unwrap();
+        assert_eq!(int(field(&fields, "id")), 0x5B);
+        assert_eq!(int(field(&fields, "channel")), 2);
+        assert_eq!(int(field(&fields, "battery_ok")), 1);
+        assert_eq!(float(field(&fields, "temperature_C")), -7.3);
+        assert_eq!(int(field(&fields, "humidity")), 64);
+        assert!(NexusTh.decode(&nexus(0x5B, 2, 215, 101)).is_none());
+    }
+
+    /// Manchester at 52 us a half bit, a one low then high, as FSK runs
+    fn manchester(data: &[bool]) -> PulseTrain {
+        let levels: Vec<bool> = data.iter().flat_map(|&one| [!one, one]).collect();
+        let mut runs: Vec<(bool, usize)> = Vec::new();
+        for level in levels {
+            match runs.last_mut() {
+                Some((l, n)) if *l == level => *n += 1,
+                _ => runs.push((level, 1)),
+            }
+        }
+        assert!(runs[0].0, "a packet starts with a mark");
+        let mut pulses: Vec<(f64, f64)> = runs
+            .chunks(2)
+            .map(|pair| (pair[0].1 as f64 * 52.0, pair.get(1).map_or(20_000.0, |s| s.1 as f64 * 52.0)))
+            .collect();
+        pulses.last_mut().unwrap().1 = 20_000.0;
+        PulseTrain { modulation: Modulation::Fsk, pulses }
+    }
+
+    fn ford_frame() -> Vec<bool> {
+        let mut bytes = [0x12, 0x34, 0x56, 0x78, 0x50, 25 + 56, 0x00, 0];
+        bytes[7] = bytes[..7].iter().fold(0u8, |acc, &x| acc.wrapping_add(x));
+        bytes.iter().flat_map(|&b| bits(b as u64, 8)).collect()
+    }
+
+    #[test]
+    fn ford_tpms_decodes_a_frame_of_exactly_64_bits() {
+        let fields = FordTpms.decode(&manchester(&ford_frame())).unwrap();
+        assert!(matches!(field(&fields, "id"), Value::Str(id) if id == "12345678"));
+        assert_eq!(float(field(&fields, "pressure_PSI")), 20.0);
+        assert_eq!(int(field(&fields, "temperature_C")), 25);
+    }
+
+    #[test]
+    fn ford_tpms_finds_the_frame_after_a_preamble() {
+        let mut data = vec![false, true, false, true, false, true, false, true];
+        data.extend(ford_frame());
+        let fields = FordTpms.decode(&manchester(&data)).unwrap();
+        assert!(matches!(field(&fields, "id"), Value::Str(id) if id == "12345678"));
+
+        let mut corrupt = ford_frame();
+        corrupt[60] = !corrupt[60];
+        assert!(FordTpms.decode(&manchester(&corrupt)).is_none());
+    }
+}
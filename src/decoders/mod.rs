@@ -0,0 +1,60 @@
+//! Protocol decoders fed from the IQ sample stream.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod ais;
+mod bch;
+pub mod cw;
+pub mod dtmf;
+#[cfg(all(unix, feature = "plugins"))]
+pub mod dylib;
+mod flex;
+pub mod ft8;
+pub mod ism;
+pub mod navtex;
+pub mod pager;
+pub mod plugin;
+mod pocsag;
+pub mod psk;
+pub mod rtty;
+pub mod same;
+pub mod wspr;
+
+/// Read `len` bits starting at `start` from an MSB-first bit slice
+pub fn bits_to_u32(bits: &[bool], start: usize, len: usize) -> u32 {
+    bits[start..start + len]
+        .iter()
+        .fold(0, |acc, &b| (acc << 1) | b as u32)
+}
+
+/// Same as [`bits_to_u32`] but sign-extends the field
+pub fn bits_to_i32(bits: &[bool], start: usize, len: usize) -> i32 {
+    let raw = bits_to_u32(bits, start, len);
+    ((raw << (32 - len)) as i32) >> (32 - len)
+}
+
+/// Format a timestamp as `HH:MM:SS` UTC
+pub fn format_utc_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("{:02}:{:02}:{:02}", (secs / 3600) % 24, (secs / 60) % 60, secs % 60)
+}
+
+/// Split seconds since the epoch into `YYYY-MM-DD` and `HH:MM:SS` UTC strings
+pub fn utc_date_time(secs: u64) -> (String, String) {
+    // Civil-from-days, valid for any date after 1970
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let time = format_utc_time(UNIX_EPOCH + std::time::Duration::from_secs(secs));
+    (format!("{:04}-{:02}-{:02}", year, month, day), time)
+}
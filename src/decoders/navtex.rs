@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex32;
+
+use super::utc_date_time;
+use crate::dsp::{FskBit, FskConfig, FskDemod, SyncMatch};
+
+/// International, national and HF NAVTEX carriers
+pub const NAVTEX_FREQUENCIES: [f64; 3] = [518e3, 490e3, 4209.5e3];
+
+/// SITOR-B: 100 Bd, 170 Hz shift, B (mark) above the carrier is a one
+const BAUD: f64 = 100.0;
+const DEVIATION: f64 = 85.0;
+const MAX_TEXT: usize = 8000;
+const MAX_MESSAGES: usize = 200;
+/// Longest message kept waiting for its `NNNN`, well over the 1000 or so
+/// characters stations send, so one lost to noise is given up
+const MAX_MESSAGE_LEN: usize = 4000;
+/// Uncorrectable characters tolerated before phasing is searched again
+const MAX_ERRORS: u32 = 8;
+
+/// Phasing signal 1, sent in the DX position
+const ALPHA: u8 = 0x0F;
+const BETA: u8 = 0x33;
+/// Phasing signal 2, sent in the RX position
+const REP: u8 = 0x66;
+/// REP ALPHA REP ALPHA, the next character is an RX repetition
+const PHASING: u64 = (REP as u64) << 21 | (ALPHA as u64) << 14 | (REP as u64) << 7 | ALPHA as u64;
+const CHAR32: u8 = 0x6A;
+const LTRS: u8 = 0x5A;
+const FIGS: u8 = 0x36;
+
+/// CCIR 476 codes with their letters and figures meanings
+const CODES: [(u8, char, char); 29] = [
+    (0x17, 'J', '\''), (0x1B, 'F', '!'), (0x1D, 'C', ':'), (0x1E, 'K', '('), (0x27, 'W', '2'),
+    (0x2B, 'Y', '6'), (0x2D, 'P', '0'), (0x2E, 'Q', '1'), (0x35, 'G', '&'), (0x39, 'M', '.'),
+    (0x3A, 'X', '/'), (0x3C, 'V', ';'), (0x47, 'A', '-'), (0x4B, 'S', '\x07'), (0x4D, 'I', '8'),
+    (0x4E, 'U', '7'), (0x53, 'D', '$'), (0x55, 'R', '4'), (0x56, 'E', '3'), (0x59, 'N', ','),
+    (0x5C, ' ', ' '), (0x63, 'Z', '"'), (0x65, 'L', ')'), (0x69, 'H', '#'), (0x6C, '\n', '\n'),
+    (0x71, 'O', '9'), (0x72, 'B', '?'), (0x74, 'T', '5'), (0x78, '\r', '\r'),
+];
+
+/// Subject indicators (B2 character of the `ZCZC` header)
+const SUBJECTS: [(char, &str); 14] = [
+    ('A', "Navigational warning"),
+    ('B', "Meteorological warning"),
+    ('C', "Ice report"),
+    ('D', "Search and rescue / piracy"),
+    ('E', "Meteorological forecast"),
+    ('F', "Pilot service"),
+    ('G', "AIS"),
+    ('H', "LORAN"),
+    ('J', "SATNAV"),
+    ('K', "Other navaid"),
+    ('L', "Navigational warning"),
+    ('T', "Test"),
+    ('X', "Special service"),
+    ('Z', "No messages on hand"),
+];
+
+/// A complete `ZCZC` ... `NNNN` message
+#[derive(Clone, Debug)]
+pub struct NavtexMessage {
+    pub received: SystemTime,
+    /// B1: transmitting station identity
+    pub station: char,
+    /// B2: subject indicator
+    pub subject: char,
+    /// B3B4: serial number
+    pub number: String,
+    pub text: String,
+}
+
+impl NavtexMessage {
+    pub fn subject_name(&self) -> &'static str {
+        SUBJECTS
+            .iter()
+            .find(|(c, _)| *c == self.subject)
+            .map_or("Unknown subject", |(_, name)| name)
+    }
+}
+
+/// Every valid CCIR 476 character has four ones in seven bits
+fn is_valid(code: u8) -> bool {
+    code.count_ones() == 4 && code < 0x80
+}
+
+enum Sync {
+    /// Looking for alternating phasing signals
+    Phasing,
+    /// Character aligned, `rx_slot` tells which half of the pair comes next
+    Locked { bits: u8, rx_slot: bool, inverted: bool, errors: u32 },
+}
+
+/// SITOR-B (NAVTEX) receiver with time-diversity FEC
+pub struct NavtexDecoder {
+    tuned: (f64, f64),
+    /// NAVTEX carrier inside the current passband
+    pub carrier: Option<f64>,
+    demod: Option<FskDemod>,
+    shift: u32,
+    sync: Sync,
+    /// DX characters waiting for their RX repetition, which follows five slots later
+    dx: VecDeque<u8>,
+    figures: bool,
+    /// Text since the last `ZCZC`, `None` outside a message
+    message: Option<String>,
+    pub text: String,
+    pub messages: Vec<NavtexMessage>,
+    /// Characters where neither copy was valid
+    pub errors: u32,
+    /// Smoothed discriminator level of the upper and lower tone
+    tone_levels: (f32, f32),
+    log: Option<File>,
+}
+
+impl NavtexDecoder {
+    pub fn new() -> Self {
+        Self {
+            tuned: (0.0, 0.0),
+            carrier: None,
+            demod: None,
+            shift: 0,
+            sync: Sync::Phasing,
+            dx: VecDeque::new(),
+            figures: false,
+            message: None,
+            text: String::new(),
+            messages: Vec::new(),
+            errors: 0,
+            tone_levels: (1.0, -1.0),
+            log: None,
+        }
+    }
+
+    /// Append every complete message to `path`, or stop logging with `None`
+    pub fn set_log(&mut self, path: Option<&str>) -> io::Result<()> {
+        self.log = match path {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+
+    /// Offset of the tone pair from the carrier in Hz, the tones read ±1 when centred
+    pub fn offset_hz(&self) -> f32 {
+        (self.tone_levels.0 + self.tone_levels.1) / 2.0 * DEVIATION as f32
+    }
+
+    pub fn is_locked(&self) -> bool {
+        matches!(self.sync, Sync::Locked { .. })
+    }
+
+    fn retune(&mut self, center_freq: f64, sample_rate: f64) {
+        self.tuned = (center_freq, sample_rate);
+        self.sync = Sync::Phasing;
+        let config = FskConfig {
+            sync_word: Some((PHASING, 28)),
+            ..FskConfig::new(DEVIATION, BAUD)
+        };
+        self.carrier = NAVTEX_FREQUENCIES
+            .iter()
+            .copied()
+            .find(|f| (f - center_freq).abs() < sample_rate / 2.0 - config.bandwidth());
+        self.demod = self.carrier.map(|carrier| {
+            let config = FskConfig {
+                offset_hz: carrier - center_freq,
+                ..config
+            };
+            FskDemod::new(config, sample_rate)
+        });
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        if self.tuned != (center_freq, sample_rate) {
+            self.retune(center_freq, sample_rate);
+        }
+        let Some(demod) = &mut self.demod else {
+            return;
+        };
+
+        let mut bits = Vec::new();
+        demod.process(samples, &mut bits);
+        for bit in bits {
+            self.push_bit(bit);
+        }
+    }
+
+    /// Characters are sent MSB first
+    fn push_bit(&mut self, bit: FskBit) {
+        let level = if bit.value { &mut self.tone_levels.0 } else { &mut self.tone_levels.1 };
+        *level += 0.02 * (bit.soft - *level);
+        match &mut self.sync {
+            Sync::Phasing => {
+                if let Some(polarity) = bit.sync {
+                    self.sync = Sync::Locked {
+                        bits: 0,
+                        rx_slot: true,
+                        inverted: polarity == SyncMatch::Inverted,
+                        errors: 0,
+                    };
+                    self.dx.clear();
+                    self.dx.extend([ALPHA; 3]);
+                }
+            }
+            Sync::Locked { bits, rx_slot, inverted, .. } => {
+                self.shift = (self.shift << 1) | (bit.value != *inverted) as u32;
+                *bits += 1;
+                if *bits < 7 {
+                    return;
+                }
+                *bits = 0;
+                let is_rx = *rx_slot;
+                *rx_slot = !*rx_slot;
+                let code = (self.shift & 0x7F) as u8;
+                if !is_rx {
+                    self.dx.push_back(code);
+                } else if self.dx.len() >= 3 {
+                    // RX at slot 2n+5 repeats DX at slot 2n
+                    let dx = self.dx.pop_front().unwrap_or(CHAR32);
+                    self.resolve(dx, code);
+                }
+            }
+        }
+    }
+
+    /// Pick whichever copy of the character arrived intact
+    fn resolve(&mut self, dx: u8, rx: u8) {
+        let code = if is_valid(dx) {
+            dx
+        } else if is_valid(rx) {
+            rx
+        } else {
+            self.errors += 1;
+            if let Sync::Locked { errors, .. } = &mut self.sync {
+                *errors += 1;
+                if *errors > MAX_ERRORS {
+                    self.sync = Sync::Phasing;
+                    self.dx.clear();
+                    return;
+                }
+            }
+            self.push_char('_');
+            return;
+        };
+        if let Sync::Locked { errors, .. } = &mut self.sync {
+            *errors = errors.saturating_sub(1);
+        }
+
+        match code {
+            ALPHA | BETA | REP | CHAR32 => {}
+            LTRS => self.figures = false,
+            FIGS => self.figures = true,
+            _ => {
+                if let Some(&(_, letter, figure)) = CODES.iter().find(|(c, _, _)| *c == code) {
+                    self.push_char(if self.figures { figure } else { letter });
+                }
+            }
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        if c == '\r' || c == '\x07' {
+            return;
+        }
+        self.text.push(c);
+        if self.text.len() > MAX_TEXT {
+            let cut = self.text.len() - MAX_TEXT;
+            self.text.drain(..cut);
+        }
+
+        if let Some(message) = &mut self.message {
+            message.push(c);
+            if message.len() > MAX_MESSAGE_LEN {
+                self.message = None;
+            } else if message.ends_with("NNNN") {
+                let mut message = self.message.take().unwrap_or_default();
+                message.truncate(message.len() - 4);
+                self.finish_message(&message);
+            }
+        } else if self.text.ends_with("ZCZC") {
+            self.message = Some(String::new());
+        }
+    }
+
+    fn finish_message(&mut self, body: &str) {
+        // Header is ` B1B2B3B4` directly after ZCZC
+        let body = body.trim_start_matches(' ');
+        let header: Vec<char> = body.chars().take(4).collect();
+        let [station, subject, n1, n2] = header[..] else {
+            return;
+        };
+        if !station.is_ascii_uppercase() || !subject.is_ascii_uppercase() {
+            return;
+        }
+        let message = NavtexMessage {
+            received: SystemTime::now(),
+            station,
+            subject,
+            number: format!("{}{}", n1, n2),
+            text: body[4..].trim().to_string(),
+        };
+
+        if let Some(log) = &mut self.log {
+            let secs = message.received.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let (date, time) = utc_date_time(secs);
+            if writeln!(
+                log,
+                "{} {} UTC  {}{}{}  {}\n{}\n",
+                date,
+                time,
+                message.station,
+                message.subject,
+                message.number,
+                message.subject_name(),
+                message.text
+            )
+            .is_err()
+            {
+                self.log = None;
+            }
+        }
+        self.messages.push(message);
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+}
+
+impl Default for NavtexDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(decoder: &mut NavtexDecoder, text: &str) {
+        text.chars().for_each(|c| decoder.push_char(c));
+    }
+
+    #[test]
+    fn gives_up_a_message_that_never_ends() {
+        let mut decoder = NavtexDecoder::new();
+        receive(&mut decoder, "ZCZC ");
+        receive(&mut decoder, &"_".repeat(MAX_MESSAGE_LEN * 3));
+        assert!(decoder.message.is_none());
+        assert!(decoder.text.len() <= MAX_TEXT);
+
+        receive(&mut decoder, "ZCZC GA12\nGALE WARNING\nNNNN");
+        assert_eq!(decoder.messages.len(), 1);
+        assert_eq!(decoder.messages[0].text, "GALE WARNING");
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use num_complex::Complex32;
+
+use super::flex::Flex;
+use super::pocsag::Pocsag;
+use crate::dsp::{ClockRecovery, FskConfig, FskDemod};
+
+/// Deviation of POCSAG and two-level FLEX
+const DEVIATION: f64 = 4.5e3;
+/// Fastest rate, which the channel is sized for
+const MAX_BAUD: f64 = 2400.0;
+const POCSAG_RATES: [u32; 3] = [512, 1200, 2400];
+const FLEX_BAUD: f64 = 1600.0;
+const MAX_MESSAGES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Pocsag(u32),
+    Flex,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Pocsag(baud) => write!(f, "POCSAG{}", baud),
+            Protocol::Flex => write!(f, "FLEX"),
+        }
+    }
+}
+
+/// A decoded page
+#[derive(Clone, Debug)]
+pub struct PagerMessage {
+    pub protocol: Protocol,
+    pub address: u32,
+    pub function: u8,
+    pub text: String,
+    pub received: SystemTime,
+}
+
+impl PagerMessage {
+    pub fn new(protocol: Protocol, address: u32, function: u8, text: String) -> Self {
+        Self {
+            protocol,
+            address,
+            function,
+            text,
+            received: SystemTime::now(),
+        }
+    }
+}
+
+/// FSK pager receiver on the tuned centre frequency, running every supported rate in parallel
+pub struct PagerDecoder {
+    /// One channel for every rate, each with a clock of its own
+    demod: FskDemod,
+    pocsag: Vec<(ClockRecovery, Pocsag)>,
+    flex: (ClockRecovery, Flex),
+    sample_rate: f64,
+    pub messages: Vec<PagerMessage>,
+}
+
+impl PagerDecoder {
+    pub fn new() -> Self {
+        let mut decoder = Self {
+            demod: FskDemod::new(FskConfig::new(DEVIATION, MAX_BAUD), 1e6),
+            pocsag: Vec::new(),
+            flex: (ClockRecovery::new(1.0, 1.0), Flex::new()),
+            sample_rate: 0.0,
+            messages: Vec::new(),
+        };
+        decoder.retune(1e6);
+        decoder
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        self.demod = FskDemod::new(FskConfig::new(DEVIATION, MAX_BAUD), sample_rate);
+        let rate = self.demod.rate();
+        self.sample_rate = sample_rate;
+        self.pocsag = POCSAG_RATES
+            .iter()
+            .map(|&baud| (ClockRecovery::new(rate, baud as f64), Pocsag::new(baud)))
+            .collect();
+        self.flex = (ClockRecovery::new(rate, FLEX_BAUD), Flex::new());
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+
+        let mut decoded = Vec::new();
+        for &s in samples {
+            let Some(soft) = self.demod.push_soft(s) else {
+                continue;
+            };
+
+            for (clock, pocsag) in &mut self.pocsag {
+                if let Some(bit) = clock.push(soft) {
+                    pocsag.push_bit(bit, &mut decoded);
+                }
+            }
+            if let Some(bit) = self.flex.0.push(soft) {
+                self.flex.1.push_bit(bit, &mut decoded);
+            }
+        }
+
+        self.messages.extend(decoded);
+        if self.messages.len() > MAX_MESSAGES {
+            let excess = self.messages.len() - MAX_MESSAGES;
+            self.messages.drain(..excess);
+        }
+    }
+}
+
+impl Default for PagerDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A POCSAG codeword of 21 bits of data with its check bits and parity
+    fn codeword(data: u32) -> u32 {
+        let mut rem = data << 10;
+        for bit in (10..31).rev() {
+            if rem & (1 << bit) != 0 {
+                rem ^= 0x769 << (bit - 10);
+            }
+        }
+        let word = (data << 11) | (rem << 1);
+        word | (word.count_ones() % 2)
+    }
+
+    /// Bits of a POCSAG alphanumeric page to `address`
+    fn page(address: u32, text: &str) -> Vec<bool> {
+        let mut words = vec![0x7A89_C197; 16];
+        let frame = (address & 7) as usize;
+        words[2 * frame] = codeword(((address >> 3) << 2) | 3);
+        let text: Vec<bool> = text.bytes().flat_map(|c| (0..7).map(move |i| c >> i & 1 == 1)).collect();
+        for (i, chunk) in text.chunks(20).enumerate() {
+            let data = chunk.iter().enumerate().fold(0, |acc, (j, &b)| acc | (b as u32) << (19 - j));
+            words[2 * frame + 1 + i] = codeword(1 << 20 | data);
+        }
+        let mut bits: Vec<bool> = (0..576).map(|i| i % 2 == 0).collect();
+        for word in std::iter::once(0x7CD2_15D8).chain(words) {
+            bits.extend((0..32).rev().map(|i| word >> i & 1 == 1));
+        }
+        bits
+    }
+
+    #[test]
+    fn decodes_a_pocsag_page() {
+        let (rate, baud, deviation) = (240e3, 1200.0, 4.5e3);
+        let mut iq = Vec::new();
+        let mut phase = 0.0f64;
+        for (i, &bit) in page(1_234_562, "Meet at 5").iter().chain(&[false; 64]).enumerate() {
+            // A one is the lower tone
+            let freq = if bit { -deviation } else { deviation };
+            while (iq.len() as f64) < (i + 1) as f64 * rate / baud {
+                phase += 2.0 * std::f64::consts::PI * freq / rate;
+                iq.push(Complex32::from_polar(0.5, phase as f32));
+            }
+        }
+        let mut decoder = PagerDecoder::new();
+        for block in iq.chunks(4096) {
+            decoder.process(block, rate);
+        }
+        let pages: Vec<_> = decoder.messages.iter().map(|m| (m.protocol, m.address, m.text.as_str())).collect();
+        assert_eq!(pages, [(Protocol::Pocsag(1200), 1_234_562, "Meet at 5")]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Decoders added from outside the crate. A [`Decoder`] gets the same IQ
+//! and decoder audio as the built-in decoders and hands back text
+//! messages, which the TUI lists in a panel of its own.
+//!
+//! Programs embedding the engine register decoders with
+//! [`Registry::register`]. The TUI also loads them from shared libraries
+//! named under `[plugins]` in the config file, see [`super::dylib`].
+
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+use num_complex::Complex32;
+
+/// Messages kept per decoder
+const MAX_MESSAGES: usize = 200;
+
+/// One block of receiver output
+pub struct Input<'a> {
+    pub iq: &'a [Complex32],
+    pub center_freq: f64,
+    pub sample_rate: f64,
+    /// Audio of the VFOs routed to the decoders
+    pub audio: &'a [f32],
+    pub audio_rate: f64,
+}
+
+pub trait Decoder {
+    /// Short name, the title of the decoder's panel
+    fn name(&self) -> &str;
+
+    fn process(&mut self, input: &Input);
+
+    /// Messages decoded since the last call, oldest first
+    fn take_messages(&mut self) -> Vec<String>;
+
+    /// A line on the decoder's state for its panel, such as whether it is locked
+    fn status(&self) -> String {
+        String::new()
+    }
+}
+
+pub struct Message {
+    pub received: SystemTime,
+    pub text: String,
+}
+
+/// A registered decoder and what it has decoded
+pub struct Plugin {
+    pub decoder: Box<dyn Decoder>,
+    /// Newest last
+    pub messages: VecDeque<Message>,
+    /// Messages since registration, including those no longer kept
+    pub total: u64,
+}
+
+#[derive(Default)]
+pub struct Registry {
+    pub plugins: Vec<Plugin>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, decoder: Box<dyn Decoder>) {
+        self.plugins.push(Plugin {
+            decoder,
+            messages: VecDeque::new(),
+            total: 0,
+        });
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.plugins.iter().any(|plugin| plugin.decoder.name() == name)
+    }
+
+    /// Feed every decoder and collect its messages
+    pub fn process(&mut self, input: &Input) {
+        for plugin in &mut self.plugins {
+            plugin.decoder.process(input);
+            for text in plugin.decoder.take_messages() {
+                if plugin.messages.len() == MAX_MESSAGES {
+                    plugin.messages.pop_front();
+                }
+                plugin.messages.push_back(Message { received: SystemTime::now(), text });
+                plugin.total += 1;
+            }
+        }
+    }
+}
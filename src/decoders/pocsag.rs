@@ -0,0 +1,132 @@
+use super::bch::bch3121_correct;
+use super::pager::{PagerMessage, Protocol};
+
+const SYNC: u32 = 0x7CD2_15D8;
+const IDLE: u32 = 0x7A89_C197;
+const NUMERIC_CHARS: &[u8; 16] = b"0123456789*U -)(";
+
+/// Message being assembled from an address codeword and its data codewords
+struct Pending {
+    address: u32,
+    function: u8,
+    data: Vec<bool>,
+}
+
+/// POCSAG batch synchroniser and message assembler for a single baud rate
+pub struct Pocsag {
+    pub baud: u32,
+    shift: u32,
+    synced: bool,
+    inverted: bool,
+    bit_count: u32,
+    word_index: usize,
+    pending: Option<Pending>,
+}
+
+impl Pocsag {
+    pub fn new(baud: u32) -> Self {
+        Self {
+            baud,
+            shift: 0,
+            synced: false,
+            inverted: false,
+            bit_count: 0,
+            word_index: 0,
+            pending: None,
+        }
+    }
+
+    pub fn push_bit(&mut self, bit: bool, out: &mut Vec<PagerMessage>) {
+        self.shift = (self.shift << 1) | bit as u32;
+
+        if !self.synced {
+            if self.shift == SYNC || self.shift == !SYNC {
+                self.synced = true;
+                self.inverted = self.shift == !SYNC;
+                self.bit_count = 0;
+                self.word_index = 0;
+            }
+            return;
+        }
+
+        self.bit_count += 1;
+        if self.bit_count < 32 {
+            return;
+        }
+        self.bit_count = 0;
+
+        let word = if self.inverted { !self.shift } else { self.shift };
+
+        // Every 16 codewords a new batch starts with a sync word
+        if self.word_index == 16 {
+            if word == SYNC {
+                self.word_index = 0;
+            } else {
+                self.synced = false;
+                self.flush(out);
+            }
+            return;
+        }
+
+        let frame = self.word_index / 2;
+        self.word_index += 1;
+
+        let Some(word) = bch3121_correct(word) else {
+            self.flush(out);
+            return;
+        };
+
+        if word == IDLE {
+            self.flush(out);
+        } else if word & 0x8000_0000 == 0 {
+            self.flush(out);
+            self.pending = Some(Pending {
+                address: (((word >> 13) & 0x3FFFF) << 3) | frame as u32,
+                function: ((word >> 11) & 0x3) as u8,
+                data: Vec::new(),
+            });
+        } else if let Some(pending) = &mut self.pending {
+            pending.data.extend((0..20).map(|i| word & (1 << (30 - i)) != 0));
+        }
+    }
+
+    fn flush(&mut self, out: &mut Vec<PagerMessage>) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        let text = if pending.data.is_empty() {
+            "<tone only>".to_string()
+        } else if pending.function == 0 {
+            decode_numeric(&pending.data)
+        } else {
+            decode_alpha(&pending.data)
+        };
+
+        out.push(PagerMessage::new(
+            Protocol::Pocsag(self.baud),
+            pending.address,
+            pending.function,
+            text,
+        ));
+    }
+}
+
+/// 7-bit ASCII, each character sent LSB first
+fn decode_alpha(bits: &[bool]) -> String {
+    bits.chunks_exact(7)
+        .map(|c| c.iter().enumerate().fold(0u8, |acc, (i, &b)| acc | ((b as u8) << i)))
+        .filter(|&c| c != 0 && c != 0x04)
+        .map(|c| if c.is_ascii_graphic() || c == b' ' { c as char } else { '.' })
+        .collect()
+}
+
+/// 4-bit BCD digits, each sent LSB first
+fn decode_numeric(bits: &[bool]) -> String {
+    bits.chunks_exact(4)
+        .map(|c| c.iter().enumerate().fold(0usize, |acc, (i, &b)| acc | ((b as usize) << i)))
+        .map(|d| NUMERIC_CHARS[d] as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+
+use num_complex::Complex32;
+
+use crate::dsp::filter::lowpass_taps;
+use crate::dsp::{DecimatingFir, Nco};
+
+/// Samples per symbol after decimation, also the number of timing bins
+const TIMING_BINS: usize = 16;
+const AFC_RANGE_HZ: f64 = 50.0;
+const AFC_GAIN: f64 = 0.05;
+const MAX_TEXT: usize = 8000;
+const SCOPE_POINTS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PskMode {
+    Psk31,
+    Psk63,
+}
+
+impl PskMode {
+    pub fn baud(self) -> f64 {
+        match self {
+            PskMode::Psk31 => 31.25,
+            PskMode::Psk63 => 62.5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PskMode::Psk31 => "BPSK31",
+            PskMode::Psk63 => "BPSK63",
+        }
+    }
+}
+
+/// Varicode for ASCII 0..127, each code starts and ends with a one
+const VARICODE: [u16; 128] = [
+    0b1010101011, 0b1011011011, 0b1011101101, 0b1101110111, 0b1011101011, 0b1101011111,
+    0b1011101111, 0b1011111101, 0b1011111111, 0b11101111, 0b11101, 0b1101101111,
+    0b1011011101, 0b11111, 0b1101110101, 0b1110101011, 0b1011110111, 0b1011110101,
+    0b1110101101, 0b1110101111, 0b1101011011, 0b1101101011, 0b1101101101, 0b1101010111,
+    0b1101111011, 0b1101111101, 0b1110110111, 0b1101010101, 0b1101011101, 0b1110111011,
+    0b1011111011, 0b1101111111, 0b1, 0b111111111, 0b101011111, 0b111110101,
+    0b111011011, 0b1011010101, 0b1010111011, 0b101111111, 0b11111011, 0b11110111,
+    0b101101111, 0b111011111, 0b1110101, 0b110101, 0b1010111, 0b110101111,
+    0b10110111, 0b10111101, 0b11101101, 0b11111111, 0b101110111, 0b101011011,
+    0b101101011, 0b110101101, 0b110101011, 0b110110111, 0b11110101, 0b110111101,
+    0b111101101, 0b1010101, 0b111010111, 0b1010101111, 0b1010111101, 0b1111101,
+    0b11101011, 0b10101101, 0b10110101, 0b1110111, 0b11011011, 0b11111101,
+    0b101010101, 0b1111111, 0b111111101, 0b101111101, 0b11010111, 0b10111011,
+    0b11011101, 0b10101011, 0b11010101, 0b111011101, 0b10101111, 0b1101111,
+    0b1101101, 0b101010111, 0b110110101, 0b101011101, 0b101110101, 0b101111011,
+    0b1010101101, 0b111110111, 0b111101111, 0b111111011, 0b1010111111, 0b101101101,
+    0b1011011111, 0b1011, 0b1011111, 0b101111, 0b101101, 0b11,
+    0b111101, 0b1011011, 0b101011, 0b1101, 0b111101011, 0b10111111,
+    0b11011, 0b111011, 0b1111, 0b111, 0b111111, 0b110111111,
+    0b10101, 0b10111, 0b101, 0b110111, 0b1111011, 0b1101011,
+    0b11011111, 0b1011101, 0b111010101, 0b1010110111, 0b110111011, 0b1010110101,
+    0b1011010111, 0b1110110101,
+];
+
+/// Differential BPSK receiver with varicode decoding and AFC around the tuned frequency
+pub struct PskDecoder {
+    pub mode: PskMode,
+    sample_rate: f64,
+    fir: DecimatingFir,
+    matched: DecimatingFir,
+    nco: Nco,
+    rate: f64,
+    /// Frequency correction applied by the AFC loop
+    pub afc_hz: f64,
+    symbol_phase: f64,
+    envelope: [f32; TIMING_BINS],
+    latest: [Complex32; TIMING_BINS],
+    last_symbol: Complex32,
+    code: u16,
+    zeros: u32,
+    pub text: String,
+    /// Recent differential phase vectors for the phase-scope display
+    pub scope: VecDeque<Complex32>,
+}
+
+impl PskDecoder {
+    pub fn new() -> Self {
+        let mut decoder = Self {
+            mode: PskMode::Psk31,
+            sample_rate: 0.0,
+            fir: DecimatingFir::new(vec![1.0], 1),
+            matched: DecimatingFir::new(vec![1.0], 1),
+            nco: Nco::new(0.0, 1.0),
+            rate: 1.0,
+            afc_hz: 0.0,
+            symbol_phase: 0.0,
+            envelope: [0.0; TIMING_BINS],
+            latest: [Complex32::default(); TIMING_BINS],
+            last_symbol: Complex32::default(),
+            code: 0,
+            zeros: 0,
+            text: String::new(),
+            scope: VecDeque::with_capacity(SCOPE_POINTS),
+        };
+        decoder.retune(1e6);
+        decoder
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        let baud = self.mode.baud();
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, baud * TIMING_BINS as f64, baud * 4.0 + 2.0 * AFC_RANGE_HZ);
+        self.fir = fir;
+        self.rate = rate;
+        self.sample_rate = sample_rate;
+        self.matched = DecimatingFir::new(lowpass_taps((baud / rate) as f32, TIMING_BINS * 2 + 1), 1);
+        self.afc_hz = 0.0;
+        self.nco = Nco::new(0.0, rate);
+    }
+
+    pub fn set_mode(&mut self, mode: PskMode) {
+        self.mode = mode;
+        self.retune(self.sample_rate);
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+
+        let step = self.mode.baud() / self.rate;
+        for &s in samples {
+            let Some(channel) = self.fir.push(s) else {
+                continue;
+            };
+            let Some(x) = self.matched.push(self.nco.mix(channel)) else {
+                continue;
+            };
+
+            // Track the envelope in each timing bin, symbols are sampled at the peak
+            let bin = ((self.symbol_phase * TIMING_BINS as f64) as usize).min(TIMING_BINS - 1);
+            self.envelope[bin] = 0.9 * self.envelope[bin] + 0.1 * x.norm();
+            self.latest[bin] = x;
+
+            self.symbol_phase += step;
+            if self.symbol_phase >= 1.0 {
+                self.symbol_phase -= 1.0;
+                let best = (0..TIMING_BINS)
+                    .max_by(|&a, &b| self.envelope[a].total_cmp(&self.envelope[b]))
+                    .unwrap_or(0);
+                self.symbol(self.latest[best]);
+            }
+        }
+    }
+
+    fn symbol(&mut self, symbol: Complex32) {
+        let diff = symbol * self.last_symbol.conj();
+        self.last_symbol = symbol;
+
+        let norm = diff.norm();
+        if norm > 0.0 {
+            if self.scope.len() == SCOPE_POINTS {
+                self.scope.pop_front();
+            }
+            self.scope.push_back(diff / norm);
+
+            // Squaring strips the BPSK modulation, leaving twice the phase drift per symbol
+            let drift = (diff * diff).arg() as f64 / 2.0;
+            let error_hz = drift / (2.0 * std::f64::consts::PI) * self.mode.baud();
+            self.afc_hz = (self.afc_hz + AFC_GAIN * error_hz).clamp(-AFC_RANGE_HZ, AFC_RANGE_HZ);
+            self.nco.set_frequency(self.afc_hz, self.rate);
+        }
+
+        // No phase reversal is a one
+        self.push_bit(diff.re > 0.0);
+    }
+
+    fn push_bit(&mut self, bit: bool) {
+        if bit {
+            self.zeros = 0;
+            self.code = (self.code << 1) | 1;
+            // Longer than any valid code: resynchronise on the next gap
+            if self.code > 0x3FF {
+                self.code = 0;
+            }
+            return;
+        }
+
+        self.zeros += 1;
+        if self.zeros == 1 {
+            self.code <<= 1;
+            return;
+        }
+
+        // Two zeros end a character; undo the first zero's shift
+        if self.zeros == 2 && self.code != 0 {
+            let code = self.code >> 1;
+            if let Some(ch) = VARICODE.iter().position(|&c| c == code) {
+                let ch = ch as u8 as char;
+                if ch == '\n' || ch == ' ' || ch.is_ascii_graphic() {
+                    self.text.push(ch);
+                }
+            }
+            if self.text.len() > MAX_TEXT {
+                let cut = self.text.len() - MAX_TEXT;
+                self.text.drain(..cut);
+            }
+        }
+        self.code = 0;
+    }
+}
+
+impl Default for PskDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+
+use num_complex::Complex32;
+
+use crate::dsp::filter::lowpass_taps;
+use crate::dsp::{DecimatingFir, Nco};
+
+pub const SHIFTS: [f64; 4] = [85.0, 170.0, 425.0, 850.0];
+pub const BAUD_RATES: [f64; 4] = [45.45, 50.0, 75.0, 100.0];
+
+const CHANNEL_RATE: f64 = 4e3;
+const MAX_TEXT: usize = 8000;
+const SCOPE_POINTS: usize = 256;
+
+const LETTERS: [char; 32] = [
+    '\0', 'E', '\n', 'A', ' ', 'S', 'I', 'U', '\r', 'D', 'R', 'J', 'N', 'F', 'C', 'K', 'T', 'Z',
+    'L', 'W', 'H', 'Y', 'P', 'Q', 'O', 'B', 'G', '\0', 'M', 'X', 'V', '\0',
+];
+const FIGURES: [char; 32] = [
+    '\0', '3', '\n', '-', ' ', '\x07', '8', '7', '\r', '$', '4', '\'', ',', '!', ':', '(', '5',
+    '"', ')', '2', '#', '6', '0', '1', '9', '?', '&', '\0', '.', '/', ';', '\0',
+];
+const FIGS: u8 = 0x1B;
+const LTRS: u8 = 0x1F;
+
+/// Per-tone mixer and matched lowpass
+struct ToneFilter {
+    nco: Nco,
+    fir: DecimatingFir,
+}
+
+impl ToneFilter {
+    fn new(offset_hz: f64, rate: f64, baud: f64) -> Self {
+        let cutoff = (baud * 0.75 / rate) as f32;
+        Self {
+            nco: Nco::new(offset_hz, rate),
+            fir: DecimatingFir::new(lowpass_taps(cutoff, 127), 1),
+        }
+    }
+
+    /// Returns the tone envelope and the filtered tone re-mixed to its original frequency
+    fn push(&mut self, sample: Complex32) -> (f32, f32) {
+        let lo = self.nco.next_lo();
+        let filtered = self.fir.push(sample * lo).unwrap_or_default();
+        (filtered.norm(), (filtered * lo.conj()).re)
+    }
+}
+
+/// Asynchronous 5-bit start/stop framing state
+enum Uart {
+    Idle,
+    Receiving { countdown: f64, bit: u8, value: u8 },
+}
+
+/// Baudot RTTY receiver centred on the tuned frequency (mark above, space below)
+pub struct RttyDecoder {
+    pub shift: f64,
+    pub baud: f64,
+    sample_rate: f64,
+    fir: DecimatingFir,
+    rate: f64,
+    mark: ToneFilter,
+    space: ToneFilter,
+    last_bit: bool,
+    uart: Uart,
+    figures: bool,
+    pub text: String,
+    /// Mark/space filter outputs for the crossed-bananas tuning display
+    pub scope: VecDeque<(f32, f32)>,
+}
+
+impl RttyDecoder {
+    pub fn new() -> Self {
+        let mut decoder = Self {
+            shift: 170.0,
+            baud: 45.45,
+            sample_rate: 0.0,
+            fir: DecimatingFir::new(vec![1.0], 1),
+            rate: CHANNEL_RATE,
+            mark: ToneFilter::new(0.0, CHANNEL_RATE, 45.45),
+            space: ToneFilter::new(0.0, CHANNEL_RATE, 45.45),
+            last_bit: true,
+            uart: Uart::Idle,
+            figures: false,
+            text: String::new(),
+            scope: VecDeque::with_capacity(SCOPE_POINTS),
+        };
+        decoder.retune(1e6);
+        decoder
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, CHANNEL_RATE, self.shift + 2.0 * self.baud);
+        self.fir = fir;
+        self.rate = rate;
+        self.sample_rate = sample_rate;
+        self.mark = ToneFilter::new(self.shift / 2.0, rate, self.baud);
+        self.space = ToneFilter::new(-self.shift / 2.0, rate, self.baud);
+        self.uart = Uart::Idle;
+    }
+
+    /// Change the tone shift and keying speed
+    pub fn set_params(&mut self, shift: f64, baud: f64) {
+        self.shift = shift;
+        self.baud = baud;
+        self.retune(self.sample_rate);
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+
+        let samples_per_bit = self.rate / self.baud;
+        for &s in samples {
+            let Some(baseband) = self.fir.push(s) else {
+                continue;
+            };
+            let (mark_env, mark_wave) = self.mark.push(baseband);
+            let (space_env, space_wave) = self.space.push(baseband);
+
+            if self.scope.len() == SCOPE_POINTS {
+                self.scope.pop_front();
+            }
+            self.scope.push_back((mark_wave, space_wave));
+
+            let bit = mark_env > space_env;
+            self.uart = match self.uart {
+                // Falling edge into space is the start bit, first sample lands mid-bit
+                Uart::Idle if self.last_bit && !bit => Uart::Receiving {
+                    countdown: samples_per_bit * 1.5,
+                    bit: 0,
+                    value: 0,
+                },
+                Uart::Idle => Uart::Idle,
+                Uart::Receiving { countdown, bit: index, value } => {
+                    let countdown = countdown - 1.0;
+                    if countdown > 0.0 {
+                        Uart::Receiving { countdown, bit: index, value }
+                    } else if index < 5 {
+                        Uart::Receiving {
+                            countdown: countdown + samples_per_bit,
+                            bit: index + 1,
+                            value: value | ((bit as u8) << index),
+                        }
+                    } else {
+                        // Stop bit must be mark, otherwise drop the character
+                        if bit {
+                            self.push_code(value);
+                        }
+                        Uart::Idle
+                    }
+                }
+            };
+            self.last_bit = bit;
+        }
+    }
+
+    fn push_code(&mut self, code: u8) {
+        match code {
+            FIGS => self.figures = true,
+            LTRS => self.figures = false,
+            _ => {
+                let table = if self.figures { &FIGURES } else { &LETTERS };
+                let ch = table[code as usize];
+                // Unshift on space
+                if ch == ' ' {
+                    self.figures = false;
+                }
+                if ch != '\0' && ch != '\r' && ch != '\x07' {
+                    self.text.push(ch);
+                }
+            }
+        }
+
+        if self.text.len() > MAX_TEXT {
+            let cut = self.text.len() - MAX_TEXT;
+            self.text.drain(..cut);
+        }
+    }
+}
+
+impl Default for RttyDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,403 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use num_complex::Complex32;
+
+use crate::dsp::{FskConfig, FskDemod};
+
+/// SAME AFSK: 520.83 Bd, mark (one) at 2083.3 Hz, space (zero) at 1562.5 Hz
+const BAUD: f64 = 520.83;
+const MARK_HZ: f64 = 2083.3;
+const SPACE_HZ: f64 = 1562.5;
+const PREAMBLE: u8 = 0xAB;
+/// Longest legal header with 31 locations
+const MAX_HEADER: usize = 268;
+/// The three header bursts are one second apart
+const REPEAT_WINDOW: Duration = Duration::from_secs(10);
+const MAX_ALERTS: usize = 100;
+
+const ORIGINATORS: [(&str, &str); 4] = [
+    ("EAS", "EAS Participant"),
+    ("CIV", "Civil authorities"),
+    ("WXR", "National Weather Service"),
+    ("PEP", "Primary Entry Point System"),
+];
+
+const EVENTS: [(&str, &str); 36] = [
+    ("EAN", "Emergency Action Notification"),
+    ("NPT", "National Periodic Test"),
+    ("RMT", "Required Monthly Test"),
+    ("RWT", "Required Weekly Test"),
+    ("DMO", "Practice/Demo Warning"),
+    ("ADR", "Administrative Message"),
+    ("AVW", "Avalanche Warning"),
+    ("AVA", "Avalanche Watch"),
+    ("BZW", "Blizzard Warning"),
+    ("CAE", "Child Abduction Emergency"),
+    ("CDW", "Civil Danger Warning"),
+    ("CEM", "Civil Emergency Message"),
+    ("EQW", "Earthquake Warning"),
+    ("EVI", "Evacuation Immediate"),
+    ("FFW", "Flash Flood Warning"),
+    ("FFA", "Flash Flood Watch"),
+    ("FFS", "Flash Flood Statement"),
+    ("FLW", "Flood Warning"),
+    ("FLA", "Flood Watch"),
+    ("FLS", "Flood Statement"),
+    ("FRW", "Fire Warning"),
+    ("HWW", "High Wind Warning"),
+    ("HWA", "High Wind Watch"),
+    ("HUW", "Hurricane Warning"),
+    ("HUA", "Hurricane Watch"),
+    ("HLS", "Hurricane Statement"),
+    ("SPW", "Shelter in Place Warning"),
+    ("SVR", "Severe Thunderstorm Warning"),
+    ("SVA", "Severe Thunderstorm Watch"),
+    ("SVS", "Severe Weather Statement"),
+    ("SPS", "Special Weather Statement"),
+    ("TOR", "Tornado Warning"),
+    ("TOA", "Tornado Watch"),
+    ("TSW", "Tsunami Warning"),
+    ("TSA", "Tsunami Watch"),
+    ("WSW", "Winter Storm Warning"),
+];
+
+/// State FIPS codes used in SAME location codes
+const STATES: [(u32, &str); 56] = [
+    (1, "AL"), (2, "AK"), (4, "AZ"), (5, "AR"), (6, "CA"), (8, "CO"), (9, "CT"), (10, "DE"),
+    (11, "DC"), (12, "FL"), (13, "GA"), (15, "HI"), (16, "ID"), (17, "IL"), (18, "IN"),
+    (19, "IA"), (20, "KS"), (21, "KY"), (22, "LA"), (23, "ME"), (24, "MD"), (25, "MA"),
+    (26, "MI"), (27, "MN"), (28, "MS"), (29, "MO"), (30, "MT"), (31, "NE"), (32, "NV"),
+    (33, "NH"), (34, "NJ"), (35, "NM"), (36, "NY"), (37, "NC"), (38, "ND"), (39, "OH"),
+    (40, "OK"), (41, "OR"), (42, "PA"), (44, "RI"), (45, "SC"), (46, "SD"), (47, "TN"),
+    (48, "TX"), (49, "UT"), (50, "VT"), (51, "VA"), (53, "WA"), (54, "WV"), (55, "WI"),
+    (56, "WY"), (60, "AS"), (66, "GU"), (69, "MP"), (72, "PR"), (78, "VI"),
+];
+
+/// How urgently an alert should be presented
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Watch,
+    Statement,
+    Test,
+}
+
+/// A decoded `ZCZC` header
+#[derive(Clone, Debug)]
+pub struct SameAlert {
+    pub received: SystemTime,
+    pub originator: String,
+    pub event: String,
+    /// `PSSCCC` location codes
+    pub locations: Vec<String>,
+    pub purge: Duration,
+    /// Issue time as `JJJHHMM` (day of year, UTC)
+    pub issued: String,
+    pub sender: String,
+}
+
+impl SameAlert {
+    pub fn originator_name(&self) -> &str {
+        lookup(&ORIGINATORS, &self.originator).unwrap_or(&self.originator)
+    }
+
+    pub fn event_name(&self) -> &str {
+        lookup(&EVENTS, &self.event).unwrap_or(&self.event)
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self.event.as_str() {
+            "EAN" | "CEM" | "EVI" | "CAE" | "CDW" | "SPW" => Severity::Warning,
+            "NPT" | "RMT" | "RWT" | "DMO" | "ADR" => Severity::Test,
+            e if e.ends_with('W') => Severity::Warning,
+            e if e.ends_with('A') => Severity::Watch,
+            _ => Severity::Statement,
+        }
+    }
+
+    pub fn expires(&self) -> SystemTime {
+        self.received + self.purge
+    }
+
+    /// Issue time as `day JJJ HH:MM UTC`
+    pub fn issued_text(&self) -> String {
+        format!("day {} {}:{} UTC", &self.issued[..3], &self.issued[3..5], &self.issued[5..])
+    }
+
+    /// Locations as `ST 123`, or `ST (all)` for state-wide codes
+    pub fn location_names(&self) -> Vec<String> {
+        self.locations
+            .iter()
+            .map(|code| {
+                let state: u32 = code[1..3].parse().unwrap_or(0);
+                let state = STATES
+                    .iter()
+                    .find(|(fips, _)| *fips == state)
+                    .map_or_else(|| code[1..3].to_string(), |(_, abbr)| abbr.to_string());
+                let part = match &code[..1] {
+                    "0" => String::new(),
+                    p => format!(" part {}", p),
+                };
+                match &code[3..] {
+                    "000" => format!("{} (all){}", state, part),
+                    county => format!("{} {}{}", state, county, part),
+                }
+            })
+            .collect()
+    }
+
+    /// Canonical header text, used to match the repeated bursts
+    fn header(&self) -> String {
+        format!(
+            "ZCZC-{}-{}-{}+{:02}{:02}-{}-{}-",
+            self.originator,
+            self.event,
+            self.locations.join("-"),
+            self.purge.as_secs() / 3600,
+            self.purge.as_secs() / 60 % 60,
+            self.issued,
+            self.sender
+        )
+    }
+}
+
+fn lookup<'a>(table: &[(&str, &'a str)], code: &str) -> Option<&'a str> {
+    table.iter().find(|(c, _)| *c == code).map(|(_, name)| *name)
+}
+
+/// The two tones as an FSK channel over the audio, mark the upper
+fn channel() -> FskConfig {
+    FskConfig {
+        offset_hz: (MARK_HZ + SPACE_HZ) / 2.0,
+        ..FskConfig::new((MARK_HZ - SPACE_HZ) / 2.0, BAUD)
+    }
+}
+
+/// Byte framing: hunt for the preamble, then collect ASCII until it stops
+enum Framer {
+    Hunting,
+    Synced { bits: u8, text: String },
+}
+
+/// NOAA Weather Radio / EAS Specific Area Message Encoding decoder on FM audio
+pub struct SameDecoder {
+    rate: f64,
+    /// Takes the audio as IQ with nothing in Q, the channel filter removing
+    /// the image of the tones
+    demod: FskDemod,
+    shift: u8,
+    framer: Framer,
+    /// Headers heard once, waiting for a matching repeat
+    pending: Vec<(Instant, String)>,
+    confirmed: Option<(Instant, String)>,
+    pub alerts: Vec<SameAlert>,
+    /// Alerts confirmed in all, still counting once the oldest are dropped
+    pub alert_count: u64,
+    /// Number of `NNNN` end-of-message markers heard
+    pub end_of_messages: u32,
+}
+
+impl SameDecoder {
+    pub fn new() -> Self {
+        let mut decoder = Self {
+            rate: 0.0,
+            demod: FskDemod::new(channel(), 8e3),
+            shift: 0,
+            framer: Framer::Hunting,
+            pending: Vec::new(),
+            confirmed: None,
+            alerts: Vec::new(),
+            alert_count: 0,
+            end_of_messages: 0,
+        };
+        decoder.retune(8e3);
+        decoder
+    }
+
+    fn retune(&mut self, rate: f64) {
+        self.rate = rate;
+        self.demod = FskDemod::new(channel(), rate);
+        self.framer = Framer::Hunting;
+    }
+
+    pub fn process(&mut self, audio: &[f32], rate: f64) {
+        if self.rate != rate {
+            self.retune(rate);
+        }
+        for &s in audio {
+            if let Some(bit) = self.demod.push(Complex32::new(s, 0.0)) {
+                self.push_bit(bit.value);
+            }
+        }
+    }
+
+    /// Bytes are sent LSB first with no start or stop bits
+    fn push_bit(&mut self, bit: bool) {
+        self.shift = (self.shift >> 1) | ((bit as u8) << 7);
+        match &mut self.framer {
+            Framer::Hunting => {
+                if self.shift == PREAMBLE {
+                    self.framer = Framer::Synced { bits: 0, text: String::new() };
+                }
+            }
+            Framer::Synced { bits, text } => {
+                *bits += 1;
+                if *bits < 8 {
+                    return;
+                }
+                *bits = 0;
+                let byte = self.shift;
+                if byte == PREAMBLE && text.is_empty() {
+                    return;
+                }
+                if (0x20..0x7F).contains(&byte) && text.len() < MAX_HEADER {
+                    text.push(byte as char);
+                    return;
+                }
+                let text = std::mem::take(text);
+                self.framer = Framer::Hunting;
+                self.handle_text(&text);
+            }
+        }
+    }
+
+    fn handle_text(&mut self, text: &str) {
+        if text.starts_with("NNNN") {
+            self.end_of_messages += 1;
+            self.pending.clear();
+            return;
+        }
+        let Some(alert) = parse_header(text) else {
+            return;
+        };
+
+        // Act on the second matching burst, ignore the third
+        let now = Instant::now();
+        let header = alert.header();
+        self.pending.retain(|(t, _)| now - *t < REPEAT_WINDOW);
+        if self
+            .confirmed
+            .as_ref()
+            .is_some_and(|(t, h)| *h == header && now - *t < REPEAT_WINDOW)
+        {
+            return;
+        }
+        if self.pending.iter().any(|(_, h)| *h == header) {
+            self.pending.clear();
+            self.confirmed = Some((now, header));
+            self.alerts.push(alert);
+            self.alert_count += 1;
+            if self.alerts.len() > MAX_ALERTS {
+                self.alerts.remove(0);
+            }
+        } else {
+            self.pending.push((now, header));
+        }
+    }
+}
+
+impl Default for SameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse `ZCZC-ORG-EEE-PSSCCC[-PSSCCC...]+TTTT-JJJHHMM-LLLLLLLL-`
+fn parse_header(text: &str) -> Option<SameAlert> {
+    let body = text.strip_prefix("ZCZC-")?;
+    let (codes, tail) = body.split_once('+')?;
+
+    let mut codes = codes.split('-');
+    let originator = codes.next().filter(|c| c.len() == 3)?.to_string();
+    let event = codes.next().filter(|c| c.len() == 3)?.to_string();
+    let locations: Vec<String> = codes.map(str::to_string).collect();
+    if locations.is_empty() || !locations.iter().all(|l| is_digits(l, 6)) {
+        return None;
+    }
+
+    let mut tail = tail.split('-');
+    let purge = tail.next().filter(|t| is_digits(t, 4))?;
+    let issued = tail.next().filter(|t| is_digits(t, 7))?.to_string();
+    let sender = tail.next().filter(|s| s.len() == 8)?.to_string();
+    let hours: u64 = purge[..2].parse().ok()?;
+    let minutes: u64 = purge[2..].parse().ok()?;
+
+    Some(SameAlert {
+        received: SystemTime::now(),
+        originator,
+        event,
+        locations,
+        purge: Duration::from_secs(hours * 3600 + minutes * 60),
+        issued,
+        sender,
+    })
+}
+
+fn is_digits(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_alerts_past_those_kept() {
+        let mut decoder = SameDecoder::new();
+        for i in 0..MAX_ALERTS + 20 {
+            let header = format!("ZCZC-WXR-TOR-{:06}+0030-1231500-KLOX/NWS-", i);
+            // Acted on at the second of the three bursts
+            for _ in 0..3 {
+                decoder.handle_text(&header);
+            }
+        }
+        assert_eq!(decoder.alerts.len(), MAX_ALERTS);
+        assert_eq!(decoder.alert_count, (MAX_ALERTS + 20) as u64);
+        assert_eq!(decoder.alerts.last().unwrap().locations, [format!("{:06}", MAX_ALERTS + 19)]);
+    }
+
+    /// AFSK audio of three bursts of `header`, a second apart
+    fn bursts(header: &str, rate: f64) -> Vec<f32> {
+        let mut bits = Vec::new();
+        for byte in std::iter::repeat_n(PREAMBLE, 16).chain(header.bytes()) {
+            bits.extend((0..8).map(|i| byte >> i & 1 == 1));
+        }
+        let mut audio = Vec::new();
+        let mut phase = 0.0f64;
+        for _ in 0..3 {
+            let mut sent = 0.0;
+            for (i, &bit) in bits.iter().enumerate() {
+                let freq = if bit { MARK_HZ } else { SPACE_HZ };
+                while sent < (i + 1) as f64 * rate / BAUD {
+                    phase += 2.0 * std::f64::consts::PI * freq / rate;
+                    audio.push(0.5 * phase.sin() as f32);
+                    sent += 1.0;
+                }
+            }
+            audio.extend(std::iter::repeat_n(0.0, rate as usize));
+        }
+        audio
+    }
+
+    #[test]
+    fn decodes_afsk_bursts() {
+        let header = "ZCZC-WXR-RWT-020103-020209+0015-1231500-KLWX/NWS-";
+        for rate in [8e3, 24e3, 48e3] {
+            let mut decoder = SameDecoder::new();
+            for block in bursts(header, rate).chunks(1000) {
+                decoder.process(block, rate);
+            }
+            assert_eq!(decoder.alert_count, 1, "at {} Hz", rate);
+            assert_eq!(decoder.alerts[0].header(), parse_header(header).unwrap().header());
+        }
+    }
+
+    #[test]
+    fn parses_a_header() {
+        let alert = parse_header("ZCZC-WXR-SVR-048029-048013+0045-1231500-KEWX/NWS-").unwrap();
+        assert_eq!((alert.originator.as_str(), alert.event.as_str()), ("WXR", "SVR"));
+        assert_eq!(alert.locations, ["048029", "048013"]);
+        assert_eq!(alert.purge, Duration::from_secs(45 * 60));
+        assert!(parse_header("ZCZC-WXR-SVR-48029+0045-1231500-KEWX/NWS-").is_none());
+    }
+}
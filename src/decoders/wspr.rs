@@ -0,0 +1,526 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex32;
+use rustfft::FftPlanner;
+
+use super::utc_date_time;
+use crate::dsp::{DecimatingFir, Nco};
+
+/// Standard WSPR dial frequencies (USB), the signals sit 1400-1600 Hz above
+pub const DIAL_FREQUENCIES: [f64; 14] = [
+    0.136e6, 0.4742e6, 1.8366e6, 3.5686e6, 5.2872e6, 7.0386e6, 10.1387e6, 14.0956e6,
+    18.1046e6, 21.0946e6, 24.9246e6, 28.1246e6, 50.293e6, 144.489e6,
+];
+const AUDIO_CENTER: f64 = 1500.0;
+
+const RATE: f64 = 375.0;
+const SYMBOL_SAMPLES: usize = 256;
+const HALF_SYMBOL: usize = SYMBOL_SAMPLES / 2;
+const SYMBOLS: usize = 162;
+const TONE_SPACING: f64 = RATE / SYMBOL_SAMPLES as f64;
+const SEARCH_HZ: f64 = 110.0;
+const MAX_CANDIDATES: usize = 20;
+/// Spots kept, the oldest dropped first, about a day of a busy band
+const MAX_SPOTS: usize = 2000;
+
+const POLY1: u32 = 0xF2D0_5351;
+const POLY2: u32 = 0xE461_3C47;
+const MESSAGE_BITS: usize = 50;
+const FANO_BITS: usize = MESSAGE_BITS + 31;
+const FANO_DELTA: i32 = 50;
+const FANO_MAX_CYCLES: usize = 10_000 * FANO_BITS;
+
+const WSPRNET_HOST: &str = "wsprnet.org";
+
+const SYNC: [u8; SYMBOLS] = [
+    1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0,
+    0, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 0, 1, 1, 0, 1, 0,
+    0, 0, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 0, 0, 0, 1, 1, 0, 1, 0, 1, 0,
+    0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 0, 1, 1, 1,
+    0, 0, 0, 0, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0, 1, 1, 0, 0, 0, 1, 1, 0,
+    0, 0,
+];
+
+/// A decoded WSPR transmission
+#[derive(Clone, Debug)]
+pub struct WsprSpot {
+    /// Start of the two-minute window, seconds since the epoch
+    pub window_start: u64,
+    pub callsign: String,
+    pub grid: String,
+    pub dbm: i32,
+    pub snr: f32,
+    /// Start offset relative to the nominal one second into the window
+    pub dt: f32,
+    /// Transmitter frequency in Hz
+    pub frequency: f64,
+}
+
+/// Station details needed for wsprnet reports
+#[derive(Clone, Debug)]
+pub struct Reporter {
+    pub callsign: String,
+    pub grid: String,
+}
+
+/// Two-minute window recorder and decoder for the standard WSPR sub-bands
+pub struct WsprDecoder {
+    tuned: (f64, f64),
+    /// Dial frequency of the sub-band inside the current passband
+    pub dial: Option<f64>,
+    nco: Nco,
+    fir: DecimatingFir,
+    window_start: u64,
+    buffer: Vec<Complex32>,
+    results: Receiver<Vec<WsprSpot>>,
+    results_tx: Sender<Vec<WsprSpot>>,
+    /// Set while a window is being decoded in the background
+    pub decoding: bool,
+    pub spots: Vec<WsprSpot>,
+    pub reporter: Option<Reporter>,
+    pub upload: bool,
+    upload_status: Receiver<String>,
+    upload_status_tx: Sender<String>,
+    pub last_upload: Option<String>,
+}
+
+impl WsprDecoder {
+    pub fn new() -> Self {
+        let (results_tx, results) = mpsc::channel();
+        let (upload_status_tx, upload_status) = mpsc::channel();
+        Self {
+            tuned: (0.0, 0.0),
+            dial: None,
+            nco: Nco::new(0.0, 1.0),
+            fir: DecimatingFir::new(vec![1.0], 1),
+            window_start: 0,
+            buffer: Vec::new(),
+            results,
+            results_tx,
+            decoding: false,
+            spots: Vec::new(),
+            reporter: None,
+            upload: false,
+            upload_status,
+            upload_status_tx,
+            last_upload: None,
+        }
+    }
+
+    /// Fraction of the current two-minute window that has been recorded
+    pub fn window_progress(&self) -> f32 {
+        (self.buffer.len() as f32 / (120.0 * RATE as f32)).min(1.0)
+    }
+
+    fn retune(&mut self, center_freq: f64, sample_rate: f64) {
+        self.tuned = (center_freq, sample_rate);
+        self.buffer.clear();
+        self.dial = DIAL_FREQUENCIES
+            .iter()
+            .copied()
+            .find(|dial| (dial + AUDIO_CENTER - center_freq).abs() < sample_rate / 2.0 - SEARCH_HZ * 2.0);
+        if let Some(dial) = self.dial {
+            self.nco = Nco::new(dial + AUDIO_CENTER - center_freq, sample_rate);
+            self.fir = DecimatingFir::for_rates(sample_rate, RATE, SEARCH_HZ * 2.0).0;
+        }
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        if let Ok(spots) = self.results.try_recv() {
+            self.decoding = false;
+            if self.upload {
+                self.upload_spots(&spots);
+            }
+            self.spots.extend(spots);
+            if self.spots.len() > MAX_SPOTS {
+                let excess = self.spots.len() - MAX_SPOTS;
+                self.spots.drain(..excess);
+            }
+        }
+        if let Ok(status) = self.upload_status.try_recv() {
+            self.last_upload = Some(status);
+        }
+
+        if self.tuned != (center_freq, sample_rate) {
+            self.retune(center_freq, sample_rate);
+        }
+        let Some(dial) = self.dial else {
+            return;
+        };
+
+        // Windows start on even UTC minutes
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let window = now - now % 120;
+        if window != self.window_start {
+            if self.buffer.len() >= SYMBOLS * SYMBOL_SAMPLES && !self.decoding {
+                self.start_decode(dial);
+            }
+            self.buffer.clear();
+            self.window_start = window;
+        }
+
+        for &s in samples {
+            if let Some(x) = self.fir.push(self.nco.mix(s)) {
+                self.buffer.push(x);
+            }
+        }
+    }
+
+    fn start_decode(&mut self, dial: f64) {
+        let buffer = std::mem::take(&mut self.buffer);
+        let window_start = self.window_start;
+        let tx = self.results_tx.clone();
+        self.decoding = true;
+        thread::spawn(move || {
+            let _ = tx.send(decode_window(&buffer, window_start, dial));
+        });
+    }
+
+    fn upload_spots(&self, spots: &[WsprSpot]) {
+        let Some(reporter) = self.reporter.clone() else {
+            return;
+        };
+        let Some(dial) = self.dial else {
+            return;
+        };
+        let spots = spots.to_vec();
+        let status = self.upload_status_tx.clone();
+        thread::spawn(move || {
+            let mut sent = 0;
+            for spot in &spots {
+                match post_spot(&reporter, dial, spot) {
+                    Ok(()) => sent += 1,
+                    Err(e) => {
+                        log::warn!("wsprnet upload failed: {}", e);
+                        let _ = status.send(format!("wsprnet upload failed: {}", e));
+                        return;
+                    }
+                }
+            }
+            let _ = status.send(format!("{} spots uploaded to wsprnet", sent));
+        });
+    }
+}
+
+impl Default for WsprDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Report one spot with the wsprnet HTTP GET interface
+fn post_spot(reporter: &Reporter, dial: f64, spot: &WsprSpot) -> std::io::Result<()> {
+    let (date, time) = utc_date_time(spot.window_start);
+    let query = format!(
+        "function=wspr&rcall={}&rgrid={}&rqrg={:.6}&date={}&time={}&sig={:.0}&dt={:.1}&drift=0&tqrg={:.6}&tcall={}&tgrid={}&dbm={}&version=rf_rust-{}",
+        reporter.callsign,
+        reporter.grid,
+        dial / 1e6,
+        &date[2..].replace('-', ""),
+        &time[..5].replace(':', ""),
+        spot.snr,
+        spot.dt,
+        spot.frequency / 1e6,
+        spot.callsign,
+        spot.grid,
+        spot.dbm,
+        env!("CARGO_PKG_VERSION"),
+    );
+    let mut stream = TcpStream::connect((WSPRNET_HOST, 80))?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    write!(
+        stream,
+        "GET /post?{} HTTP/1.0\r\nHost: {}\r\n\r\n",
+        query, WSPRNET_HOST
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    if response.starts_with("HTTP/1.1 200") || response.starts_with("HTTP/1.0 200") {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(response.lines().next().unwrap_or("no response").to_string()))
+    }
+}
+
+/// Search a recorded window for WSPR signals and decode them
+fn decode_window(samples: &[Complex32], window_start: u64, dial: f64) -> Vec<WsprSpot> {
+    let spectra = spectrogram(samples);
+    if spectra.len() < 2 * SYMBOLS {
+        return Vec::new();
+    }
+
+    // Average spectrum to find candidate frequencies
+    let mut average = vec![0.0f32; SYMBOL_SAMPLES];
+    for spectrum in &spectra {
+        for (a, p) in average.iter_mut().zip(spectrum) {
+            *a += p;
+        }
+    }
+    let mut sorted = average.clone();
+    sorted.sort_by(f32::total_cmp);
+    let noise = sorted[sorted.len() / 2].max(f32::MIN_POSITIVE);
+
+    let search_bins = (SEARCH_HZ / TONE_SPACING) as i32;
+    let mut candidates: Vec<i32> = (-search_bins..=search_bins)
+        .filter(|&b| average[bin_index(b)] > noise * 1.5)
+        .collect();
+    candidates.sort_by(|&a, &b| average[bin_index(b)].total_cmp(&average[bin_index(a)]));
+    candidates.truncate(MAX_CANDIDATES);
+
+    let mut spots: Vec<WsprSpot> = Vec::new();
+    for candidate in candidates {
+        let Some((tone0, start)) = best_sync(&spectra, candidate) else {
+            continue;
+        };
+        let soft = soft_symbols(&spectra, tone0, start);
+        let Some(bits) = fano_decode(&deinterleave(&soft)) else {
+            continue;
+        };
+        let Some((callsign, grid, dbm)) = unpack(&bits) else {
+            continue;
+        };
+        if spots.iter().any(|s| s.callsign == callsign) {
+            continue;
+        }
+
+        // Tone power against the noise in a 2500 Hz reference bandwidth
+        let data = encode(&bits);
+        let signal: f32 = (0..SYMBOLS)
+            .map(|i| {
+                let tone = (SYNC[i] + 2 * data[i]) as i32;
+                spectra[start + 2 * i][bin_index(tone0 + tone)]
+            })
+            .sum::<f32>()
+            / SYMBOLS as f32;
+        let noise_per_bin = noise / spectra.len() as f32;
+        let snr = 10.0 * ((signal - noise_per_bin).max(1e-12) / noise_per_bin).log10()
+            - 10.0 * (2500.0 / TONE_SPACING as f32).log10();
+
+        spots.push(WsprSpot {
+            window_start,
+            callsign,
+            grid,
+            dbm,
+            snr,
+            dt: (start * HALF_SYMBOL) as f32 / RATE as f32 - 1.0,
+            frequency: dial + AUDIO_CENTER + (tone0 as f64 + 1.5) * TONE_SPACING,
+        });
+    }
+    spots
+}
+
+/// Map a signed FFT bin to its index in an unshifted spectrum
+fn bin_index(bin: i32) -> usize {
+    bin.rem_euclid(SYMBOL_SAMPLES as i32) as usize
+}
+
+/// Symbol-length power spectra at half-symbol steps
+fn spectrogram(samples: &[Complex32]) -> Vec<Vec<f32>> {
+    let fft = FftPlanner::new().plan_fft_forward(SYMBOL_SAMPLES);
+    let mut spectra = Vec::new();
+    let mut start = 0;
+    while start + SYMBOL_SAMPLES <= samples.len() {
+        let mut block = samples[start..start + SYMBOL_SAMPLES].to_vec();
+        fft.process(&mut block);
+        spectra.push(block.iter().map(|c| c.norm_sqr()).collect());
+        start += HALF_SYMBOL;
+    }
+    spectra
+}
+
+/// Find the lowest-tone bin and half-symbol start offset with the strongest sync correlation
+fn best_sync(spectra: &[Vec<f32>], candidate: i32) -> Option<(i32, usize)> {
+    let max_start = spectra.len().checked_sub(2 * SYMBOLS - 1)?;
+    let mut best = (f32::MIN, 0, 0);
+    for tone0 in candidate - 3..=candidate {
+        for start in 0..=max_start {
+            let metric: f32 = (0..SYMBOLS)
+                .map(|i| {
+                    let p = &spectra[start + 2 * i];
+                    let tone = |t: i32| p[bin_index(tone0 + t)];
+                    let odd = tone(1) + tone(3) - tone(0) - tone(2);
+                    if SYNC[i] == 1 { odd } else { -odd }
+                })
+                .sum();
+            if metric > best.0 {
+                best = (metric, tone0, start);
+            }
+        }
+    }
+    (best.0 > 0.0).then_some((best.1, best.2))
+}
+
+/// Soft data bits, positive meaning a one
+fn soft_symbols(spectra: &[Vec<f32>], tone0: i32, start: usize) -> Vec<f32> {
+    (0..SYMBOLS)
+        .map(|i| {
+            let p = &spectra[start + 2 * i];
+            let tone = |t: i32| p[bin_index(tone0 + t)].sqrt();
+            (tone(2) + tone(3)) - (tone(0) + tone(1))
+        })
+        .collect()
+}
+
+fn interleave_order() -> Vec<usize> {
+    (0..=255u8)
+        .map(|i| i.reverse_bits() as usize)
+        .filter(|&j| j < SYMBOLS)
+        .collect()
+}
+
+fn deinterleave(symbols: &[f32]) -> Vec<f32> {
+    interleave_order().iter().map(|&j| symbols[j]).collect()
+}
+
+fn parity(x: u32) -> u8 {
+    (x.count_ones() & 1) as u8
+}
+
+/// Convolutionally encode and interleave message bits into channel data bits
+fn encode(bits: &[bool]) -> Vec<u8> {
+    let mut state: u32 = 0;
+    let mut coded = Vec::with_capacity(SYMBOLS);
+    for i in 0..FANO_BITS {
+        let bit = bits.get(i).copied().unwrap_or(false);
+        state = (state << 1) | bit as u32;
+        coded.push(parity(state & POLY1));
+        coded.push(parity(state & POLY2));
+    }
+    let mut out = vec![0; SYMBOLS];
+    for (p, j) in interleave_order().into_iter().enumerate() {
+        out[j] = coded[p];
+    }
+    out
+}
+
+/// Fano sequential decoder for the K=32, r=1/2 code, returns the 50 message bits
+fn fano_decode(soft: &[f32]) -> Option<Vec<bool>> {
+    // Gaussian log-likelihood branch metrics, scaled to integers
+    let mean = soft.iter().map(|s| s.abs()).sum::<f32>() / soft.len() as f32;
+    let variance = (soft.iter().map(|s| s * s).sum::<f32>() / soft.len() as f32 - mean * mean).max(1e-12);
+    let metric = |s: f32, bit: u8| -> i32 {
+        let signed = if bit == 1 { s } else { -s };
+        let ll = (2.0 / (1.0 + (-2.0 * mean * signed / variance).exp())).log2() - 0.5;
+        // Bounded so confident errors cannot overflow the path metric
+        (ll * 10.0).round().max(-100.0) as i32
+    };
+    let branch = |node: usize, state: u32| -> i32 {
+        metric(soft[2 * node], parity(state & POLY1)) + metric(soft[2 * node + 1], parity(state & POLY2))
+    };
+
+    #[derive(Clone, Copy, Default)]
+    struct Node {
+        state: u32,
+        gamma: i32,
+        metrics: [i32; 2],
+        branch: usize,
+    }
+
+    let tail = MESSAGE_BITS;
+    let mut nodes = vec![Node::default(); FANO_BITS + 1];
+    let rank = |nodes: &mut Vec<Node>, n: usize| {
+        let zero = branch(n, nodes[n].state);
+        if n >= tail {
+            nodes[n].metrics = [zero, -1_000_000];
+            return;
+        }
+        let one = branch(n, nodes[n].state | 1);
+        if one > zero {
+            nodes[n].metrics = [one, zero];
+            nodes[n].state |= 1;
+        } else {
+            nodes[n].metrics = [zero, one];
+        }
+    };
+    rank(&mut nodes, 0);
+
+    let mut threshold = 0;
+    let mut n = 0;
+    for _ in 0..FANO_MAX_CYCLES {
+        let gamma = nodes[n].gamma + nodes[n].metrics[nodes[n].branch];
+        if gamma >= threshold {
+            // First visit to this node: tighten the threshold
+            if nodes[n].gamma < threshold + FANO_DELTA {
+                while gamma >= threshold + FANO_DELTA {
+                    threshold += FANO_DELTA;
+                }
+            }
+            nodes[n + 1].gamma = gamma;
+            nodes[n + 1].state = nodes[n].state << 1;
+            n += 1;
+            if n == FANO_BITS {
+                // Each node's low state bit is the decision taken there
+                let bits = nodes[..MESSAGE_BITS].iter().map(|node| node.state & 1 == 1).collect();
+                return Some(bits);
+            }
+            rank(&mut nodes, n);
+            nodes[n].branch = 0;
+            continue;
+        }
+
+        // Threshold violated: back up until a better branch can be tried
+        loop {
+            if n == 0 || nodes[n - 1].gamma < threshold {
+                threshold -= FANO_DELTA;
+                if nodes[n].branch != 0 {
+                    nodes[n].branch = 0;
+                    nodes[n].state ^= 1;
+                }
+                break;
+            }
+            n -= 1;
+            if n < tail && nodes[n].branch != 1 {
+                nodes[n].branch = 1;
+                nodes[n].state ^= 1;
+                break;
+            }
+        }
+    }
+    None
+}
+
+/// Unpack a standard type 1 message into callsign, grid and power
+fn unpack(bits: &[bool]) -> Option<(String, String, i32)> {
+    let field = |start: usize, len: usize| {
+        bits[start..start + len]
+            .iter()
+            .fold(0u32, |acc, &b| (acc << 1) | b as u32)
+    };
+    let mut n = field(0, 28);
+    let m = field(28, 22);
+
+    const CHARS: &[u8; 37] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ ";
+    let mut call = [b' '; 6];
+    for i in (3..6).rev() {
+        call[i] = CHARS[(n % 27 + 10) as usize];
+        n /= 27;
+    }
+    call[2] = CHARS[(n % 10) as usize];
+    n /= 10;
+    call[1] = CHARS[(n % 36) as usize];
+    n /= 36;
+    call[0] = *CHARS.get(n as usize)?;
+    let callsign = String::from_utf8_lossy(&call).trim().to_string();
+
+    let dbm = (m & 127) as i32 - 64;
+    if !(0..=60).contains(&dbm) || ![0, 3, 7].contains(&(dbm % 10)) {
+        return None;
+    }
+    let grid = m >> 7;
+    let (row, col) = (grid / 180, grid % 180);
+    let t = 179u32.checked_sub(row)?;
+    let locator = [
+        b'A' + (t / 10) as u8,
+        b'A' + (col / 10) as u8,
+        b'0' + (t % 10) as u8,
+        b'0' + (col % 10) as u8,
+    ];
+    if locator[0] > b'R' || locator[1] > b'R' || callsign.is_empty() {
+        return None;
+    }
+    Some((callsign, String::from_utf8_lossy(&locator).to_string(), dbm))
+}
@@ -0,0 +1,158 @@
+//! A device without hardware, making tones, FM carriers and bursts at known
+//! frequencies over Gaussian noise. Everything follows from the seed and
+//! the sample count, not the clock, so the same calls give the same
+//! samples every run.
+
+use std::f64::consts::TAU;
+
+use num_complex::Complex32;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{Device, DeviceInfo};
+
+/// Gain at which signals have the power they are given
+const REFERENCE_GAIN_DB: f64 = 20.0;
+/// Samples each [`Device::receive`] gives by default, in seconds, one
+/// update of the TUI
+const BLOCK_SECS: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// An unmodulated carrier
+    Tone,
+    /// A carrier frequency modulated by a sine
+    Fm { deviation_hz: f64, tone_hz: f64 },
+    /// A carrier on for `on_secs` at the start of every `period_secs`
+    Burst { period_secs: f64, on_secs: f64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signal {
+    pub frequency: f64,
+    /// Power in dBFS at the reference gain
+    pub power_db: f32,
+    pub shape: Shape,
+}
+
+pub struct MockSdr {
+    pub signals: Vec<Signal>,
+    /// Noise power in dBFS at the reference gain
+    pub noise_db: f32,
+    /// Samples each receive gives, in seconds
+    pub block_secs: f64,
+    frequency: f64,
+    sample_rate: f64,
+    gain: f64,
+    rng: StdRng,
+    /// Carrier phase of each signal
+    phases: Vec<f64>,
+    /// Samples made so far, the clock for modulation and bursts
+    time: u64,
+}
+
+impl MockSdr {
+    /// Noise alone, until signals are added
+    pub fn new(seed: u64) -> Self {
+        Self {
+            signals: Vec::new(),
+            noise_db: -70.0,
+            block_secs: BLOCK_SECS,
+            frequency: 100e6,
+            sample_rate: 1e6,
+            gain: REFERENCE_GAIN_DB,
+            rng: StdRng::seed_from_u64(seed),
+            phases: Vec::new(),
+            time: 0,
+        }
+    }
+
+    /// The signals of the demo mode around 890 MHz: two FM carriers and a
+    /// weak burst every two seconds
+    pub fn demo() -> Self {
+        let mut sdr = Self::new(0);
+        sdr.signals = vec![
+            Signal { frequency: 890.0e6, power_db: -35.0, shape: Shape::Fm { deviation_hz: 40e3, tone_hz: 1e3 } },
+            Signal { frequency: 890.2e6, power_db: -50.0, shape: Shape::Fm { deviation_hz: 25e3, tone_hz: 1.5e3 } },
+            Signal { frequency: 890.35e6, power_db: -55.0, shape: Shape::Burst { period_secs: 2.0, on_secs: 0.5 } },
+        ];
+        sdr.noise_db = -68.0;
+        sdr
+    }
+
+    pub fn add(&mut self, frequency: f64, power_db: f32, shape: Shape) {
+        self.signals.push(Signal { frequency, power_db, shape });
+    }
+
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Append `count` samples to `out`. Signals outside the tuned span are
+    /// filtered out, as by a real front end.
+    pub fn generate(&mut self, count: usize, out: &mut Vec<Complex32>) {
+        self.phases.resize(self.signals.len(), 0.0);
+        let gain = 10f64.powf((self.gain - REFERENCE_GAIN_DB) / 20.0);
+        let noise = (10f64.powf(self.noise_db as f64 / 10.0) / 2.0).sqrt() * gain;
+        let start = out.len();
+        for _ in 0..count {
+            // Box-Muller, a pair of Gaussian values per pair of uniform ones
+            let (u, v): (f64, f64) = (self.rng.r#gen(), self.rng.r#gen());
+            let radius = (-2.0 * (1.0 - u).ln()).sqrt() * noise;
+            out.push(Complex32::new((radius * (TAU * v).cos()) as f32, (radius * (TAU * v).sin()) as f32));
+        }
+        for (signal, phase) in self.signals.iter().zip(&mut self.phases) {
+            let offset = signal.frequency - self.frequency;
+            if offset.abs() >= self.sample_rate / 2.0 {
+                continue;
+            }
+            let amplitude = 10f64.powf(signal.power_db as f64 / 20.0) * gain;
+            for (i, sample) in out[start..].iter_mut().enumerate() {
+                let t = (self.time + i as u64) as f64 / self.sample_rate;
+                let hz = match signal.shape {
+                    Shape::Fm { deviation_hz, tone_hz } => offset + deviation_hz * (TAU * tone_hz * t).sin(),
+                    Shape::Tone | Shape::Burst { .. } => offset,
+                };
+                *phase = (*phase + TAU * hz / self.sample_rate) % TAU;
+                if let Shape::Burst { period_secs, on_secs } = signal.shape
+                    && t % period_secs >= on_secs
+                {
+                    continue;
+                }
+                *sample += Complex32::new((amplitude * phase.cos()) as f32, (amplitude * phase.sin()) as f32);
+            }
+        }
+        self.time += count as u64;
+    }
+}
+
+impl Device for MockSdr {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo {
+            driver: "mock".to_string(),
+            tuner: Some(format!("{} simulated signals over {:.0} dBFS of noise", self.signals.len(), self.noise_db)),
+            ..DeviceInfo::default()
+        }
+    }
+
+    fn tune(&mut self, frequency: f64, sample_rate: f64, gain: f64) {
+        self.frequency = frequency;
+        self.sample_rate = sample_rate;
+        self.gain = gain;
+    }
+
+    fn receive(&mut self, out: &mut Vec<Complex32>) -> bool {
+        out.clear();
+        let count = (self.sample_rate * self.block_secs).round() as usize;
+        self.generate(count, out);
+        count > 0
+    }
+}
@@ -0,0 +1,40 @@
+//! Sources of IQ that the receiver tunes: a remote rtl_tcp server, or
+//! [`MockSdr`](mock::MockSdr), which makes known signals for the demo and
+//! for tests.
+
+pub mod mock;
+
+use num_complex::Complex32;
+
+/// What a device reports of itself, `None` where it does not say
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceInfo {
+    pub driver: String,
+    pub serial: Option<String>,
+    /// Firmware or FPGA version
+    pub firmware: Option<String>,
+    pub tuner: Option<String>,
+    /// Lowest and highest frequency in Hz
+    pub frequency_range: Option<(f64, f64)>,
+    /// Lowest and highest sample rate in samples per second
+    pub sample_rate_range: Option<(f64, f64)>,
+    /// Lowest and highest gain in dB
+    pub gain_range: Option<(f64, f64)>,
+    /// Readings such as temperatures, by name
+    pub sensors: Vec<(String, String)>,
+}
+
+pub trait Device: Send {
+    /// What the status bar calls the device
+    fn name(&self) -> &str;
+
+    /// Driver, versions, ranges and sensors, as far as the device tells
+    fn info(&self) -> DeviceInfo;
+
+    /// Ask for these settings, which a device applies as it can
+    fn tune(&mut self, frequency: f64, sample_rate: f64, gain: f64);
+
+    /// Replace `out` with the samples since the last call, returning false
+    /// when there were none
+    fn receive(&mut self, out: &mut Vec<Complex32>) -> bool;
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+
+use num_complex::Complex32;
+
+use super::filter::lowpass_taps;
+use super::{DecimatingFir, FmDiscriminator, Nco};
+
+pub const AUDIO_RATE: f64 = 8e3;
+const CHANNEL_BANDWIDTH: f64 = 12.5e3;
+const SIDEBAND_TAPS: usize = 63;
+/// Discriminator output, in radians per sample, that maps to full-scale audio at unity gain
+const FULL_SCALE: f32 = std::f32::consts::PI;
+/// Linear factor taking [`AudioDemod`] output after `gain_db` of AF gain to
+/// audio where 1.0 is full scale, as the VU meter, recordings and streams
+/// all take it
+pub fn af_gain(gain_db: f32) -> f32 {
+    10f32.powf(gain_db / 20.0) / FULL_SCALE
+}
+
+/// VU integration time
+const VU_TAU_SECS: f64 = 0.3;
+/// How long the clip indicator stays lit after the last clipped sample
+const CLIP_HOLD_SECS: f64 = 1.0;
+
+/// Sideband audio passband, relative to the suppressed carrier
+const SSB_LOW_HZ: f64 = 300.0;
+const SSB_HIGH_HZ: f64 = 2700.0;
+/// Time constant of the AM carrier level estimate
+const AM_CARRIER_TAU_SECS: f64 = 0.1;
+
+/// Demodulation applied to an audio channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioMode {
+    Fm,
+    Am,
+    Usb,
+    Lsb,
+}
+
+impl AudioMode {
+    pub fn next(self) -> Self {
+        match self {
+            AudioMode::Fm => AudioMode::Am,
+            AudioMode::Am => AudioMode::Usb,
+            AudioMode::Usb => AudioMode::Lsb,
+            AudioMode::Lsb => AudioMode::Fm,
+        }
+    }
+
+    /// Width of the audio passband in Hz
+    pub fn passband(self) -> f64 {
+        match self {
+            AudioMode::Fm | AudioMode::Am => CHANNEL_BANDWIDTH,
+            AudioMode::Usb | AudioMode::Lsb => SSB_HIGH_HZ - SSB_LOW_HZ,
+        }
+    }
+
+    /// `fm`, `am`, `usb` or `lsb` in any case
+    pub fn parse(text: &str) -> Option<Self> {
+        Some(match text.to_ascii_lowercase().as_str() {
+            "fm" => AudioMode::Fm,
+            "am" => AudioMode::Am,
+            "usb" => AudioMode::Usb,
+            "lsb" => AudioMode::Lsb,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for AudioMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AudioMode::Fm => "FM",
+            AudioMode::Am => "AM",
+            AudioMode::Usb => "USB",
+            AudioMode::Lsb => "LSB",
+        })
+    }
+}
+
+/// Narrowband FM, AM or SSB demodulator producing audio from a channel near the tuned
+/// centre frequency. Every mode is scaled so a full-scale signal gives full-scale audio.
+pub struct AudioDemod {
+    mode: AudioMode,
+    nco: Nco,
+    offset_hz: f64,
+    fir: DecimatingFir,
+    disc: FmDiscriminator,
+    /// Envelope average used as the AM carrier level
+    carrier: f32,
+    carrier_alpha: f32,
+    /// Moves the middle of the selected sideband to DC and back around the sideband filter
+    sideband_nco: Nco,
+    sideband_fir: DecimatingFir,
+    sample_rate: f64,
+    rate: f64,
+    /// Mean power of the filtered channel over the last block
+    channel_power: f32,
+}
+
+impl AudioDemod {
+    pub fn new(mode: AudioMode) -> Self {
+        let mut demod = Self {
+            mode,
+            nco: Nco::new(0.0, 1e6),
+            offset_hz: 0.0,
+            fir: DecimatingFir::new(vec![1.0], 1),
+            disc: FmDiscriminator::new(),
+            carrier: 0.0,
+            carrier_alpha: 1.0,
+            sideband_nco: Nco::new(0.0, AUDIO_RATE),
+            sideband_fir: DecimatingFir::new(vec![1.0], 1),
+            sample_rate: 0.0,
+            rate: AUDIO_RATE,
+            channel_power: 0.0,
+        };
+        demod.retune(1e6);
+        demod
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, AUDIO_RATE, CHANNEL_BANDWIDTH);
+        self.fir = fir;
+        self.rate = rate;
+        self.sample_rate = sample_rate;
+        self.nco.set_frequency(self.offset_hz, sample_rate);
+        self.carrier_alpha = (1.0 / (AM_CARRIER_TAU_SECS * rate)).min(1.0) as f32;
+        let half_width = (SSB_HIGH_HZ - SSB_LOW_HZ) / 2.0;
+        self.sideband_fir = DecimatingFir::new(lowpass_taps((half_width / rate) as f32, SIDEBAND_TAPS), 1);
+        self.set_mode(self.mode);
+    }
+
+    pub fn mode(&self) -> AudioMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: AudioMode) {
+        self.mode = mode;
+        let middle = (SSB_LOW_HZ + SSB_HIGH_HZ) / 2.0;
+        let shift = if mode == AudioMode::Lsb { -middle } else { middle };
+        self.sideband_nco.set_frequency(shift, self.rate);
+    }
+
+    /// Channel frequency relative to the tuned centre
+    pub fn offset_hz(&self) -> f64 {
+        self.offset_hz
+    }
+
+    /// Mean channel power over the last processed block, relative to full scale
+    pub fn channel_power(&self) -> f32 {
+        self.channel_power
+    }
+
+    pub fn set_offset(&mut self, offset_hz: f64) {
+        self.offset_hz = offset_hz;
+        self.nco.set_frequency(offset_hz, self.sample_rate);
+    }
+
+    /// Actual audio sample rate, close to [`AUDIO_RATE`]
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Demodulate `samples`, replacing the contents of `audio`
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64, audio: &mut Vec<f32>) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+        audio.clear();
+        let mut power = 0.0;
+        for &s in samples {
+            if let Some(x) = self.fir.push(self.nco.mix(s)) {
+                power += x.norm_sqr();
+                audio.push(self.demodulate(x));
+            }
+        }
+        if !audio.is_empty() {
+            self.channel_power = power / audio.len() as f32;
+        }
+    }
+
+    fn demodulate(&mut self, x: Complex32) -> f32 {
+        match self.mode {
+            AudioMode::Fm => self.disc.push(x),
+            AudioMode::Am => {
+                let envelope = x.norm();
+                self.carrier += self.carrier_alpha * (envelope - self.carrier);
+                (envelope - self.carrier) / self.carrier.max(1e-9) * FULL_SCALE
+            }
+            AudioMode::Usb | AudioMode::Lsb => {
+                // Weaver-style: filter the sideband around DC, then move it back and keep the
+                // real part, which folds it down to audio with the other sideband rejected
+                let lo = self.sideband_nco.next_lo();
+                self.sideband_fir.push(x * lo).map_or(0.0, |y| (y * lo.conj()).re * FULL_SCALE)
+            }
+        }
+    }
+}
+
+/// VU-style level meter with a held clip indicator for demodulated audio
+pub struct VuMeter {
+    power: f32,
+    clip_hold: usize,
+}
+
+impl VuMeter {
+    pub fn new() -> Self {
+        Self { power: 0.0, clip_hold: 0 }
+    }
+
+    /// Smoothed RMS level of the gain-scaled audio relative to full scale
+    pub fn level_db(&self) -> f32 {
+        10.0 * self.power.max(1e-12).log10()
+    }
+
+    /// Whether a sample reached full scale within the hold time
+    pub fn is_clipping(&self) -> bool {
+        self.clip_hold > 0
+    }
+
+    /// Meter `audio` from [`AudioDemod`] after applying `gain_db` of AF gain
+    pub fn process(&mut self, audio: &[f32], gain_db: f32, rate: f64) {
+        let gain = af_gain(gain_db);
+        let alpha = (1.0 / (VU_TAU_SECS * rate)).min(1.0) as f32;
+        for &a in audio {
+            let x = a * gain;
+            self.power += alpha * (x * x - self.power);
+            if x.abs() >= 1.0 {
+                self.clip_hold = (CLIP_HOLD_SECS * rate) as usize;
+            } else {
+                self.clip_hold = self.clip_hold.saturating_sub(1);
+            }
+        }
+    }
+}
+
+impl Default for VuMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Linear-interpolating rate converter for audio sent somewhere that needs
+/// one exact rate, running a sample behind so blocks join without a seam
+pub struct Resampler {
+    rate: f64,
+    /// Position of the next output sample in the next input block
+    phase: f64,
+    last: f32,
+}
+
+impl Resampler {
+    /// Convert to `rate` samples per second
+    pub fn new(rate: f64) -> Self {
+        Self { rate, phase: 0.0, last: 0.0 }
+    }
+
+    /// Append `input`, taken at `input_rate`, to `out` at the output rate
+    pub fn process(&mut self, input: &[f32], input_rate: f64, out: &mut Vec<f32>) {
+        let step = input_rate / self.rate;
+        while self.phase < input.len() as f64 {
+            let i = self.phase.floor() as usize;
+            let previous = if i == 0 { self.last } else { input[i - 1] };
+            let frac = (self.phase - i as f64) as f32;
+            out.push(previous + (input[i] - previous) * frac);
+            self.phase += step;
+        }
+        self.phase -= input.len() as f64;
+        if let Some(&last) = input.last() {
+            self.last = last;
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+use std::sync::Arc;
+
+use num_complex::Complex32;
+use rustfft::{Fft, FftPlanner};
+
+use super::{DecimatingFir, Nco};
+use super::measure::PowerSpectrum;
+
+const CHANNEL_RATE: f64 = 50e3;
+const CHANNEL_BANDWIDTH: f64 = 40e3;
+const FFT_SIZE: usize = 1024;
+/// Analysis block, four FFT frames
+const BLOCK: usize = 4 * FFT_SIZE;
+
+const MIN_SNR_DB: f32 = 6.0;
+/// One sideband must exceed the other by this much to call it SSB
+const SSB_ASYMMETRY_DB: f32 = 10.0;
+/// Share of the signal power in the strongest line for AM
+const AM_CARRIER_FRACTION: f32 = 0.3;
+/// Envelope coefficient of variation below which the signal is constant-envelope
+const CONSTANT_ENVELOPE_CV: f32 = 0.3;
+/// Peak-to-mean of the squared / fourth-power spectrum that marks PSK
+const PSK_LINE_RATIO: f32 = 30.0;
+/// Kurtosis of the instantaneous frequency below which it is two-level
+const FSK_KURTOSIS: f32 = 1.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalClass {
+    Noise,
+    Am,
+    Fm,
+    Usb,
+    Lsb,
+    Psk,
+    Fsk,
+    Unknown,
+}
+
+impl fmt::Display for SignalClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SignalClass::Noise => "NOISE",
+            SignalClass::Am => "AM",
+            SignalClass::Fm => "FM",
+            SignalClass::Usb => "SSB (USB)",
+            SignalClass::Lsb => "SSB (LSB)",
+            SignalClass::Psk => "PSK",
+            SignalClass::Fsk => "FSK",
+            SignalClass::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+/// Features measured on one block and the label derived from them
+#[derive(Clone, Copy, Debug)]
+pub struct Classification {
+    pub class: SignalClass,
+    pub snr_db: f32,
+    /// 99 % occupied bandwidth
+    pub bandwidth_hz: f32,
+    /// Upper minus lower sideband power
+    pub asymmetry_db: f32,
+    pub carrier_fraction: f32,
+    /// Standard deviation over mean of the envelope
+    pub envelope_cv: f32,
+    pub frequency_kurtosis: f32,
+    /// Strongest line in the squared or fourth-power spectrum, relative to its mean
+    pub psk_line: f32,
+}
+
+/// Heuristic AM/FM/SSB/PSK/FSK/noise labelling of the signal in one channel
+pub struct ModulationClassifier {
+    sample_rate: f64,
+    nco: Nco,
+    offset_hz: f64,
+    fir: DecimatingFir,
+    rate: f64,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    block: Vec<Complex32>,
+    pub latest: Option<Classification>,
+}
+
+impl ModulationClassifier {
+    pub fn new() -> Self {
+        let mut classifier = Self {
+            sample_rate: 0.0,
+            nco: Nco::new(0.0, 1e6),
+            offset_hz: 0.0,
+            fir: DecimatingFir::new(vec![1.0], 1),
+            rate: CHANNEL_RATE,
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window: (0..FFT_SIZE)
+                .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+                .collect(),
+            block: Vec::with_capacity(BLOCK),
+            latest: None,
+        };
+        classifier.retune(1e6);
+        classifier
+    }
+
+    fn retune(&mut self, sample_rate: f64) {
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, CHANNEL_RATE, CHANNEL_BANDWIDTH);
+        self.fir = fir;
+        self.rate = rate;
+        self.sample_rate = sample_rate;
+        self.nco.set_frequency(self.offset_hz, sample_rate);
+        self.block.clear();
+    }
+
+    /// Channel frequency relative to the tuned centre
+    pub fn offset_hz(&self) -> f64 {
+        self.offset_hz
+    }
+
+    /// Move to the channel at `offset_hz`, dropping what was gathered from the old one
+    pub fn set_offset(&mut self, offset_hz: f64) {
+        self.offset_hz = offset_hz;
+        self.nco.set_frequency(offset_hz, self.sample_rate);
+        self.block.clear();
+        self.latest = None;
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.retune(sample_rate);
+        }
+        for &s in samples {
+            if let Some(x) = self.fir.push(self.nco.mix(s)) {
+                self.block.push(x);
+                if self.block.len() == BLOCK {
+                    self.latest = Some(self.classify());
+                    self.block.clear();
+                }
+            }
+        }
+    }
+
+    /// Averaged power spectrum of `f(x)` over the block, DC in the middle
+    fn spectrum(&self, f: impl Fn(Complex32) -> Complex32) -> Vec<f32> {
+        let mut psd = vec![0.0f32; FFT_SIZE];
+        let mut frame = vec![Complex32::default(); FFT_SIZE];
+        for chunk in self.block.chunks_exact(FFT_SIZE) {
+            for ((out, &x), &w) in frame.iter_mut().zip(chunk).zip(&self.window) {
+                *out = f(x) * w;
+            }
+            self.fft.process(&mut frame);
+            for (i, v) in frame.iter().enumerate() {
+                psd[(i + FFT_SIZE / 2) % FFT_SIZE] += v.norm_sqr();
+            }
+        }
+        psd
+    }
+
+    fn classify(&self) -> Classification {
+        let bin_hz = self.rate / FFT_SIZE as f64;
+        let spectrum = PowerSpectrum {
+            bins: self.spectrum(|x| x),
+            bin_hz,
+        };
+        let noise = spectrum.noise_floor().max(1e-20);
+        let excess: Vec<f32> = spectrum.bins.iter().map(|&p| (p - noise).max(0.0)).collect();
+        let total: f32 = excess.iter().sum();
+
+        let half = self.rate / 2.0;
+        let bandwidth_hz = spectrum.occupied_bandwidth(-half, half, noise, 0.99);
+        let bins = (bandwidth_hz / bin_hz).max(1.0) as f32;
+        let snr_db = 10.0 * (total / (noise * bins)).max(1e-10).log10();
+
+        let centre = FFT_SIZE / 2;
+        let lower: f32 = excess[..centre - 1].iter().sum();
+        let upper: f32 = excess[centre + 2..].iter().sum();
+        let asymmetry_db = 10.0 * ((upper + 1e-20) / (lower + 1e-20)).log10();
+
+        let peak = (1..FFT_SIZE - 1)
+            .max_by(|&a, &b| excess[a].total_cmp(&excess[b]))
+            .unwrap_or(centre);
+        let carrier_fraction = excess[peak - 1..=peak + 1].iter().sum::<f32>() / total.max(1e-20);
+
+        let envelope: Vec<f32> = self.block.iter().map(|x| x.norm()).collect();
+        let (mean, var) = mean_var(&envelope);
+        let envelope_cv = var.sqrt() / mean.max(1e-20);
+
+        let freq: Vec<f32> = self.block.windows(2).map(|w| (w[1] * w[0].conj()).arg()).collect();
+        let (freq_mean, freq_var) = mean_var(&freq);
+        let fourth = freq.iter().map(|f| (f - freq_mean).powi(4)).sum::<f32>() / freq.len() as f32;
+        let frequency_kurtosis = fourth / (freq_var * freq_var).max(1e-20);
+
+        let line = |psd: Vec<f32>| {
+            let mean = psd.iter().sum::<f32>() / psd.len() as f32;
+            psd.iter().fold(0.0f32, |m, &p| m.max(p)) / mean.max(1e-20)
+        };
+        let psk_line = line(self.spectrum(|x| x * x)).max(line(self.spectrum(|x| (x * x) * (x * x))));
+
+        let class = if snr_db < MIN_SNR_DB {
+            SignalClass::Noise
+        } else if asymmetry_db > SSB_ASYMMETRY_DB {
+            SignalClass::Usb
+        } else if asymmetry_db < -SSB_ASYMMETRY_DB {
+            SignalClass::Lsb
+        } else if carrier_fraction > AM_CARRIER_FRACTION && envelope_cv > 0.05 {
+            SignalClass::Am
+        } else if envelope_cv < CONSTANT_ENVELOPE_CV {
+            if frequency_kurtosis < FSK_KURTOSIS {
+                SignalClass::Fsk
+            } else if psk_line > PSK_LINE_RATIO {
+                SignalClass::Psk
+            } else {
+                SignalClass::Fm
+            }
+        } else if psk_line > PSK_LINE_RATIO {
+            SignalClass::Psk
+        } else {
+            SignalClass::Unknown
+        };
+
+        Classification {
+            class,
+            snr_db,
+            bandwidth_hz: bandwidth_hz as f32,
+            asymmetry_db,
+            carrier_fraction,
+            envelope_cv,
+            frequency_kurtosis,
+            psk_line,
+        }
+    }
+}
+
+impl Default for ModulationClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn mean_var(values: &[f32]) -> (f32, f32) {
+    let n = values.len().max(1) as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    let var = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / n;
+    (mean, var)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// AM at 100 kHz from the centre over a little noise
+    fn off_centre_am(rate: f64) -> Vec<Complex32> {
+        let mut seed = 1u32;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+        };
+        (0..200_000)
+            .map(|i| {
+                let t = i as f64 / rate;
+                let envelope = 1.0 + 0.5 * (2.0 * std::f64::consts::PI * 1e3 * t).sin();
+                let carrier = Complex32::from_polar(1.0, (2.0 * std::f64::consts::PI * 100e3 * t) as f32);
+                carrier * (0.1 * envelope as f32) + Complex32::new(noise(), noise()) * 0.01
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classifies_the_channel_it_is_set_to() {
+        let rate = 1e6;
+        let samples = off_centre_am(rate);
+        let mut classifier = ModulationClassifier::new();
+        classifier.process(&samples, rate);
+        assert_eq!(classifier.latest.map(|c| c.class), Some(SignalClass::Noise));
+
+        classifier.set_offset(100e3);
+        assert!(classifier.latest.is_none());
+        classifier.process(&samples, rate);
+        assert_eq!(classifier.latest.map(|c| c.class), Some(SignalClass::Am));
+    }
+}
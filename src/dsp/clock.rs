@@ -0,0 +1,35 @@
+/// Zero-crossing symbol clock recovery for binary (NRZ) signals
+pub struct ClockRecovery {
+    step: f32,
+    phase: f32,
+    last: f32,
+    gain: f32,
+}
+
+impl ClockRecovery {
+    pub fn new(sample_rate: f64, baud: f64) -> Self {
+        Self {
+            step: (baud / sample_rate) as f32,
+            phase: 0.0,
+            last: 0.0,
+            gain: 0.3,
+        }
+    }
+
+    /// Push one soft sample, returning the sliced bit at each symbol centre
+    pub fn push(&mut self, sample: f32) -> Option<bool> {
+        // A transition should sit half a symbol away from the sampling instant
+        if (sample > 0.0) != (self.last > 0.0) {
+            self.phase += (0.5 - self.phase) * self.gain;
+        }
+        self.last = sample;
+
+        self.phase += self.step;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            Some(sample > 0.0)
+        } else {
+            None
+        }
+    }
+}
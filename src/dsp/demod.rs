@@ -0,0 +1,26 @@
+use num_complex::Complex32;
+
+/// Quadrature FM discriminator, output is the phase step per sample in radians
+pub struct FmDiscriminator {
+    last: Complex32,
+}
+
+impl FmDiscriminator {
+    pub fn new() -> Self {
+        Self {
+            last: Complex32::new(0.0, 0.0),
+        }
+    }
+
+    pub fn push(&mut self, sample: Complex32) -> f32 {
+        let out = (sample * self.last.conj()).arg();
+        self.last = sample;
+        out
+    }
+}
+
+impl Default for FmDiscriminator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,117 @@
+use num_complex::Complex32;
+
+use super::simd;
+
+/// Windowed-sinc lowpass taps, `cutoff` is normalised to the sample rate (0..0.5)
+pub fn lowpass_taps(cutoff: f32, num_taps: usize) -> Vec<f32> {
+    let mid = (num_taps - 1) as f32 / 2.0;
+    let mut taps: Vec<f32> = (0..num_taps)
+        .map(|i| {
+            let n = i as f32 - mid;
+            let sinc = if n == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * std::f32::consts::PI * cutoff * n).sin() / (std::f32::consts::PI * n)
+            };
+            // Hamming window
+            let window = 0.54
+                - 0.46 * (2.0 * std::f32::consts::PI * i as f32 / (num_taps - 1) as f32).cos();
+            sinc * window
+        })
+        .collect();
+
+    // Normalise for unity gain at DC
+    let sum: f32 = taps.iter().sum();
+    if sum != 0.0 {
+        taps.iter_mut().for_each(|t| *t /= sum);
+    }
+    taps
+}
+
+/// Largest decimation applied by a single filter stage
+const MAX_STAGE_DECIMATION: usize = 8;
+
+/// One FIR stage that only computes every `decimation`-th output
+struct Stage {
+    /// Taps oldest sample first, the reverse of the impulse response
+    taps: Vec<f32>,
+    /// Each sample is kept twice, at `pos` and one length on, so that the
+    /// newest `taps.len()` always lie in one slice for the vector kernel
+    history: Vec<Complex32>,
+    pos: usize,
+    decimation: usize,
+    counter: usize,
+}
+
+impl Stage {
+    fn new(mut taps: Vec<f32>, decimation: usize) -> Self {
+        let len = taps.len();
+        taps.reverse();
+        Self {
+            taps,
+            history: vec![Complex32::new(0.0, 0.0); 2 * len],
+            pos: 0,
+            decimation: decimation.max(1),
+            counter: 0,
+        }
+    }
+
+    fn push(&mut self, sample: Complex32) -> Option<Complex32> {
+        let len = self.taps.len();
+        self.history[self.pos] = sample;
+        self.history[self.pos + len] = sample;
+        self.pos = (self.pos + 1) % len;
+
+        self.counter += 1;
+        if self.counter < self.decimation {
+            return None;
+        }
+        self.counter = 0;
+
+        Some(simd::fir(&self.taps, &self.history[self.pos..self.pos + len]))
+    }
+}
+
+/// Complex FIR lowpass decimator, split into cascaded stages for large ratios
+pub struct DecimatingFir {
+    stages: Vec<Stage>,
+}
+
+impl DecimatingFir {
+    pub fn new(taps: Vec<f32>, decimation: usize) -> Self {
+        Self {
+            stages: vec![Stage::new(taps, decimation)],
+        }
+    }
+
+    /// Build a lowpass decimator reducing `input_rate` to roughly `target_rate`.
+    /// Returns the filter and the actual output rate.
+    pub fn for_rates(input_rate: f64, target_rate: f64, bandwidth: f64) -> (Self, f64) {
+        let mut stages = Vec::new();
+        let mut rate = input_rate;
+
+        // Coarse stages only need to protect the band of the following stage
+        while rate / target_rate >= (MAX_STAGE_DECIMATION * 2) as f64 {
+            let cutoff = 0.4 / MAX_STAGE_DECIMATION as f32;
+            stages.push(Stage::new(
+                lowpass_taps(cutoff, MAX_STAGE_DECIMATION * 8 + 1),
+                MAX_STAGE_DECIMATION,
+            ));
+            rate /= MAX_STAGE_DECIMATION as f64;
+        }
+
+        let decimation = ((rate / target_rate).floor() as usize).max(1);
+        let cutoff = ((bandwidth / 2.0) / rate).min(0.45) as f32;
+        let num_taps = (decimation * 8 + 1).clamp(31, 255);
+        stages.push(Stage::new(lowpass_taps(cutoff, num_taps), decimation));
+
+        (Self { stages }, rate / decimation as f64)
+    }
+
+    /// Push one input sample, returning an output sample when one is due
+    pub fn push(&mut self, sample: Complex32) -> Option<Complex32> {
+        self.stages
+            .iter_mut()
+            .try_fold(sample, |sample, stage| stage.push(sample))
+    }
+}
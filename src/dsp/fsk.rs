@@ -0,0 +1,129 @@
+use num_complex::Complex32;
+
+use super::{ClockRecovery, DecimatingFir, FmDiscriminator, Nco};
+
+/// Parameters of a binary FSK channel
+#[derive(Clone, Debug)]
+pub struct FskConfig {
+    /// Channel centre relative to the tuned frequency
+    pub offset_hz: f64,
+    /// Peak deviation, the tones sit at `offset_hz ± deviation`
+    pub deviation: f64,
+    pub baud: f64,
+    /// Sync word (MSB first) and its length in bits, at most 64
+    pub sync_word: Option<(u64, u32)>,
+    /// Bit errors tolerated when matching the sync word
+    pub sync_errors: u32,
+}
+
+impl FskConfig {
+    pub fn new(deviation: f64, baud: f64) -> Self {
+        Self {
+            offset_hz: 0.0,
+            deviation,
+            baud,
+            sync_word: None,
+            sync_errors: 0,
+        }
+    }
+
+    /// Carson bandwidth of the channel
+    pub fn bandwidth(&self) -> f64 {
+        2.0 * (self.deviation + self.baud)
+    }
+}
+
+/// Polarity of a matched sync word
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncMatch {
+    Normal,
+    /// The word matched with every bit flipped, e.g. swapped mark and space
+    Inverted,
+}
+
+/// One demodulated symbol
+#[derive(Clone, Copy, Debug)]
+pub struct FskBit {
+    /// Upper tone is a one
+    pub value: bool,
+    /// Discriminator output scaled so the nominal tones read ±1
+    pub soft: f32,
+    /// Set when this bit completed the configured sync word
+    pub sync: Option<SyncMatch>,
+}
+
+/// Mixer, channel filter, discriminator and clock recovery for one FSK channel
+pub struct FskDemod {
+    config: FskConfig,
+    nco: Nco,
+    fir: DecimatingFir,
+    disc: FmDiscriminator,
+    clock: ClockRecovery,
+    /// Sample rate after the channel filter
+    rate: f64,
+    scale: f32,
+    shift: u64,
+    mask: u64,
+}
+
+impl FskDemod {
+    pub fn new(config: FskConfig, sample_rate: f64) -> Self {
+        let bandwidth = config.bandwidth();
+        let target = (2.0 * bandwidth).max(5.0 * config.baud);
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, target, bandwidth);
+        let mask = match config.sync_word {
+            Some((_, 64)) => u64::MAX,
+            Some((_, bits)) => (1 << bits) - 1,
+            None => 0,
+        };
+        Self {
+            nco: Nco::new(config.offset_hz, sample_rate),
+            fir,
+            disc: FmDiscriminator::new(),
+            clock: ClockRecovery::new(rate, config.baud),
+            rate,
+            scale: (rate / (2.0 * std::f64::consts::PI * config.deviation)) as f32,
+            shift: 0,
+            mask,
+            config,
+        }
+    }
+
+    /// Sample rate of [`push_soft`](Self::push_soft)'s output
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Push one IQ sample, returning the discriminator output for each
+    /// sample left after the channel filter, for a decoder that recovers
+    /// the clock itself, such as at several bauds at once
+    pub fn push_soft(&mut self, sample: Complex32) -> Option<f32> {
+        let baseband = self.fir.push(self.nco.mix(sample))?;
+        Some(self.disc.push(baseband) * self.scale)
+    }
+
+    /// Push one IQ sample, returning a bit at each symbol centre
+    pub fn push(&mut self, sample: Complex32) -> Option<FskBit> {
+        let soft = self.push_soft(sample)?;
+        let value = self.clock.push(soft)?;
+
+        self.shift = (self.shift << 1) | value as u64;
+        let sync = self.config.sync_word.and_then(|(word, _)| {
+            let errors = ((self.shift ^ word) & self.mask).count_ones();
+            let inverted_errors = ((!self.shift ^ word) & self.mask).count_ones();
+            if errors <= self.config.sync_errors {
+                Some(SyncMatch::Normal)
+            } else if inverted_errors <= self.config.sync_errors {
+                Some(SyncMatch::Inverted)
+            } else {
+                None
+            }
+        });
+        Some(FskBit { value, soft, sync })
+    }
+
+    /// Demodulate a block, appending every recovered bit to `bits`
+    pub fn process(&mut self, samples: &[Complex32], bits: &mut Vec<FskBit>) {
+        bits.extend(samples.iter().filter_map(|&s| self.push(s)));
+    }
+}
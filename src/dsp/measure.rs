@@ -0,0 +1,409 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use num_complex::Complex32;
+use rustfft::{Fft, FftPlanner};
+
+use super::simd;
+use crate::recording::iso_timestamp;
+
+const FFT_SIZE: usize = 2048;
+const AVERAGES: usize = 4;
+/// Share of the signal power that defines the occupied bandwidth
+const OCCUPIED_FRACTION: f32 = 0.99;
+/// S9 level and width of one S unit, IARU Region 1 HF convention
+pub const S9_DBM: f32 = -73.0;
+const DB_PER_S_UNIT: f32 = 6.0;
+/// S-meter ballistics, fast attack and slow decay
+const S_METER_ATTACK_SECS: f32 = 0.01;
+const S_METER_DECAY_SECS: f32 = 0.5;
+/// Histogram bins across -1..1 full scale
+const HISTOGRAM_BINS: usize = 128;
+/// Samples over which old histogram counts fade out
+const HISTOGRAM_MEMORY: f32 = 1e6;
+/// Sample values at or beyond this share of full scale are on the rails
+const RAIL_LEVEL: f32 = 0.99;
+/// Share of samples on the rails within a block that counts as overload
+const OVERLOAD_FRACTION: f32 = 1e-3;
+const OVERLOAD_HOLD_SECS: f64 = 1.0;
+const CSV_HEADER: &str = "utc,center_hz,bandwidth_hz,channel_power_db,noise_power_db,snr_db,occupied_bw_hz";
+
+/// Median of `values`, zero when empty
+pub fn median(values: &[f32]) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted.get(sorted.len() / 2).copied().unwrap_or(0.0)
+}
+
+/// Power spectrum with DC in the middle, scaled so the bins sum to the mean sample power
+#[derive(Clone, Debug)]
+pub struct PowerSpectrum {
+    pub bins: Vec<f32>,
+    pub bin_hz: f64,
+}
+
+impl PowerSpectrum {
+    /// Bin index range covering `low_hz..high_hz` relative to the centre
+    fn bin_range(&self, low_hz: f64, high_hz: f64) -> std::ops::Range<usize> {
+        let centre = (self.bins.len() / 2) as f64;
+        let to_bin = |hz: f64| (centre + hz / self.bin_hz).round().clamp(0.0, self.bins.len() as f64) as usize;
+        to_bin(low_hz)..to_bin(high_hz).max(to_bin(low_hz) + 1).min(self.bins.len())
+    }
+
+    /// Median bin power, robust as long as signals cover less than half the span
+    pub fn noise_floor(&self) -> f32 {
+        median(&self.bins)
+    }
+
+    pub fn band_power(&self, low_hz: f64, high_hz: f64) -> f32 {
+        self.bins[self.bin_range(low_hz, high_hz)].iter().sum()
+    }
+
+    /// Width holding `fraction` of the power above `floor` between the limits
+    pub fn occupied_bandwidth(&self, low_hz: f64, high_hz: f64, floor: f32, fraction: f32) -> f64 {
+        let range = self.bin_range(low_hz, high_hz);
+        let excess: Vec<f32> = self.bins[range].iter().map(|&p| (p - floor).max(0.0)).collect();
+        let total: f32 = excess.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let tail = (1.0 - fraction) / 2.0 * total;
+        let mut cumulative = 0.0;
+        let (mut low, mut high) = (0, excess.len() - 1);
+        for (i, &p) in excess.iter().enumerate() {
+            let before = cumulative;
+            cumulative += p;
+            if before <= tail && cumulative > tail {
+                low = i;
+            }
+            if before <= total - tail && cumulative > total - tail {
+                high = i;
+            }
+        }
+        (high.saturating_sub(low) + 1) as f64 * self.bin_hz
+    }
+}
+
+/// Window applied to each frame before the FFT
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    Hann,
+    /// No window, the finest resolution and the most leakage
+    Rectangular,
+    /// Low leakage, for weak signals beside strong ones
+    BlackmanHarris,
+    /// Accurate tone levels, the coarsest resolution
+    FlatTop,
+}
+
+impl Window {
+    pub const ALL: [Window; 4] = [Window::Hann, Window::Rectangular, Window::BlackmanHarris, Window::FlatTop];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Window::Hann => "hann",
+            Window::Rectangular => "rectangular",
+            Window::BlackmanHarris => "blackman-harris",
+            Window::FlatTop => "flat-top",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|window| window.name() == text)
+    }
+
+    /// Cosine series coefficients
+    fn terms(self) -> &'static [f32] {
+        match self {
+            Window::Hann => &[0.5, 0.5],
+            Window::Rectangular => &[1.0],
+            Window::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            Window::FlatTop => &[0.21557895, 0.41663158, 0.27726316, 0.083578947, 0.006947368],
+        }
+    }
+
+    /// `size` periodic window samples
+    pub fn coefficients(self, size: usize) -> Vec<f32> {
+        (0..size)
+            .map(|i| {
+                let x = 2.0 * std::f32::consts::PI * i as f32 / size as f32;
+                let terms = self.terms().iter().enumerate();
+                terms.map(|(k, &a)| if k % 2 == 0 { a } else { -a } * (k as f32 * x).cos()).sum()
+            })
+            .collect()
+    }
+}
+
+/// Welch-averaged power spectrum estimator
+pub struct SpectrumEstimator {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// Normalisation so that the bins sum to the mean sample power
+    scale: f32,
+    frame: Vec<Complex32>,
+    accumulated: Vec<f32>,
+    frames: usize,
+    averages: usize,
+}
+
+impl SpectrumEstimator {
+    pub fn new(size: usize, averages: usize) -> Self {
+        Self::with_window(size, averages, Window::Hann)
+    }
+
+    pub fn with_window(size: usize, averages: usize, window: Window) -> Self {
+        let window = window.coefficients(size);
+        let window_power: f32 = window.iter().map(|w| w * w).sum();
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(size),
+            scale: 1.0 / (size as f32 * window_power),
+            window,
+            frame: Vec::with_capacity(size),
+            accumulated: vec![0.0; size],
+            frames: 0,
+            averages,
+        }
+    }
+
+    /// Feed samples, returning a spectrum once enough frames have been averaged
+    pub fn push(&mut self, samples: &[Complex32], sample_rate: f64) -> Option<PowerSpectrum> {
+        let size = self.window.len();
+        let mut result = None;
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let take = (size - self.frame.len()).min(rest.len());
+            self.frame.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.frame.len() < size {
+                continue;
+            }
+            simd::apply_window(&mut self.frame, &self.window);
+            self.fft.process(&mut self.frame);
+            // Negative frequencies first
+            let half = size / 2;
+            simd::accumulate_power(&self.frame[..size - half], self.scale, &mut self.accumulated[half..]);
+            simd::accumulate_power(&self.frame[size - half..], self.scale, &mut self.accumulated[..half]);
+            self.frame.clear();
+            self.frames += 1;
+            if self.frames == self.averages {
+                let n = self.frames as f32;
+                result = Some(PowerSpectrum {
+                    bins: self.accumulated.iter().map(|p| p / n).collect(),
+                    bin_hz: sample_rate / size as f64,
+                });
+                self.accumulated.iter_mut().for_each(|p| *p = 0.0);
+                self.frames = 0;
+            }
+        }
+        result
+    }
+}
+
+/// Channel measurements over the selected passband
+#[derive(Clone, Copy, Debug)]
+pub struct Measurement {
+    pub time: SystemTime,
+    pub center_freq: f64,
+    pub bandwidth_hz: f64,
+    /// Total power in the passband, dB relative to full scale
+    pub channel_power_db: f32,
+    /// Noise floor integrated over the passband
+    pub noise_power_db: f32,
+    pub snr_db: f32,
+    pub occupied_bandwidth_hz: f64,
+}
+
+fn db(power: f32) -> f32 {
+    10.0 * power.max(1e-20).log10()
+}
+
+/// Channel power, noise, SNR and occupied bandwidth of a passband around the tuned centre
+pub struct ChannelMeter {
+    estimator: SpectrumEstimator,
+    pub bandwidth_hz: f64,
+    pub latest: Option<Measurement>,
+    log: Option<File>,
+}
+
+impl ChannelMeter {
+    pub fn new(bandwidth_hz: f64) -> Self {
+        Self {
+            estimator: SpectrumEstimator::new(FFT_SIZE, AVERAGES),
+            bandwidth_hz,
+            latest: None,
+            log: None,
+        }
+    }
+
+    /// Append each measurement to the CSV file at `path`, or stop logging with `None`
+    pub fn set_log(&mut self, path: Option<&str>) -> io::Result<()> {
+        self.log = match path {
+            Some(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "{}", CSV_HEADER)?;
+                }
+                Some(file)
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        let Some(spectrum) = self.estimator.push(samples, sample_rate) else {
+            return;
+        };
+        let half = self.bandwidth_hz.min(sample_rate) / 2.0;
+        let floor = spectrum.noise_floor();
+        let bins = spectrum.bin_range(-half, half).len() as f32;
+        let channel = spectrum.band_power(-half, half);
+        let noise = floor * bins;
+
+        let measurement = Measurement {
+            time: SystemTime::now(),
+            center_freq,
+            bandwidth_hz: self.bandwidth_hz,
+            channel_power_db: db(channel),
+            noise_power_db: db(noise),
+            snr_db: db((channel - noise).max(0.0) / noise.max(1e-20)),
+            occupied_bandwidth_hz: spectrum.occupied_bandwidth(-half, half, floor, OCCUPIED_FRACTION),
+        };
+
+        if let Some(log) = &mut self.log
+            && writeln!(
+                log,
+                "{},{:.0},{:.0},{:.2},{:.2},{:.2},{:.0}",
+                iso_timestamp(measurement.time),
+                measurement.center_freq,
+                measurement.bandwidth_hz,
+                measurement.channel_power_db,
+                measurement.noise_power_db,
+                measurement.snr_db,
+                measurement.occupied_bandwidth_hz
+            )
+            .is_err()
+        {
+            self.log = None;
+        }
+        self.latest = Some(measurement);
+    }
+}
+
+/// Signal strength meter with analogue-style ballistics
+pub struct SMeter {
+    level_dbm: f32,
+}
+
+impl SMeter {
+    pub fn new() -> Self {
+        Self { level_dbm: S9_DBM - 9.0 * DB_PER_S_UNIT }
+    }
+
+    pub fn level_dbm(&self) -> f32 {
+        self.level_dbm
+    }
+
+    /// Follow `dbm` over `seconds` of signal
+    pub fn update(&mut self, dbm: f32, seconds: f32) {
+        let tau = if dbm > self.level_dbm { S_METER_ATTACK_SECS } else { S_METER_DECAY_SECS };
+        self.level_dbm += (1.0 - (-seconds / tau).exp()) * (dbm - self.level_dbm);
+    }
+
+    /// Reading such as `S7` or `S9+20`
+    pub fn reading(&self) -> String {
+        let over = self.level_dbm - S9_DBM;
+        if over > 0.0 {
+            format!("S9+{:.0}", over)
+        } else {
+            format!("S{}", (9.0 + over / DB_PER_S_UNIT).floor().max(0.0) as u8)
+        }
+    }
+
+    /// Needle position from S0 to S9+60
+    pub fn ratio(&self) -> f32 {
+        let s0 = S9_DBM - 9.0 * DB_PER_S_UNIT;
+        ((self.level_dbm - s0) / (S9_DBM + 60.0 - s0)).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for SMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Distribution of I and Q sample values for spotting ADC clipping and quantisation
+pub struct SampleHistogram {
+    i: Vec<f32>,
+    q: Vec<f32>,
+    peak: f32,
+    overload_hold: usize,
+}
+
+impl SampleHistogram {
+    pub fn new() -> Self {
+        Self {
+            i: vec![0.0; HISTOGRAM_BINS],
+            q: vec![0.0; HISTOGRAM_BINS],
+            peak: 0.0,
+            overload_hold: 0,
+        }
+    }
+
+    /// Faded sample counts of I and Q, bins spread evenly over -1..1 full scale
+    pub fn bins(&self) -> (&[f32], &[f32]) {
+        (&self.i, &self.q)
+    }
+
+    /// Largest I or Q magnitude seen recently, dB relative to full scale
+    pub fn peak_dbfs(&self) -> f32 {
+        20.0 * self.peak.max(1e-10).log10()
+    }
+
+    /// Bins holding samples, few of them under a strong signal points at coarse quantisation
+    pub fn occupied_bins(&self) -> usize {
+        self.i.iter().zip(&self.q).filter(|&(&i, &q)| i.max(q) >= 1.0).count()
+    }
+
+    /// Whether the distribution hit the rails within the hold time
+    pub fn is_overloaded(&self) -> bool {
+        self.overload_hold > 0
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        let fade = (-(samples.len() as f32) / HISTOGRAM_MEMORY).exp();
+        self.i.iter_mut().chain(self.q.iter_mut()).for_each(|count| *count *= fade);
+        self.peak *= fade;
+
+        let bin = |x: f32| (((x + 1.0) / 2.0 * HISTOGRAM_BINS as f32) as usize).min(HISTOGRAM_BINS - 1);
+        let mut on_rails = 0;
+        for s in samples {
+            self.i[bin(s.re.clamp(-1.0, 1.0))] += 1.0;
+            self.q[bin(s.im.clamp(-1.0, 1.0))] += 1.0;
+            let largest = s.re.abs().max(s.im.abs());
+            self.peak = self.peak.max(largest);
+            if largest >= RAIL_LEVEL {
+                on_rails += 1;
+            }
+        }
+
+        if on_rails as f32 > OVERLOAD_FRACTION * samples.len() as f32 {
+            self.overload_hold = (OVERLOAD_HOLD_SECS * sample_rate) as usize;
+        } else {
+            self.overload_hold = self.overload_hold.saturating_sub(samples.len());
+        }
+    }
+}
+
+impl Default for SampleHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,34 @@
+use num_complex::Complex32;
+
+/// Numerically controlled oscillator used to shift a channel down to baseband
+pub struct Nco {
+    phase: f64,
+    step: f64,
+}
+
+impl Nco {
+    /// `offset_hz` is the channel frequency relative to the tuned centre
+    pub fn new(offset_hz: f64, sample_rate: f64) -> Self {
+        Self {
+            phase: 0.0,
+            step: 2.0 * std::f64::consts::PI * offset_hz / sample_rate,
+        }
+    }
+
+    /// Retune without resetting the phase, keeping the output continuous
+    pub fn set_frequency(&mut self, offset_hz: f64, sample_rate: f64) {
+        self.step = 2.0 * std::f64::consts::PI * offset_hz / sample_rate;
+    }
+
+    /// Advance the oscillator, returning the down-conversion local oscillator value
+    pub fn next_lo(&mut self) -> Complex32 {
+        let lo = Complex32::from_polar(1.0, -self.phase as f32);
+        self.phase = (self.phase + self.step) % (2.0 * std::f64::consts::PI);
+        lo
+    }
+
+    /// Mix one sample down by the oscillator frequency
+    pub fn mix(&mut self, sample: Complex32) -> Complex32 {
+        sample * self.next_lo()
+    }
+}
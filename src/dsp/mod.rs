@@ -0,0 +1,24 @@
+//! Signal processing building blocks shared by the decoders.
+
+pub mod audio;
+pub mod classify;
+pub mod clock;
+pub mod demod;
+pub mod filter;
+pub mod fsk;
+pub mod measure;
+pub mod mixer;
+pub mod pipeline;
+pub mod psk;
+pub mod ring;
+pub mod simd;
+
+pub use audio::{AudioDemod, AudioMode, Resampler, VuMeter, af_gain};
+pub use classify::ModulationClassifier;
+pub use clock::ClockRecovery;
+pub use demod::FmDiscriminator;
+pub use filter::DecimatingFir;
+pub use fsk::{FskBit, FskConfig, FskDemod, SyncMatch};
+pub use measure::{ChannelMeter, SampleHistogram, SMeter};
+pub use mixer::Nco;
+pub use psk::{PskConfig, PskDemod, PskOrder};
@@ -0,0 +1,136 @@
+//! Worker threads for the heavy processing, so that a fast stream does not
+//! hold up whoever drives the receiver, the terminal above all.
+//!
+//! Each [`Worker`] owns a stage, such as a decoder or the spectrum FFT, on a
+//! thread of its own and takes blocks from a single-producer
+//! single-consumer queue. The receiver hands every stage the same shared
+//! [`SampleBlock`] and reads a stage's state between blocks through
+//! [`Worker::lock`]. A stage that falls behind loses blocks rather than
+//! delaying the others.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use num_complex::Complex32;
+
+/// Blocks queued per stage before new ones are dropped for it
+pub const QUEUE_BLOCKS: usize = 32;
+/// How often [`Worker::wait`] checks on the stage
+const WAIT_POLL: Duration = Duration::from_millis(1);
+
+/// IQ from the source, shared by the stages it is handed to
+#[derive(Clone)]
+pub struct SampleBlock {
+    pub iq: Arc<[Complex32]>,
+    pub center_freq: f64,
+    pub sample_rate: f64,
+}
+
+/// Sending end of a queue made by [`spsc`]
+pub struct Producer<T> {
+    tx: SyncSender<T>,
+}
+
+/// Receiving end of a queue made by [`spsc`]
+pub struct Consumer<T> {
+    rx: Receiver<T>,
+}
+
+/// A queue holding up to `capacity` items, with one end for each of two
+/// threads. Neither end can be cloned.
+pub fn spsc<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let (tx, rx) = mpsc::sync_channel(capacity);
+    (Producer { tx }, Consumer { rx })
+}
+
+impl<T> Producer<T> {
+    /// Queue `item`, handing it back when the queue is full or the consumer
+    /// has gone
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        self.tx.try_send(item).map_err(|e| match e {
+            mpsc::TrySendError::Full(item) | mpsc::TrySendError::Disconnected(item) => item,
+        })
+    }
+}
+
+impl<T> Consumer<T> {
+    /// The next item if one is waiting
+    pub fn pop(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+
+    /// The next item, waiting for it, or None once the producer has gone
+    pub fn recv(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+/// A stage of type `T` running on its own thread
+pub struct Worker<T> {
+    name: String,
+    state: Arc<Mutex<T>>,
+    queue: Producer<SampleBlock>,
+    /// Blocks queued and not yet processed
+    pending: Arc<AtomicUsize>,
+    /// Samples the stage lost because it could not keep up
+    pub dropped: u64,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    /// Start a thread passing each block pushed to `process` with the stage.
+    /// The thread ends when the worker is dropped.
+    pub fn spawn(name: &str, state: T, mut process: impl FnMut(&mut T, &SampleBlock) + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(state));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let (queue, mut blocks) = spsc::<SampleBlock>(QUEUE_BLOCKS);
+        let (shared, done) = (Arc::clone(&state), Arc::clone(&pending));
+        thread::Builder::new()
+            .name(format!("dsp-{}", name))
+            .spawn(move || {
+                while let Some(block) = blocks.recv() {
+                    process(&mut shared.lock().unwrap_or_else(|e| e.into_inner()), &block);
+                    done.fetch_sub(1, Ordering::Release);
+                }
+            })
+            .expect("cannot start a DSP worker thread");
+        Self {
+            name: name.to_string(),
+            state,
+            queue,
+            pending,
+            dropped: 0,
+        }
+    }
+}
+
+impl<T> Worker<T> {
+    /// Queue `block` for the stage, dropping it if the stage is too far behind
+    pub fn push(&mut self, block: &SampleBlock) {
+        self.pending.fetch_add(1, Ordering::Acquire);
+        if self.queue.push(block.clone()).is_err() {
+            self.pending.fetch_sub(1, Ordering::Release);
+            log::warn!("{}: falling behind, samples dropped", self.name);
+            self.dropped += block.iq.len() as u64;
+        }
+    }
+
+    /// The stage, waiting for any block it is processing
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether every queued block has been processed
+    pub fn is_idle(&self) -> bool {
+        self.pending.load(Ordering::Acquire) == 0
+    }
+
+    /// Wait until every queued block has been processed
+    pub fn wait(&self) {
+        while !self.is_idle() {
+            thread::sleep(WAIT_POLL);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use num_complex::Complex32;
+
+use super::{DecimatingFir, Nco};
+
+/// Samples per symbol fed to the timing loop
+const SAMPLES_PER_SYMBOL: f64 = 8.0;
+/// Costas loop proportional and integral gains, per symbol
+const CARRIER_ALPHA: f32 = 0.1;
+const CARRIER_BETA: f32 = 0.004;
+/// Frequency-locked loop gain, pulls in offsets beyond the Costas loop's reach
+const FLL_GAIN: f32 = 0.05;
+/// Gardner loop gain in symbols per unit of timing error
+const TIMING_GAIN: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PskOrder {
+    Bpsk,
+    Qpsk,
+}
+
+impl PskOrder {
+    pub fn name(self) -> &'static str {
+        match self {
+            PskOrder::Bpsk => "BPSK",
+            PskOrder::Qpsk => "QPSK",
+        }
+    }
+
+    pub fn bits_per_symbol(self) -> usize {
+        match self {
+            PskOrder::Bpsk => 1,
+            PskOrder::Qpsk => 2,
+        }
+    }
+}
+
+/// Parameters of a PSK channel
+#[derive(Clone, Debug)]
+pub struct PskConfig {
+    /// Channel centre relative to the tuned frequency
+    pub offset_hz: f64,
+    pub baud: f64,
+    pub order: PskOrder,
+}
+
+impl PskConfig {
+    pub fn new(order: PskOrder, baud: f64) -> Self {
+        Self {
+            offset_hz: 0.0,
+            baud,
+            order,
+        }
+    }
+}
+
+/// Coherent BPSK/QPSK demodulator with Costas carrier and Gardner timing recovery
+pub struct PskDemod {
+    config: PskConfig,
+    sample_rate: f64,
+    nco: Nco,
+    fir: DecimatingFir,
+    rate: f64,
+    agc: f32,
+    /// Carrier phase and frequency in radians per channel sample
+    phase: f32,
+    freq: f32,
+    /// Samples until the next half-symbol strobe, and the current half-symbol length
+    countdown: f32,
+    half_symbol: f32,
+    at_mid: bool,
+    prev: Complex32,
+    mid: Complex32,
+    last_symbol: Complex32,
+    last_stripped: Complex32,
+    /// Smoothed squared distance of the symbols from the ideal points
+    error_power: f32,
+    /// Channel samples since the last symbol strobe
+    since_symbol: f32,
+    /// Most recent channel sample and its time relative to the nearest symbol centre
+    eye: Option<(f32, Complex32)>,
+}
+
+impl PskDemod {
+    pub fn new(config: PskConfig, sample_rate: f64) -> Self {
+        let bandwidth = 2.0 * config.baud;
+        let (fir, rate) = DecimatingFir::for_rates(sample_rate, SAMPLES_PER_SYMBOL * config.baud, bandwidth);
+        let half_symbol = (rate / config.baud / 2.0) as f32;
+        Self {
+            nco: Nco::new(config.offset_hz, sample_rate),
+            fir,
+            rate,
+            agc: 1.0,
+            phase: 0.0,
+            freq: 0.0,
+            countdown: half_symbol,
+            half_symbol,
+            at_mid: false,
+            prev: Complex32::default(),
+            mid: Complex32::default(),
+            last_symbol: Complex32::default(),
+            last_stripped: Complex32::default(),
+            error_power: 1.0,
+            since_symbol: 0.0,
+            eye: None,
+            config,
+            sample_rate,
+        }
+    }
+
+    pub fn config(&self) -> &PskConfig {
+        &self.config
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Residual carrier offset tracked by the Costas loop in Hz
+    pub fn frequency_offset(&self) -> f64 {
+        self.freq as f64 * self.rate / (2.0 * std::f64::consts::PI)
+    }
+
+    /// RMS error vector magnitude relative to the unit constellation
+    pub fn evm(&self) -> f32 {
+        self.error_power.sqrt()
+    }
+
+    /// Nearest constellation point
+    pub fn ideal(&self, symbol: Complex32) -> Complex32 {
+        match self.config.order {
+            PskOrder::Bpsk => Complex32::new(symbol.re.signum(), 0.0),
+            PskOrder::Qpsk => Complex32::new(symbol.re.signum(), symbol.im.signum()) * std::f32::consts::FRAC_1_SQRT_2,
+        }
+    }
+
+    /// Gray-coded bits of a symbol, MSB first
+    pub fn decide(&self, symbol: Complex32) -> u8 {
+        match self.config.order {
+            PskOrder::Bpsk => (symbol.re > 0.0) as u8,
+            PskOrder::Qpsk => ((symbol.re > 0.0) as u8) << 1 | (symbol.im > 0.0) as u8,
+        }
+    }
+
+    /// Push one IQ sample, returning the carrier-corrected symbol at each symbol centre
+    pub fn push(&mut self, sample: Complex32) -> Option<Complex32> {
+        let x = self.fir.push(self.nco.mix(sample))?;
+        self.agc += 0.002 * (x.norm() - self.agc);
+        self.phase = (self.phase + self.freq) % std::f32::consts::TAU;
+        let x = x / self.agc.max(1e-9) * Complex32::from_polar(1.0, -self.phase);
+
+        self.since_symbol += 1.0;
+        let symbol = self.strobe(x);
+        let t = self.since_symbol / (2.0 * self.half_symbol);
+        self.eye = Some((if t >= 0.5 { t - 1.0 } else { t }, x));
+        symbol
+    }
+
+    /// Timing, carrier and AGC updates for one carrier-corrected channel sample
+    fn strobe(&mut self, x: Complex32) -> Option<Complex32> {
+        let prev = std::mem::replace(&mut self.prev, x);
+        self.countdown -= 1.0;
+        if self.countdown > 0.0 {
+            return None;
+        }
+        // Linear interpolation back to the strobe instant
+        let y = x + (prev - x) * (-self.countdown);
+        self.countdown += self.half_symbol;
+        self.at_mid = !self.at_mid;
+        if !self.at_mid {
+            self.mid = y;
+            return None;
+        }
+
+        // Gardner: the mid-point between two symbols should sit on the transition
+        self.since_symbol = -self.countdown + self.half_symbol;
+        let timing_error = ((y - self.last_symbol) * self.mid.conj()).re;
+        self.countdown -= TIMING_GAIN * timing_error.clamp(-1.0, 1.0) * 2.0 * self.half_symbol;
+        self.last_symbol = y;
+
+        // Raising to the modulation order strips the data, leaving the residual carrier
+        let stripped = match self.config.order {
+            PskOrder::Bpsk => y * y,
+            PskOrder::Qpsk => (y * y) * (y * y),
+        };
+        let order = (1 << self.config.order.bits_per_symbol()) as f32;
+        let rotation = (stripped * self.last_stripped.conj()).arg() / order;
+        self.last_stripped = stripped;
+
+        let ideal = self.ideal(y);
+        let phase_error = match self.config.order {
+            PskOrder::Bpsk => y.im * y.re.signum(),
+            PskOrder::Qpsk => y.im * y.re.signum() - y.re * y.im.signum(),
+        }
+        .clamp(-1.0, 1.0);
+        self.phase += CARRIER_ALPHA * phase_error;
+        self.freq += (CARRIER_BETA * phase_error + FLL_GAIN * rotation) / (2.0 * self.half_symbol);
+        self.error_power += 0.02 * ((y - ideal).norm_sqr() - self.error_power);
+
+        Some(y)
+    }
+
+    /// Demodulate a block, appending every symbol to `symbols` and every channel sample to
+    /// `eye` with its time in symbols from the nearest symbol centre, in [-0.5, 0.5)
+    pub fn process(&mut self, samples: &[Complex32], symbols: &mut Vec<Complex32>, eye: &mut Vec<(f32, Complex32)>) {
+        for &s in samples {
+            symbols.extend(self.push(s));
+            eye.extend(self.eye.take());
+        }
+    }
+}
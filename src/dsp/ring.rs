@@ -0,0 +1,247 @@
+//! Lock-free ring buffer of samples from a device reader to the receiver.
+//!
+//! The reader writes what it reads as it comes and never waits. When the
+//! receiver has not kept up and the ring is full, the samples that do not
+//! fit are dropped and counted as an overrun, so the loss is known rather
+//! than hidden in a growing queue.
+
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    /// Capacity minus one, the capacity being a power of two
+    mask: usize,
+    /// Samples ever written, stored only by the writer
+    head: AtomicUsize,
+    /// Samples ever read, stored only by the reader
+    tail: AtomicUsize,
+    /// Samples dropped because the ring was full
+    overruns: AtomicU64,
+}
+
+// SAFETY: the slots are shared between exactly one Writer and one Reader,
+// neither of which is Clone and both of which take `&mut self` to touch the
+// slots. A slot is only written by the writer while outside `tail..head` and
+// only read by the reader while inside it, the writer publishing `head` with
+// a release store after writing and the reader publishing `tail` with a
+// release store after reading, each taken by the other end with an acquire
+// load, so no slot is ever accessed by both at once. The samples themselves
+// cross threads, hence `T: Send`.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn base(&self) -> *mut T {
+        // UnsafeCell<T> has the layout of T
+        UnsafeCell::raw_get(self.slots.as_ptr())
+    }
+}
+
+/// Writing end of a ring made by [`ring`]
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Reading end of a ring made by [`ring`]
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// A ring holding at least `capacity` samples, rounded up to a power of two,
+/// with one end for each of two threads
+pub fn ring<T: Copy + Default>(capacity: usize) -> (Writer<T>, Reader<T>) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let shared = Arc::new(Shared {
+        slots: (0..capacity).map(|_| UnsafeCell::new(T::default())).collect(),
+        mask: capacity - 1,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        overruns: AtomicU64::new(0),
+    });
+    (Writer { shared: Arc::clone(&shared) }, Reader { shared })
+}
+
+impl<T: Copy> Writer<T> {
+    /// Append as many of `samples` as fit, counting the rest as an overrun,
+    /// and return how many were written
+    pub fn write(&mut self, samples: &[T]) -> usize {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        let tail = shared.tail.load(Ordering::Acquire);
+        let free = shared.slots.len() - head.wrapping_sub(tail);
+        let count = samples.len().min(free);
+        let start = head & shared.mask;
+        let first = count.min(shared.slots.len() - start);
+        // SAFETY:
+        // - `count <= free`, so the slots from `head` for `count` lie outside
+        //   `tail..head` and the reader does not touch them until `head` is
+        //   stored below
+        // - `first <= len - start` and `count - first <= start` when the copy
+        //   wraps, so both copies stay within the slots
+        // - `samples` holds at least `count` and is a separate allocation
+        //   from the slots, so the copies read valid memory and do not overlap
+        // - both pointers are aligned for T, coming from a slice of T and a
+        //   slice of UnsafeCell<T>, which has T's layout
+        unsafe {
+            ptr::copy_nonoverlapping(samples.as_ptr(), shared.base().add(start), first);
+            ptr::copy_nonoverlapping(samples.as_ptr().add(first), shared.base(), count - first);
+        }
+        shared.head.store(head.wrapping_add(count), Ordering::Release);
+        if count < samples.len() {
+            shared.overruns.fetch_add((samples.len() - count) as u64, Ordering::Relaxed);
+        }
+        count
+    }
+
+    /// Whether the reader has been dropped
+    pub fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl<T: Copy> Reader<T> {
+    /// Append every sample written since the last call to `out`, returning
+    /// how many there were
+    pub fn read_into(&mut self, out: &mut Vec<T>) -> usize {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let head = shared.head.load(Ordering::Acquire);
+        let count = head.wrapping_sub(tail);
+        let start = tail & shared.mask;
+        let first = count.min(shared.slots.len() - start);
+        out.reserve(count);
+        // SAFETY:
+        // - the slots from `tail` for `count` lie inside `tail..head`, were
+        //   written before `head` was stored with release and taken here with
+        //   acquire, and the writer leaves them alone until `tail` is stored
+        //   below
+        // - `count <= len` as the writer never gets more than `len` ahead, and
+        //   `first <= len - start`, so both copies stay within the slots
+        // - `out` has room for `count` more after the reserve, in an
+        //   allocation apart from the slots, so the copies do not overlap
+        // - `set_len` covers only the `count` samples just copied in, so every
+        //   element up to the new length is initialised
+        unsafe {
+            let end = out.as_mut_ptr().add(out.len());
+            ptr::copy_nonoverlapping(shared.base().add(start), end, first);
+            ptr::copy_nonoverlapping(shared.base(), end.add(first), count - first);
+            out.set_len(out.len() + count);
+        }
+        shared.tail.store(tail.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    /// Samples dropped so far because the ring was full
+    pub fn overruns(&self) -> u64 {
+        self.shared.overruns.load(Ordering::Relaxed)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_rounds_up_to_a_power_of_two() {
+        assert_eq!(ring::<u8>(0).1.capacity(), 1);
+        assert_eq!(ring::<u8>(5).1.capacity(), 8);
+        assert_eq!(ring::<u8>(1024).1.capacity(), 1024);
+    }
+
+    #[test]
+    fn empty_reads_return_nothing() {
+        let (mut writer, mut reader) = ring::<i32>(8);
+        let mut out = vec![7];
+        assert_eq!(reader.read_into(&mut out), 0);
+        writer.write(&[]);
+        assert_eq!(reader.read_into(&mut out), 0);
+        assert_eq!(out, [7]);
+        assert_eq!(reader.overruns(), 0);
+    }
+
+    #[test]
+    fn wraps_around_the_end() {
+        let (mut writer, mut reader) = ring::<i32>(8);
+        let mut out = Vec::new();
+        let mut next = 0;
+        // Writes of 5 against a capacity of 8 start at every offset, most
+        // of them splitting across the end
+        for _ in 0..20 {
+            let samples: Vec<i32> = (next..next + 5).collect();
+            assert_eq!(writer.write(&samples), 5);
+            out.clear();
+            assert_eq!(reader.read_into(&mut out), 5);
+            assert_eq!(out, samples);
+            next += 5;
+        }
+        assert_eq!(reader.overruns(), 0);
+    }
+
+    #[test]
+    fn overruns_drop_the_newest_samples() {
+        let (mut writer, mut reader) = ring::<i32>(8);
+        assert_eq!(writer.write(&[0, 1, 2, 3, 4, 5]), 6);
+        assert_eq!(writer.write(&[6, 7, 8, 9]), 2);
+        assert_eq!(writer.write(&[10]), 0);
+        assert_eq!(reader.overruns(), 3);
+        let mut out = Vec::new();
+        assert_eq!(reader.read_into(&mut out), 8);
+        assert_eq!(out, [0, 1, 2, 3, 4, 5, 6, 7]);
+        // Room again once read, across the end
+        assert_eq!(writer.write(&[11, 12, 13]), 3);
+        out.clear();
+        reader.read_into(&mut out);
+        assert_eq!(out, [11, 12, 13]);
+        assert_eq!(reader.overruns(), 3);
+    }
+
+    #[test]
+    fn writer_sees_the_reader_go() {
+        let (writer, reader) = ring::<i32>(8);
+        assert!(!writer.is_closed());
+        drop(reader);
+        assert!(writer.is_closed());
+    }
+
+    #[test]
+    fn keeps_the_sequence_across_threads() {
+        const TOTAL: u64 = 200_000;
+        let (mut writer, mut reader) = ring::<u64>(1024);
+        let producer = std::thread::spawn(move || {
+            let mut next = 0;
+            let mut written = Vec::new();
+            while next < TOTAL {
+                let samples: Vec<u64> = (next..(next + 97).min(TOTAL)).collect();
+                let count = writer.write(&samples);
+                written.extend_from_slice(&samples[..count]);
+                next += samples.len() as u64;
+                if count < samples.len() {
+                    std::thread::yield_now();
+                }
+            }
+            written
+        });
+        let mut read = Vec::new();
+        let mut out = Vec::new();
+        loop {
+            let finished = producer.is_finished();
+            out.clear();
+            reader.read_into(&mut out);
+            read.extend_from_slice(&out);
+            if finished && out.is_empty() {
+                break;
+            }
+        }
+        let written = producer.join().unwrap();
+        // Whatever was dropped, what came through is exactly what went in
+        assert_eq!(read, written);
+        assert_eq!(reader.overruns() + written.len() as u64, TOTAL);
+        assert!(read.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
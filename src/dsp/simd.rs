@@ -0,0 +1,245 @@
+//! Vector versions of the hot loops: FIR filtering, windowing and power
+//! spectra. The instruction set is chosen once at run time from what the
+//! CPU supports, AVX2 with FMA on x86-64, and anything else runs the
+//! scalar loops, which give the same results to rounding.
+//!
+//! Setting `SDR_SIMD=off` forces the scalar loops, to compare the two.
+
+use std::sync::OnceLock;
+
+use num_complex::Complex32;
+
+/// Disables the vector loops when set to `off`
+const SIMD_ENV: &str = "SDR_SIMD";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Scalar,
+    /// AVX2 and FMA, x86-64 since Haswell
+    Avx2,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Scalar => "scalar",
+            Level::Avx2 => "AVX2+FMA",
+        }
+    }
+}
+
+/// The instruction set the kernels use on this CPU
+pub fn level() -> Level {
+    static LEVEL: OnceLock<Level> = OnceLock::new();
+    *LEVEL.get_or_init(|| {
+        if std::env::var(SIMD_ENV).is_ok_and(|v| v.eq_ignore_ascii_case("off")) {
+            return Level::Scalar;
+        }
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return Level::Avx2;
+        }
+        Level::Scalar
+    })
+}
+
+/// Sum of `taps[i] * samples[i]`, the output of a FIR filter whose history
+/// is `samples` in the order of the taps
+pub fn fir(taps: &[f32], samples: &[Complex32]) -> Complex32 {
+    let len = taps.len().min(samples.len());
+    let (taps, samples) = (&taps[..len], &samples[..len]);
+    match level() {
+        // SAFETY: level() only gives Avx2 when the CPU has AVX2 and FMA
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::fir(taps, samples) },
+        _ => scalar::fir(taps, samples),
+    }
+}
+
+/// Multiply each sample by the window coefficient at the same index
+pub fn apply_window(samples: &mut [Complex32], window: &[f32]) {
+    let len = samples.len().min(window.len());
+    let (samples, window) = (&mut samples[..len], &window[..len]);
+    match level() {
+        // SAFETY: as in fir()
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::apply_window(samples, window) },
+        _ => scalar::apply_window(samples, window),
+    }
+}
+
+/// Add `|values[i]|² * scale` to `power[i]`
+pub fn accumulate_power(values: &[Complex32], scale: f32, power: &mut [f32]) {
+    let len = values.len().min(power.len());
+    let (values, power) = (&values[..len], &mut power[..len]);
+    match level() {
+        // SAFETY: as in fir()
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::accumulate_power(values, scale, power) },
+        _ => scalar::accumulate_power(values, scale, power),
+    }
+}
+
+mod scalar {
+    use num_complex::Complex32;
+
+    pub fn fir(taps: &[f32], samples: &[Complex32]) -> Complex32 {
+        taps.iter().zip(samples).fold(Complex32::new(0.0, 0.0), |acc, (&t, &s)| acc + s * t)
+    }
+
+    pub fn apply_window(samples: &mut [Complex32], window: &[f32]) {
+        samples.iter_mut().zip(window).for_each(|(s, &w)| *s *= w);
+    }
+
+    pub fn accumulate_power(values: &[Complex32], scale: f32, power: &mut [f32]) {
+        power.iter_mut().zip(values).for_each(|(p, v)| *p += v.norm_sqr() * scale);
+    }
+}
+
+/// The kernels for AVX2 with FMA. Complex32 is a pair of f32, so a slice of
+/// them is read as interleaved I and Q, four samples to a register.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use num_complex::Complex32;
+
+    /// Four real coefficients from `p`, each repeated for the I and Q of a sample
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn load_pairs(p: *const f32) -> __m256 {
+        // SAFETY: the caller has four floats at `p`
+        let four = unsafe { _mm_loadu_ps(p) };
+        _mm256_set_m128(_mm_unpackhi_ps(four, four), _mm_unpacklo_ps(four, four))
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn fir(taps: &[f32], samples: &[Complex32]) -> Complex32 {
+        let chunks = taps.len() / 4;
+        let iq = samples.as_ptr() as *const f32;
+        let mut acc = _mm256_setzero_ps();
+        for i in 0..chunks {
+            // SAFETY: 4 taps and 4 samples, 8 floats, from 4 * i are in bounds
+            unsafe {
+                acc = _mm256_fmadd_ps(_mm256_loadu_ps(iq.add(8 * i)), load_pairs(taps.as_ptr().add(4 * i)), acc);
+            }
+        }
+        let mut lanes = [0f32; 8];
+        // SAFETY: `lanes` holds 8 floats
+        unsafe { _mm256_storeu_ps(lanes.as_mut_ptr(), acc) };
+        let sum = Complex32::new(lanes[0] + lanes[2] + lanes[4] + lanes[6], lanes[1] + lanes[3] + lanes[5] + lanes[7]);
+        sum + super::scalar::fir(&taps[4 * chunks..], &samples[4 * chunks..])
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn apply_window(samples: &mut [Complex32], window: &[f32]) {
+        let chunks = samples.len() / 4;
+        let iq = samples.as_mut_ptr() as *mut f32;
+        for i in 0..chunks {
+            // SAFETY: 4 samples and 4 coefficients from 4 * i are in bounds
+            unsafe {
+                let product = _mm256_mul_ps(_mm256_loadu_ps(iq.add(8 * i)), load_pairs(window.as_ptr().add(4 * i)));
+                _mm256_storeu_ps(iq.add(8 * i), product);
+            }
+        }
+        super::scalar::apply_window(&mut samples[4 * chunks..], &window[4 * chunks..]);
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn accumulate_power(values: &[Complex32], scale: f32, power: &mut [f32]) {
+        let chunks = values.len() / 8;
+        let iq = values.as_ptr() as *const f32;
+        let scales = _mm256_set1_ps(scale);
+        for i in 0..chunks {
+            // SAFETY: 8 values, 16 floats, and 8 powers from 8 * i are in bounds
+            unsafe {
+                let a = _mm256_loadu_ps(iq.add(16 * i));
+                let b = _mm256_loadu_ps(iq.add(16 * i + 8));
+                // |v|² of values 0 1 4 5 2 3 6 7, put back in order
+                let norms = _mm256_hadd_ps(_mm256_mul_ps(a, a), _mm256_mul_ps(b, b));
+                let norms = _mm256_castpd_ps(_mm256_permute4x64_pd(_mm256_castps_pd(norms), 0b11_01_10_00));
+                let out = power.as_mut_ptr().add(8 * i);
+                _mm256_storeu_ps(out, _mm256_fmadd_ps(norms, scales, _mm256_loadu_ps(out)));
+            }
+        }
+        super::scalar::accumulate_power(&values[8 * chunks..], scale, &mut power[8 * chunks..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Samples spread over ±1 from a fixed seed, the same on every run
+    fn noise(len: usize, seed: u32) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(2_654_435_761).max(1);
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 * 2.0 - 1.0
+            })
+            .collect()
+    }
+
+    fn complex(len: usize, seed: u32) -> Vec<Complex32> {
+        noise(2 * len, seed).chunks(2).map(|c| Complex32::new(c[0], c[1])).collect()
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-5 * (1.0 + a.abs().max(b.abs()))
+    }
+
+    /// Lengths around the vector widths, and slices starting off the
+    /// allocation's alignment, so every tail and misalignment is covered
+    fn cases() -> impl Iterator<Item = (usize, usize)> {
+        (0..=41).flat_map(|len| [0, 1, 3].into_iter().map(move |offset| (len, offset)))
+    }
+
+    #[test]
+    fn dispatch_matches_scalar() {
+        let taps = noise(45, 1);
+        let samples = complex(45, 2);
+        for (len, offset) in cases() {
+            let (taps, samples) = (&taps[offset..offset + len], &samples[offset..offset + len]);
+            let (vector, scalar) = (fir(taps, samples), scalar::fir(taps, samples));
+            assert!(close(vector.re, scalar.re) && close(vector.im, scalar.im), "fir {} at {}", len, offset);
+        }
+        // Mismatched lengths use the shorter
+        assert_eq!(fir(&taps[..3], &samples), scalar::fir(&taps[..3], &samples[..3]));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_matches_scalar() {
+        if !(is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma")) {
+            eprintln!("no AVX2 and FMA here, skipping");
+            return;
+        }
+        let window = noise(45, 3);
+        let values = complex(45, 4);
+        for (len, offset) in cases() {
+            let (window, values) = (&window[offset..offset + len], &values[offset..offset + len]);
+
+            // SAFETY: the CPU has AVX2 and FMA, checked above
+            let vector = unsafe { x86::fir(window, values) };
+            let scalar = scalar::fir(window, values);
+            assert!(close(vector.re, scalar.re) && close(vector.im, scalar.im), "fir {} at {}", len, offset);
+
+            let (mut vector, mut scalar) = (values.to_vec(), values.to_vec());
+            // SAFETY: as above
+            unsafe { x86::apply_window(&mut vector, window) };
+            scalar::apply_window(&mut scalar, window);
+            assert_eq!(vector, scalar, "apply_window {} at {}", len, offset);
+
+            let start = noise(len, 5);
+            let (mut vector, mut scalar) = (start.clone(), start);
+            // SAFETY: as above
+            unsafe { x86::accumulate_power(values, 0.25, &mut vector) };
+            scalar::accumulate_power(values, 0.25, &mut scalar);
+            for (i, (v, s)) in vector.iter().zip(&scalar).enumerate() {
+                assert!(close(*v, *s), "accumulate_power {} at {}: {} is {} not {}", len, offset, i, v, s);
+            }
+        }
+    }
+}
@@ -0,0 +1,1669 @@
+//! The receiver itself, without a terminal: the source of samples and its
+//! tuning, the VFOs, the decoders, recording and playback, the scanner,
+//! scheduler and script, and the network services. A program embedding the
+//! receiver makes an [`Engine`], applies its config and calls
+//! [`Engine::tick`] at [`Engine::tick_interval`]; the TUI and the headless
+//! subcommands of the `rf_rust` binary do just that.
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex32;
+
+use crate::config::Config;
+use crate::decoders::ais::AisDecoder;
+use crate::decoders::cw::CwDecoder;
+use crate::decoders::dtmf::DtmfDetector;
+use crate::decoders::ft8::Ft8Decoder;
+use crate::decoders::ism::IsmDecoder;
+use crate::decoders::navtex::NavtexDecoder;
+use crate::decoders::pager::PagerDecoder;
+use crate::decoders::plugin::{Input, Registry};
+use crate::decoders::psk::PskDecoder;
+use crate::decoders::rtty::{self, RttyDecoder};
+use crate::decoders::same::SameDecoder;
+use crate::decoders::utc_date_time;
+use crate::decoders::wspr::{Reporter, WsprDecoder};
+#[cfg(all(unix, feature = "plugins"))]
+use crate::decoders::{dylib::DynamicDecoder, plugin::Decoder};
+use crate::device::Device;
+use crate::device::mock::MockSdr;
+use crate::dsp::measure::{PowerSpectrum, SpectrumEstimator, Window, median};
+use crate::dsp::pipeline::{Consumer, QUEUE_BLOCKS, SampleBlock, Worker, spsc};
+use crate::dsp::{AudioDemod, AudioMode, ChannelMeter, ModulationClassifier, PskConfig, PskDemod, PskOrder, SMeter, VuMeter, af_gain};
+use crate::error::RfError;
+use crate::json::Value;
+use crate::logging;
+use crate::net::icecast::{IcecastConfig, IcecastSource};
+use crate::net::iq_stream::IqStream;
+use crate::net::mqtt::{MqttConfig, MqttPublisher};
+use crate::net::nmea::NmeaSender;
+use crate::net::remote::RemoteSource;
+use crate::net::rest::RestServer;
+use crate::net::rigctl::{Dialect, RigctlServer};
+use crate::net::rtl_tcp::RtlTcpServer;
+use crate::net::rtp::{AudioEncoding, AudioSender};
+use crate::net::vita49::Vita49Sender;
+use crate::net::websocket::WebSocketServer;
+use crate::net::zmq::ZmqPublisher;
+use crate::net::{Command, ReceiverState};
+use crate::recording::audio::AudioRecorder;
+use crate::recording::burst::BurstCapture;
+use crate::recording::format_size;
+use crate::recording::iq::{IqRecorder, SampleFormat};
+use crate::recording::playback::FilePlayer;
+use crate::recording::schedule::{JobState, Scheduler};
+use crate::scanner::{Channel, ScanState, Scanner};
+use crate::script::{Host, Script};
+
+pub const AIS_NMEA_LOG: &str = "ais_nmea.log";
+pub const ISM_JSON_LOG: &str = "ism_records.json";
+pub const NAVTEX_LOG: &str = "navtex.log";
+pub const BURST_DIR: &str = "bursts";
+pub const RECORDING_DIR: &str = "recordings";
+/// Longest pre-record buffer accepted from the config, 2.4 GB at 10 MS/s
+const MAX_PRE_RECORD_SECS: f64 = 30.0;
+const PLAYBACK_AVERAGES: usize = 4;
+const PLAYBACK_SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// Most VFOs at once, one per digit key of the TUI
+pub const MAX_VFOS: usize = 9;
+/// Spacing of a new VFO from the selected one
+const NEW_VFO_STEP_HZ: f64 = 25e3;
+/// Default time per update of the sources, decoders, spectrum and services
+pub const TICK: Duration = Duration::from_millis(50);
+/// Bounds of the update time in milliseconds set in the config
+const TICK_MS: RangeInclusive<u64> = 10..=1000;
+/// Assumed level in dBm for 0 dBFS at 0 dB gain when no calibration is set
+const UNCALIBRATED_DB: f32 = -10.0;
+/// Spectrum shown before the first is measured, about the demo noise floor
+const DEMO_NOISE_DBFS: f32 = -95.0;
+/// Smoothing of the spectrum noise floor estimate per update
+const NOISE_FLOOR_ALPHA: f32 = 0.1;
+pub const MEASURE_CSV: &str = "measurements.csv";
+const SCAN_CSV: &str = "scanner_hits.csv";
+/// Script log lines held for `rf_rust run`, which takes them each update
+const MAX_SCRIPT_LOG: usize = 1000;
+/// Time for a remote receiver to retune and the spectrum to follow
+const SCAN_RETUNE_SETTLE: Duration = Duration::from_millis(300);
+/// Passband widths selectable for channel measurements
+pub const MEASURE_BANDWIDTHS: [f64; 6] = [2.7e3, 6e3, 12.5e3, 25e3, 200e3, 1e6];
+/// Time spans of the channel power history, in seconds, the longest kept
+pub const POWER_HISTORY_SPANS: [u64; 4] = [10, 30, 60, 300];
+pub const CONSTELLATION_BAUDS: [f64; 5] = [1200.0, 2400.0, 4800.0, 9600.0, 19200.0];
+const CONSTELLATION_POINTS: usize = 512;
+/// Channel samples kept for the eye diagram, 8 per symbol
+const EYE_POINTS: usize = 8 * CONSTELLATION_POINTS;
+
+/// Where the audio of a VFO goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioRoute {
+    Muted,
+    /// Mixed into the monitored audio shown on the VU meter and scope
+    Monitor,
+    /// Monitored and also fed to the DTMF and SAME decoders
+    Decoders,
+}
+
+impl AudioRoute {
+    pub const ALL: [AudioRoute; 3] = [AudioRoute::Muted, AudioRoute::Monitor, AudioRoute::Decoders];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioRoute::Muted => "muted",
+            AudioRoute::Monitor => "monitor",
+            AudioRoute::Decoders => "decoders",
+        }
+    }
+
+    /// The route with `label` as its label
+    pub fn parse(label: &str) -> Option<Self> {
+        AudioRoute::ALL.into_iter().find(|route| route.label() == label)
+    }
+}
+
+/// A demodulator tuned somewhere within the captured bandwidth
+pub struct Vfo {
+    pub demod: AudioDemod,
+    pub route: AudioRoute,
+    audio: Vec<f32>,
+}
+
+impl Vfo {
+    pub fn new(mode: AudioMode, offset_hz: f64, route: AudioRoute) -> Self {
+        let mut demod = AudioDemod::new(mode);
+        demod.set_offset(offset_hz);
+        Self { demod, route, audio: Vec::new() }
+    }
+}
+
+/// PSK demodulator output for the constellation and eye views
+pub struct Constellation {
+    pub demod: PskDemod,
+    /// Recent symbols
+    pub points: VecDeque<Complex32>,
+    /// Recent channel samples with their time from the symbol centre
+    pub eye: VecDeque<(f32, Complex32)>,
+}
+
+impl Constellation {
+    fn new(config: PskConfig, sample_rate: f64) -> Self {
+        Self {
+            demod: PskDemod::new(config, sample_rate),
+            points: VecDeque::with_capacity(CONSTELLATION_POINTS),
+            eye: VecDeque::with_capacity(EYE_POINTS),
+        }
+    }
+
+    fn process(&mut self, samples: &[Complex32], sample_rate: f64) {
+        if self.demod.sample_rate() != sample_rate {
+            self.demod = PskDemod::new(self.demod.config().clone(), sample_rate);
+        }
+        let (mut symbols, mut eye) = (Vec::new(), Vec::new());
+        self.demod.process(samples, &mut symbols, &mut eye);
+        for symbol in symbols {
+            if self.points.len() == CONSTELLATION_POINTS {
+                self.points.pop_front();
+            }
+            self.points.push_back(symbol);
+        }
+        for point in eye {
+            if self.eye.len() == EYE_POINTS {
+                self.eye.pop_front();
+            }
+            self.eye.push_back(point);
+        }
+    }
+}
+
+/// Data lost between the source and the decoders
+#[derive(Default)]
+pub struct Losses {
+    /// Samples dropped by the source or a DSP stage because the ring or the
+    /// stage's queue was full
+    pub dropped: u64,
+    /// Updates in which a streaming device delivered nothing after it had
+    /// been delivering
+    pub underruns: u64,
+    pub last_overrun: Option<Instant>,
+    pub last_underrun: Option<Instant>,
+    /// Source and stage drops counted so far
+    seen: u64,
+    /// Whether the last update brought samples
+    flowing: bool,
+}
+
+/// When the newest decode already handed on was received, per decoder, so
+/// only later ones go out even once a decoder's list is full and rolling
+#[derive(Clone, Copy)]
+pub struct DecodeMarks {
+    pager: SystemTime,
+    same: SystemTime,
+    ism: SystemTime,
+    navtex: SystemTime,
+    /// Start of the cycle or window, seconds since the epoch
+    ft8: u64,
+    wspr: u64,
+}
+
+impl DecodeMarks {
+    /// Marks at the present, so only decodes from now on are handed on
+    pub fn now() -> Self {
+        let now = SystemTime::now();
+        let secs = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self { pager: now, same: now, ism: now, navtex: now, ft8: secs, wspr: secs }
+    }
+}
+
+/// Items of `items`, oldest first, with `key` past `mark`, moving `mark` up to the newest
+fn newer<'a, T, K: PartialOrd + Copy>(items: &'a [T], mark: &mut K, key: impl Fn(&T) -> K) -> &'a [T] {
+    let start = items.iter().rposition(|item| key(item) <= *mark).map_or(0, |i| i + 1);
+    if let Some(last) = items.last().map(&key)
+        && last > *mark
+    {
+        *mark = last;
+    }
+    &items[start..]
+}
+
+/// Add `audio` sample by sample into `mix`, extending it as needed
+fn mix_into(mix: &mut Vec<f32>, audio: &[f32]) {
+    if mix.len() < audio.len() {
+        mix.resize(audio.len(), 0.0);
+    }
+    mix.iter_mut().zip(audio).for_each(|(m, a)| *m += a);
+}
+
+/// The receiver: where its samples come from and everything they go to
+pub struct Engine {
+    pub frequency: f64,
+    pub sample_rate: f64,
+    pub gain: f64,
+    pub is_streaming: bool,
+    /// When streaming last started
+    pub streaming_since: Option<Instant>,
+    /// What the receiver or its user last did, for a status line
+    pub status_message: String,
+    /// Power per bin in dBFS
+    pub spectrum_data: Vec<f32>,
+    /// Smoothed median of `spectrum_data`
+    pub noise_floor: f32,
+    /// Spectrum settings, applied by [`reset_spectrum`](Self::reset_spectrum)
+    pub fft_size: usize,
+    pub fft_window: Window,
+    pub fft_averages: usize,
+    /// Level in dBm that reads 0 dBFS at 0 dB gain for the device in use,
+    /// from [`calibrations`](Self::calibrations)
+    pub calibration_db: Option<f32>,
+    /// Levels for `calibration_db` by driver and serial, or by driver alone,
+    /// from `offset_db` under `[calibration DRIVER SERIAL]` and
+    /// `[calibration DRIVER]` in the config file. rtl_tcp reporting no
+    /// serial, its server's address stands in for one.
+    pub calibrations: HashMap<String, f32>,
+    pub sample_buffer: Vec<Complex32>,
+    pub vfos: Vec<Vfo>,
+    /// VFO followed by the S-meter, the recorders and remote control
+    pub active_vfo: usize,
+    /// Mix of the VFOs routed to the monitor or decoders
+    pub audio_buffer: Vec<f32>,
+    /// Mix of the VFOs routed to the decoders
+    decoder_audio: Vec<f32>,
+    /// Audio gain in dB applied before metering and recording
+    pub af_gain_db: f32,
+    pub vu: VuMeter,
+    pub s_meter: SMeter,
+    pub ais: Worker<AisDecoder>,
+    pub pager: Worker<PagerDecoder>,
+    pub rtty: Worker<RttyDecoder>,
+    pub psk: Worker<PskDecoder>,
+    pub wspr: Worker<WsprDecoder>,
+    pub ft8: Worker<Ft8Decoder>,
+    pub dtmf: DtmfDetector,
+    pub cw: Worker<CwDecoder>,
+    pub ism: Worker<IsmDecoder>,
+    pub navtex: Worker<NavtexDecoder>,
+    pub constellation: Worker<Constellation>,
+    pub bursts: BurstCapture,
+    pub burst_capture: bool,
+    /// Writes the IQ on a thread of its own, so that the disk never holds
+    /// up the receiver
+    pub recorder: Worker<IqRecorder>,
+    pub audio_recorder: AudioRecorder,
+    /// Recording replacing the demo source while streaming
+    pub player: Option<FilePlayer>,
+    /// Receiver at another host replacing the demo source while streaming
+    pub remote: Option<RemoteSource>,
+    /// Source of the demo mode, without a remote receiver
+    pub demo: MockSdr,
+    pub scanner: Scanner,
+    pub plugins: Registry,
+    pub schedule: Scheduler,
+    pub losses: Losses,
+    /// Spectrum of the samples from a recording or remote receiver, which
+    /// unlike the demo source are not drawn from a made-up spectrum
+    measured_spectrum: Worker<SpectrumEstimator>,
+    /// Spectra from `measured_spectrum` not yet taken
+    measured: Consumer<PowerSpectrum>,
+    pub classifier: Worker<ModulationClassifier>,
+    /// Active VFO offset last handed to the classifier
+    classifier_offset: f64,
+    pub meter: ChannelMeter,
+    /// Demodulator channel power in dBFS per sample block, over the longest
+    /// of [`POWER_HISTORY_SPANS`]
+    pub power_history: VecDeque<(Instant, f32)>,
+    pub same: SameDecoder,
+    /// Time between updates, which the demo and playback keep to
+    pub tick_interval: Duration,
+    pub rtl_tcp: Option<RtlTcpServer>,
+    pub iq_stream: Option<IqStream>,
+    pub vita49: Option<Vita49Sender>,
+    pub zmq: Option<ZmqPublisher>,
+    pub rest: Option<RestServer>,
+    pub websocket: Option<WebSocketServer>,
+    pub rigctl: Option<RigctlServer>,
+    pub gqrx: Option<RigctlServer>,
+    pub mqtt: Option<MqttPublisher>,
+    pub audio_out: Option<AudioSender>,
+    pub icecast: Option<IcecastSource>,
+    pub nmea_out: Option<NmeaSender>,
+    /// AIS sentences decoded when the last ones were sent to `nmea_out`
+    nmea_forwarded: u64,
+    /// Newest decode of each kind already published over MQTT
+    published: DecodeMarks,
+    pub script: Option<Script>,
+    /// Lines the script logged, until taken by `rf_rust run`
+    pub script_log: Vec<String>,
+    /// Newest decode of each kind already handed to the script
+    scripted: DecodeMarks,
+}
+
+
+impl Engine {
+    /// The demo receiver, not streaming yet
+    pub fn new() -> Self {
+        let (mut spectra, measured) = spsc(QUEUE_BLOCKS);
+        Self {
+            frequency: 890e6,
+            sample_rate: 1e6,
+            gain: 20.0,
+            is_streaming: false,
+            streaming_since: None,
+            status_message: "DEMO MODE - No USRP hardware detected".to_string(),
+            spectrum_data: vec![DEMO_NOISE_DBFS; 512], // Half of FFT size
+            noise_floor: f32::NEG_INFINITY,
+            fft_size: 512,
+            fft_window: Window::Hann,
+            fft_averages: PLAYBACK_AVERAGES,
+            calibration_db: None,
+            calibrations: HashMap::new(),
+            sample_buffer: Vec::new(),
+            vfos: vec![Vfo::new(AudioMode::Fm, 0.0, AudioRoute::Decoders)],
+            active_vfo: 0,
+            audio_buffer: Vec::new(),
+            decoder_audio: Vec::new(),
+            af_gain_db: 0.0,
+            vu: VuMeter::new(),
+            s_meter: SMeter::new(),
+            ais: Worker::spawn("ais", AisDecoder::new(), |ais, block| {
+                ais.process(&block.iq, block.center_freq, block.sample_rate)
+            }),
+            pager: Worker::spawn("pager", PagerDecoder::new(), |pager, block| pager.process(&block.iq, block.sample_rate)),
+            rtty: Worker::spawn("rtty", RttyDecoder::new(), |rtty, block| rtty.process(&block.iq, block.sample_rate)),
+            psk: Worker::spawn("psk", PskDecoder::new(), |psk, block| psk.process(&block.iq, block.sample_rate)),
+            wspr: Worker::spawn("wspr", WsprDecoder::new(), |wspr, block| {
+                wspr.process(&block.iq, block.center_freq, block.sample_rate)
+            }),
+            ft8: Worker::spawn("ft8", Ft8Decoder::new(), |ft8, block| {
+                ft8.process(&block.iq, block.center_freq, block.sample_rate)
+            }),
+            dtmf: DtmfDetector::new(),
+            cw: Worker::spawn("cw", CwDecoder::new(), |cw, block| cw.process(&block.iq, block.sample_rate)),
+            ism: Worker::spawn("ism", IsmDecoder::new(), |ism, block| ism.process(&block.iq, block.sample_rate)),
+            navtex: Worker::spawn("navtex", NavtexDecoder::new(), |navtex, block| {
+                navtex.process(&block.iq, block.center_freq, block.sample_rate)
+            }),
+            constellation: Worker::spawn(
+                "constellation",
+                Constellation::new(PskConfig::new(PskOrder::Bpsk, CONSTELLATION_BAUDS[0]), 1e6),
+                |constellation, block| constellation.process(&block.iq, block.sample_rate),
+            ),
+            bursts: BurstCapture::new(BURST_DIR),
+            burst_capture: false,
+            recorder: Worker::spawn("recorder", IqRecorder::new(RECORDING_DIR), |recorder, block| {
+                if let Err(e) = recorder.write(&block.iq, block.center_freq, block.sample_rate) {
+                    log::error!("Recording stopped: {}", e);
+                }
+            }),
+            audio_recorder: AudioRecorder::new(RECORDING_DIR),
+            player: None,
+            remote: None,
+            demo: MockSdr::demo(),
+            scanner: Scanner::new(),
+            plugins: Registry::new(),
+            schedule: Scheduler::default(),
+            losses: Losses::default(),
+            measured_spectrum: Worker::spawn(
+                "spectrum",
+                SpectrumEstimator::new(512, PLAYBACK_AVERAGES),
+                move |estimator, block| {
+                    if let Some(spectrum) = estimator.push(&block.iq, block.sample_rate) {
+                        // The newest is taken, an older one not taken yet can go
+                        let _ = spectra.push(spectrum);
+                    }
+                },
+            ),
+            measured,
+            classifier: Worker::spawn("classifier", ModulationClassifier::new(), |classifier, block| {
+                classifier.process(&block.iq, block.sample_rate)
+            }),
+            classifier_offset: 0.0,
+            meter: ChannelMeter::new(MEASURE_BANDWIDTHS[2]),
+            power_history: VecDeque::new(),
+            same: SameDecoder::new(),
+            tick_interval: TICK,
+            rtl_tcp: None,
+            iq_stream: None,
+            vita49: None,
+            zmq: None,
+            rest: None,
+            websocket: None,
+            rigctl: None,
+            gqrx: None,
+            mqtt: None,
+            audio_out: None,
+            icecast: None,
+            nmea_out: None,
+            nmea_forwarded: 0,
+            published: DecodeMarks::now(),
+            script: None,
+            script_log: Vec::new(),
+            scripted: DecodeMarks::now(),
+        }
+    }
+
+    /// Take the settings of the receiver from the config file
+    pub fn apply_config(&mut self, config: &Config) {
+        if let Some(value) = config.get("ui.tick_ms") {
+            match value.parse::<u64>() {
+                Ok(ms) if TICK_MS.contains(&ms) => self.set_tick_interval(Duration::from_millis(ms)),
+                _ => log::warn!(
+                    "Config: ui.tick_ms must be {} to {} ms, not `{}`",
+                    TICK_MS.start(),
+                    TICK_MS.end(),
+                    value
+                ),
+            }
+        }
+        if let Some(value) = config.get("recording.pre_record_secs") {
+            match value.parse::<f64>() {
+                Ok(secs) if (0.0..=MAX_PRE_RECORD_SECS).contains(&secs) => self.recorder.lock().pre_record_secs = secs,
+                _ => log::warn!("Config: recording.pre_record_secs must be 0 to {} seconds, not `{}`", MAX_PRE_RECORD_SECS, value),
+            }
+        }
+        if let Some(value) = config.get("recording.split_mb") {
+            match value.parse::<u64>() {
+                Ok(mb) if mb > 0 => self.recorder.lock().split_bytes = Some(mb * 1024 * 1024),
+                _ => log::warn!("Config: recording.split_mb must be a whole number of MB, not `{}`", value),
+            }
+        }
+        if let Some(value) = config.get("recording.split_minutes") {
+            match value.parse::<f64>() {
+                Ok(minutes) if minutes > 0.0 && minutes.is_finite() => self.recorder.lock().split_secs = Some(minutes * 60.0),
+                _ => log::warn!("Config: recording.split_minutes must be a positive number, not `{}`", value),
+            }
+        }
+        if let Some(value) = config.get("recording.squelch_db") {
+            match value.parse::<f32>() {
+                Ok(db) if db <= 0.0 => self.audio_recorder.squelch_db = db,
+                _ => log::warn!("Config: recording.squelch_db must be a level in dBFS, not `{}`", value),
+            }
+        }
+        for section in config.sections() {
+            let Some(device) = section.strip_prefix("calibration ") else {
+                continue;
+            };
+            match config.get(&format!("{}.offset_db", section)).map(|v| v.parse::<f32>()) {
+                Some(Ok(db)) if db.is_finite() => _ = self.calibrations.insert(device.split_whitespace().collect::<Vec<_>>().join(" "), db),
+                _ => log::warn!("Config: [{}] needs offset_db, the level in dBm that reads 0 dBFS at 0 dB gain", section),
+            }
+        }
+        let remote = self.remote.take();
+        self.set_remote(remote);
+        self.load_plugins(config);
+        if let Some(value) = config.get("scanner.threshold_db") {
+            match value.parse::<f32>() {
+                Ok(db) if db > 0.0 => self.scanner.threshold_db = db,
+                _ => log::warn!("Config: scanner.threshold_db must be a positive number of dB, not `{}`", value),
+            }
+        }
+        if let Some(value) = config.get("scanner.dwell_secs") {
+            match value.parse::<f64>() {
+                Ok(secs) if secs >= 0.0 && secs.is_finite() => self.scanner.dwell = Duration::from_secs_f64(secs),
+                _ => log::warn!("Config: scanner.dwell_secs must be a number of seconds, not `{}`", value),
+            }
+        }
+        if let Some(decoder) = config.get("ft8.decoder") {
+            self.ft8.lock().decoder = decoder.to_string();
+        }
+        match (config.get("wspr.callsign"), config.get("wspr.grid")) {
+            (Some(callsign), Some(grid)) => {
+                self.wspr.lock().reporter = Some(Reporter { callsign: callsign.to_string(), grid: grid.to_string() })
+            }
+            (None, None) => {}
+            _ => log::warn!("Config: [wspr] needs both callsign and grid to upload spots"),
+        }
+        match Scheduler::from_config(config, SystemTime::now()) {
+            Ok(schedule) => self.schedule = schedule,
+            Err(e) => log::warn!("Config: schedule.{}", e),
+        }
+        if let Some(addr) = config.get("network.rtl_tcp") {
+            match RtlTcpServer::bind(addr) {
+                Ok(server) => self.rtl_tcp = Some(server),
+                Err(e) => log::warn!("Config: cannot serve rtl_tcp on {}: {}", addr, e),
+            }
+        }
+        if let Some(addr) = config.get("network.zmq_pub") {
+            match ZmqPublisher::bind(addr) {
+                Ok(publisher) => self.zmq = Some(publisher),
+                Err(e) => log::warn!("Config: cannot publish ZeroMQ on {}: {}", addr, e),
+            }
+        }
+        if let Some(addr) = config.get("network.rest") {
+            match RestServer::bind(addr) {
+                Ok(server) => self.rest = Some(server),
+                Err(e) => log::warn!("Config: cannot serve the REST API on {}: {}", addr, e),
+            }
+        }
+        if let Some(addr) = config.get("network.websocket") {
+            match WebSocketServer::bind(addr) {
+                Ok(server) => self.websocket = Some(server),
+                Err(e) => log::warn!("Config: cannot serve WebSocket on {}: {}", addr, e),
+            }
+        }
+        if let Some(addr) = config.get("network.rigctld") {
+            match RigctlServer::bind(addr, Dialect::Hamlib) {
+                Ok(server) => self.rigctl = Some(server),
+                Err(e) => log::warn!("Config: cannot serve rigctld on {}: {}", addr, e),
+            }
+        }
+        if let Some(addr) = config.get("network.gqrx") {
+            match RigctlServer::bind(addr, Dialect::Gqrx) {
+                Ok(server) => self.gqrx = Some(server),
+                Err(e) => log::warn!("Config: cannot serve GQRX remote control on {}: {}", addr, e),
+            }
+        }
+        if let Some(broker) = config.get("mqtt.broker") {
+            let mut mqtt = MqttConfig::new(broker);
+            if let Some(id) = config.get("mqtt.client_id") {
+                mqtt.client_id = id.to_string();
+            }
+            mqtt.username = config.get("mqtt.username").map(str::to_string);
+            mqtt.password = config.get("mqtt.password").map(str::to_string);
+            if mqtt.password.is_some() && mqtt.username.is_none() {
+                log::warn!("Config: mqtt.password needs mqtt.username, it is not sent without one");
+            }
+            if let Some(topic) = config.get("mqtt.telemetry_topic") {
+                mqtt.telemetry_topic = topic.to_string();
+            }
+            if let Some(topic) = config.get("mqtt.events_topic") {
+                mqtt.events_topic = topic.trim_end_matches('/').to_string();
+            }
+            if let Some(value) = config.get("mqtt.interval_secs") {
+                match value.parse::<f64>() {
+                    Ok(secs) if secs >= 1.0 && secs.is_finite() => mqtt.interval = Duration::from_secs_f64(secs),
+                    _ => log::warn!("Config: mqtt.interval_secs must be at least 1, not `{}`", value),
+                }
+            }
+            self.mqtt = Some(MqttPublisher::start(mqtt));
+            self.published = DecodeMarks::now();
+        }
+        if let Some(url) = config.get("network.audio_out") {
+            let encoding = match config.get("network.audio_format") {
+                None => Ok(AudioEncoding::Pcmu),
+                Some(name) => AudioEncoding::parse(name).ok_or(format!("audio_format must be pcmu or l16, not `{}`", name)),
+            };
+            match encoding.and_then(|encoding| AudioSender::open(url, encoding)) {
+                Ok(sender) => self.audio_out = Some(sender),
+                Err(e) => log::warn!("Config: network.{}", e),
+            }
+        }
+        if let Some(server) = config.get("icecast.server") {
+            match config.get("icecast.password") {
+                Some(password) => {
+                    let mut icecast = IcecastConfig::new(server, password);
+                    if let Some(mount) = config.get("icecast.mount") {
+                        icecast.mount = format!("/{}", mount.trim_start_matches('/'));
+                    }
+                    if let Some(user) = config.get("icecast.user") {
+                        icecast.user = user.to_string();
+                    }
+                    if let Some(name) = config.get("icecast.name") {
+                        icecast.name = name.to_string();
+                    }
+                    self.icecast = Some(IcecastSource::start(icecast));
+                }
+                None => log::warn!("Config: icecast.password is needed to stream to Icecast"),
+            }
+        }
+        if let Some(url) = config.get("network.vita49") {
+            let stream_id = match config.get("network.vita49_stream_id") {
+                None => Ok(1),
+                Some(id) => id.parse::<u32>().map_err(|_| format!("vita49_stream_id must be a 32-bit number, not `{}`", id)),
+            };
+            match stream_id.and_then(|id| Vita49Sender::open(url, id)) {
+                Ok(sender) => self.vita49 = Some(sender),
+                Err(e) => log::warn!("Config: network.vita49: {}", e),
+            }
+        }
+        if let Some(url) = config.get("network.nmea_out") {
+            match NmeaSender::open(url) {
+                Ok(sender) => {
+                    self.nmea_out = Some(sender);
+                    self.nmea_forwarded = self.ais.lock().sentences;
+                }
+                Err(e) => log::warn!("Config: network.nmea_out: {}", e),
+            }
+        }
+        if let Some(url) = config.get("network.iq_out") {
+            let format = match config.get("network.iq_format") {
+                None | Some("cf32") => Some(SampleFormat::Cf32),
+                Some("cs16") => Some(SampleFormat::Cs16),
+                Some(other) => {
+                    log::warn!("Config: network.iq_format must be cf32 or cs16, not `{}`", other);
+                    None
+                }
+            };
+            if let Some(format) = format {
+                match IqStream::open(url, format) {
+                    Ok(stream) => self.iq_stream = Some(stream),
+                    Err(e) => log::warn!("Config: network.iq_out: {}", e),
+                }
+            }
+        }
+    }
+
+    /// Measure spectra with the FFT settings from the next block, dropping
+    /// those measured with the old ones
+    pub fn reset_spectrum(&mut self) {
+        *self.measured_spectrum.lock() = SpectrumEstimator::with_window(self.fft_size, self.fft_averages, self.fft_window);
+        while self.measured.pop().is_some() {}
+        self.spectrum_data = vec![self.noise_floor; self.fft_size];
+    }
+
+    /// Move the active VFO to `channel`, which when the hardware has to be
+    /// retuned sits a quarter span below the centre, clear of the DC spike
+    /// with the channels above it in view
+    pub fn tune_scan_channel(&mut self, channel: &Channel) {
+        if self.tune_vfo(channel.frequency, channel.bandwidth) {
+            self.scanner.hold(Instant::now() + SCAN_RETUNE_SETTLE);
+        }
+        self.vfos[self.active_vfo].demod.set_mode(channel.mode);
+    }
+
+    /// Move the active VFO to `hz`, retuning the hardware when a channel
+    /// `bandwidth` wide there is outside the span. Returns whether it did.
+    pub fn tune_vfo(&mut self, hz: f64, bandwidth: f64) -> bool {
+        let retune = (hz - self.frequency).abs() + bandwidth / 2.0 > self.sample_rate / 2.0;
+        if retune {
+            self.frequency = (hz + self.sample_rate / 4.0).clamp(1e6, 6e9);
+        }
+        self.vfos[self.active_vfo].demod.set_offset(hz - self.frequency);
+        retune
+    }
+
+    /// Highest level over `channel` above the noise floor, in dB
+    pub fn channel_level(&self, channel: &Channel) -> f32 {
+        let low = channel.frequency - channel.bandwidth / 2.0 - (self.frequency - self.sample_rate / 2.0);
+        let first = (low / self.bin_hz()).floor().max(0.0) as usize;
+        let last = (((low + channel.bandwidth) / self.bin_hz()).ceil() as usize).min(self.spectrum_data.len());
+        let peak = self.spectrum_data.get(first..last.max(first + 1)).and_then(|bins| bins.iter().copied().reduce(f32::max));
+        peak.unwrap_or(f32::NEG_INFINITY) - self.noise_floor
+    }
+
+    /// Start the script at `path`, replacing any running one
+    pub fn load_script(&mut self, path: &Path) -> Result<(), RfError> {
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Script::parse(&text))
+            .map_err(|e| RfError::Config(format!("Script failed: {}: {}", path.display(), e)))?;
+        self.script = Some(script);
+        self.scripted = DecodeMarks::now();
+        self.status_message = format!("Running {}", path.display());
+        Ok(())
+    }
+
+    fn run_script(&mut self) {
+        let Some(mut script) = self.script.take() else {
+            return;
+        };
+        let mut marks = self.scripted;
+        for (decoder, json) in self.decode_events(&mut marks) {
+            script.event(decoder, &json);
+        }
+        self.scripted = marks;
+        match script.step(self, Instant::now()) {
+            Ok(true) => self.script = Some(script),
+            Ok(false) => log::info!("Script finished"),
+            Err(e) => log::error!("Script failed: {}", e),
+        }
+    }
+
+    fn run_scanner(&mut self) {
+        let Some(channel) = self.scanner.current().cloned() else {
+            return;
+        };
+        let was_active = matches!(self.scanner.state, ScanState::Active { .. });
+        let level = self.channel_level(&channel);
+        if let Some(next) = self.scanner.update(level, Instant::now()).cloned() {
+            self.tune_scan_channel(&next);
+        }
+        if !was_active && matches!(self.scanner.state, ScanState::Active { .. }) {
+            log::info!("Scanner stopped on {} at {:+.1} dB", channel.name, level);
+        }
+    }
+
+    pub fn toggle_nmea_log(&mut self) {
+        let mut ais = self.ais.lock();
+        let path = if ais.is_logging_nmea() { None } else { Some(AIS_NMEA_LOG) };
+        match ais.set_nmea_log(path) {
+            Ok(()) if path.is_some() => self.status_message = format!("AIS NMEA logging to {}", AIS_NMEA_LOG),
+            Ok(()) => self.status_message = "AIS NMEA logging stopped".to_string(),
+            Err(e) => log::warn!("Cannot open {}: {}", AIS_NMEA_LOG, e),
+        }
+    }
+
+    pub fn toggle_ism_log(&mut self) {
+        let mut ism = self.ism.lock();
+        let path = if ism.is_logging() { None } else { Some(ISM_JSON_LOG) };
+        match ism.set_log(path) {
+            Ok(()) if path.is_some() => self.status_message = format!("ISM records logging to {}", ISM_JSON_LOG),
+            Ok(()) => self.status_message = "ISM record logging stopped".to_string(),
+            Err(e) => log::warn!("Cannot open {}: {}", ISM_JSON_LOG, e),
+        }
+    }
+
+    pub fn toggle_navtex_log(&mut self) {
+        let mut navtex = self.navtex.lock();
+        let path = if navtex.is_logging() { None } else { Some(NAVTEX_LOG) };
+        match navtex.set_log(path) {
+            Ok(()) if path.is_some() => self.status_message = format!("NAVTEX messages logging to {}", NAVTEX_LOG),
+            Ok(()) => self.status_message = "NAVTEX logging stopped".to_string(),
+            Err(e) => log::warn!("Cannot open {}: {}", NAVTEX_LOG, e),
+        }
+    }
+
+    pub fn toggle_measure_log(&mut self) {
+        let path = if self.meter.is_logging() { None } else { Some(MEASURE_CSV) };
+        match self.meter.set_log(path) {
+            Ok(()) if path.is_some() => self.status_message = format!("Measurements logging to {}", MEASURE_CSV),
+            Ok(()) => self.status_message = "Measurement logging stopped".to_string(),
+            Err(e) => log::warn!("Cannot open {}: {}", MEASURE_CSV, e),
+        }
+    }
+
+    /// Load the decoder libraries under `[plugins]`, each key naming one
+    #[cfg(all(unix, feature = "plugins"))]
+    fn load_plugins(&mut self, config: &Config) {
+        for key in config.keys("plugins") {
+            let path = config.get(&format!("plugins.{}", key)).unwrap_or_default();
+            match DynamicDecoder::load(Path::new(path)) {
+                Ok(decoder) if self.plugins.is_registered(decoder.name()) => {
+                    log::warn!("Config: plugins.{}: a decoder named {} is loaded already", key, decoder.name())
+                }
+                Ok(decoder) => self.plugins.register(Box::new(decoder)),
+                Err(e) => log::warn!("Config: plugins.{}: {}", key, e),
+            }
+        }
+    }
+
+    #[cfg(not(all(unix, feature = "plugins")))]
+    fn load_plugins(&mut self, config: &Config) {
+        if config.keys("plugins").next().is_some() {
+            self.status_message = "Config: [plugins] needs a Unix build with the plugins feature".to_string();
+        }
+    }
+
+    pub fn toggle_scan_log(&mut self) {
+        let path = if self.scanner.is_logging() { None } else { Some(SCAN_CSV) };
+        match self.scanner.set_log(path) {
+            Ok(()) if path.is_some() => self.status_message = format!("Scanner hits logging to {}", SCAN_CSV),
+            Ok(()) => self.status_message = "Scanner hit logging stopped".to_string(),
+            Err(e) => log::warn!("Cannot open {}: {}", SCAN_CSV, e),
+        }
+    }
+
+    pub fn toggle_recording(&mut self) {
+        // Blocks still queued belong in the recording
+        self.recorder.wait();
+        let mut recorder = self.recorder.lock();
+        if recorder.is_recording() {
+            let part = recorder.part();
+            match recorder.stop() {
+                Ok(Some((path, bytes))) => {
+                    self.status_message = match part {
+                        Some(part) if part > 1 => {
+                            format!("Saved {} parts up to {} ({})", part, path.display(), format_size(bytes))
+                        }
+                        _ => format!("Saved {} ({})", path.display(), format_size(bytes)),
+                    }
+                }
+                Ok(None) => self.status_message.clear(),
+                Err(e) => log::error!("Recording failed: {}", e),
+            }
+        } else {
+            let pre_recorded = recorder.pre_recorded_secs();
+            match recorder.start(self.frequency, self.sample_rate) {
+                Ok(path) if pre_recorded > 0.0 => {
+                    self.status_message = format!("Recording to {} from {:.1} s ago", path.display(), pre_recorded)
+                }
+                Ok(path) => self.status_message = format!("Recording to {}", path.display()),
+                Err(e) => log::error!("Cannot start recording in {}/: {}", RECORDING_DIR, e),
+            }
+        }
+    }
+
+    /// Mark the IQ recording where the active VFO is tuned now
+    pub fn annotate_recording(&mut self) {
+        let vfo = self.vfo();
+        let label = format!("{:.4} MHz {}", self.vfo_frequency(vfo) / 1e6, vfo.demod.mode());
+        let annotated = self.recorder.lock().annotate(&label);
+        match annotated {
+            Ok(Some(secs)) => self.status_message = format!("Marked {} at {:.1} s", label, secs),
+            Ok(None) => self.status_message = "Nothing to mark, no IQ recording is running".to_string(),
+            Err(e) => log::warn!("Mark failed: {}", e),
+        }
+    }
+
+    pub fn toggle_audio_recording(&mut self) {
+        if self.audio_recorder.is_recording() {
+            match self.audio_recorder.stop() {
+                Ok(files) => self.status_message = format!("Audio recording stopped, {} files in {}/", files, RECORDING_DIR),
+                Err(e) => log::error!("Audio recording failed: {}", e),
+            }
+        } else {
+            match self.audio_recorder.start() {
+                Ok(()) if self.audio_recorder.squelch_gated => {
+                    self.status_message = format!(
+                        "Recording {} audio to {}/ while above {:.0} dBFS",
+                        self.audio_recorder.format, RECORDING_DIR, self.audio_recorder.squelch_db
+                    )
+                }
+                Ok(()) => self.status_message = format!("Recording {} audio to {}/", self.audio_recorder.format, RECORDING_DIR),
+                Err(e) => log::error!("Cannot record audio in {}/: {}", RECORDING_DIR, e),
+            }
+        }
+    }
+
+    /// Write the monitored audio, after AF gain, gated on the active VFO's channel power
+    fn record_audio(&mut self) {
+        if !self.audio_recorder.is_recording() {
+            return;
+        }
+        let gain = af_gain(self.af_gain_db);
+        let audio: Vec<f32> = self.audio_buffer.iter().map(|s| s * gain).collect();
+        let vfo = self.vfo();
+        let channel_db = 10.0 * vfo.demod.channel_power().max(1e-20).log10();
+        let (frequency, mode, rate) = (self.vfo_frequency(vfo), vfo.demod.mode().to_string(), vfo.demod.rate());
+        match self.audio_recorder.process(&audio, rate, channel_db, frequency, &mode) {
+            Ok(Some(path)) if self.audio_recorder.squelch_gated => {
+                log::info!("Saved transmission {}", path.display());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let _ = self.audio_recorder.stop();
+                log::error!("Audio recording stopped: {}", e);
+            }
+        }
+    }
+
+    pub fn toggle_playback(&mut self) {
+        if self.player.take().is_some() {
+            self.is_streaming = false;
+            self.status_message = "Playback stopped".to_string();
+            return;
+        }
+        let Some(path) = FilePlayer::latest(Path::new(RECORDING_DIR)) else {
+            self.status_message = format!("No recordings in {}/, start with --play FILE to play another", RECORDING_DIR);
+            return;
+        };
+        if let Err(e) = self.open_playback(&path) {
+            log::error!("{}", e);
+        }
+    }
+
+    /// Replace the demo source with the recording at `path`
+    pub fn open_playback(&mut self, path: &Path) -> Result<(), RfError> {
+        let player = FilePlayer::open(path).map_err(|e| RfError::Device(format!("Cannot play {}: {}", path.display(), e)))?;
+        self.frequency = player.meta.frequency;
+        self.sample_rate = player.meta.sample_rate;
+        self.status_message = format!("Playing {}", path.display());
+        self.player = Some(player);
+        self.is_streaming = true;
+        Ok(())
+    }
+
+    pub fn seek_playback(&mut self, secs: f64) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        match player.seek(secs) {
+            Ok(()) => self.status_message = format!("Playback at {:.1} s", player.progress().0),
+            Err(e) => log::warn!("Seek failed: {}", e),
+        }
+    }
+
+    pub fn step_playback_speed(&mut self, faster: bool) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        let index = PLAYBACK_SPEEDS.iter().position(|&s| s == player.speed).unwrap_or(2);
+        let index = if faster { (index + 1).min(PLAYBACK_SPEEDS.len() - 1) } else { index.saturating_sub(1) };
+        player.speed = PLAYBACK_SPEEDS[index];
+        self.status_message = format!("Playback speed ×{}", player.speed);
+    }
+
+    /// Replace the demo source with the next block of the file being played,
+    /// returning false while paused
+    fn play_file(&mut self) -> bool {
+        let Some(player) = &mut self.player else {
+            return false;
+        };
+        if player.paused {
+            return false;
+        }
+        // An update's worth, keeping playback near real time
+        let block = (player.meta.sample_rate * self.tick_interval.as_secs_f64() * player.speed).max(1.0) as usize;
+        let result = player.read(block, &mut self.sample_buffer);
+        match result {
+            Ok(_) if player.is_finished() => {
+                log::info!("Finished playing {}", player.path().display());
+                self.player = None;
+                self.is_streaming = false;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Playback failed: {}", e);
+                self.player = None;
+                self.is_streaming = false;
+            }
+        }
+        true
+    }
+
+    /// Take the samples the remote receiver, or else the demo device, made
+    /// since the last call, first passing on any retuning
+    fn receive_device(&mut self) -> bool {
+        let device: &mut dyn Device = match &mut self.remote {
+            Some(remote) => remote,
+            None => &mut self.demo,
+        };
+        device.tune(self.frequency, self.sample_rate, self.gain);
+        device.receive(&mut self.sample_buffer)
+    }
+
+    /// Hand `block` to the spectrum worker and take the newest spectrum it
+    /// has finished
+    fn measure_spectrum(&mut self, block: &SampleBlock) {
+        self.measured_spectrum.push(block);
+        let mut latest = None;
+        while let Some(spectrum) = self.measured.pop() {
+            latest = Some(spectrum);
+        }
+        if let Some(spectrum) = latest {
+            self.spectrum_data.resize(spectrum.bins.len(), self.noise_floor);
+            for (out, power) in self.spectrum_data.iter_mut().zip(&spectrum.bins) {
+                *out = 10.0 * power.max(1e-20).log10();
+            }
+        }
+    }
+
+    /// Start and stop scheduled recordings as their windows open and close
+    fn run_schedule(&mut self) {
+        let now = SystemTime::now();
+        if let Some(index) = self.schedule.finished(now) {
+            self.recorder.wait();
+            let stopped = self.recorder.lock().stop();
+            let job = &mut self.schedule.jobs[index];
+            job.state = match stopped {
+                Ok(Some((path, bytes))) => JobState::Done { path, bytes },
+                Ok(None) => JobState::Failed("recording stopped by hand".to_string()),
+                Err(e) => JobState::Failed(e.to_string()),
+            };
+            log::info!("Scheduled recording {} finished", job.label);
+        }
+
+        let Some(index) = self.schedule.due(now) else {
+            return;
+        };
+        if self.recorder.lock().is_recording() {
+            let job = &mut self.schedule.jobs[index];
+            job.state = JobState::Failed("recorder busy".to_string());
+            log::warn!("Scheduled recording {} skipped, already recording", job.label);
+            return;
+        }
+        let (frequency, mode) = (self.schedule.jobs[index].frequency, self.schedule.jobs[index].mode);
+        self.player = None;
+        self.frequency = frequency;
+        let demod = &mut self.vfos[self.active_vfo].demod;
+        demod.set_offset(0.0);
+        demod.set_mode(mode);
+        if !self.is_streaming {
+            self.start_streaming();
+        }
+        let started = self.recorder.lock().start(self.frequency, self.sample_rate).map(|path| path.display().to_string());
+        let job = &mut self.schedule.jobs[index];
+        job.state = match started {
+            Ok(path) => {
+                log::info!("Scheduled recording {} to {}", job.label, path);
+                JobState::Recording
+            }
+            Err(e) => {
+                log::error!("Scheduled recording {} failed: {}", job.label, e);
+                JobState::Failed(e.to_string())
+            }
+        };
+    }
+
+    pub fn reconfigure_psk_demod(&mut self, config: PskConfig) {
+        self.status_message = format!("PSK demod {} {} Bd", config.order.name(), config.baud);
+        *self.constellation.lock() = Constellation::new(config, self.sample_rate);
+    }
+
+    /// Step the RTTY shift (`shift == true`) or baud rate to the next preset
+    pub fn cycle_rtty(&mut self, shift: bool) {
+        let next = |presets: &[f64], current: f64| {
+            let index = presets.iter().position(|&p| p == current).unwrap_or(0);
+            presets[(index + 1) % presets.len()]
+        };
+        let mut rtty = self.rtty.lock();
+        let (mut new_shift, mut new_baud) = (rtty.shift, rtty.baud);
+        if shift {
+            new_shift = next(&rtty::SHIFTS, new_shift);
+        } else {
+            new_baud = next(&rtty::BAUD_RATES, new_baud);
+        }
+        rtty.set_params(new_shift, new_baud);
+        self.status_message = format!("RTTY {} Hz shift, {} Bd", new_shift, new_baud);
+    }
+
+    pub fn toggle_wspr_upload(&mut self) {
+        let mut wspr = self.wspr.lock();
+        if wspr.reporter.is_none() {
+            self.status_message = "Set wspr.callsign and wspr.grid in the config to upload spots".to_string();
+            return;
+        }
+        wspr.upload = !wspr.upload;
+        self.status_message = format!(
+            "wsprnet upload {}",
+            if wspr.upload { "enabled" } else { "disabled" }
+        );
+    }
+
+    pub fn bin_hz(&self) -> f64 {
+        self.sample_rate / self.spectrum_data.len() as f64
+    }
+
+    /// Level in dBFS of the spectrum bin containing `hz`
+    pub fn power_at(&self, hz: f64) -> Option<f32> {
+        let offset = hz - (self.frequency - self.sample_rate / 2.0);
+        if offset < 0.0 {
+            return None;
+        }
+        self.spectrum_data.get((offset / self.bin_hz()) as usize).copied()
+    }
+
+    /// Take samples from `remote`, or the demo signals for `None`, with the
+    /// calibration of that device
+    pub fn set_remote(&mut self, remote: Option<RemoteSource>) {
+        self.remote = remote;
+        let device: &dyn Device = match &self.remote {
+            Some(remote) => remote,
+            None => &self.demo,
+        };
+        let info = device.info();
+        let serial = info.serial.as_deref().unwrap_or(device.name());
+        self.calibration_db = self
+            .calibrations
+            .get(&format!("{} {}", info.driver, serial))
+            .or_else(|| self.calibrations.get(&info.driver))
+            .copied();
+    }
+
+    /// Update every `interval`, the demo and playback giving that much of
+    /// their samples each time so they keep to real time
+    pub fn set_tick_interval(&mut self, interval: Duration) {
+        self.tick_interval = interval;
+        self.demo.block_secs = interval.as_secs_f64();
+    }
+
+    /// Pass fresh samples to the network services and apply what their clients asked for
+    fn serve_network(&mut self, fresh: bool) {
+        let state = self.receiver_state();
+        let mut commands = Vec::new();
+        if let Some(server) = &mut self.rtl_tcp {
+            if fresh {
+                server.send(&self.sample_buffer);
+            }
+            commands.extend(server.commands().map(|command| ("rtl_tcp", command)));
+        }
+        if fresh && let Some(publisher) = &mut self.zmq {
+            publisher.send(&self.sample_buffer);
+        }
+        if fresh && let Some(stream) = &mut self.iq_stream {
+            stream.send(&self.sample_buffer);
+        }
+        if fresh && let Some(sender) = &mut self.vita49 {
+            sender.send(&self.sample_buffer, self.frequency, self.sample_rate, self.gain);
+        }
+        if let Some(server) = &mut self.rest {
+            server.update(&state);
+            commands.extend(server.commands().map(|command| ("REST", command)));
+        }
+        if let Some(server) = &mut self.websocket {
+            if fresh {
+                server.send(&state, &self.spectrum_data);
+            }
+            commands.extend(server.commands().map(|command| ("WebSocket", command)));
+        }
+        if let Some(server) = &mut self.rigctl {
+            server.update(&state);
+            commands.extend(server.commands().map(|command| ("rigctl", command)));
+        }
+        if let Some(server) = &mut self.gqrx {
+            server.update(&state);
+            commands.extend(server.commands().map(|command| ("GQRX remote", command)));
+        }
+        if fresh && (self.audio_out.is_some() || self.icecast.is_some()) {
+            let gain = af_gain(self.af_gain_db);
+            let audio: Vec<f32> = self.audio_buffer.iter().map(|s| s * gain).collect();
+            let rate = self.vfos[self.active_vfo].demod.rate();
+            if let Some(sender) = &mut self.audio_out {
+                sender.send(&audio, rate);
+            }
+            if let Some(source) = &mut self.icecast {
+                source.send(&audio, rate);
+            }
+        }
+        if let Some(sender) = &mut self.nmea_out {
+            // Only the newest sentences are kept, so a long gap loses the oldest
+            let ais = self.ais.lock();
+            let new = (ais.sentences - self.nmea_forwarded).min(ais.nmea.len() as u64) as usize;
+            sender.send(&ais.nmea[ais.nmea.len() - new..]);
+            self.nmea_forwarded = ais.sentences;
+        }
+        if let Some(mqtt) = &mut self.mqtt {
+            mqtt.telemetry(&state);
+        }
+        if fresh {
+            self.publish_decodes();
+        }
+        for (source, command) in commands {
+            self.apply_remote(source, command);
+        }
+    }
+
+    /// Hand decodes received since the last call to MQTT as JSON
+    fn publish_decodes(&mut self) {
+        if self.mqtt.is_none() {
+            return;
+        }
+        let mut marks = self.published;
+        let events = self.decode_events(&mut marks);
+        self.published = marks;
+        if let Some(mqtt) = &mut self.mqtt {
+            for (decoder, json) in events {
+                mqtt.event(decoder, json);
+            }
+        }
+    }
+
+    /// Decodes received since `marks` as JSON, each named by its decoder,
+    /// moving `marks` up to the newest
+    pub fn decode_events(&self, marks: &mut DecodeMarks) -> Vec<(&'static str, String)> {
+        let mut events = Vec::new();
+        let time = |at: SystemTime| {
+            let (date, time) = utc_date_time(at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+            ("time".to_string(), Value::String(format!("{} {}", date, time)))
+        };
+        let text = |key: &str, value: String| (key.to_string(), Value::String(value));
+        let number = |key: &str, value: f64| (key.to_string(), Value::Number(value));
+
+        for page in newer(&self.pager.lock().messages, &mut marks.pager, |m| m.received) {
+            let event = vec![
+                time(page.received),
+                text("protocol", page.protocol.to_string()),
+                number("address", page.address as f64),
+                number("function", page.function as f64),
+                text("text", page.text.clone()),
+            ];
+            events.push(("pager", Value::Object(event).to_string()));
+        }
+        for alert in newer(&self.same.alerts, &mut marks.same, |a| a.received) {
+            let event = vec![
+                time(alert.received),
+                text("originator", alert.originator.clone()),
+                text("event", alert.event.clone()),
+                (
+                    "locations".to_string(),
+                    Value::Array(alert.locations.iter().cloned().map(Value::String).collect()),
+                ),
+                text("issued", alert.issued.clone()),
+                text("sender", alert.sender.clone()),
+            ];
+            events.push(("same", Value::Object(event).to_string()));
+        }
+        for record in newer(&self.ism.lock().records, &mut marks.ism, |r| r.received) {
+            events.push(("ism", record.to_json()));
+        }
+        for message in newer(&self.navtex.lock().messages, &mut marks.navtex, |m| m.received) {
+            let event = vec![
+                time(message.received),
+                text("station", message.station.to_string()),
+                text("subject", message.subject.to_string()),
+                text("number", message.number.clone()),
+                text("text", message.text.clone()),
+            ];
+            events.push(("navtex", Value::Object(event).to_string()));
+        }
+        for decode in newer(&self.ft8.lock().decodes, &mut marks.ft8, |d| d.cycle_start) {
+            let event = vec![
+                time(UNIX_EPOCH + Duration::from_secs(decode.cycle_start)),
+                number("frequency", self.frequency + decode.audio_hz as f64),
+                number("snr", decode.snr as f64),
+                number("dt", (decode.dt as f64 * 10.0).round() / 10.0),
+                text("message", decode.message.clone()),
+            ];
+            events.push(("ft8", Value::Object(event).to_string()));
+        }
+        for spot in newer(&self.wspr.lock().spots, &mut marks.wspr, |s| s.window_start) {
+            let event = vec![
+                time(UNIX_EPOCH + Duration::from_secs(spot.window_start)),
+                number("frequency", spot.frequency),
+                text("callsign", spot.callsign.clone()),
+                text("grid", spot.grid.clone()),
+                number("dbm", spot.dbm as f64),
+                number("snr", spot.snr.round() as f64),
+            ];
+            events.push(("wspr", Value::Object(event).to_string()));
+        }
+        events
+    }
+
+    pub fn receiver_state(&self) -> ReceiverState {
+        ReceiverState {
+            frequency: self.frequency,
+            vfo_frequency: self.vfo_frequency(self.vfo()),
+            sample_rate: self.sample_rate,
+            gain: self.gain,
+            mode: self.vfo().demod.mode(),
+            streaming: self.is_streaming,
+            signal_db: 10.0 * self.vfo().demod.channel_power().max(1e-20).log10(),
+            squelch_db: self.audio_recorder.squelch_db,
+            recording: self.audio_recorder.is_recording(),
+        }
+    }
+
+    pub fn apply_remote(&mut self, source: &str, command: Command) {
+        self.status_message = match command {
+            Command::Frequency(hz) => {
+                self.frequency = hz.clamp(1e6, 6e9);
+                format!("{} tuned to {:.4} MHz", source, self.frequency / 1e6)
+            }
+            Command::SampleRate(rate) => {
+                self.sample_rate = rate.clamp(0.1e6, 10e6);
+                format!("{} set the sample rate to {:.3} MS/s", source, self.sample_rate / 1e6)
+            }
+            Command::Gain(db) => {
+                self.gain = db.clamp(0.0, 60.0);
+                format!("{} set the gain to {:.1} dB", source, self.gain)
+            }
+            Command::Mode(mode) => {
+                self.vfos[self.active_vfo].demod.set_mode(mode);
+                format!("{} set VFO {} to {}", source, self.active_vfo + 1, mode)
+            }
+            Command::Streaming(on) => {
+                if on && !self.is_streaming {
+                    self.start_streaming();
+                } else if !on && self.is_streaming {
+                    self.stop_streaming();
+                }
+                format!("{} {} streaming", source, if on { "started" } else { "stopped" })
+            }
+            Command::Squelch(db) => {
+                self.audio_recorder.squelch_db = db.clamp(-150.0, 0.0);
+                format!("{} set the squelch to {:.0} dBFS", source, self.audio_recorder.squelch_db)
+            }
+            Command::Recording(on) => {
+                if on != self.audio_recorder.is_recording() {
+                    self.toggle_audio_recording();
+                }
+                return;
+            }
+        };
+    }
+
+    pub fn vfo(&self) -> &Vfo {
+        &self.vfos[self.active_vfo]
+    }
+
+    /// Absolute frequency the VFO is tuned to
+    pub fn vfo_frequency(&self, vfo: &Vfo) -> f64 {
+        self.frequency + vfo.demod.offset_hz()
+    }
+
+    pub fn add_vfo(&mut self) {
+        if self.vfos.len() == MAX_VFOS {
+            self.status_message = format!("At most {} VFOs", MAX_VFOS);
+            return;
+        }
+        let limit = self.sample_rate / 2.0 - NEW_VFO_STEP_HZ;
+        let offset = (self.vfo().demod.offset_hz() + NEW_VFO_STEP_HZ).clamp(-limit, limit);
+        // New VFOs start muted so they do not disturb the decoders
+        self.vfos.push(Vfo::new(self.vfo().demod.mode(), offset, AudioRoute::Muted));
+        self.active_vfo = self.vfos.len() - 1;
+        self.status_message = format!(
+            "VFO {} added at {:.4} MHz",
+            self.active_vfo + 1,
+            self.vfo_frequency(self.vfo()) / 1e6
+        );
+    }
+
+    pub fn remove_vfo(&mut self) {
+        if self.vfos.len() == 1 {
+            self.status_message = "The last VFO cannot be removed".to_string();
+            return;
+        }
+        self.vfos.remove(self.active_vfo);
+        self.status_message = format!("VFO {} removed", self.active_vfo + 1);
+        self.active_vfo = self.active_vfo.min(self.vfos.len() - 1);
+    }
+
+    /// Count samples dropped since the last update, and an underrun when a
+    /// device that was delivering delivered nothing
+    fn track_losses(&mut self, fresh: bool) {
+        let now = Instant::now();
+        let stages = [
+            self.ais.dropped,
+            self.pager.dropped,
+            self.rtty.dropped,
+            self.psk.dropped,
+            self.wspr.dropped,
+            self.ft8.dropped,
+            self.cw.dropped,
+            self.ism.dropped,
+            self.navtex.dropped,
+            self.classifier.dropped,
+            self.recorder.dropped,
+            self.constellation.dropped,
+            self.measured_spectrum.dropped,
+        ];
+        let total = stages.iter().sum::<u64>() + self.remote.as_ref().map_or(0, |remote| remote.dropped);
+        let losses = &mut self.losses;
+        // A new remote source counts from zero
+        if total < losses.seen {
+            losses.seen = 0;
+        }
+        if total > losses.seen {
+            losses.dropped += total - losses.seen;
+            losses.last_overrun = Some(now);
+        }
+        losses.seen = total;
+        if self.remote.is_some() && self.is_streaming {
+            if losses.flowing && !fresh {
+                losses.underruns += 1;
+                losses.last_underrun = Some(now);
+            }
+            losses.flowing = fresh;
+        } else {
+            losses.flowing = false;
+        }
+    }
+
+    fn track_noise_floor(&mut self) {
+        let floor = median(&self.spectrum_data);
+        self.noise_floor = if self.noise_floor.is_finite() {
+            self.noise_floor + NOISE_FLOOR_ALPHA * (floor - self.noise_floor)
+        } else {
+            floor
+        };
+    }
+
+    /// Run the protocol decoders over the latest sample block
+    fn feed_decoders(&mut self, block: &SampleBlock) {
+        self.audio_buffer.clear();
+        self.decoder_audio.clear();
+        for vfo in &mut self.vfos {
+            vfo.demod.process(&self.sample_buffer, self.sample_rate, &mut vfo.audio);
+            if vfo.route != AudioRoute::Muted {
+                mix_into(&mut self.audio_buffer, &vfo.audio);
+            }
+            if vfo.route == AudioRoute::Decoders {
+                mix_into(&mut self.decoder_audio, &vfo.audio);
+            }
+        }
+        // Every VFO runs at the same audio rate
+        let audio_rate = self.vfo().demod.rate();
+        self.vu.process(&self.audio_buffer, self.af_gain_db, audio_rate);
+        let channel_dbfs = 10.0 * self.vfo().demod.channel_power().max(1e-20).log10();
+        let channel_dbm = channel_dbfs + self.calibration_db.unwrap_or(UNCALIBRATED_DB) - self.gain as f32;
+        let now = Instant::now();
+        self.power_history.push_back((now, channel_dbfs));
+        let longest = Duration::from_secs(POWER_HISTORY_SPANS[POWER_HISTORY_SPANS.len() - 1]);
+        while self.power_history.front().is_some_and(|&(t, _)| now - t > longest) {
+            self.power_history.pop_front();
+        }
+        self.s_meter.update(channel_dbm, (self.sample_buffer.len() as f64 / self.sample_rate) as f32);
+        self.dtmf.process(&self.decoder_audio, audio_rate);
+        self.same.process(&self.decoder_audio, audio_rate);
+        self.ais.push(block);
+        self.pager.push(block);
+        self.rtty.push(block);
+        self.psk.push(block);
+        self.wspr.push(block);
+        self.ft8.push(block);
+        self.cw.push(block);
+        self.ism.push(block);
+        self.navtex.push(block);
+        // Classify what the active VFO is listening to
+        let offset = self.vfo().demod.offset_hz();
+        if offset != self.classifier_offset {
+            self.classifier_offset = offset;
+            self.classifier.lock().set_offset(offset);
+        }
+        self.classifier.push(block);
+        self.constellation.push(block);
+        self.plugins.process(&Input {
+            iq: &self.sample_buffer,
+            center_freq: self.frequency,
+            sample_rate: self.sample_rate,
+            audio: &self.decoder_audio,
+            audio_rate,
+        });
+        self.meter.process(&self.sample_buffer, self.frequency, self.sample_rate);
+        if self.burst_capture {
+            self.bursts.process(&self.sample_buffer, self.frequency, self.sample_rate);
+        }
+    }
+
+    /// Wait until the decoders, the spectrum, the classifier and the recorder
+    /// have processed every block handed to them
+    pub fn wait_for_workers(&self) {
+        self.measured_spectrum.wait();
+        self.classifier.wait();
+        self.constellation.wait();
+        self.recorder.wait();
+        self.ais.wait();
+        self.pager.wait();
+        self.rtty.wait();
+        self.psk.wait();
+        self.wspr.wait();
+        self.ft8.wait();
+        self.cw.wait();
+        self.ism.wait();
+        self.navtex.wait();
+    }
+
+    pub fn start_streaming(&mut self) {
+        self.is_streaming = true;
+        if self.player.is_some() {
+            return;
+        }
+        self.status_message = match &self.remote {
+            Some(remote) => format!("Streaming from {}", remote.server),
+            None => "Mock streaming started (demo mode)".to_string(),
+        };
+    }
+
+    pub fn stop_streaming(&mut self) {
+        self.is_streaming = false;
+        self.status_message = "Streaming stopped".to_string();
+    }
+
+    /// Take the next samples from the source and pass them to everything that
+    /// uses them, returning whether there were any
+    pub fn tick(&mut self) -> bool {
+        self.run_schedule();
+        self.run_script();
+        match self.is_streaming {
+            true => _ = self.streaming_since.get_or_insert_with(Instant::now),
+            false => self.streaming_since = None,
+        }
+
+        let fresh = match self.is_streaming {
+            true if self.player.is_some() => self.play_file(),
+            true => self.receive_device(),
+            false => false,
+        };
+        if fresh {
+            self.process_samples();
+        }
+        self.track_losses(fresh);
+        self.serve_network(fresh);
+        fresh
+    }
+
+    /// Pass the samples in `sample_buffer` to everything that uses them, for
+    /// a program bringing samples of its own instead of calling [`tick`](Self::tick)
+    pub fn process_samples(&mut self) {
+        let block = SampleBlock {
+            iq: self.sample_buffer.as_slice().into(),
+            center_freq: self.frequency,
+            sample_rate: self.sample_rate,
+        };
+        self.measure_spectrum(&block);
+        self.track_noise_floor();
+        self.feed_decoders(&block);
+        self.recorder.push(&block);
+        self.record_audio();
+        self.run_scanner();
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Host for Engine {
+    fn tune(&mut self, hz: f64) {
+        self.tune_vfo(hz.clamp(1e6, 6e9), self.vfo().demod.mode().passband());
+    }
+
+    fn set_mode(&mut self, mode: AudioMode) {
+        self.vfos[self.active_vfo].demod.set_mode(mode);
+    }
+
+    fn set_gain(&mut self, db: f64) {
+        self.gain = db.clamp(0.0, 60.0);
+    }
+
+    fn power(&self) -> f32 {
+        10.0 * self.vfo().demod.channel_power().max(1e-20).log10()
+    }
+
+    fn snr(&self) -> f32 {
+        let mode = self.vfo().demod.mode();
+        self.channel_level(&Channel {
+            name: String::new(),
+            frequency: self.vfo_frequency(self.vfo()),
+            mode,
+            bandwidth: mode.passband(),
+        })
+    }
+
+    fn start_recording(&mut self) -> Result<(), String> {
+        if !self.recorder.lock().is_recording() {
+            self.toggle_recording();
+        }
+        match self.recorder.lock().is_recording() {
+            true => Ok(()),
+            false => Err(logging::since(0).pop().map_or_else(|| "Recording not started".to_string(), |entry| entry.message)),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if self.recorder.lock().is_recording() {
+            self.toggle_recording();
+        }
+    }
+
+    fn log(&mut self, text: &str) {
+        self.status_message = text.to_string();
+        if self.script_log.len() < MAX_SCRIPT_LOG {
+            self.script_log.push(text.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The demo receiver after a second of streaming
+    fn streaming() -> Engine {
+        let mut engine = Engine::new();
+        engine.start_streaming();
+        for _ in 0..20 {
+            engine.tick();
+        }
+        engine.wait_for_workers();
+        engine.tick();
+        engine
+    }
+
+    #[test]
+    fn demo_spectrum_shows_the_mock_signals() {
+        let engine = streaming();
+        let bins = &engine.spectrum_data;
+        let bin = |hz: f64| ((hz - engine.frequency) / engine.sample_rate * bins.len() as f64 + bins.len() as f64 / 2.0) as usize;
+        // Highest bin within `span` of `hz`, where an FM carrier swings to
+        // wherever its tone had it when the spectrum was measured
+        let at = |hz: f64, span: f64| bins[bin(hz - span)..=bin(hz + span)].iter().copied().fold(f32::MIN, f32::max);
+        let floor = median(bins);
+        assert!(at(890.0e6, 40e3) > floor + 30.0, "890.0 MHz at {:.1} dBFS over {:.1}", at(890.0e6, 40e3), floor);
+        assert!(at(890.2e6, 25e3) > floor + 15.0, "890.2 MHz at {:.1} dBFS over {:.1}", at(890.2e6, 25e3), floor);
+        assert!(at(889.7e6, 0.0) < floor + 6.0);
+    }
+
+    #[test]
+    fn sources_keep_to_real_time_at_any_tick() {
+        let mut engine = Engine::new();
+        engine.set_tick_interval(Duration::from_millis(120));
+        engine.receive_device();
+        assert_eq!(engine.sample_buffer.len(), (engine.sample_rate * 0.12).round() as usize);
+
+        let path = std::env::temp_dir().join(format!("rf_rust_tick_{}_100000000Hz_48000sps.cs16", std::process::id()));
+        std::fs::write(&path, vec![0u8; 48_000 * 4]).unwrap();
+        engine.player = Some(FilePlayer::open(&path).unwrap());
+        engine.play_file();
+        std::fs::remove_file(&path).unwrap();
+        let (position, duration) = engine.player.as_ref().unwrap().progress();
+        assert_eq!((position, duration), (0.12, 1.0));
+    }
+
+    #[test]
+    fn classifier_follows_the_active_vfo() {
+        let mut engine = Engine::new();
+        engine.sample_buffer = vec![Complex32::default(); 1024];
+        engine.add_vfo();
+        let offset = engine.vfo().demod.offset_hz();
+        assert_ne!(offset, 0.0);
+        engine.process_samples();
+        engine.classifier.wait();
+        assert_eq!(engine.classifier.lock().offset_hz(), offset);
+
+        engine.active_vfo = 0;
+        engine.process_samples();
+        engine.classifier.wait();
+        assert_eq!(engine.classifier.lock().offset_hz(), 0.0);
+    }
+
+    #[test]
+    fn wsprnet_reporter_comes_from_the_config() {
+        let mut engine = Engine::new();
+        engine.toggle_wspr_upload();
+        assert!(!engine.wspr.lock().upload);
+        engine.apply_config(&Config::parse("[wspr]\ncallsign = N0CALL").unwrap());
+        assert!(engine.wspr.lock().reporter.is_none());
+        engine.apply_config(&Config::parse("[wspr]\ncallsign = N0CALL\ngrid = FN31").unwrap());
+        engine.toggle_wspr_upload();
+        let wspr = engine.wspr.lock();
+        assert_eq!(wspr.reporter.as_ref().map(|r| (r.callsign.as_str(), r.grid.as_str())), Some(("N0CALL", "FN31")));
+        assert!(wspr.upload);
+    }
+
+    #[test]
+    fn remote_control_tunes_and_streams() {
+        let mut engine = Engine::new();
+        engine.apply_remote("rigctl", Command::Frequency(145.5e6));
+        assert_eq!(engine.frequency, 145.5e6);
+        assert_eq!(engine.status_message, "rigctl tuned to 145.5000 MHz");
+        engine.apply_remote("REST", Command::Mode(AudioMode::Usb));
+        assert_eq!(engine.receiver_state().mode, AudioMode::Usb);
+        engine.apply_remote("REST", Command::Streaming(true));
+        assert!(engine.tick());
+        assert!(engine.receiver_state().streaming);
+    }
+}
@@ -0,0 +1,47 @@
+//! Errors of the receiver, by where they come from. The TUI shows those it
+//! can carry on after in the status bar and only exits for [`RfError::Ui`],
+//! when the terminal itself has failed.
+
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum RfError {
+    /// A source of samples: an SDR, a remote receiver or a recording
+    Device(String),
+    /// A decoder or other processing stage
+    Dsp(String),
+    /// Settings the user gave: the config file, the command line or a script
+    Config(String),
+    Io(io::Error),
+    /// The terminal
+    Ui(String),
+}
+
+pub type Result<T> = std::result::Result<T, RfError>;
+
+impl fmt::Display for RfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RfError::Device(message) | RfError::Dsp(message) | RfError::Config(message) | RfError::Ui(message) => {
+                f.write_str(message)
+            }
+            RfError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RfError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RfError {
+    fn from(e: io::Error) -> Self {
+        RfError::Io(e)
+    }
+}
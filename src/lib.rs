@@ -0,0 +1,23 @@
+//! The receiver engine without the terminal UI: DSP, protocol decoders,
+//! recording and playback, and the network services, tied together by
+//! [`engine::Engine`] for other programs to embed. The `rf_rust` binary is
+//! the TUI on top of it.
+
+pub mod bandplan;
+pub mod bookmarks;
+pub mod config;
+pub mod decoders;
+pub mod device;
+pub mod dsp;
+pub mod engine;
+pub mod error;
+pub mod json;
+pub mod logging;
+pub mod net;
+pub mod png;
+pub mod presets;
+pub mod recording;
+pub mod runtime;
+pub mod scanner;
+pub mod script;
+pub mod session;
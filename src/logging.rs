@@ -0,0 +1,87 @@
+//! A [`log`] logger keeping recent records in memory, for the TUI's log
+//! panel and for `serve` to print. Device faults, dropped blocks, decode
+//! failures and config errors are logged where they happen, so none is
+//! lost behind the next status message.
+//!
+//! A record repeating the one before it counts as a repeat rather than a
+//! new entry, so a steady stream of dropped blocks takes one line.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Increases with every record, repeats included
+    pub id: u64,
+    /// Time of the latest repeat
+    pub time: SystemTime,
+    pub level: Level,
+    /// Module the record came from
+    pub target: String,
+    pub message: String,
+    /// Times logged in a row
+    pub count: u32,
+}
+
+struct Logger {
+    entries: Mutex<(u64, VecDeque<Entry>)>,
+}
+
+static LOGGER: Logger = Logger { entries: Mutex::new((0, VecDeque::new())) };
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        let mut guard = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let (last_id, entries) = &mut *guard;
+        *last_id += 1;
+        let id = *last_id;
+        match entries.back_mut() {
+            Some(last) if last.level == record.level() && last.target == record.target() && last.message == message => {
+                last.id = id;
+                last.time = SystemTime::now();
+                last.count += 1;
+            }
+            _ => {
+                if entries.len() == MAX_ENTRIES {
+                    entries.pop_front();
+                }
+                entries.push_back(Entry {
+                    id,
+                    time: SystemTime::now(),
+                    level: record.level(),
+                    target: record.target().to_string(),
+                    message,
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Keep records at `level` and above. Only the first call has an effect.
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Kept entries with an id above `id`, oldest first, all of them for 0
+pub fn since(id: u64) -> Vec<Entry> {
+    let guard = LOGGER.entries.lock().unwrap_or_else(|e| e.into_inner());
+    guard.1.iter().filter(|entry| entry.id > id).cloned().collect()
+}
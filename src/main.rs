@@ -1,41 +1,42 @@
+mod args;
 mod tui;
 
-use std::error::Error;
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // Check if we have a TTY before trying to run TUI
-    if !atty::is(atty::Stream::Stdout) {
-        println!("🚨 SDR CONTROL TERMINAL 🚨");
-        println!("═══════════════════════════");
-        println!();
-        println!("❌ ERROR: No TTY detected!");
-        println!("This is a Terminal User Interface (TUI) application that requires a proper terminal.");
-        println!();
-        println!("Please run this in:");
-        println!("• A real terminal emulator (Terminal, iTerm, etc.)");
-        println!("• Not in an IDE output panel");
-        println!("• Not in a web-based environment");
-        println!();
-        println!("For demo purposes, here's what the interface would show:");
-        println!();
-        println!("🛰️  SDR CONTROL TERMINAL  🛰️");
-        println!("┌─────────────────────────────────────┐");
-        println!("│ 📡 FREQUENCY ⚡ GAIN 📊 SAMPLE RATE │");
-        println!("├─────────────────────────────────────┤");
-        println!("│ Frequency: 100.000 MHz              │");
-        println!("│ Use ↑↓ to adjust                    │");
-        println!("│ Step: 1 MHz                         │");
-        println!("├─────────────────────────────────────┤");
-        println!("│ [C] Connect USRP  [S] Start Streaming │");
-        println!("│ [Q] Quit                             │");
-        println!("└─────────────────────────────────────┘");
-        println!();
-        println!("STATUS: DEMO MODE - Mock SDR data active");
-        println!("STREAMING: INACTIVE");
-        return Ok(());
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}\n{}", tui::headless::USAGE, args::OPTIONS_USAGE);
+        return ExitCode::SUCCESS;
     }
-
-    // Launch the futuristic SDR TUI
-    tui::run_tui()?;
-    Ok(())
-}
\ No newline at end of file
+    let (mut options, words) = match args::parse(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n{}\n{}", e, tui::headless::USAGE, args::OPTIONS_USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    rf_rust::logging::init(log::LevelFilter::Info);
+    let result = match words[..] {
+        [] | ["connect", _] if !atty::is(atty::Stream::Stdout) => {
+            eprintln!("No terminal for the TUI, run it in a terminal emulator or use a subcommand.");
+            eprintln!("{}\n{}", tui::headless::USAGE, args::OPTIONS_USAGE);
+            return ExitCode::from(2);
+        }
+        [] => tui::run_tui(&options),
+        // The TUI for a receiver elsewhere running `serve`
+        ["connect", server] => {
+            options.source = Some(args::Source::RtlTcp(server.to_string()));
+            tui::run_tui(&options)
+        }
+        _ => tui::headless::run(&words, &options),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
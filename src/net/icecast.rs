@@ -0,0 +1,254 @@
+//! An Icecast source client publishing the monitored audio as a web stream.
+//!
+//! The stream is Ogg FLAC, 8 kHz mono, from the same encoder as FLAC audio
+//! recordings. Icecast relays it to browsers and players without
+//! re-encoding. There is no MP3 or Opus encoder here to offer a smaller
+//! stream, but lossless speech at 8 kHz is well under 128 kbit/s.
+
+use std::io;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::time;
+
+use super::http::base64;
+use crate::dsp::Resampler;
+use crate::recording::flac::{self, BLOCK_SIZE};
+use crate::runtime;
+
+/// Sample rate of the stream
+const RATE: u32 = 8000;
+/// Blocks of audio waiting for the server before new ones are dropped
+const QUEUE_BLOCKS: usize = 64;
+const RECONNECT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ogg page header types
+const BOS: u8 = 0x02;
+
+/// Server, mount and credentials
+#[derive(Clone, Debug)]
+pub struct IcecastConfig {
+    /// `host:port`
+    pub server: String,
+    pub mount: String,
+    pub user: String,
+    pub password: String,
+    /// Stream name shown by the server and players
+    pub name: String,
+}
+
+impl IcecastConfig {
+    pub fn new(server: &str, password: &str) -> Self {
+        Self {
+            server: server.to_string(),
+            mount: "/rf_rust.ogg".to_string(),
+            user: "source".to_string(),
+            password: password.to_string(),
+            name: "rf_rust".to_string(),
+        }
+    }
+}
+
+pub struct IcecastSource {
+    pub config: IcecastConfig,
+    blocks: Sender<Vec<i16>>,
+    status_rx: watch::Receiver<String>,
+    /// Latest connection state or error from the streaming task
+    pub status: String,
+    /// Blocks lost because the server could not keep up
+    pub dropped: u64,
+    resampler: Resampler,
+}
+
+impl IcecastSource {
+    pub fn start(config: IcecastConfig) -> Self {
+        let (blocks, queued) = mpsc::channel(QUEUE_BLOCKS);
+        let (status_tx, status_rx) = watch::channel("connecting".to_string());
+        runtime::spawn(run(config.clone(), queued, status_tx));
+        Self {
+            config,
+            blocks,
+            status_rx,
+            status: "connecting".to_string(),
+            dropped: 0,
+            resampler: Resampler::new(RATE as f64),
+        }
+    }
+
+    /// Queue `audio`, full scale at ±1, taken at `rate`
+    pub fn send(&mut self, audio: &[f32], rate: f64) {
+        if self.status_rx.has_changed().unwrap_or(false) {
+            self.status = self.status_rx.borrow_and_update().clone();
+        }
+        let mut resampled = Vec::new();
+        self.resampler.process(audio, rate, &mut resampled);
+        let pcm = resampled.iter().map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).collect();
+        match self.blocks.try_send(pcm) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log::warn!("Icecast: audio dropped, the server is too slow");
+                self.dropped += 1;
+            }
+            Err(TrySendError::Closed(_)) => self.status = "stopped".to_string(),
+        }
+    }
+}
+
+/// Stream to the server, starting a fresh Ogg stream on every connection,
+/// until the source is dropped
+async fn run(config: IcecastConfig, mut blocks: Receiver<Vec<i16>>, status: watch::Sender<String>) {
+    loop {
+        let error = match connect(&config).await {
+            Ok(stream) => {
+                status.send_replace(format!("streaming to {}", config.mount));
+                match stream_to(stream, &mut blocks).await {
+                    Ok(()) => return,
+                    Err(e) => format!("disconnected: {}", e),
+                }
+            }
+            Err(e) => e.to_string(),
+        };
+        log::warn!("Icecast {}: {}, retrying", config.server, error);
+        status.send_replace(format!("{}, retrying", error));
+        // Drain the queue meanwhile so the stream resumes with current audio
+        let retry_at = time::Instant::now() + RECONNECT;
+        while let Ok(block) = time::timeout_at(retry_at, blocks.recv()).await {
+            if block.is_none() {
+                return;
+            }
+        }
+    }
+}
+
+/// Log in as a source with an HTTP PUT to the mount
+async fn connect(config: &IcecastConfig) -> io::Result<TcpStream> {
+    let timed_out = |_| io::Error::new(io::ErrorKind::TimedOut, "server did not answer");
+    let mut stream = time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&config.server)).await.map_err(timed_out)??;
+    let credentials = base64(format!("{}:{}", config.user, config.password).as_bytes());
+    let request = format!(
+        "PUT {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Basic {}\r\nUser-Agent: rf_rust/{}\r\n\
+         Content-Type: audio/ogg\r\nIce-Name: {}\r\nIce-Public: 0\r\nIce-Audio-Info: samplerate={};channels=1\r\n\r\n",
+        config.mount,
+        config.server,
+        credentials,
+        env!("CARGO_PKG_VERSION"),
+        config.name,
+        RATE
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let status_line = time::timeout(CONNECT_TIMEOUT, async {
+        let mut reader = BufReader::new(&mut stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line).await?;
+        // Skip the rest of the response head
+        let mut line = String::new();
+        while reader.read_line(&mut line).await? > 2 {
+            line.clear();
+        }
+        io::Result::Ok(status_line)
+    })
+    .await
+    .map_err(timed_out)??;
+    let code = status_line.split_whitespace().nth(1).unwrap_or("");
+    match code {
+        "100" | "200" => Ok(stream),
+        "401" => Err(io::Error::new(io::ErrorKind::PermissionDenied, "server refused the source password")),
+        "403" => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("mount {} is in use or not allowed", config.mount))),
+        _ => Err(io::Error::other(format!("server answered `{}`", status_line.trim()))),
+    }
+}
+
+/// Send the Ogg FLAC headers, then a page per FLAC frame until the
+/// connection fails, or until the source is dropped for `Ok`
+async fn stream_to(mut stream: TcpStream, blocks: &mut Receiver<Vec<i16>>) -> io::Result<()> {
+    let mut ogg = OggStream::new(rand::random());
+
+    // Mapping header: packet type, "FLAC", version 1.0, one more header packet
+    let mut first = vec![0x7f];
+    first.extend_from_slice(b"FLAC");
+    first.extend_from_slice(&[1, 0]);
+    first.extend_from_slice(&1u16.to_be_bytes());
+    first.extend_from_slice(b"fLaC");
+    first.extend(flac::streaminfo(RATE, 0, (0, 0), false));
+    stream.write_all(&ogg.page(&first, BOS, 0)).await?;
+    stream.write_all(&ogg.page(&vorbis_comment(), 0, 0)).await?;
+
+    let (mut block, mut frames, mut samples) = (Vec::with_capacity(BLOCK_SIZE), 0u64, 0u64);
+    while let Some(pcm) = blocks.recv().await {
+        for s in pcm {
+            block.push(s);
+            if block.len() == BLOCK_SIZE {
+                let frame = flac::encode_frame(&block, frames);
+                frames += 1;
+                samples += block.len() as u64;
+                block.clear();
+                stream.write_all(&ogg.page(&frame, 0, samples)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// VORBIS_COMMENT metadata block, the last one, naming the encoder
+fn vorbis_comment() -> Vec<u8> {
+    let vendor = format!("rf_rust {}", env!("CARGO_PKG_VERSION"));
+    let mut body = (vendor.len() as u32).to_le_bytes().to_vec();
+    body.extend_from_slice(vendor.as_bytes());
+    body.extend_from_slice(&0u32.to_le_bytes());
+    // Last-block flag and block type 4
+    let mut block = vec![0x80 | 4];
+    block.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    block.extend(body);
+    block
+}
+
+/// Pages of one logical Ogg stream, a whole packet per page
+struct OggStream {
+    serial: u32,
+    sequence: u32,
+}
+
+impl OggStream {
+    fn new(serial: u32) -> Self {
+        Self { serial, sequence: 0 }
+    }
+
+    /// A page holding `packet`, with `granule` samples decoded by its end
+    fn page(&mut self, packet: &[u8], header_type: u8, granule: u64) -> Vec<u8> {
+        // Lacing: runs of 255 then the remainder, which ends the packet
+        let mut lacing = vec![255u8; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+        assert!(lacing.len() <= 255, "packet too long for one Ogg page");
+
+        let mut page = b"OggS".to_vec();
+        page.push(0);
+        page.push(header_type);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&self.serial.to_le_bytes());
+        page.extend_from_slice(&self.sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        page.extend_from_slice(packet);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        self.sequence += 1;
+        page
+    }
+}
+
+fn ogg_crc(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |mut crc, &b| {
+        crc ^= (b as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+        crc
+    })
+}
@@ -0,0 +1,153 @@
+//! Raw IQ sent to another program as UDP datagrams or over a TCP connection,
+//! for processing pipelines that read cf32 or cs16 from a socket.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use num_complex::Complex32;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::time::{self, Instant};
+
+use crate::recording::iq::SampleFormat;
+use crate::runtime;
+
+/// Largest UDP payload that fits an Ethernet frame, a whole number of samples in either format
+const MAX_DATAGRAM: usize = 1472;
+/// Blocks waiting for the network before new ones are dropped
+const QUEUE_BLOCKS: usize = 64;
+const RECONNECT_SECS: u64 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// Streams the live IQ to one endpoint from a task on the
+/// [`runtime`](crate::runtime), which reconnects a TCP stream that drops
+pub struct IqStream {
+    /// The endpoint as configured, `udp://host:port` or `tcp://host:port`
+    pub url: String,
+    pub format: SampleFormat,
+    blocks: Sender<Vec<u8>>,
+    status_rx: watch::Receiver<String>,
+    /// Latest connection state or error from the sending task
+    pub status: String,
+    /// Blocks lost because the network could not keep up
+    pub dropped: u64,
+}
+
+impl IqStream {
+    pub fn open(url: &str, format: SampleFormat) -> Result<Self, String> {
+        let (transport, host) = if let Some(host) = url.strip_prefix("udp://") {
+            (Transport::Udp, host)
+        } else if let Some(host) = url.strip_prefix("tcp://") {
+            (Transport::Tcp, host)
+        } else {
+            return Err(format!("`{}` should start with udp:// or tcp://", url));
+        };
+        let addr = host
+            .to_socket_addrs()
+            .map_err(|e| format!("{}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("{}: no address", host))?;
+        let (blocks, queued) = mpsc::channel(QUEUE_BLOCKS);
+        let (status_tx, status_rx) = watch::channel("starting".to_string());
+        runtime::spawn(async move {
+            match transport {
+                Transport::Udp => send_udp(addr, queued, status_tx).await,
+                Transport::Tcp => send_tcp(addr, queued, status_tx).await,
+            }
+        });
+        Ok(Self {
+            url: url.to_string(),
+            format,
+            blocks,
+            status_rx,
+            status: "starting".to_string(),
+            dropped: 0,
+        })
+    }
+
+    pub fn send(&mut self, samples: &[Complex32]) {
+        if self.status_rx.has_changed().unwrap_or(false) {
+            self.status = self.status_rx.borrow_and_update().clone();
+        }
+        let mut block = Vec::with_capacity(samples.len() * self.format.bytes_per_sample() as usize);
+        // Writing to a Vec cannot fail
+        let _ = self.format.write(&mut block, samples);
+        match self.blocks.try_send(block) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log::warn!("IQ stream to {}: block dropped, the link is too slow", self.url);
+                self.dropped += 1;
+            }
+            Err(TrySendError::Closed(_)) => self.status = "stopped".to_string(),
+        }
+    }
+}
+
+async fn send_udp(addr: SocketAddr, mut blocks: Receiver<Vec<u8>>, status: watch::Sender<String>) {
+    let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = match UdpSocket::bind(local).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::error!("IQ stream to {}: cannot open a UDP socket: {}", addr, e);
+            status.send_replace(format!("cannot open a UDP socket: {}", e));
+            return;
+        }
+    };
+    status.send_replace("sending".to_string());
+    let mut failing = false;
+    while let Some(block) = blocks.recv().await {
+        for datagram in block.chunks(MAX_DATAGRAM) {
+            match socket.send_to(datagram, addr).await {
+                Ok(_) if failing => {
+                    failing = false;
+                    status.send_replace("sending".to_string());
+                }
+                Ok(_) => {}
+                Err(e) if !failing => {
+                    failing = true;
+                    log::warn!("IQ stream to {}: {}", addr, e);
+                    status.send_replace(e.to_string());
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+async fn send_tcp(addr: SocketAddr, mut blocks: Receiver<Vec<u8>>, status: watch::Sender<String>) {
+    let mut stream: Option<TcpStream> = None;
+    let mut retry_at = Instant::now();
+    while let Some(block) = blocks.recv().await {
+        if stream.is_none() && Instant::now() >= retry_at {
+            let connecting = time::timeout(Duration::from_secs(RECONNECT_SECS), TcpStream::connect(addr)).await;
+            match connecting.unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into())) {
+                Ok(connected) => {
+                    let _ = connected.set_nodelay(true);
+                    status.send_replace("connected".to_string());
+                    stream = Some(connected);
+                }
+                Err(e) => {
+                    log::warn!("IQ stream to {}: {}, retrying", addr, e);
+                    status.send_replace(format!("{}, retrying", e));
+                    retry_at = Instant::now() + Duration::from_secs(RECONNECT_SECS);
+                }
+            }
+        }
+        if let Some(connected) = &mut stream
+            && let Err(e) = connected.write_all(&block).await
+        {
+            log::warn!("IQ stream to {}: disconnected: {}", addr, e);
+            status.send_replace(format!("disconnected: {}", e));
+            stream = None;
+            retry_at = Instant::now() + Duration::from_secs(RECONNECT_SECS);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Network services sharing the receiver with other programs while the TUI runs.
+
+pub mod http;
+pub mod icecast;
+pub mod iq_stream;
+pub mod mqtt;
+pub mod nmea;
+pub mod remote;
+pub mod rest;
+pub mod rigctl;
+pub mod rtl_tcp;
+pub mod rtp;
+pub mod vita49;
+pub mod websocket;
+pub mod zmq;
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time;
+
+use crate::dsp::AudioMode;
+use crate::json::Value;
+use crate::runtime;
+
+/// Blocks of data queued per client before new ones are dropped for it
+const CLIENT_QUEUE: usize = 64;
+/// Commands from clients waiting for the receiver to apply them
+const COMMAND_QUEUE: usize = 64;
+/// Wait after a failed accept, doubled for each further failure in a row
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Queues of the connected clients of a [`TcpFanout`]
+type Clients = Arc<Mutex<Vec<Sender<Arc<[u8]>>>>>;
+/// The latest [`ReceiverState`], shared with the tasks answering clients,
+/// empty until the receiver first reports it
+type SharedState = Arc<Mutex<Option<ReceiverState>>>;
+
+/// A change asked for by a network client, applied on the next update
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Frequency(f64),
+    SampleRate(f64),
+    Gain(f64),
+    Mode(AudioMode),
+    Streaming(bool),
+    /// Channel power in dBFS that opens the recording squelch
+    Squelch(f32),
+    /// Start or stop recording audio
+    Recording(bool),
+}
+
+impl Command {
+    /// Settings a client can change by name
+    pub const SETTINGS: [&str; 5] = ["frequency", "sample_rate", "gain", "mode", "streaming"];
+
+    /// The command setting `name` to a JSON `value`, which may also be a
+    /// number or mode written as a string
+    pub fn from_setting(name: &str, value: &Value) -> Result<Self, String> {
+        let number = || {
+            value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| format!("{} must be a number", name))
+        };
+        Ok(match name {
+            "frequency" => Command::Frequency(number()?),
+            "sample_rate" => Command::SampleRate(number()?),
+            "gain" => Command::Gain(number()?),
+            "mode" => Command::Mode(
+                value.as_str().and_then(AudioMode::parse).ok_or("mode must be fm, am, usb or lsb")?,
+            ),
+            "streaming" => Command::Streaming(match value {
+                Value::Bool(on) => *on,
+                _ => return Err("streaming must be true or false".to_string()),
+            }),
+            _ => return Err(format!("no setting `{}`", name)),
+        })
+    }
+}
+
+/// What the receiver is doing, kept up to date for services that report it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceiverState {
+    pub frequency: f64,
+    /// Frequency the active VFO listens on, `frequency` plus its offset
+    pub vfo_frequency: f64,
+    pub sample_rate: f64,
+    pub gain: f64,
+    /// Demodulator mode of the active VFO
+    pub mode: AudioMode,
+    pub streaming: bool,
+    /// Channel power of the active VFO in dBFS
+    pub signal_db: f32,
+    pub squelch_db: f32,
+    /// Whether audio is being recorded
+    pub recording: bool,
+}
+
+impl ReceiverState {
+    /// JSON members named as in [`Command::SETTINGS`]
+    pub fn json_members(&self) -> Vec<(String, Value)> {
+        vec![
+            ("frequency".to_string(), Value::Number(self.frequency)),
+            ("sample_rate".to_string(), Value::Number(self.sample_rate)),
+            ("gain".to_string(), Value::Number(self.gain)),
+            ("mode".to_string(), Value::String(self.mode.to_string().to_lowercase())),
+            ("streaming".to_string(), Value::Bool(self.streaming)),
+        ]
+    }
+}
+
+/// Listen on `addr` on the [`runtime`], binding at once so a taken port is
+/// reported to the caller
+fn listen(addr: &str) -> io::Result<TcpListener> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let _runtime = runtime::runtime().enter();
+    TcpListener::from_std(listener)
+}
+
+/// The next client of `listener`. Failures such as running out of file
+/// descriptors tend to repeat, so each one in a row waits longer before
+/// trying again rather than spinning on it.
+async fn accept(listener: &TcpListener) -> TcpStream {
+    let mut backoff = ACCEPT_BACKOFF;
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => return stream,
+            Err(e) => {
+                log::warn!("Cannot accept a client: {}, waiting {:?}", e, backoff);
+                time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+            }
+        }
+    }
+}
+
+/// TCP server sending the same stream to every connected client through a
+/// bounded queue of its own, so a slow client loses data instead of stalling
+/// the others
+pub struct TcpFanout {
+    local_addr: SocketAddr,
+    clients: Clients,
+    /// Blocks not delivered to a client because its queue was full
+    pub dropped: u64,
+}
+
+impl TcpFanout {
+    /// Listen on `addr`. Each new client is passed to `greet` for any
+    /// handshake, then gets the stream while `read` takes what it sends; the
+    /// client is dropped when either ends.
+    pub fn bind<G, R>(
+        addr: &str,
+        greet: impl Fn(TcpStream) -> G + Send + 'static,
+        read: impl Fn(OwnedReadHalf) -> R + Send + Sync + 'static,
+    ) -> io::Result<Self>
+    where
+        G: Future<Output = io::Result<TcpStream>> + Send + 'static,
+        R: Future<Output = ()> + Send + 'static,
+    {
+        let listener = listen(addr)?;
+        let local_addr = listener.local_addr()?;
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&clients);
+        let read = Arc::new(read);
+        runtime::spawn(async move {
+            loop {
+                let stream = accept(&listener).await;
+                let _ = stream.set_nodelay(true);
+                let greeting = greet(stream);
+                let read = Arc::clone(&read);
+                let accepted = Arc::clone(&accepted);
+                tokio::spawn(async move {
+                    let Ok(stream) = greeting.await else {
+                        return;
+                    };
+                    let (reader, mut writer) = stream.into_split();
+                    let (queue, mut blocks) = mpsc::channel::<Arc<[u8]>>(CLIENT_QUEUE);
+                    accepted.lock().expect("client list poisoned").push(queue);
+                    let reading = read(reader);
+                    tokio::pin!(reading);
+                    loop {
+                        tokio::select! {
+                            () = &mut reading => break,
+                            block = blocks.recv() => match block {
+                                Some(block) if writer.write_all(&block).await.is_ok() => {}
+                                _ => break,
+                            },
+                        }
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            local_addr,
+            clients,
+            dropped: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.lock().expect("client list poisoned").len()
+    }
+
+    /// Queue `data` for every client, forgetting those that have disconnected
+    pub fn send(&mut self, data: &[u8]) {
+        let mut clients = self.clients.lock().expect("client list poisoned");
+        if clients.is_empty() {
+            return;
+        }
+        let block: Arc<[u8]> = data.into();
+        let mut dropped = 0;
+        clients.retain(|client| match client.try_send(Arc::clone(&block)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                log::warn!("Client too slow, block dropped");
+                dropped += 1;
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        });
+        self.dropped += dropped;
+    }
+}
@@ -0,0 +1,240 @@
+//! An MQTT 3.1.1 client publishing telemetry and decoder events as JSON for
+//! home-automation and monitoring systems.
+//!
+//! Telemetry goes to one topic every few seconds, decodes to the events
+//! topic with the decoder's name appended, such as `rf_rust/events/pager`.
+//! Everything is published at QoS 0 from a task on the
+//! [`runtime`](crate::runtime), which reconnects when the broker goes away.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::time;
+
+use super::ReceiverState;
+use crate::json::Value;
+use crate::runtime;
+
+/// Messages waiting for the broker before new ones are dropped
+const QUEUE_MESSAGES: usize = 256;
+const KEEP_ALIVE_SECS: u16 = 60;
+const RECONNECT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PINGREQ: u8 = 0xc0;
+
+/// Broker address and session settings
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    /// `host:port`
+    pub broker: String,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub telemetry_topic: String,
+    /// Prefix of the per-decoder event topics
+    pub events_topic: String,
+    pub interval: Duration,
+}
+
+impl MqttConfig {
+    pub fn new(broker: &str) -> Self {
+        Self {
+            broker: broker.to_string(),
+            client_id: "rf_rust".to_string(),
+            username: None,
+            password: None,
+            telemetry_topic: "rf_rust/telemetry".to_string(),
+            events_topic: "rf_rust/events".to_string(),
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+pub struct MqttPublisher {
+    pub config: MqttConfig,
+    messages: Sender<(String, Vec<u8>)>,
+    status_rx: watch::Receiver<String>,
+    /// Latest connection state or error from the publishing task
+    pub status: String,
+    /// Messages lost because the broker could not keep up
+    pub dropped: u64,
+    pub published: u64,
+    last_telemetry: Option<Instant>,
+}
+
+impl MqttPublisher {
+    pub fn start(config: MqttConfig) -> Self {
+        let (messages, queued) = mpsc::channel(QUEUE_MESSAGES);
+        let (status_tx, status_rx) = watch::channel("connecting".to_string());
+        runtime::spawn(run(config.clone(), queued, status_tx));
+        Self {
+            config,
+            messages,
+            status_rx,
+            status: "connecting".to_string(),
+            dropped: 0,
+            published: 0,
+            last_telemetry: None,
+        }
+    }
+
+    /// Publish the receiver state if the telemetry interval has passed
+    pub fn telemetry(&mut self, state: &ReceiverState) {
+        if self.status_rx.has_changed().unwrap_or(false) {
+            self.status = self.status_rx.borrow_and_update().clone();
+        }
+        if self.last_telemetry.is_some_and(|last| last.elapsed() < self.config.interval) {
+            return;
+        }
+        self.last_telemetry = Some(Instant::now());
+        let mut members = state.json_members();
+        members.extend([
+            ("vfo_frequency".to_string(), Value::Number(state.vfo_frequency)),
+            ("signal_db".to_string(), Value::Number((state.signal_db as f64 * 10.0).round() / 10.0)),
+            ("squelch_db".to_string(), Value::Number(state.squelch_db as f64)),
+            ("squelch_open".to_string(), Value::Bool(state.signal_db >= state.squelch_db)),
+            ("recording".to_string(), Value::Bool(state.recording)),
+        ]);
+        let topic = self.config.telemetry_topic.clone();
+        self.publish(topic, Value::Object(members).to_string());
+    }
+
+    /// Publish one decode as JSON under the events topic
+    pub fn event(&mut self, decoder: &str, json: String) {
+        let topic = format!("{}/{}", self.config.events_topic, decoder);
+        self.publish(topic, json);
+    }
+
+    fn publish(&mut self, topic: String, payload: String) {
+        match self.messages.try_send((topic, payload.into_bytes())) {
+            Ok(()) => self.published += 1,
+            Err(TrySendError::Full(_)) => {
+                log::warn!("MQTT {}: message dropped, the broker is too slow", self.config.broker);
+                self.dropped += 1;
+            }
+            Err(TrySendError::Closed(_)) => self.status = "stopped".to_string(),
+        }
+    }
+}
+
+/// Keep a session with the broker, sending queued messages and pings,
+/// until the publisher is dropped
+async fn run(config: MqttConfig, mut messages: Receiver<(String, Vec<u8>)>, status: watch::Sender<String>) {
+    let keep_alive = Duration::from_secs(KEEP_ALIVE_SECS as u64 / 2);
+    loop {
+        let mut stream = match connect(&config).await {
+            Ok(stream) => {
+                log::info!("MQTT: connected to {}", config.broker);
+                status.send_replace(format!("connected to {}", config.broker));
+                stream
+            }
+            Err(e) => {
+                log::warn!("MQTT {}: {}, retrying", config.broker, e);
+                status.send_replace(format!("{}, retrying", e));
+                // Keep the queue moving so stale telemetry is not sent late
+                let retry_at = time::Instant::now() + RECONNECT;
+                while let Ok(message) = time::timeout_at(retry_at, messages.recv()).await {
+                    if message.is_none() {
+                        return;
+                    }
+                }
+                continue;
+            }
+        };
+        let mut responses = [0u8; 64];
+        let sent = loop {
+            let packet = tokio::select! {
+                message = time::timeout(keep_alive, messages.recv()) => match message {
+                    Ok(Some((topic, payload))) => {
+                        let mut body = string(&topic);
+                        body.extend_from_slice(&payload);
+                        packet(PUBLISH, &body)
+                    }
+                    Ok(None) => return,
+                    Err(_) => packet(PINGREQ, &[]),
+                },
+                // Only ping responses come back, read and forget them so
+                // they do not pile up
+                read = stream.read(&mut responses) => match read {
+                    Ok(0) => break io::Error::new(io::ErrorKind::UnexpectedEof, "broker closed the connection"),
+                    Ok(_) => continue,
+                    Err(e) => break e,
+                },
+            };
+            if let Err(e) = stream.write_all(&packet).await {
+                break e;
+            }
+        };
+        log::warn!("MQTT {}: disconnected: {}", config.broker, sent);
+        status.send_replace(format!("disconnected: {}", sent));
+    }
+}
+
+async fn connect(config: &MqttConfig) -> io::Result<TcpStream> {
+    let timed_out = |_| io::Error::new(io::ErrorKind::TimedOut, "broker did not answer");
+    let mut stream = time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&config.broker)).await.map_err(timed_out)??;
+
+    // Clean session, no will
+    let mut flags = 0x02;
+    let mut body = string("MQTT");
+    body.push(4);
+    let mut payload = string(&config.client_id);
+    if let Some(username) = &config.username {
+        flags |= 0x80;
+        payload.extend(string(username));
+        // MQTT 3.1.1 allows a password only after a username
+        if let Some(password) = &config.password {
+            flags |= 0x40;
+            payload.extend(string(password));
+        }
+    }
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    body.extend(payload);
+    stream.write_all(&packet(CONNECT, &body)).await?;
+
+    let mut ack = [0u8; 4];
+    time::timeout(CONNECT_TIMEOUT, stream.read_exact(&mut ack)).await.map_err(timed_out)??;
+    if ack[0] != CONNACK || ack[1] != 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "broker sent no CONNACK"));
+    }
+    match ack[3] {
+        0 => Ok(stream),
+        4 => Err(io::Error::new(io::ErrorKind::PermissionDenied, "broker refused the username or password")),
+        5 => Err(io::Error::new(io::ErrorKind::PermissionDenied, "broker refused the connection")),
+        code => Err(io::Error::other(format!("broker refused the connection, code {}", code))),
+    }
+}
+
+/// Fixed header with the remaining length as a variable-length integer, then `body`
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        if len == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+    out.extend_from_slice(body);
+    out
+}
+
+/// UTF-8 string with its 16-bit length first
+fn string(text: &str) -> Vec<u8> {
+    let mut out = (text.len() as u16).to_be_bytes().to_vec();
+    out.extend_from_slice(text.as_bytes());
+    out
+}
@@ -0,0 +1,51 @@
+//! Decoded AIS sentences sent as NMEA 0183 over UDP, one sentence per
+//! datagram, the way AIS receivers feed chart plotters. In OpenCPN add a
+//! network connection with protocol NMEA 0183, UDP, and the port here.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+pub struct NmeaSender {
+    /// The endpoint as configured, `host:port` with an optional `udp://`
+    pub url: String,
+    socket: UdpSocket,
+    addr: SocketAddr,
+    pub sentences: u64,
+    /// Latest send error, cleared by the next sentence that goes out
+    pub error: Option<String>,
+}
+
+impl NmeaSender {
+    pub fn open(url: &str) -> Result<Self, String> {
+        let host = url.strip_prefix("udp://").unwrap_or(url);
+        let addr = host
+            .to_socket_addrs()
+            .map_err(|e| format!("{}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("{}: no address", host))?;
+        let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local).map_err(|e| format!("cannot open a UDP socket: {}", e))?;
+        // Plotters usually listen on the broadcast address of the LAN
+        if addr.is_ipv4() {
+            socket.set_broadcast(true).map_err(|e| format!("cannot send broadcasts: {}", e))?;
+        }
+        Ok(Self { url: url.to_string(), socket, addr, sentences: 0, error: None })
+    }
+
+    /// Send each of `sentences`, which lack the line ending
+    pub fn send(&mut self, sentences: &[String]) {
+        for sentence in sentences {
+            match self.socket.send_to(format!("{}\r\n", sentence).as_bytes(), self.addr) {
+                Ok(_) => {
+                    self.sentences += 1;
+                    self.error = None;
+                }
+                Err(e) => {
+                    if self.error.is_none() {
+                        log::warn!("NMEA to {}: {}", self.url, e);
+                    }
+                    self.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+}
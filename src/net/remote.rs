@@ -0,0 +1,236 @@
+//! The receiver of a headless rf_rust elsewhere, such as a Pi at the antenna
+//! running `rf_rust serve`, taken in place of the demo source.
+//!
+//! The link is rtl_tcp: tuning goes to the remote end as rtl_tcp commands
+//! and its IQ comes back as 8-bit samples, which the TUI demodulates,
+//! decodes and draws exactly as local samples. Any other rtl_tcp server, an
+//! RTL-SDR dongle's own `rtl_tcp` included, works the same.
+//!
+//! The connection is a task on the [`runtime`](crate::runtime), writing
+//! samples as they are read into a [`ring`] for the receiver to take
+//! whenever it is ready.
+
+use std::io;
+use std::time::Duration;
+
+use num_complex::Complex32;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::time;
+
+use super::rtl_tcp::{SET_FREQUENCY, SET_GAIN, SET_GAIN_MODE, SET_SAMPLE_RATE};
+use crate::device::{Device, DeviceInfo};
+use crate::dsp::ring::{self, Reader, Writer};
+use crate::runtime;
+
+/// Samples waiting for the receiver before new ones are dropped, a third
+/// of a second at the highest RTL-SDR sample rate
+const RING_SAMPLES: usize = 1 << 20;
+/// Bytes read at a time, 8192 samples
+const READ_BYTES: usize = 16 * 1024;
+const RECONNECT: Duration = Duration::from_secs(2);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Sample rates the RTL2832U takes, some between them excepted
+const RTL_SAMPLE_RATES: (f64, f64) = (0.225e6, 3.2e6);
+
+/// Name, frequency range and gain range of the rtl_tcp tuner type `kind`
+/// with `gains` gain steps, in `info`
+fn describe_tuner(kind: u32, gains: u32, info: &mut DeviceInfo) {
+    let (name, frequencies, gain) = match kind {
+        1 => ("Elonics E4000", (52e6, 2.2e9), (-1.0, 42.0)),
+        2 => ("Fitipower FC0012", (22e6, 948.6e6), (-9.9, 19.2)),
+        3 => ("Fitipower FC0013", (22e6, 1.1e9), (-9.9, 19.7)),
+        4 => ("FCI FC2580", (146e6, 924e6), (0.0, 0.0)),
+        5 => ("Rafael Micro R820T", (24e6, 1.766e9), (0.0, 49.6)),
+        6 => ("Rafael Micro R828D", (24e6, 1.766e9), (0.0, 49.6)),
+        _ => {
+            info.tuner = Some(format!("type {}, {} gain steps", kind, gains));
+            return;
+        }
+    };
+    info.tuner = Some(format!("{}, {} gain steps", name, gains));
+    info.frequency_range = Some(frequencies);
+    info.gain_range = Some(gain);
+}
+
+/// Frequency, sample rate and gain
+type Tuning = (f64, f64, f64);
+
+/// rtl_tcp commands taking the server from `last`, if it was tuned, to `tuning`
+fn tuning_commands(last: Option<Tuning>, tuning: Tuning) -> Vec<u8> {
+    let (frequency, sample_rate, gain) = tuning;
+    let last = last.unwrap_or((f64::NAN, f64::NAN, f64::NAN));
+    let mut commands = Vec::new();
+    let mut send = |command: u8, param: u32| {
+        commands.push(command);
+        commands.extend_from_slice(&param.to_be_bytes());
+    };
+    if frequency != last.0 {
+        send(SET_FREQUENCY, frequency.round() as u32);
+    }
+    if sample_rate != last.1 {
+        send(SET_SAMPLE_RATE, sample_rate.round() as u32);
+    }
+    if gain != last.2 {
+        send(SET_GAIN_MODE, 1);
+        send(SET_GAIN, (gain * 10.0).round() as i32 as u32);
+    }
+    commands
+}
+
+pub struct RemoteSource {
+    /// `host:port` of the rtl_tcp server
+    pub server: String,
+    /// Latest tuning asked for, which the connection task catches up with
+    tuning: watch::Sender<Option<Tuning>>,
+    samples: Reader<Complex32>,
+    status_rx: watch::Receiver<String>,
+    /// Tuner type and gain count from the server's header, once connected
+    header_rx: watch::Receiver<Option<(u32, u32)>>,
+    /// Latest connection state or error from the connection task
+    pub status: String,
+    /// Samples lost because the TUI could not keep up
+    pub dropped: u64,
+}
+
+impl RemoteSource {
+    pub fn connect(server: &str) -> Self {
+        let (tuning, tuning_rx) = watch::channel(None);
+        let (writer, samples) = ring::ring(RING_SAMPLES);
+        let (status_tx, status_rx) = watch::channel("connecting".to_string());
+        let (header_tx, header_rx) = watch::channel(None);
+        runtime::spawn(run(server.to_string(), tuning_rx, writer, status_tx, header_tx));
+        Self {
+            server: server.to_string(),
+            tuning,
+            samples,
+            status_rx,
+            header_rx,
+            status: "connecting".to_string(),
+            dropped: 0,
+        }
+    }
+}
+
+impl Device for RemoteSource {
+    fn name(&self) -> &str {
+        &self.server
+    }
+
+    /// What the `RTL0` header tells, rtl_tcp carrying no serial, firmware
+    /// or sensors
+    fn info(&self) -> DeviceInfo {
+        let mut info = DeviceInfo { driver: "rtl_tcp".to_string(), ..DeviceInfo::default() };
+        let Some((kind, gains)) = *self.header_rx.borrow() else {
+            return info;
+        };
+        describe_tuner(kind, gains, &mut info);
+        info.sample_rate_range = Some(RTL_SAMPLE_RATES);
+        info
+    }
+
+    /// Only the latest tuning is kept, so retuning faster than the link
+    /// sends skips the settings in between
+    fn tune(&mut self, frequency: f64, sample_rate: f64, gain: f64) {
+        self.tuning.send_replace(Some((frequency, sample_rate, gain)));
+    }
+
+    fn receive(&mut self, out: &mut Vec<Complex32>) -> bool {
+        match self.status_rx.has_changed() {
+            Ok(true) => self.status = self.status_rx.borrow_and_update().clone(),
+            Ok(false) => {}
+            Err(_) => self.status = "stopped".to_string(),
+        }
+        self.dropped = self.samples.overruns();
+        out.clear();
+        self.samples.read_into(out) > 0
+    }
+}
+
+/// Keep a connection to the server, passing on tuning and samples, until
+/// the source is dropped. Only what changed is sent, everything again after
+/// reconnecting.
+async fn run(
+    server: String,
+    mut tuning: watch::Receiver<Option<Tuning>>,
+    mut samples: Writer<Complex32>,
+    status: watch::Sender<String>,
+    header: watch::Sender<Option<(u32, u32)>>,
+) {
+    loop {
+        let error = match connect(&server).await {
+            Ok((mut stream, kind, gains)) => {
+                log::info!("rtl_tcp {}: connected, tuner type {}", server, kind);
+                status.send_replace(format!("connected, tuner type {}", kind));
+                header.send_replace(Some((kind, gains)));
+                let mut sent: Option<Tuning> = None;
+                let mut buffer = vec![0u8; READ_BYTES];
+                let mut block = Vec::with_capacity(READ_BYTES / 2 + 1);
+                // A byte of a sample split between reads
+                let mut odd: Option<u8> = None;
+                loop {
+                    let wanted = *tuning.borrow_and_update();
+                    if let Some(wanted) = wanted {
+                        let message = tuning_commands(sent, wanted);
+                        sent = Some(wanted);
+                        if let Err(e) = stream.write_all(&message).await {
+                            break e.to_string();
+                        }
+                    }
+                    let read = tokio::select! {
+                        changed = tuning.changed() => {
+                            if changed.is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+                        read = stream.read(&mut buffer) => read,
+                    };
+                    let len = match read {
+                        Ok(0) => break "server closed the connection".to_string(),
+                        Ok(len) => len,
+                        Err(e) => break e.to_string(),
+                    };
+                    let to_f32 = |v: u8| (v as f32 - 127.5) / 127.5;
+                    let mut bytes = &buffer[..len];
+                    if let Some(i) = odd.take() {
+                        block.push(Complex32::new(to_f32(i), to_f32(bytes[0])));
+                        bytes = &bytes[1..];
+                    }
+                    let pairs = bytes.chunks_exact(2);
+                    odd = pairs.remainder().first().copied();
+                    block.extend(pairs.map(|iq| Complex32::new(to_f32(iq[0]), to_f32(iq[1]))));
+                    if samples.is_closed() {
+                        return;
+                    }
+                    if samples.write(&block) < block.len() {
+                        log::warn!("rtl_tcp {}: samples dropped, the receiver is falling behind", server);
+                    }
+                    block.clear();
+                }
+            }
+            Err(e) => e.to_string(),
+        };
+        log::warn!("rtl_tcp {}: {}, retrying", server, error);
+        if status.send(format!("{}, retrying", error)).is_err() {
+            return;
+        }
+        time::sleep(RECONNECT).await;
+    }
+}
+
+/// Open the connection and read the `RTL0` header, returning the tuner type
+/// and gain count
+async fn connect(server: &str) -> io::Result<(TcpStream, u32, u32)> {
+    let timed_out = |_| io::Error::new(io::ErrorKind::TimedOut, "connection timed out");
+    let mut stream = time::timeout(CONNECT_TIMEOUT, TcpStream::connect(server)).await.map_err(timed_out)??;
+    stream.set_nodelay(true)?;
+    let mut header = [0u8; 12];
+    time::timeout(CONNECT_TIMEOUT, stream.read_exact(&mut header)).await.map_err(timed_out)??;
+    if &header[..4] != b"RTL0" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an rtl_tcp server"));
+    }
+    let word = |i: usize| u32::from_be_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+    Ok((stream, word(4), word(8)))
+}
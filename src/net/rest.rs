@@ -0,0 +1,133 @@
+//! An HTTP API for scripting the receiver from curl or home-automation
+//! systems while the TUI runs.
+//!
+//! `GET /api/status` returns everything as one JSON object. Each setting is
+//! also its own resource, `/api/frequency`, `/api/sample_rate`, `/api/gain`,
+//! `/api/mode` and `/api/streaming`: GET returns it and PUT changes it with a
+//! JSON body such as `145.5e6`, `"usb"` or `true`. Frequencies and rates are
+//! in Hz, gain in dB.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::time;
+
+use super::http::{Request, respond};
+use super::{COMMAND_QUEUE, Command, ReceiverState, SharedState, accept, listen};
+use crate::json::Value;
+use crate::runtime;
+
+/// Time a client has to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct RestServer {
+    local_addr: SocketAddr,
+    state: SharedState,
+    commands: Receiver<Command>,
+}
+
+impl RestServer {
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let listener = listen(addr)?;
+        let local_addr = listener.local_addr()?;
+        let state: SharedState = Arc::new(Mutex::new(None));
+        let (command_tx, commands) = mpsc::channel(COMMAND_QUEUE);
+        let shared = Arc::clone(&state);
+        runtime::spawn(async move {
+            loop {
+                let mut stream = accept(&listener).await;
+                let state = Arc::clone(&shared);
+                let command_tx = command_tx.clone();
+                tokio::spawn(async move {
+                    let _ = serve(&mut stream, &state, &command_tx).await;
+                });
+            }
+        });
+        Ok(Self { local_addr, state, commands })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Publish the receiver state that GET requests report
+    pub fn update(&self, state: &ReceiverState) {
+        let mut shared = self.state.lock().expect("receiver state poisoned");
+        if shared.as_ref() != Some(state) {
+            *shared = Some(state.clone());
+        }
+    }
+
+    /// Commands received since the last call
+    pub fn commands(&mut self) -> impl Iterator<Item = Command> + '_ {
+        std::iter::from_fn(|| self.commands.try_recv().ok())
+    }
+}
+
+async fn serve(
+    stream: &mut TcpStream,
+    state: &Mutex<Option<ReceiverState>>,
+    commands: &Sender<Command>,
+) -> io::Result<()> {
+    let request = match time::timeout(REQUEST_TIMEOUT, Request::read(&mut *stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => return error(stream, 400, &e.to_string()).await,
+        Err(_) => return error(stream, 400, "request timed out").await,
+    };
+    let Some(name) = request.path.strip_prefix("/api/") else {
+        return error(stream, 404, "not found, try /api/status").await;
+    };
+    let name = name.trim_end_matches('/');
+    if name != "status" && !Command::SETTINGS.contains(&name) {
+        return error(stream, 404, &format!("no setting `{}`", name)).await;
+    }
+
+    match request.method.as_str() {
+        "GET" => {
+            let Some(state) = state.lock().expect("receiver state poisoned").clone() else {
+                return error(stream, 503, "receiver not running yet").await;
+            };
+            let members = state.json_members();
+            let body = if name == "status" {
+                Value::Object(members)
+            } else {
+                members.into_iter().find(|(key, _)| key == name).map(|(_, value)| value).unwrap_or(Value::Null)
+            };
+            respond(stream, 200, "application/json", body.to_string().as_bytes()).await
+        }
+        "PUT" | "POST" if name != "status" => {
+            let text = String::from_utf8_lossy(&request.body);
+            // Bare words such as `-d usb` are taken as strings, broken arrays
+            // and objects are refused
+            let text = text.trim();
+            let value = match Value::parse(text) {
+                Ok(value) => value,
+                Err(e) if text.starts_with(['[', '{']) => return error(stream, 400, &format!("invalid JSON: {}", e)).await,
+                Err(_) => Value::String(text.to_string()),
+            };
+            let command = match Command::from_setting(name, &value) {
+                Ok(command) => command,
+                Err(e) => return error(stream, 400, &e).await,
+            };
+            match commands.try_send(command) {
+                Ok(()) => {
+                    let body = Value::Object(vec![(name.to_string(), value)]);
+                    respond(stream, 202, "application/json", body.to_string().as_bytes()).await
+                }
+                Err(TrySendError::Full(_)) => error(stream, 503, "too many changes waiting, try again").await,
+                Err(TrySendError::Closed(_)) => error(stream, 503, "receiver stopped").await,
+            }
+        }
+        _ => error(stream, 405, &format!("{} is not supported on /api/{}", request.method, name)).await,
+    }
+}
+
+async fn error(stream: &mut TcpStream, status: u16, message: &str) -> io::Result<()> {
+    let body = Value::Object(vec![("error".to_string(), Value::String(message.to_string()))]);
+    respond(stream, status, "application/json", body.to_string().as_bytes()).await
+}
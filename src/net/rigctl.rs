@@ -0,0 +1,220 @@
+//! The Hamlib rigctld network protocol, so logging and digital-mode programs
+//! such as WSJT-X, fldigi and CQRLOG can read and set the frequency and mode
+//! as if the receiver were a transceiver. Point them at the "Hamlib NET
+//! rigctl" rig model.
+//!
+//! Clients send one command per line, a short letter such as `F 14074000`
+//! or a long name such as `\set_freq 14074000`. Queries are answered with
+//! their values one per line, changes with `RPRT 0` or a negative Hamlib
+//! error code. The frequency is the one the active VFO listens on, so
+//! setting it retunes the hardware and keeps the VFO's offset.
+//!
+//! The GQRX remote-control protocol is the same with a few additions, the
+//! signal strength (`l STRENGTH`), squelch (`l SQL`, `L SQL -60`) and audio
+//! recording (`u RECORD`, `U RECORD 1`, `AOS`, `LOS`), and `RPRT 1` for
+//! every error.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use super::{COMMAND_QUEUE, Command, ReceiverState, SharedState, accept, listen};
+use crate::dsp::AudioMode;
+use crate::runtime;
+
+/// Hamlib error codes sent as `RPRT <code>`
+const RIG_OK: i32 = 0;
+const RIG_EINVAL: i32 = -1;
+const RIG_EIO: i32 = -6;
+const RIG_ENAVAIL: i32 = -11;
+
+/// Hamlib level bits
+const LEVEL_SQL: u64 = 0x20;
+const LEVEL_STRENGTH: u64 = 0x4000_0000;
+
+/// Hamlib mode bits of the modes here: AM, USB, LSB and FM
+const MODES: u32 = 0x01 | 0x04 | 0x08 | 0x20;
+
+/// Which program's variant of the protocol to speak
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Hamlib,
+    Gqrx,
+}
+
+pub struct RigctlServer {
+    local_addr: SocketAddr,
+    state: SharedState,
+    commands: Receiver<Command>,
+}
+
+impl RigctlServer {
+    pub fn bind(addr: &str, dialect: Dialect) -> io::Result<Self> {
+        let listener = listen(addr)?;
+        let local_addr = listener.local_addr()?;
+        let state: SharedState = Arc::new(Mutex::new(None));
+        let (command_tx, commands) = mpsc::channel(COMMAND_QUEUE);
+        let shared = Arc::clone(&state);
+        runtime::spawn(async move {
+            loop {
+                let stream = accept(&listener).await;
+                let state = Arc::clone(&shared);
+                let command_tx = command_tx.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, dialect, &state, &command_tx).await;
+                });
+            }
+        });
+        Ok(Self { local_addr, state, commands })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Publish the receiver state that queries report
+    pub fn update(&self, state: &ReceiverState) {
+        let mut shared = self.state.lock().expect("receiver state poisoned");
+        if shared.as_ref() != Some(state) {
+            *shared = Some(state.clone());
+        }
+    }
+
+    /// Commands received since the last call
+    pub fn commands(&mut self) -> impl Iterator<Item = Command> + '_ {
+        std::iter::from_fn(|| self.commands.try_recv().ok())
+    }
+}
+
+/// Answer one client's commands until it quits or disconnects
+async fn serve(
+    stream: TcpStream,
+    dialect: Dialect,
+    state: &Mutex<Option<ReceiverState>>,
+    commands: &Sender<Command>,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+        if matches!(command, "q" | "Q" | "\\quit") {
+            break;
+        }
+        let current = state.lock().expect("receiver state poisoned").clone();
+        let reply = match current {
+            Some(current) => answer(command, &args, dialect, &current, commands),
+            None => report(dialect, RIG_EIO),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+fn answer(command: &str, args: &[&str], dialect: Dialect, state: &ReceiverState, commands: &Sender<Command>) -> String {
+    // A full queue means the receiver has stopped taking changes
+    let send = |command: Command| match commands.try_send(command) {
+        Ok(()) => report(dialect, RIG_OK),
+        Err(_) => report(dialect, RIG_EIO),
+    };
+    let common = match (command, args) {
+        ("f" | "\\get_freq", _) => Some(format!("{:.0}\n", state.vfo_frequency)),
+        ("F" | "\\set_freq", [hz, ..]) => Some(match hz.parse::<f64>() {
+            Ok(hz) if hz.is_finite() && hz > 0.0 => send(Command::Frequency(hz - (state.vfo_frequency - state.frequency))),
+            _ => report(dialect, RIG_EINVAL),
+        }),
+        ("m" | "\\get_mode", _) => Some(format!("{}\n{:.0}\n", state.mode, state.mode.passband())),
+        // Data modes are the voice modes as far as a receiver is concerned
+        ("M" | "\\set_mode", [mode, ..]) => Some(match AudioMode::parse(mode.trim_start_matches("PKT")) {
+            Some(mode) => send(Command::Mode(mode)),
+            None => report(dialect, RIG_EINVAL),
+        }),
+        ("v" | "\\get_vfo", _) => Some("VFOA\n".to_string()),
+        ("V" | "\\set_vfo", [_, ..]) => Some(report(dialect, RIG_OK)),
+        ("t" | "\\get_ptt", _) => Some("0\n".to_string()),
+        ("T" | "\\set_ptt", ["0", ..]) => Some(report(dialect, RIG_OK)),
+        ("s" | "\\get_split_vfo", _) => Some("0\nVFOA\n".to_string()),
+        ("S" | "\\set_split_vfo", ["0", ..]) => Some(report(dialect, RIG_OK)),
+        ("\\chk_vfo", _) => Some("0\n".to_string()),
+        ("\\get_powerstat", _) => Some("1\n".to_string()),
+        ("\\dump_state", _) => Some(dump_state(dialect)),
+        _ => None,
+    };
+    if let Some(reply) = common {
+        return reply;
+    }
+    if dialect == Dialect::Hamlib {
+        return report(dialect, RIG_ENAVAIL);
+    }
+    match (command, args) {
+        ("l", ["STRENGTH"]) => format!("{:.1}\n", state.signal_db),
+        ("l", ["SQL"]) => format!("{:.1}\n", state.squelch_db),
+        ("l", ["?"]) => "STRENGTH SQL\n".to_string(),
+        ("L", ["SQL", db]) => match db.parse::<f32>() {
+            Ok(db) if db.is_finite() => send(Command::Squelch(db)),
+            _ => report(dialect, RIG_EINVAL),
+        },
+        ("L", ["?"]) => "SQL\n".to_string(),
+        ("u", ["RECORD"]) => format!("{}\n", state.recording as u8),
+        ("U", ["RECORD", on @ ("0" | "1")]) => send(Command::Recording(*on == "1")),
+        ("AOS", []) => send(Command::Recording(true)),
+        ("LOS", []) => send(Command::Recording(false)),
+        _ => report(dialect, RIG_ENAVAIL),
+    }
+}
+
+/// `RPRT` with a Hamlib error code, or with 1 for any error in GQRX
+fn report(dialect: Dialect, code: i32) -> String {
+    let code = match dialect {
+        Dialect::Gqrx if code != RIG_OK => 1,
+        _ => code,
+    };
+    format!("RPRT {}\n", code)
+}
+
+/// What Hamlib's network rig backend reads on opening the connection:
+/// a receive-only rig with one VFO, and levels only in the GQRX dialect
+fn dump_state(dialect: Dialect) -> String {
+    let mut lines = vec![
+        // Protocol version, rig model (NET rigctl), ITU region
+        "0".to_string(),
+        "2".to_string(),
+        "1".to_string(),
+        // Receive range: start, end, modes, low and high power (none), VFO A, any antenna
+        format!("1000000.000000 6000000000.000000 {:#x} -1 -1 0x1 0x0", MODES),
+        "0 0 0 0 0 0 0".to_string(),
+        // No transmit ranges
+        "0 0 0 0 0 0 0".to_string(),
+        // Tuning steps
+        format!("{:#x} 1", MODES),
+        "0 0".to_string(),
+    ];
+    // Filter widths
+    for mode in [AudioMode::Fm, AudioMode::Am, AudioMode::Usb, AudioMode::Lsb] {
+        let bit = match mode {
+            AudioMode::Am => 0x01,
+            AudioMode::Usb => 0x04,
+            AudioMode::Lsb => 0x08,
+            AudioMode::Fm => 0x20,
+        };
+        lines.push(format!("{:#x} {:.0}", bit, mode.passband()));
+    }
+    lines.push("0 0".to_string());
+    // Maximum RIT, XIT and IF shift, announcements, preamps, attenuators,
+    // get and set functions
+    lines.extend(["0"; 8].map(String::from));
+    // Get and set levels, STRENGTH and SQL and just SQL, then get and set parameters
+    let (get_levels, set_levels) = match dialect {
+        Dialect::Hamlib => (0, 0),
+        Dialect::Gqrx => (LEVEL_STRENGTH | LEVEL_SQL, LEVEL_SQL),
+    };
+    lines.extend([format!("{:#x}", get_levels), format!("{:#x}", set_levels), "0".to_string(), "0".to_string()]);
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,103 @@
+//! The rtl_tcp protocol, so GQRX, SDR# and other rtl_tcp clients can take the
+//! live IQ as if from an RTL-SDR dongle and tune it.
+//!
+//! Clients get a 12-byte `RTL0` header with the tuner type and gain count,
+//! then interleaved unsigned 8-bit I and Q. They send 5-byte commands, a
+//! command number and a big-endian 32-bit parameter.
+
+use std::io;
+use std::net::SocketAddr;
+
+use num_complex::Complex32;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use super::{COMMAND_QUEUE, Command, TcpFanout};
+
+/// Tuner type reported to clients, a Rafael Micro R820T
+const TUNER_R820T: u32 = 5;
+/// R820T gain steps in tenths of a dB, which clients pick from by index
+const GAINS: [u32; 29] = [
+    0, 9, 14, 27, 37, 77, 87, 125, 144, 157, 166, 197, 207, 229, 254, 280, 297, 328, 338, 364, 372, 386, 402, 421, 434,
+    439, 445, 480, 496,
+];
+
+pub const SET_FREQUENCY: u8 = 0x01;
+pub const SET_SAMPLE_RATE: u8 = 0x02;
+/// 1 for manual gain, 0 for the tuner's AGC
+pub const SET_GAIN_MODE: u8 = 0x03;
+/// Gain in tenths of a dB
+pub const SET_GAIN: u8 = 0x04;
+const SET_GAIN_BY_INDEX: u8 = 0x0d;
+
+/// Serves the live IQ to rtl_tcp clients and collects their tuning commands
+pub struct RtlTcpServer {
+    fanout: TcpFanout,
+    commands: Receiver<Command>,
+}
+
+impl RtlTcpServer {
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let (command_tx, commands) = mpsc::channel(COMMAND_QUEUE);
+        let fanout = TcpFanout::bind(
+            addr,
+            |mut stream| async move {
+                let mut header = b"RTL0".to_vec();
+                header.extend_from_slice(&TUNER_R820T.to_be_bytes());
+                header.extend_from_slice(&(GAINS.len() as u32).to_be_bytes());
+                stream.write_all(&header).await?;
+                Ok(stream)
+            },
+            move |reader| read_commands(reader, command_tx.clone()),
+        )?;
+        Ok(Self { fanout, commands })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.fanout.local_addr()
+    }
+
+    pub fn clients(&self) -> usize {
+        self.fanout.clients()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.fanout.dropped
+    }
+
+    /// Send samples to every client as offset 8-bit values
+    pub fn send(&mut self, samples: &[Complex32]) {
+        let to_u8 = |v: f32| (v * 127.5 + 127.5).round().clamp(0.0, 255.0) as u8;
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| [to_u8(s.re), to_u8(s.im)]).collect();
+        self.fanout.send(&bytes);
+    }
+
+    /// Commands received since the last call
+    pub fn commands(&mut self) -> impl Iterator<Item = Command> + '_ {
+        std::iter::from_fn(|| self.commands.try_recv().ok())
+    }
+}
+
+/// Turn a client's commands into [`Command`]s until it disconnects,
+/// ignoring the ones with no equivalent here such as AGC or bias tee. A
+/// client sending faster than the receiver applies them waits.
+async fn read_commands(mut stream: OwnedReadHalf, commands: Sender<Command>) {
+    let mut message = [0u8; 5];
+    while stream.read_exact(&mut message).await.is_ok() {
+        let param = u32::from_be_bytes([message[1], message[2], message[3], message[4]]);
+        let command = match message[0] {
+            SET_FREQUENCY => Command::Frequency(param as f64),
+            SET_SAMPLE_RATE => Command::SampleRate(param as f64),
+            SET_GAIN => Command::Gain(param as i32 as f64 / 10.0),
+            SET_GAIN_BY_INDEX => match GAINS.get(param as usize) {
+                Some(&tenths) => Command::Gain(tenths as f64 / 10.0),
+                None => continue,
+            },
+            _ => continue,
+        };
+        if commands.send(command).await.is_err() {
+            break;
+        }
+    }
+}
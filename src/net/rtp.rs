@@ -0,0 +1,155 @@
+//! The monitored audio sent to another host over UDP, so the receiver can
+//! run headless near the antenna with the audio heard elsewhere.
+//!
+//! `rtp://host:port` sends RTP, by default G.711 µ-law (payload type 0),
+//! which players take without a session description: `ffplay rtp://@:5004`.
+//! Linear 16-bit PCM is payload type 96 and needs one, with
+//! `a=rtpmap:96 L16/8000`. `udp://host:port` sends bare little-endian 16-bit
+//! samples instead: `nc -lu 5004 | aplay -f S16_LE -r 8000`. Either way the
+//! audio is resampled to exactly 8 kHz mono in 20 ms packets.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use crate::dsp::Resampler;
+
+/// Sample rate of every packet, the RTP clock of both payload types
+const RATE: f64 = 8000.0;
+/// Samples per packet, 20 ms
+const PACKET_SAMPLES: usize = 160;
+const RTP_VERSION: u8 = 2 << 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioEncoding {
+    /// G.711 µ-law
+    Pcmu,
+    /// Big-endian 16-bit linear PCM
+    L16,
+}
+
+impl AudioEncoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "pcmu" => Some(AudioEncoding::Pcmu),
+            "l16" => Some(AudioEncoding::L16),
+            _ => None,
+        }
+    }
+
+    fn payload_type(self) -> u8 {
+        match self {
+            AudioEncoding::Pcmu => 0,
+            AudioEncoding::L16 => 96,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioEncoding::Pcmu => "PCMU",
+            AudioEncoding::L16 => "L16",
+        }
+    }
+}
+
+pub struct AudioSender {
+    /// The endpoint as configured
+    pub url: String,
+    /// `None` for bare samples without RTP headers
+    pub encoding: Option<AudioEncoding>,
+    socket: UdpSocket,
+    addr: SocketAddr,
+    ssrc: u32,
+    sequence: u16,
+    timestamp: u32,
+    resampler: Resampler,
+    pending: Vec<i16>,
+    pub packets: u64,
+    /// Latest send error, cleared by the next packet that goes out
+    pub error: Option<String>,
+}
+
+impl AudioSender {
+    pub fn open(url: &str, encoding: AudioEncoding) -> Result<Self, String> {
+        let (encoding, host) = if let Some(host) = url.strip_prefix("rtp://") {
+            (Some(encoding), host)
+        } else if let Some(host) = url.strip_prefix("udp://") {
+            (None, host)
+        } else {
+            return Err(format!("audio_out `{}` should start with rtp:// or udp://", url));
+        };
+        let addr = host
+            .to_socket_addrs()
+            .map_err(|e| format!("audio_out {}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("audio_out {}: no address", host))?;
+        let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local).map_err(|e| format!("audio_out cannot open a UDP socket: {}", e))?;
+        Ok(Self {
+            url: url.to_string(),
+            encoding,
+            socket,
+            addr,
+            ssrc: rand::random(),
+            sequence: rand::random(),
+            timestamp: rand::random(),
+            resampler: Resampler::new(RATE),
+            pending: Vec::new(),
+            packets: 0,
+            error: None,
+        })
+    }
+
+    /// Resample `audio`, full scale at ±1, from `rate` and send every
+    /// complete packet
+    pub fn send(&mut self, audio: &[f32], rate: f64) {
+        let mut resampled = Vec::new();
+        self.resampler.process(audio, rate, &mut resampled);
+        self.pending.extend(resampled.iter().map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16));
+
+        while self.pending.len() >= PACKET_SAMPLES {
+            let samples: Vec<i16> = self.pending.drain(..PACKET_SAMPLES).collect();
+            let packet = self.packet(&samples);
+            match self.socket.send_to(&packet, self.addr) {
+                Ok(_) => {
+                    self.packets += 1;
+                    self.error = None;
+                }
+                Err(e) => {
+                    if self.error.is_none() {
+                        log::warn!("Audio to {}: {}", self.url, e);
+                    }
+                    self.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    fn packet(&mut self, samples: &[i16]) -> Vec<u8> {
+        let Some(encoding) = self.encoding else {
+            return samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        };
+        let mut packet = Vec::with_capacity(12 + 2 * samples.len());
+        packet.push(RTP_VERSION);
+        packet.push(encoding.payload_type());
+        packet.extend_from_slice(&self.sequence.to_be_bytes());
+        packet.extend_from_slice(&self.timestamp.to_be_bytes());
+        packet.extend_from_slice(&self.ssrc.to_be_bytes());
+        match encoding {
+            AudioEncoding::Pcmu => packet.extend(samples.iter().map(|&s| ulaw(s))),
+            AudioEncoding::L16 => packet.extend(samples.iter().flat_map(|s| s.to_be_bytes())),
+        }
+        self.sequence = self.sequence.wrapping_add(1);
+        self.timestamp = self.timestamp.wrapping_add(samples.len() as u32);
+        packet
+    }
+}
+
+/// G.711 µ-law code of a 16-bit sample
+fn ulaw(sample: i16) -> u8 {
+    const BIAS: i32 = 0x84;
+    const CLIP: i32 = 32_635;
+    let sign = if sample < 0 { 0x80 } else { 0 };
+    let magnitude = (sample as i32).abs().min(CLIP) + BIAS;
+    let exponent = (31 - magnitude.leading_zeros() as i32 - 7).clamp(0, 7);
+    let mantissa = (magnitude >> (exponent + 3)) & 0x0f;
+    !(sign | (exponent << 4) as u8 | mantissa as u8)
+}
@@ -0,0 +1,182 @@
+//! The live IQ as VITA 49.0 (VRT) packets over UDP, for receiver
+//! infrastructure and recorders that take a VRT stream.
+//!
+//! IF data packets carry big-endian 16-bit complex samples with the UTC
+//! time of their first sample in picoseconds. IF context packets on the
+//! same stream ID describe them: RF frequency, bandwidth, sample rate, gain
+//! and the payload format. A context packet goes out when the stream
+//! starts, whenever the tuning changes, and once a second for receivers
+//! joining late.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex32;
+
+/// Samples per data packet, which with its 20-byte header fits an Ethernet frame
+const PACKET_SAMPLES: usize = 360;
+const CONTEXT_INTERVAL: Duration = Duration::from_secs(1);
+const PICOS_PER_SEC: u64 = 1_000_000_000_000;
+
+/// Header packet types
+const IF_DATA_WITH_STREAM_ID: u32 = 0x1;
+const IF_CONTEXT: u32 = 0x4;
+/// Header timestamp kinds: integer seconds of UTC, fraction in picoseconds
+const TSI_UTC: u32 = 0x1;
+const TSF_REAL_TIME: u32 = 0x2;
+
+/// Context indicator field bits, in the order their fields follow
+const CIF_CHANGED: u32 = 1 << 31;
+const CIF_BANDWIDTH: u32 = 1 << 29;
+const CIF_RF_FREQUENCY: u32 = 1 << 27;
+const CIF_GAIN: u32 = 1 << 23;
+const CIF_SAMPLE_RATE: u32 = 1 << 21;
+const CIF_PAYLOAD_FORMAT: u32 = 1 << 15;
+
+/// Signed fixed-point complex cartesian samples, 16-bit items in 16-bit containers
+const PAYLOAD_FORMAT: u64 = (0b01 << 61) | (15 << 38) | (15 << 32);
+
+pub struct Vita49Sender {
+    /// The endpoint as configured, `host:port` with an optional `udp://`
+    pub url: String,
+    pub stream_id: u32,
+    socket: UdpSocket,
+    addr: SocketAddr,
+    pending: Vec<Complex32>,
+    /// Time of the first pending sample, seconds and picoseconds of UTC
+    time: Option<(u64, u64)>,
+    data_count: u32,
+    context_count: u32,
+    /// Frequency, sample rate and gain in the last context packet
+    context: Option<(f64, f64, f64)>,
+    context_sent: Instant,
+    pub packets: u64,
+    /// Latest send error, cleared by the next packet that goes out
+    pub error: Option<String>,
+}
+
+impl Vita49Sender {
+    pub fn open(url: &str, stream_id: u32) -> Result<Self, String> {
+        let host = url.strip_prefix("udp://").unwrap_or(url);
+        let addr = host
+            .to_socket_addrs()
+            .map_err(|e| format!("{}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("{}: no address", host))?;
+        let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local).map_err(|e| format!("cannot open a UDP socket: {}", e))?;
+        Ok(Self {
+            url: url.to_string(),
+            stream_id,
+            socket,
+            addr,
+            pending: Vec::new(),
+            time: None,
+            data_count: 0,
+            context_count: 0,
+            context: None,
+            context_sent: Instant::now(),
+            packets: 0,
+            error: None,
+        })
+    }
+
+    /// Send `samples`, taken at `frequency` and `sample_rate` with `gain` in
+    /// dB, as data packets, with a context packet first when one is due
+    pub fn send(&mut self, samples: &[Complex32], frequency: f64, sample_rate: f64, gain: f64) {
+        let tuning = (frequency, sample_rate, gain);
+        let changed = self.context.is_some_and(|context| context != tuning);
+        if changed {
+            // Samples from before the change are not described by the new context
+            self.pending.clear();
+            self.time = None;
+        }
+        let time = *self.time.get_or_insert_with(now);
+        if self.context.is_none() || changed || self.context_sent.elapsed() >= CONTEXT_INTERVAL {
+            let packet = self.context_packet(time, tuning, changed);
+            self.transmit(&packet);
+            self.context = Some(tuning);
+            self.context_sent = Instant::now();
+        }
+
+        self.pending.extend_from_slice(samples);
+        while self.pending.len() >= PACKET_SAMPLES {
+            let block: Vec<Complex32> = self.pending.drain(..PACKET_SAMPLES).collect();
+            let (secs, picos) = self.time.unwrap_or_else(now);
+            let packet = self.data_packet((secs, picos), &block);
+            self.transmit(&packet);
+            let picos = picos + (PACKET_SAMPLES as f64 * PICOS_PER_SEC as f64 / sample_rate).round() as u64;
+            self.time = Some((secs + picos / PICOS_PER_SEC, picos % PICOS_PER_SEC));
+        }
+    }
+
+    fn data_packet(&mut self, time: (u64, u64), samples: &[Complex32]) -> Vec<u8> {
+        let words = 5 + samples.len();
+        let mut packet = self.prologue(IF_DATA_WITH_STREAM_ID, self.data_count, words, time);
+        self.data_count = (self.data_count + 1) % 16;
+        let to_i16 = |v: f32| (v.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        for s in samples {
+            packet.extend_from_slice(&to_i16(s.re).to_be_bytes());
+            packet.extend_from_slice(&to_i16(s.im).to_be_bytes());
+        }
+        packet
+    }
+
+    fn context_packet(&mut self, time: (u64, u64), (frequency, sample_rate, gain): (f64, f64, f64), changed: bool) -> Vec<u8> {
+        let mut cif = CIF_BANDWIDTH | CIF_RF_FREQUENCY | CIF_GAIN | CIF_SAMPLE_RATE | CIF_PAYLOAD_FORMAT;
+        if changed {
+            cif |= CIF_CHANGED;
+        }
+        // CIF word, then bandwidth, frequency, gain, rate and format
+        let words = 5 + 1 + 2 + 2 + 1 + 2 + 2;
+        let mut packet = self.prologue(IF_CONTEXT, self.context_count, words, time);
+        self.context_count = (self.context_count + 1) % 16;
+        packet.extend_from_slice(&cif.to_be_bytes());
+        // Complex samples span the whole sample rate
+        packet.extend_from_slice(&hertz(sample_rate).to_be_bytes());
+        packet.extend_from_slice(&hertz(frequency).to_be_bytes());
+        // Stage 2 gain unused, stage 1 in 1/128 dB
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(&((gain * 128.0).round() as i16).to_be_bytes());
+        packet.extend_from_slice(&hertz(sample_rate).to_be_bytes());
+        packet.extend_from_slice(&PAYLOAD_FORMAT.to_be_bytes());
+        packet
+    }
+
+    /// Header, stream ID and timestamp of a packet `words` long in all
+    fn prologue(&self, packet_type: u32, count: u32, words: usize, (secs, picos): (u64, u64)) -> Vec<u8> {
+        let header = (packet_type << 28) | (TSI_UTC << 22) | (TSF_REAL_TIME << 20) | (count << 16) | words as u32;
+        let mut packet = Vec::with_capacity(words * 4);
+        packet.extend_from_slice(&header.to_be_bytes());
+        packet.extend_from_slice(&self.stream_id.to_be_bytes());
+        packet.extend_from_slice(&(secs as u32).to_be_bytes());
+        packet.extend_from_slice(&picos.to_be_bytes());
+        packet
+    }
+
+    fn transmit(&mut self, packet: &[u8]) {
+        match self.socket.send_to(packet, self.addr) {
+            Ok(_) => {
+                self.packets += 1;
+                self.error = None;
+            }
+            Err(e) => {
+                if self.error.is_none() {
+                    log::warn!("VITA 49 to {}: {}", self.url, e);
+                }
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Seconds and picoseconds of UTC now
+fn now() -> (u64, u64) {
+    let since = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since.as_secs(), since.subsec_nanos() as u64 * 1000)
+}
+
+/// Hertz as 64-bit fixed point with 20 fraction bits
+fn hertz(hz: f64) -> i64 {
+    (hz * (1u64 << 20) as f64).round() as i64
+}
@@ -0,0 +1,244 @@
+//! A WebSocket endpoint for browser companion views: it pushes a JSON text
+//! message with the receiver settings and spectrum on every update, and
+//! takes tune commands back as JSON objects of settings to change, such as
+//! `{"frequency": 145.5e6, "mode": "fm"}`.
+//!
+//! Each pushed message holds the members of `GET /api/status` plus
+//! `spectrum`, the power per bin in dBFS from the lowest frequency up.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::time;
+
+use super::http::{Request, base64, respond};
+use super::{COMMAND_QUEUE, Command, ReceiverState, TcpFanout};
+use crate::json::Value;
+
+/// Appended to the client's key before hashing, fixed by RFC 6455
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest message taken from a client, far more than any command needs
+const MAX_MESSAGE: u64 = 64 * 1024;
+
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const FIN: u8 = 0x80;
+
+pub struct WebSocketServer {
+    fanout: TcpFanout,
+    commands: Receiver<Command>,
+}
+
+impl WebSocketServer {
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let (command_tx, commands) = mpsc::channel(COMMAND_QUEUE);
+        let fanout = TcpFanout::bind(addr, handshake, move |reader| read_commands(reader, command_tx.clone()))?;
+        Ok(Self { fanout, commands })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.fanout.local_addr()
+    }
+
+    pub fn clients(&self) -> usize {
+        self.fanout.clients()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.fanout.dropped
+    }
+
+    /// Push the settings and spectrum to every client
+    pub fn send(&mut self, state: &ReceiverState, spectrum: &[f32]) {
+        if self.fanout.clients() == 0 {
+            return;
+        }
+        let mut members = state.json_members();
+        // Tenths of a dB are as fine as any display needs and keep messages small
+        let bins = spectrum.iter().map(|&db| Value::Number((db as f64 * 10.0).round() / 10.0)).collect();
+        members.push(("spectrum".to_string(), Value::Array(bins)));
+        let message = Value::Object(members).to_string();
+        self.fanout.send(&frame(TEXT, message.as_bytes()));
+    }
+
+    /// Commands received since the last call
+    pub fn commands(&mut self) -> impl Iterator<Item = Command> + '_ {
+        std::iter::from_fn(|| self.commands.try_recv().ok())
+    }
+}
+
+/// Take the client's upgrade request and agree to it
+async fn handshake(mut stream: TcpStream) -> io::Result<TcpStream> {
+    let request = time::timeout(HANDSHAKE_TIMEOUT, Request::read(&mut stream))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no upgrade request"))??;
+    let key = request.header("sec-websocket-key");
+    let upgrade = request.header("upgrade").is_some_and(|u| u.eq_ignore_ascii_case("websocket"));
+    let (Some(key), true) = (key, upgrade) else {
+        respond(&mut stream, 400, "text/plain", b"WebSocket connections only\n").await?;
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket upgrade"));
+    };
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(stream)
+}
+
+/// Turn a client's text messages into [`Command`]s until it closes the
+/// connection, skipping messages and settings that make no sense. The
+/// client is forgotten once this returns.
+async fn read_commands(mut stream: OwnedReadHalf, commands: Sender<Command>) {
+    while let Ok((opcode, payload)) = read_frame(&mut stream).await {
+        match opcode {
+            TEXT => {}
+            CLOSE => break,
+            // Pings, pongs and binary messages have nothing to act on
+            _ => continue,
+        }
+        let Ok(Value::Object(settings)) = Value::parse(&String::from_utf8_lossy(&payload)) else {
+            continue;
+        };
+        let accepted = settings.iter().filter_map(|(name, value)| Command::from_setting(name, value).ok());
+        for command in accepted {
+            if commands.send(command).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// One client frame, unmasked. Fragments are returned as they come, which
+/// is fine for commands that fit one frame as browsers send them.
+async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<(u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).await?;
+    let opcode = head[0] & 0x0f;
+    let masked = head[1] & 0x80 != 0;
+    let len = match head[1] & 0x7f {
+        126 => {
+            let mut ext = [0u8; 2];
+            stream.read_exact(&mut ext).await?;
+            u16::from_be_bytes(ext) as u64
+        }
+        127 => {
+            let mut ext = [0u8; 8];
+            stream.read_exact(&mut ext).await?;
+            u64::from_be_bytes(ext)
+        }
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload).await?;
+    payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+    Ok((opcode, payload))
+}
+
+/// A whole unmasked message as servers send them
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 10);
+    out.push(FIN | opcode);
+    match payload.len() {
+        len @ 0..=125 => out.push(len as u8),
+        len @ 126..=0xffff => {
+            out.push(126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(payload);
+    out
+}
+
+/// The `Sec-WebSocket-Accept` answering a client's `Sec-WebSocket-Key`
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+/// SHA-1, needed only for the handshake's accept key
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (out, word) in digest.chunks_mut(4).zip(h) {
+        out.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_6455() {
+        // The example handshake of RFC 6455 section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn sha1_matches_known_digests() {
+        let hex = |digest: [u8; 20]| digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        // Two blocks once padded
+        assert_eq!(
+            hex(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn frames_carry_their_length() {
+        assert_eq!(frame(TEXT, b"hi"), [FIN | TEXT, 2, b'h', b'i']);
+        assert_eq!(frame(TEXT, &[0; 300])[..4], [FIN | TEXT, 126, 1, 44]);
+        assert_eq!(frame(TEXT, &[0; 70_000])[..2], [FIN | TEXT, 127]);
+    }
+}
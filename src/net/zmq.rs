@@ -0,0 +1,112 @@
+//! A ZeroMQ PUB socket publishing the live IQ, for GNU Radio's ZMQ SUB
+//! Source block and other ZeroMQ subscribers.
+//!
+//! Speaks ZMTP 3.0 with the NULL mechanism: a 64-byte greeting each way, a
+//! READY command naming the socket type, then one single-frame message per
+//! block of samples as little-endian cf32, the items of a `gr_complex`
+//! source with tags off. Subscribers filter messages on their side, so every
+//! block goes to every subscriber whatever it subscribed to.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use num_complex::Complex32;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time;
+
+use super::TcpFanout;
+
+const GREETING_LEN: usize = 64;
+/// Time a new peer has to send its greeting before it is turned away
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Frame flags
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+
+pub struct ZmqPublisher {
+    fanout: TcpFanout,
+}
+
+impl ZmqPublisher {
+    /// Bind to `addr`, either `host:port` or a ZeroMQ endpoint `tcp://host:port`
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let addr = addr.strip_prefix("tcp://").unwrap_or(addr);
+        let fanout = TcpFanout::bind(
+            addr,
+            |mut stream| async move {
+                stream.write_all(&greeting()).await?;
+                let mut peer = [0u8; GREETING_LEN];
+                time::timeout(HANDSHAKE_TIMEOUT, stream.read_exact(&mut peer))
+                    .await
+                    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no greeting from the peer"))??;
+                if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 || !peer[12..32].starts_with(b"NULL\0") {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "not a ZMTP 3 peer with the NULL mechanism"));
+                }
+                stream.write_all(&frame(COMMAND, &ready("PUB"))).await?;
+                Ok(stream)
+            },
+            // Nothing the subscriber sends after this changes what it gets,
+            // but it has to be read so the connection does not back up
+            |mut reader| async move {
+                let _ = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await;
+            },
+        )?;
+        Ok(Self { fanout })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.fanout.local_addr()
+    }
+
+    pub fn clients(&self) -> usize {
+        self.fanout.clients()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.fanout.dropped
+    }
+
+    /// Publish `samples` as one message
+    pub fn send(&mut self, samples: &[Complex32]) {
+        let body: Vec<u8> = samples.iter().flat_map(|s| [s.re.to_le_bytes(), s.im.to_le_bytes()]).flatten().collect();
+        self.fanout.send(&frame(0, &body));
+    }
+}
+
+/// Signature, version 3.0, the NULL mechanism and the as-server flag left clear
+fn greeting() -> [u8; GREETING_LEN] {
+    let mut greeting = [0u8; GREETING_LEN];
+    greeting[0] = 0xff;
+    greeting[9] = 0x7f;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    greeting
+}
+
+fn ready(socket_type: &str) -> Vec<u8> {
+    let mut body = vec![5];
+    body.extend_from_slice(b"READY");
+    body.push(11);
+    body.extend_from_slice(b"Socket-Type");
+    body.extend_from_slice(&(socket_type.len() as u32).to_be_bytes());
+    body.extend_from_slice(socket_type.as_bytes());
+    body
+}
+
+/// The last or only frame of a message, or a command with `COMMAND` in `flags`
+fn frame(flags: u8, body: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(flags & (MORE | LONG), 0);
+    let mut out = Vec::with_capacity(body.len() + 9);
+    if body.len() < 256 {
+        out.push(flags);
+        out.push(body.len() as u8);
+    } else {
+        out.push(flags | LONG);
+        out.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    }
+    out.extend_from_slice(body);
+    out
+}
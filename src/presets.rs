@@ -0,0 +1,97 @@
+//! Named receiver setups kept in `presets.toml` next to the config file,
+//! one `[name]` section each, such as
+//!
+//! ```text
+//! [2m repeater]
+//! frequency = 145.7e6
+//! sample_rate = 1e6
+//! gain = 30
+//! mode = "fm"
+//! device = "demo"
+//! ```
+//!
+//! `device` is `demo` for the demo source or the `host:port` of an rtl_tcp
+//! server. The sample rate is the bandwidth a preset captures, as the
+//! demodulators' channel filters are fixed per mode.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::dsp::AudioMode;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub frequency: f64,
+    pub sample_rate: f64,
+    pub gain: f64,
+    pub mode: AudioMode,
+    /// rtl_tcp server, `None` for the demo source
+    pub device: Option<String>,
+}
+
+/// `presets.toml` beside the config file
+pub fn path() -> Option<PathBuf> {
+    Some(Config::path()?.with_file_name("presets.toml"))
+}
+
+/// Presets in file order, none when the file does not exist
+pub fn load(path: &Path) -> io::Result<Vec<Preset>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+pub fn parse(text: &str) -> Result<Vec<Preset>, String> {
+    let config = Config::parse(text)?;
+    config
+        .sections()
+        .iter()
+        .map(|name| {
+            let get = |key: &str| config.get(&format!("{}.{}", name, key));
+            let number = |key: &str| {
+                get(key)
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .filter(|v| v.is_finite())
+                    .ok_or(format!("[{}] needs a number for {}", name, key))
+            };
+            Ok(Preset {
+                name: name.clone(),
+                frequency: number("frequency")?,
+                sample_rate: number("sample_rate")?,
+                gain: number("gain")?,
+                mode: get("mode")
+                    .and_then(AudioMode::parse)
+                    .ok_or(format!("[{}] mode must be fm, am, usb or lsb", name))?,
+                device: get("device").filter(|d| *d != "demo").map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Write `presets` over the file at `path`, creating its directory
+pub fn save(path: &Path, presets: &[Preset]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let sections: Vec<String> = presets
+        .iter()
+        .map(|p| {
+            format!(
+                "[{}]\nfrequency = {}\nsample_rate = {}\ngain = {}\nmode = \"{}\"\ndevice = \"{}\"\n",
+                p.name,
+                p.frequency,
+                p.sample_rate,
+                p.gain,
+                p.mode.to_string().to_lowercase(),
+                p.device.as_deref().unwrap_or("demo")
+            )
+        })
+        .collect();
+    fs::write(path, sections.join("\n"))
+}
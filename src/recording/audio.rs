@@ -0,0 +1,205 @@
+//! Demodulated audio recording to WAV or FLAC, continuously or as one file per
+//! transmission while the squelch is open.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::file_timestamp;
+use super::flac::FlacWriter;
+use super::wav;
+
+/// Time the squelch stays open after the channel drops below the threshold,
+/// so pauses within a transmission do not split it
+const SQUELCH_HANG_SECS: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Flac,
+}
+
+impl AudioFormat {
+    pub fn next(self) -> Self {
+        match self {
+            AudioFormat::Wav => AudioFormat::Flac,
+            AudioFormat::Flac => AudioFormat::Wav,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Flac => "FLAC",
+        })
+    }
+}
+
+enum Sink {
+    /// Writer and samples written, for the header sizes
+    Wav(BufWriter<File>, u32),
+    Flac(FlacWriter<BufWriter<File>>),
+}
+
+struct Segment {
+    sink: Sink,
+    path: PathBuf,
+    samples: u64,
+    rate: u32,
+    /// Seconds of audio since the channel was last above the squelch
+    quiet_secs: f64,
+}
+
+/// Records the demodulated audio, optionally only while a signal is present
+pub struct AudioRecorder {
+    pub format: AudioFormat,
+    /// Channel power in dBFS the squelch opens at
+    pub squelch_db: f32,
+    /// Write one file per transmission instead of one continuous file
+    pub squelch_gated: bool,
+    dir: PathBuf,
+    armed: bool,
+    segment: Option<Segment>,
+    /// Files finished since recording was armed
+    pub files: usize,
+}
+
+impl AudioRecorder {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            format: AudioFormat::Wav,
+            squelch_db: -60.0,
+            squelch_gated: false,
+            dir: dir.into(),
+            armed: false,
+            segment: None,
+            files: 0,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.armed
+    }
+
+    pub fn start(&mut self) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        self.armed = true;
+        self.files = 0;
+        Ok(())
+    }
+
+    /// Stop recording, returning the number of files written
+    pub fn stop(&mut self) -> io::Result<usize> {
+        self.armed = false;
+        self.close()?;
+        Ok(self.files)
+    }
+
+    /// Record a block of audio at `rate` with the channel at `channel_db`,
+    /// returning the path of a file that was just closed
+    pub fn process(
+        &mut self,
+        audio: &[f32],
+        rate: f64,
+        channel_db: f32,
+        frequency: f64,
+        mode: &str,
+    ) -> io::Result<Option<PathBuf>> {
+        if !self.armed || audio.is_empty() {
+            return Ok(None);
+        }
+        let open = !self.squelch_gated || channel_db >= self.squelch_db;
+        let mut closed = None;
+        if let Some(segment) = &mut self.segment {
+            segment.quiet_secs = if open { 0.0 } else { segment.quiet_secs + audio.len() as f64 / rate };
+            if segment.quiet_secs > SQUELCH_HANG_SECS || segment.rate != rate.round() as u32 {
+                closed = self.close()?;
+            }
+        }
+        if self.segment.is_none() {
+            if !open {
+                return Ok(closed);
+            }
+            self.open(rate, frequency, mode)?;
+        }
+        let segment = self.segment.as_mut().expect("segment opened above");
+        let pcm: Vec<i16> = audio
+            .iter()
+            .map(|&s| (s * i16::MAX as f32).round().clamp(-(i16::MAX as f32), i16::MAX as f32) as i16)
+            .collect();
+        match &mut segment.sink {
+            Sink::Wav(out, written) => {
+                for s in &pcm {
+                    out.write_all(&s.to_le_bytes())?;
+                }
+                *written = written.saturating_add(pcm.len() as u32);
+            }
+            Sink::Flac(writer) => writer.write(&pcm)?,
+        }
+        segment.samples += pcm.len() as u64;
+        Ok(closed)
+    }
+
+    /// Length of the open file, `None` while the squelch is closed
+    pub fn segment_duration(&self) -> Option<Duration> {
+        self.segment
+            .as_ref()
+            .map(|s| Duration::from_secs_f64(s.samples as f64 / s.rate as f64))
+    }
+
+    fn open(&mut self, rate: f64, frequency: f64, mode: &str) -> io::Result<()> {
+        let rate = rate.round() as u32;
+        let path = self.dir.join(format!(
+            "audio_{}_{:.0}Hz_{}.{}",
+            file_timestamp(SystemTime::now()),
+            frequency,
+            mode,
+            self.format.extension()
+        ));
+        let mut out = BufWriter::new(File::create(&path)?);
+        let sink = match self.format {
+            AudioFormat::Wav => {
+                out.write_all(&wav::audio_header(rate, 0))?;
+                Sink::Wav(out, 0)
+            }
+            AudioFormat::Flac => Sink::Flac(FlacWriter::new(out, rate)?),
+        };
+        self.segment = Some(Segment {
+            sink,
+            path,
+            samples: 0,
+            rate,
+            quiet_secs: 0.0,
+        });
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<Option<PathBuf>> {
+        let Some(segment) = self.segment.take() else {
+            return Ok(None);
+        };
+        match segment.sink {
+            Sink::Wav(mut out, written) => {
+                out.seek(SeekFrom::Start(0))?;
+                out.write_all(&wav::audio_header(segment.rate, written as u64))?;
+                out.flush()?;
+            }
+            Sink::Flac(writer) => {
+                writer.finish()?;
+            }
+        }
+        self.files += 1;
+        Ok(Some(segment.path))
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use num_complex::Complex32;
+
+use super::{file_timestamp, write_cf32};
+
+const PRE_ROLL: Duration = Duration::from_millis(20);
+/// Quiet time that ends a burst, long enough to keep the gaps of one OOK packet together
+const POST_ROLL: Duration = Duration::from_millis(50);
+const MAX_BURST: Duration = Duration::from_secs(5);
+/// Power smoothing time constant
+const POWER_TAU_SECS: f64 = 100e-6;
+/// Noise floor time constants while rising and falling
+const FLOOR_RISE_SECS: f64 = 2.0;
+const FLOOR_FALL_SECS: f64 = 0.1;
+/// Power must drop this far below the trigger level to count as quiet
+const HYSTERESIS_DB: f32 = 3.0;
+/// Time the floor estimate gets to settle before triggering is allowed
+const SETTLE: Duration = Duration::from_millis(200);
+const MAX_BURSTS: usize = 500;
+
+/// A captured transmission
+#[derive(Clone, Debug)]
+pub struct Burst {
+    pub started: SystemTime,
+    pub duration: Duration,
+    /// Peak power above the noise floor
+    pub peak_db: f32,
+    pub center_freq: f64,
+    pub path: PathBuf,
+}
+
+struct Active {
+    started: SystemTime,
+    samples: Vec<Complex32>,
+    quiet: usize,
+    peak: f32,
+}
+
+/// Energy detector saving bursts with pre- and post-roll as timestamped cf32 snippets
+pub struct BurstCapture {
+    /// Trigger level above the noise floor
+    pub threshold_db: f32,
+    dir: PathBuf,
+    sample_rate: f64,
+    power_alpha: f32,
+    rise_alpha: f32,
+    fall_alpha: f32,
+    power: f32,
+    floor: f32,
+    settling: usize,
+    history: VecDeque<Complex32>,
+    active: Option<Active>,
+    pub bursts: Vec<Burst>,
+    pub last_error: Option<String>,
+}
+
+impl BurstCapture {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            threshold_db: 10.0,
+            dir: dir.into(),
+            sample_rate: 0.0,
+            power_alpha: 1.0,
+            rise_alpha: 1.0,
+            fall_alpha: 1.0,
+            power: 0.0,
+            floor: 0.0,
+            settling: 0,
+            history: VecDeque::new(),
+            active: None,
+            bursts: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Whether a burst is being recorded right now
+    pub fn is_triggered(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn noise_floor_db(&self) -> f32 {
+        10.0 * self.floor.max(1e-20).log10()
+    }
+
+    fn reset(&mut self, sample_rate: f64) {
+        let alpha = |tau: f64| (1.0 / (tau * sample_rate)).min(1.0) as f32;
+        self.sample_rate = sample_rate;
+        self.power_alpha = alpha(POWER_TAU_SECS);
+        self.rise_alpha = alpha(FLOOR_RISE_SECS);
+        self.fall_alpha = alpha(FLOOR_FALL_SECS);
+        self.power = 0.0;
+        self.floor = 0.0;
+        self.settling = self.samples_for(SETTLE);
+        self.history.clear();
+        self.active = None;
+    }
+
+    fn samples_for(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate) as usize
+    }
+
+    pub fn process(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.reset(sample_rate);
+        }
+        let pre_roll = self.samples_for(PRE_ROLL);
+        let post_roll = self.samples_for(POST_ROLL);
+        let max_len = self.samples_for(MAX_BURST);
+        let trigger = 10f32.powf(self.threshold_db / 10.0);
+        let release = 10f32.powf((self.threshold_db - HYSTERESIS_DB) / 10.0);
+
+        for &s in samples {
+            let p = s.norm_sqr();
+            self.power += self.power_alpha * (p - self.power);
+            if self.settling > 0 {
+                self.settling -= 1;
+                self.floor += self.fall_alpha * (self.power - self.floor);
+                continue;
+            }
+
+            match &mut self.active {
+                None => {
+                    if self.power > self.floor * trigger {
+                        let mut captured: Vec<Complex32> = self.history.drain(..).collect();
+                        captured.push(s);
+                        self.active = Some(Active {
+                            started: SystemTime::now() - PRE_ROLL,
+                            samples: captured,
+                            quiet: 0,
+                            peak: self.power,
+                        });
+                        continue;
+                    }
+                    // The floor only follows the signal while nothing is being captured
+                    let alpha = if self.power > self.floor { self.rise_alpha } else { self.fall_alpha };
+                    self.floor += alpha * (self.power - self.floor);
+                    self.history.push_back(s);
+                    if self.history.len() > pre_roll {
+                        self.history.pop_front();
+                    }
+                }
+                Some(active) => {
+                    active.samples.push(s);
+                    active.peak = active.peak.max(self.power);
+                    active.quiet = if self.power < self.floor * release { active.quiet + 1 } else { 0 };
+                    if active.quiet >= post_roll || active.samples.len() >= max_len {
+                        self.finish(center_freq);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, center_freq: f64) {
+        let Some(active) = self.active.take() else {
+            return;
+        };
+        let path = self.dir.join(format!(
+            "burst_{}_{:.0}Hz_{:.0}sps.cf32",
+            file_timestamp(active.started),
+            center_freq,
+            self.sample_rate
+        ));
+        let result = std::fs::create_dir_all(&self.dir).and_then(|_| write_cf32(&path, &active.samples));
+        if let Err(e) = result {
+            log::error!("Burst capture: cannot write {}: {}", path.display(), e);
+            self.last_error = Some(format!("cannot write {}: {}", path.display(), e));
+            return;
+        }
+        self.last_error = None;
+
+        self.bursts.push(Burst {
+            started: active.started,
+            duration: Duration::from_secs_f64(active.samples.len() as f64 / self.sample_rate),
+            peak_db: 10.0 * (active.peak / self.floor).log10(),
+            center_freq,
+            path,
+        });
+        if self.bursts.len() > MAX_BURSTS {
+            self.bursts.remove(0);
+        }
+    }
+}
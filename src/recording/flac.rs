@@ -0,0 +1,237 @@
+//! Streaming encoder for 16-bit mono FLAC, using fixed linear prediction with
+//! Rice-coded residuals and falling back to verbatim frames where that is smaller.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+pub const BLOCK_SIZE: usize = 4096;
+/// Largest Rice parameter before the escape code
+const MAX_RICE_PARAM: u32 = 14;
+const STREAMINFO_LEN: u32 = 34;
+
+pub struct FlacWriter<W: Write + Seek> {
+    out: W,
+    sample_rate: u32,
+    block: Vec<i16>,
+    frames: u64,
+    samples: u64,
+    frame_sizes: (u32, u32),
+}
+
+impl<W: Write + Seek> FlacWriter<W> {
+    pub fn new(mut out: W, sample_rate: u32) -> io::Result<Self> {
+        out.write_all(b"fLaC")?;
+        out.write_all(&streaminfo(sample_rate, 0, (0, 0), true))?;
+        Ok(Self {
+            out,
+            sample_rate,
+            block: Vec::with_capacity(BLOCK_SIZE),
+            frames: 0,
+            samples: 0,
+            frame_sizes: (u32::MAX, 0),
+        })
+    }
+
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        for &s in samples {
+            self.block.push(s);
+            if self.block.len() == BLOCK_SIZE {
+                self.write_frame()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode any partial block and fill in the stream length
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.block.is_empty() {
+            self.write_frame()?;
+        }
+        let sizes = if self.frames == 0 { (0, 0) } else { self.frame_sizes };
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&streaminfo(self.sample_rate, self.samples, sizes, true))?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_frame(&mut self) -> io::Result<()> {
+        let frame = encode_frame(&self.block, self.frames);
+        self.out.write_all(&frame)?;
+        let size = frame.len() as u32;
+        self.frame_sizes = (self.frame_sizes.0.min(size), self.frame_sizes.1.max(size));
+        self.frames += 1;
+        self.samples += self.block.len() as u64;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+/// STREAMINFO metadata block with its header, marked as the `last` one or not
+pub fn streaminfo(sample_rate: u32, total_samples: u64, frame_sizes: (u32, u32), last: bool) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    bits.put(last as u64, 1);
+    bits.put(0, 7);
+    bits.put(STREAMINFO_LEN as u64, 24);
+    bits.put(BLOCK_SIZE as u64, 16);
+    bits.put(BLOCK_SIZE as u64, 16);
+    bits.put(frame_sizes.0 as u64, 24);
+    bits.put(frame_sizes.1 as u64, 24);
+    bits.put(sample_rate as u64, 20);
+    // One channel of 16 bits, both stored minus one
+    bits.put(0, 3);
+    bits.put(15, 5);
+    bits.put(total_samples, 36);
+    // MD5 of the audio left unset, which decoders treat as unknown
+    bits.put(0, 64);
+    bits.put(0, 64);
+    bits.bytes
+}
+
+/// One frame of up to [`BLOCK_SIZE`] samples
+pub fn encode_frame(block: &[i16], frame_number: u64) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    // Sync code, fixed block size
+    bits.put(0b1111_1111_1111_1000, 16);
+    // Block size from the 16-bit field after the header, rate from STREAMINFO
+    bits.put(0b0111, 4);
+    bits.put(0b0000, 4);
+    // Mono, 16 bits per sample
+    bits.put(0b0000, 4);
+    bits.put(0b100, 3);
+    bits.put(0, 1);
+    for byte in utf8_number(frame_number) {
+        bits.put(byte as u64, 8);
+    }
+    bits.put(block.len() as u64 - 1, 16);
+    let crc = crc8(&bits.bytes);
+    bits.put(crc as u64, 8);
+
+    encode_subframe(&mut bits, block);
+    bits.align();
+    let crc = crc16(&bits.bytes);
+    bits.put(crc as u64, 16);
+    bits.bytes
+}
+
+fn encode_subframe(bits: &mut BitWriter, block: &[i16]) {
+    const ORDER: usize = 2;
+    let verbatim_bits = 16 * block.len() as u64;
+    let residual: Vec<i32> = (ORDER..block.len())
+        .map(|i| block[i] as i32 - 2 * block[i - 1] as i32 + block[i - 2] as i32)
+        .collect();
+    let best = (0..=MAX_RICE_PARAM)
+        .map(|k| (rice_bits(&residual, k), k))
+        .min()
+        .filter(|&(size, _)| block.len() > ORDER && size + 16 * ORDER as u64 + 10 < verbatim_bits);
+
+    match best {
+        Some((_, k)) => {
+            // Fixed predictor subframe of the given order
+            bits.put(0b0001000 | ORDER as u64, 7);
+            bits.put(0, 1);
+            for &s in &block[..ORDER] {
+                bits.put(s as u16 as u64, 16);
+            }
+            // Rice coding with 4-bit parameters, a single partition
+            bits.put(0b00, 2);
+            bits.put(0, 4);
+            bits.put(k as u64, 4);
+            for &r in &residual {
+                let u = zigzag(r);
+                bits.unary((u >> k) as u64);
+                bits.put((u & ((1 << k) - 1)) as u64, k);
+            }
+        }
+        None => {
+            // Verbatim subframe
+            bits.put(0b0000001, 7);
+            bits.put(0, 1);
+            for &s in block {
+                bits.put(s as u16 as u64, 16);
+            }
+        }
+    }
+}
+
+fn zigzag(r: i32) -> u32 {
+    ((r << 1) ^ (r >> 31)) as u32
+}
+
+fn rice_bits(residual: &[i32], k: u32) -> u64 {
+    residual.iter().map(|&r| (zigzag(r) >> k) as u64 + 1 + k as u64).sum()
+}
+
+/// Frame number in the UTF-8 style variable-length coding FLAC uses
+fn utf8_number(n: u64) -> Vec<u8> {
+    if n < 0x80 {
+        return vec![n as u8];
+    }
+    let mut continuation = Vec::new();
+    let mut rest = n;
+    // Each continuation byte holds six bits, the lead byte what is left
+    while rest >= 1 << (6 - continuation.len()) {
+        continuation.push(0x80 | (rest & 0x3f) as u8);
+        rest >>= 6;
+    }
+    let count = continuation.len() + 1;
+    let lead = (0xff00u16 >> count) as u8 | rest as u8;
+    let mut out = vec![lead];
+    out.extend(continuation.iter().rev());
+    out
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, &b| {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// Most-significant-bit-first bit packing
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    used: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u64, count: u32) {
+        for i in (0..count).rev() {
+            self.bit((value >> i) & 1 != 0);
+        }
+    }
+
+    fn unary(&mut self, zeros: u64) {
+        for _ in 0..zeros {
+            self.bit(false);
+        }
+        self.bit(true);
+    }
+
+    fn bit(&mut self, set: bool) {
+        if self.used == 0 {
+            self.bytes.push(0);
+        }
+        if set {
+            *self.bytes.last_mut().expect("byte pushed above") |= 0x80 >> self.used;
+        }
+        self.used = (self.used + 1) % 8;
+    }
+
+    fn align(&mut self) {
+        self.used = 0;
+    }
+}
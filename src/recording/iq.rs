@@ -0,0 +1,554 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use num_complex::Complex32;
+
+use super::sigmf::{self, Annotation, SigmfMeta};
+use super::wav;
+use super::{file_timestamp, iso_timestamp};
+
+/// Encoding of raw IQ files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Interleaved little-endian 32-bit floats
+    Cf32,
+    /// Interleaved little-endian 16-bit integers, full scale at ±32767
+    Cs16,
+}
+
+impl SampleFormat {
+    pub fn next(self) -> Self {
+        match self {
+            SampleFormat::Cf32 => SampleFormat::Cs16,
+            SampleFormat::Cs16 => SampleFormat::Cf32,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SampleFormat::Cf32 => "cf32",
+            SampleFormat::Cs16 => "cs16",
+        }
+    }
+
+    /// SigMF `core:datatype` name
+    pub fn sigmf_datatype(self) -> &'static str {
+        match self {
+            SampleFormat::Cf32 => "cf32_le",
+            SampleFormat::Cs16 => "ci16_le",
+        }
+    }
+
+    pub fn from_sigmf(datatype: &str) -> Option<Self> {
+        match datatype {
+            "cf32_le" => Some(SampleFormat::Cf32),
+            "ci16_le" => Some(SampleFormat::Cs16),
+            _ => None,
+        }
+    }
+
+    pub fn bytes_per_sample(self) -> u64 {
+        match self {
+            SampleFormat::Cf32 => 8,
+            SampleFormat::Cs16 => 4,
+        }
+    }
+
+    /// Read up to `count` samples, stopping early only at the end of the input
+    pub fn read(self, input: &mut impl Read, count: usize, out: &mut Vec<Complex32>) -> io::Result<usize> {
+        let mut bytes = vec![0u8; count * self.bytes_per_sample() as usize];
+        let mut filled = 0;
+        while filled < bytes.len() {
+            match input.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let chunks = bytes[..filled].chunks_exact(self.bytes_per_sample() as usize);
+        let read = chunks.len();
+        out.extend(chunks.map(|chunk| match self {
+            SampleFormat::Cf32 => Complex32::new(
+                f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                f32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+            ),
+            SampleFormat::Cs16 => Complex32::new(
+                i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32767.0,
+                i16::from_le_bytes([chunk[2], chunk[3]]) as f32 / 32767.0,
+            ),
+        }));
+        Ok(read)
+    }
+
+    pub fn write(self, out: &mut impl Write, samples: &[Complex32]) -> io::Result<u64> {
+        for s in samples {
+            match self {
+                SampleFormat::Cf32 => {
+                    out.write_all(&s.re.to_le_bytes())?;
+                    out.write_all(&s.im.to_le_bytes())?;
+                }
+                SampleFormat::Cs16 => {
+                    let scale = |x: f32| ((x * 32767.0).round().clamp(-32767.0, 32767.0) as i16).to_le_bytes();
+                    out.write_all(&scale(s.re))?;
+                    out.write_all(&scale(s.im))?;
+                }
+            }
+        }
+        Ok(samples.len() as u64 * self.bytes_per_sample())
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Pre-recording is off unless set, as it holds seconds of IQ in memory
+const DEFAULT_PRE_RECORD_SECS: f64 = 0.0;
+
+/// File layout around the samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Container {
+    /// Bare samples, with the frequency and rate in the file name
+    Raw,
+    /// `.sigmf-data` with a `.sigmf-meta` description beside it
+    Sigmf,
+    /// Two-channel WAV with an `auxi` chunk, for SDR#, HDSDR and SDRuno
+    Wav,
+}
+
+impl Container {
+    pub fn next(self) -> Self {
+        match self {
+            Container::Raw => Container::Sigmf,
+            Container::Sigmf => Container::Wav,
+            Container::Wav => Container::Raw,
+        }
+    }
+}
+
+struct Active {
+    out: BufWriter<File>,
+    path: PathBuf,
+    /// File name before any part number and the extension
+    stem: String,
+    /// Number of the current file when splitting, from 1
+    part: Option<u32>,
+    started: Instant,
+    /// Time of the first sample in the current file
+    file_started_at: SystemTime,
+    center_freq: f64,
+    sample_rate: f64,
+    bytes: u64,
+    file_samples: u64,
+    /// Marks in the current file, kept in its SigMF metadata
+    annotations: Vec<Annotation>,
+}
+
+/// Records the live IQ stream to files named after the time, frequency and rate
+pub struct IqRecorder {
+    pub format: SampleFormat,
+    pub container: Container,
+    /// Seconds of IQ kept while idle and written at the start of each
+    /// recording, nothing being kept at 0
+    pub pre_record_secs: f64,
+    /// Start a new, sequence-numbered file after this many bytes or seconds
+    pub split_bytes: Option<u64>,
+    pub split_secs: Option<f64>,
+    /// Samples from before the recording started, with their centre
+    /// frequency and rate, until the first write after the start puts them
+    /// in the file
+    history: VecDeque<Complex32>,
+    history_tuning: (f64, f64),
+    dir: PathBuf,
+    active: Option<Active>,
+}
+
+impl IqRecorder {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            format: SampleFormat::Cf32,
+            container: Container::Raw,
+            pre_record_secs: DEFAULT_PRE_RECORD_SECS,
+            split_bytes: None,
+            split_secs: None,
+            history: VecDeque::new(),
+            history_tuning: (0.0, 0.0),
+            dir: dir.into(),
+            active: None,
+        }
+    }
+
+    /// Step through both sample formats of each container in turn
+    pub fn next_format(&mut self) {
+        if self.format == SampleFormat::Cs16 {
+            self.container = self.container.next();
+        }
+        self.format = self.format.next();
+    }
+
+    pub fn format_label(&self) -> String {
+        match self.container {
+            Container::Raw => self.format.to_string(),
+            Container::Sigmf => format!("SigMF {}", self.format),
+            Container::Wav => format!("WAV {}", self.format),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Time since recording started and bytes written so far
+    pub fn progress(&self) -> Option<(Duration, u64)> {
+        self.active.as_ref().map(|a| (a.started.elapsed(), a.bytes))
+    }
+
+    /// Number of the file being written when recordings are split
+    pub fn part(&self) -> Option<u32> {
+        self.active.as_ref().and_then(|a| a.part)
+    }
+
+    /// Seconds of buffered IQ a recording started now would begin with
+    pub fn pre_recorded_secs(&self) -> f64 {
+        match self.history_tuning.1 {
+            rate if rate > 0.0 => self.history.len() as f64 / rate,
+            _ => 0.0,
+        }
+    }
+
+    /// Start a new file beginning with the buffered samples if they were taken
+    /// at the same tuning, returning its path. The samples go in with the
+    /// next write, so that starting does not wait on writing them.
+    pub fn start(&mut self, center_freq: f64, sample_rate: f64) -> io::Result<&Path> {
+        std::fs::create_dir_all(&self.dir)?;
+        if self.history_tuning != (center_freq, sample_rate) {
+            self.history.clear();
+        }
+        let lead = Duration::from_secs_f64(self.history.len() as f64 / sample_rate);
+        let now = SystemTime::now() - lead;
+        let stem = format!("iq_{}_{:.0}Hz_{:.0}sps", file_timestamp(now), center_freq, sample_rate);
+        let splitting = self.split_bytes.is_some() || self.split_secs.is_some();
+        let part = splitting.then_some(1);
+        let (path, out) = self.create(&stem, part, center_freq, sample_rate, now)?;
+        self.active = Some(Active {
+            out,
+            path,
+            stem,
+            part,
+            started: Instant::now() - lead,
+            file_started_at: now,
+            center_freq,
+            sample_rate,
+            bytes: 0,
+            file_samples: 0,
+            annotations: Vec::new(),
+        });
+        Ok(&self.active.as_ref().expect("recording started above").path)
+    }
+
+    /// Append samples, moving on to the next file at the split size or when
+    /// the tuning changes, and stopping the recording if a write fails.
+    /// While idle the samples are kept for the next recording instead.
+    pub fn write(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) -> io::Result<()> {
+        if self.active.is_none() {
+            self.remember(samples, center_freq, sample_rate);
+            return Ok(());
+        }
+        let result = self
+            .flush_history()
+            .and_then(|()| match self.active.as_ref().map(|a| (a.center_freq, a.sample_rate)) {
+                Some(tuning) if tuning != (center_freq, sample_rate) => self.retune(center_freq, sample_rate),
+                _ => Ok(()),
+            })
+            .and_then(|()| self.write_split(samples));
+        if result.is_err() {
+            self.active = None;
+        }
+        result
+    }
+
+    /// Write the samples from before the start, if not yet written
+    fn flush_history(&mut self) -> io::Result<()> {
+        if self.active.is_none() {
+            return Ok(());
+        }
+        let history = std::mem::take(&mut self.history);
+        let (front, back) = history.as_slices();
+        self.write_split(front)?;
+        self.write_split(back)
+    }
+
+    fn write_split(&mut self, mut samples: &[Complex32]) -> io::Result<()> {
+        while let Some(active) = &mut self.active {
+            let limit = [
+                self.split_bytes.map(|bytes| bytes / self.format.bytes_per_sample()),
+                self.split_secs.map(|secs| (secs * active.sample_rate) as u64),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|samples| samples.max(1));
+            let room = limit.map_or(samples.len(), |limit| {
+                limit.saturating_sub(active.file_samples).min(samples.len() as u64) as usize
+            });
+            active.bytes += self.format.write(&mut active.out, &samples[..room])?;
+            active.file_samples += room as u64;
+            samples = &samples[room..];
+            if samples.is_empty() {
+                return Ok(());
+            }
+            self.next_part()?;
+        }
+        Ok(())
+    }
+
+    /// Close the current file and continue in a new one named after the new
+    /// tuning, as neither a raw file nor its name can describe two
+    fn retune(&mut self, center_freq: f64, sample_rate: f64) -> io::Result<()> {
+        let Some(mut active) = self.active.take() else {
+            return Ok(());
+        };
+        self.finish(&mut active)?;
+        let now = SystemTime::now();
+        let stem = format!("iq_{}_{:.0}Hz_{:.0}sps", file_timestamp(now), center_freq, sample_rate);
+        let part = active.part.map(|_| 1);
+        let (path, out) = self.create(&stem, part, center_freq, sample_rate, now)?;
+        log::info!("Retuned while recording, continuing in {}", path.display());
+        self.active = Some(Active {
+            out,
+            path,
+            stem,
+            part,
+            file_started_at: now,
+            center_freq,
+            sample_rate,
+            file_samples: 0,
+            annotations: Vec::new(),
+            ..active
+        });
+        Ok(())
+    }
+
+    /// Close the current file and continue in the next numbered one
+    fn next_part(&mut self) -> io::Result<()> {
+        let Some(mut active) = self.active.take() else {
+            return Ok(());
+        };
+        self.finish(&mut active)?;
+        let part = active.part.map_or(2, |part| part + 1);
+        let now = SystemTime::now();
+        let (path, out) = self.create(&active.stem, Some(part), active.center_freq, active.sample_rate, now)?;
+        self.active = Some(Active {
+            out,
+            path,
+            part: Some(part),
+            file_started_at: now,
+            file_samples: 0,
+            annotations: Vec::new(),
+            ..active
+        });
+        Ok(())
+    }
+
+    /// Open a file with any container header or metadata beside it
+    fn create(
+        &self,
+        stem: &str,
+        part: Option<u32>,
+        center_freq: f64,
+        sample_rate: f64,
+        first_sample: SystemTime,
+    ) -> io::Result<(PathBuf, BufWriter<File>)> {
+        let extension = match self.container {
+            Container::Raw => self.format.extension(),
+            Container::Sigmf => sigmf::DATA_EXTENSION,
+            Container::Wav => wav::EXTENSION,
+        };
+        let name = match part {
+            Some(part) => format!("{}_{:03}.{}", stem, part, extension),
+            None => format!("{}.{}", stem, extension),
+        };
+        let path = self.dir.join(name);
+        if self.container == Container::Sigmf {
+            self.write_meta(&path, center_freq, sample_rate, first_sample, Vec::new())?;
+        }
+        let mut out = BufWriter::new(File::create(&path)?);
+        if self.container == Container::Wav {
+            // Sizes and stop time are filled in by `finish`
+            out.write_all(&wav::header(self.format, sample_rate, center_freq, first_sample, first_sample, 0))?;
+        }
+        Ok((path, out))
+    }
+
+    fn write_meta(
+        &self,
+        path: &Path,
+        center_freq: f64,
+        sample_rate: f64,
+        first_sample: SystemTime,
+        annotations: Vec<Annotation>,
+    ) -> io::Result<()> {
+        let mut meta = SigmfMeta::new(self.format, sample_rate, center_freq);
+        meta.datetime = Some(iso_timestamp(first_sample));
+        meta.annotations = annotations;
+        meta.write(path)
+    }
+
+    /// Mark the latest sample with `label` in the SigMF metadata, returning
+    /// the seconds into the current file, or `None` while not recording
+    pub fn annotate(&mut self, label: &str) -> io::Result<Option<f64>> {
+        let Some(active) = &mut self.active else {
+            return Ok(None);
+        };
+        if self.container != Container::Sigmf {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "annotations are kept in SigMF recordings only",
+            ));
+        }
+        // After any samples from before the start still to be written
+        let latest = active.file_samples + self.history.len() as u64;
+        active.annotations.push(Annotation {
+            sample_start: latest,
+            sample_count: 0,
+            label: label.to_string(),
+        });
+        let active = self.active.as_ref().expect("checked above");
+        self.write_meta(
+            &active.path,
+            active.center_freq,
+            active.sample_rate,
+            active.file_started_at,
+            active.annotations.clone(),
+        )?;
+        Ok(Some(latest as f64 / active.sample_rate))
+    }
+
+    /// Complete the container header of a file and flush it
+    fn finish(&self, active: &mut Active) -> io::Result<()> {
+        if self.container == Container::Wav {
+            let header = wav::header(
+                self.format,
+                active.sample_rate,
+                active.center_freq,
+                active.file_started_at,
+                SystemTime::now(),
+                active.file_samples * self.format.bytes_per_sample(),
+            );
+            active.out.seek(SeekFrom::Start(0))?;
+            active.out.write_all(&header)?;
+        }
+        active.out.flush()
+    }
+
+    fn remember(&mut self, samples: &[Complex32], center_freq: f64, sample_rate: f64) {
+        if self.history_tuning != (center_freq, sample_rate) {
+            self.history.clear();
+            self.history_tuning = (center_freq, sample_rate);
+        }
+        let capacity = (self.pre_record_secs.max(0.0) * sample_rate) as usize;
+        if capacity == 0 {
+            self.history = VecDeque::new();
+            return;
+        }
+        self.history.extend(samples.iter().skip(samples.len().saturating_sub(capacity)));
+        let excess = self.history.len().saturating_sub(capacity);
+        self.history.drain(..excess);
+    }
+
+    /// Finish the current file, returning its path and the size of the whole recording
+    pub fn stop(&mut self) -> io::Result<Option<(PathBuf, u64)>> {
+        let flushed = self.flush_history();
+        let Some(mut active) = self.active.take() else {
+            return Ok(None);
+        };
+        flushed?;
+        self.finish(&mut active)?;
+        Ok(Some((active.path, active.bytes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(from: usize, len: usize) -> Vec<Complex32> {
+        (from..from + len).map(|i| Complex32::new(i as f32, 0.0)).collect()
+    }
+
+    fn read(path: &Path) -> Vec<Complex32> {
+        let mut samples = Vec::new();
+        let count = std::fs::metadata(path).unwrap().len() / SampleFormat::Cf32.bytes_per_sample();
+        SampleFormat::Cf32.read(&mut File::open(path).unwrap(), count as usize, &mut samples).unwrap();
+        samples
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rf_rust_iq_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn keeps_nothing_before_a_recording_by_default() {
+        let mut recorder = IqRecorder::new(temp_dir("default"));
+        recorder.write(&ramp(0, 1000), 100e6, 1000.0).unwrap();
+        assert_eq!(recorder.pre_recorded_secs(), 0.0);
+        assert_eq!(recorder.history.capacity(), 0);
+    }
+
+    #[test]
+    fn pre_recorded_samples_lead_the_file() {
+        let dir = temp_dir("pre_record");
+        let mut recorder = IqRecorder::new(&dir);
+        recorder.pre_record_secs = 0.5;
+        recorder.write(&ramp(0, 800), 100e6, 1000.0).unwrap();
+        recorder.write(&ramp(800, 200), 100e6, 1000.0).unwrap();
+        assert_eq!(recorder.pre_recorded_secs(), 0.5);
+
+        let path = recorder.start(100e6, 1000.0).unwrap().to_path_buf();
+        // Left for the next write
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        recorder.write(&ramp(1000, 100), 100e6, 1000.0).unwrap();
+        recorder.stop().unwrap();
+        let samples = read(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(samples, ramp(500, 600));
+    }
+
+    #[test]
+    fn stopping_at_once_still_writes_the_pre_recording() {
+        let dir = temp_dir("stop_at_once");
+        let mut recorder = IqRecorder::new(&dir);
+        recorder.pre_record_secs = 1.0;
+        recorder.write(&ramp(0, 300), 100e6, 1000.0).unwrap();
+        let path = recorder.start(100e6, 1000.0).unwrap().to_path_buf();
+        let stopped = recorder.stop().unwrap();
+        let samples = read(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(stopped.map(|(_, bytes)| bytes), Some(300 * 8));
+        assert_eq!(samples, ramp(0, 300));
+    }
+
+    #[test]
+    fn retuning_starts_a_new_file() {
+        let dir = temp_dir("retune");
+        let mut recorder = IqRecorder::new(&dir);
+        let first = recorder.start(100e6, 1000.0).unwrap().to_path_buf();
+        recorder.write(&ramp(0, 100), 100e6, 1000.0).unwrap();
+        recorder.write(&ramp(100, 50), 101e6, 2000.0).unwrap();
+        recorder.write(&ramp(150, 50), 101e6, 2000.0).unwrap();
+        let (second, bytes) = recorder.stop().unwrap().unwrap();
+        let files = (read(&first), read(&second));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_ne!(first, second);
+        assert!(second.to_string_lossy().ends_with("_101000000Hz_2000sps.cf32"), "{}", second.display());
+        assert_eq!(files, (ramp(0, 100), ramp(100, 100)));
+        assert_eq!(bytes, 200 * 8);
+    }
+}
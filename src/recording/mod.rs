@@ -0,0 +1,60 @@
+//! Writing captured IQ samples to disk.
+
+pub mod audio;
+pub mod burst;
+pub mod flac;
+pub mod iq;
+pub mod playback;
+pub mod schedule;
+pub mod sigmf;
+pub mod wav;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex32;
+
+use crate::decoders::utc_date_time;
+
+/// `YYYYMMDD_HHMMSS.mmm` UTC stamp for file names
+pub fn file_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (date, clock) = utc_date_time(since_epoch.as_secs());
+    format!(
+        "{}_{}.{:03}",
+        date.replace('-', ""),
+        clock.replace(':', ""),
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Byte count in B, kB, MB or GB
+pub fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "kB", "MB"] {
+        if size < 1000.0 {
+            return format!("{:.1} {}", size, unit);
+        }
+        size /= 1000.0;
+    }
+    format!("{:.2} GB", size)
+}
+
+/// ISO 8601 UTC time with milliseconds, `YYYY-MM-DDTHH:MM:SS.mmmZ`
+pub fn iso_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (date, clock) = utc_date_time(since_epoch.as_secs());
+    format!("{}T{}.{:03}Z", date, clock, since_epoch.subsec_millis())
+}
+
+/// Write samples as interleaved little-endian 32-bit floats (cf32)
+pub fn write_cf32(path: &Path, samples: &[Complex32]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for s in samples {
+        out.write_all(&s.re.to_le_bytes())?;
+        out.write_all(&s.im.to_le_bytes())?;
+    }
+    out.flush()
+}
@@ -0,0 +1,150 @@
+//! Reading recorded IQ files back in place of a live receiver.
+
+use std::fs::File;
+use std::io::{self, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use num_complex::Complex32;
+
+use super::iq::SampleFormat;
+use super::sigmf::{self, SigmfMeta};
+use super::wav;
+
+/// Plays a SigMF or WAV IQ recording, or a raw one whose name carries the
+/// frequency and rate as written by [`super::iq::IqRecorder`]
+pub struct FilePlayer {
+    path: PathBuf,
+    pub meta: SigmfMeta,
+    input: BufReader<File>,
+    /// Byte offset of the first sample
+    data_offset: u64,
+    total_samples: u64,
+    position: u64,
+    pub paused: bool,
+    /// Start again from the beginning at the end of the file
+    pub looping: bool,
+    /// Playback rate relative to real time
+    pub speed: f64,
+}
+
+impl FilePlayer {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if extension.eq_ignore_ascii_case(wav::EXTENSION) {
+            return Self::open_wav(path);
+        }
+        let (meta, data_path) = if extension == sigmf::META_EXTENSION || extension == sigmf::DATA_EXTENSION {
+            (SigmfMeta::read(path)?, SigmfMeta::data_path(path))
+        } else {
+            (raw_meta(path)?, path.to_path_buf())
+        };
+        let file = File::open(&data_path)?;
+        let total_samples = file.metadata()?.len() / meta.format.bytes_per_sample();
+        Ok(Self::new(path, meta, file, 0, total_samples))
+    }
+
+    fn open_wav(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let header = wav::read_header(&mut file)?;
+        let data_offset = file.stream_position()?;
+        let mut meta = SigmfMeta::new(header.format, header.sample_rate, header.center_freq);
+        meta.datetime = header.datetime;
+        let total_samples = header.data_len / meta.format.bytes_per_sample();
+        Ok(Self::new(path, meta, file, data_offset, total_samples))
+    }
+
+    fn new(path: &Path, meta: SigmfMeta, file: File, data_offset: u64, total_samples: u64) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            meta,
+            input: BufReader::new(file),
+            data_offset,
+            total_samples,
+            position: 0,
+            paused: false,
+            looping: false,
+            speed: 1.0,
+        }
+    }
+
+    /// Newest recording in `dir` that can be played back
+    pub fn latest(dir: &Path) -> Option<PathBuf> {
+        std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                let path = entry.path();
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                extension == sigmf::META_EXTENSION
+                    || extension.eq_ignore_ascii_case(wav::EXTENSION)
+                    || raw_meta(&path).is_ok()
+            })
+            .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+            .map(|entry| entry.path())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Seconds played so far and in total
+    pub fn progress(&self) -> (f64, f64) {
+        (
+            self.position as f64 / self.meta.sample_rate,
+            self.total_samples as f64 / self.meta.sample_rate,
+        )
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.position >= self.total_samples
+    }
+
+    /// Move by `secs`, clamped to the start and end of the recording
+    pub fn seek(&mut self, secs: f64) -> io::Result<()> {
+        let target = self.position as f64 + secs * self.meta.sample_rate;
+        self.seek_to(target.clamp(0.0, self.total_samples as f64) as u64)
+    }
+
+    fn seek_to(&mut self, sample: u64) -> io::Result<()> {
+        let offset = self.data_offset + sample * self.meta.format.bytes_per_sample();
+        self.input.seek(SeekFrom::Start(offset))?;
+        self.position = sample;
+        Ok(())
+    }
+
+    /// Replace `out` with the next `count` samples, fewer at the end of the
+    /// file unless looping
+    pub fn read(&mut self, count: usize, out: &mut Vec<Complex32>) -> io::Result<usize> {
+        out.clear();
+        loop {
+            // Stop short of any chunks that follow the samples
+            let wanted = (count - out.len()).min(self.total_samples.saturating_sub(self.position) as usize);
+            let read = self.meta.format.read(&mut self.input, wanted, out)?;
+            self.position += read as u64;
+            if out.len() == count || !self.looping || self.total_samples == 0 {
+                return Ok(out.len());
+            }
+            if read < wanted || self.position >= self.total_samples {
+                self.seek_to(0)?;
+            }
+        }
+    }
+}
+
+/// Metadata of a raw recording from its `..._{freq}Hz_{rate}sps.{cf32,cs16}` name
+fn raw_meta(path: &Path) -> io::Result<SigmfMeta> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", path.display(), what));
+    let format = match path.extension().and_then(|e| e.to_str()) {
+        Some("cf32") => SampleFormat::Cf32,
+        Some("cs16") => SampleFormat::Cs16,
+        _ => return Err(invalid("not a cf32, cs16 or SigMF recording")),
+    };
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let field = |suffix: &str| {
+        stem.split('_')
+            .find_map(|part| part.strip_suffix(suffix)?.parse::<f64>().ok())
+    };
+    let sample_rate = field("sps").ok_or_else(|| invalid("no sample rate in the file name"))?;
+    let frequency = field("Hz").ok_or_else(|| invalid("no frequency in the file name"))?;
+    Ok(SigmfMeta::new(format, sample_rate, frequency))
+}
@@ -0,0 +1,185 @@
+//! Timed IQ recordings, such as a satellite pass, read from the `[schedule]`
+//! section of the config file:
+//!
+//! ```toml
+//! [schedule]
+//! noaa19 = "03:12 12m 137.1M fm"
+//! beacon = "2026-10-16T18:00:30 90s 144.8M usb"
+//! ```
+//!
+//! The start is a UTC time of day, meaning its next occurrence, or a full UTC
+//! date and time. Durations take an `s`, `m` or `h` suffix and frequencies an
+//! optional `k`, `M` or `G`.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::dsp::AudioMode;
+
+const DAY_SECS: u64 = 86_400;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobState {
+    Pending,
+    Recording,
+    Done { path: PathBuf, bytes: u64 },
+    /// The window had passed before the job could start
+    Missed,
+    Cancelled,
+    Failed(String),
+}
+
+/// One recording window
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub label: String,
+    pub start: SystemTime,
+    pub duration: Duration,
+    pub frequency: f64,
+    pub mode: AudioMode,
+    pub state: JobState,
+}
+
+impl Job {
+    /// Parse `"<start> <duration> <frequency> <mode>"`, placing a time of day
+    /// at its next occurrence whose window has not ended by `now`
+    pub fn parse(label: &str, spec: &str, now: SystemTime) -> Result<Self, String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [start, duration, frequency, mode] = fields[..] else {
+            return Err(format!("{}: expected `<start> <duration> <frequency> <mode>`", label));
+        };
+        let duration = parse_duration(duration).ok_or_else(|| format!("{}: invalid duration `{}`", label, duration))?;
+        let frequency = parse_frequency(frequency).ok_or_else(|| format!("{}: invalid frequency `{}`", label, frequency))?;
+        let mode = AudioMode::parse(mode).ok_or_else(|| format!("{}: unknown mode `{}`, expected fm, am, usb or lsb", label, mode))?;
+        let start = parse_start(start, duration, now).ok_or_else(|| format!("{}: invalid start `{}`", label, start))?;
+        Ok(Self {
+            label: label.to_string(),
+            start,
+            duration,
+            frequency,
+            mode,
+            state: JobState::Pending,
+        })
+    }
+
+    pub fn end(&self) -> SystemTime {
+        self.start + self.duration
+    }
+}
+
+/// Pending and past jobs in start order
+#[derive(Default)]
+pub struct Scheduler {
+    pub jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn from_config(config: &Config, now: SystemTime) -> Result<Self, String> {
+        let mut jobs = config
+            .keys("schedule")
+            .map(|label| {
+                let spec = config.get(&format!("schedule.{}", label)).unwrap_or_default();
+                Job::parse(label, spec, now)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        jobs.sort_by_key(|job| job.start);
+        Ok(Self { jobs })
+    }
+
+    pub fn pending(&self) -> usize {
+        self.jobs.iter().filter(|job| job.state == JobState::Pending).count()
+    }
+
+    /// Index of a pending job whose window has opened, marking any whose
+    /// window closed unnoticed as missed
+    pub fn due(&mut self, now: SystemTime) -> Option<usize> {
+        for job in &mut self.jobs {
+            if job.state == JobState::Pending && job.end() <= now {
+                job.state = JobState::Missed;
+            }
+        }
+        self.jobs
+            .iter()
+            .position(|job| job.state == JobState::Pending && job.start <= now)
+    }
+
+    /// Index of a recording job whose window has closed
+    pub fn finished(&self, now: SystemTime) -> Option<usize> {
+        self.jobs
+            .iter()
+            .position(|job| job.state == JobState::Recording && job.end() <= now)
+    }
+
+    /// Cancel the next pending job, returning its label
+    pub fn cancel_next(&mut self) -> Option<&str> {
+        let job = self.jobs.iter_mut().find(|job| job.state == JobState::Pending)?;
+        job.state = JobState::Cancelled;
+        Some(&job.label)
+    }
+}
+
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, scale) = match text.chars().last()? {
+        's' => (&text[..text.len() - 1], 1.0),
+        'm' => (&text[..text.len() - 1], 60.0),
+        'h' => (&text[..text.len() - 1], 3600.0),
+        _ => (text, 1.0),
+    };
+    let secs = number.parse::<f64>().ok()? * scale;
+    (secs > 0.0 && secs.is_finite()).then(|| Duration::from_secs_f64(secs))
+}
+
+fn parse_frequency(text: &str) -> Option<f64> {
+    let (number, scale) = match text.chars().last()? {
+        'k' | 'K' => (&text[..text.len() - 1], 1e3),
+        'M' => (&text[..text.len() - 1], 1e6),
+        'G' | 'g' => (&text[..text.len() - 1], 1e9),
+        _ => (text, 1.0),
+    };
+    let hz = number.parse::<f64>().ok()? * scale;
+    (hz > 0.0 && hz.is_finite()).then_some(hz)
+}
+
+/// `HH:MM[:SS]` as seconds into the day
+fn parse_clock(text: &str) -> Option<u64> {
+    let parts: Vec<u64> = text.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let (h, m, s) = match parts[..] {
+        [h, m] => (h, m, 0),
+        [h, m, s] => (h, m, s),
+        _ => return None,
+    };
+    (h < 24 && m < 60 && s < 60).then_some(h * 3600 + m * 60 + s)
+}
+
+fn parse_start(text: &str, duration: Duration, now: SystemTime) -> Option<SystemTime> {
+    let text = text.trim_end_matches('Z');
+    if let Some((date, clock)) = text.split_once('T') {
+        let parts: Vec<i64> = date.split('-').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        let [year, month, day] = parts[..] else {
+            return None;
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+        return Some(UNIX_EPOCH + Duration::from_secs(days * DAY_SECS + parse_clock(clock)?));
+    }
+    let clock = parse_clock(text)?;
+    let today = now.duration_since(UNIX_EPOCH).ok()?.as_secs() / DAY_SECS * DAY_SECS;
+    let mut start = UNIX_EPOCH + Duration::from_secs(today + clock);
+    if start + duration <= now {
+        start += Duration::from_secs(DAY_SECS);
+    }
+    Some(start)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
@@ -0,0 +1,147 @@
+//! SigMF metadata: a `.sigmf-meta` JSON file describing the samples in the
+//! `.sigmf-data` file beside it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::json::Value;
+
+use super::iq::SampleFormat;
+
+pub const META_EXTENSION: &str = "sigmf-meta";
+pub const DATA_EXTENSION: &str = "sigmf-data";
+
+const VERSION: &str = "1.0.0";
+
+/// Labelled span of samples
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub sample_start: u64,
+    pub sample_count: u64,
+    pub label: String,
+}
+
+/// Global and capture fields of a recording with a single capture segment
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigmfMeta {
+    pub format: SampleFormat,
+    pub sample_rate: f64,
+    pub frequency: f64,
+    /// ISO 8601 UTC time of the first sample
+    pub datetime: Option<String>,
+    pub description: Option<String>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl SigmfMeta {
+    pub fn new(format: SampleFormat, sample_rate: f64, frequency: f64) -> Self {
+        Self {
+            format,
+            sample_rate,
+            frequency,
+            datetime: None,
+            description: None,
+            annotations: Vec::new(),
+        }
+    }
+
+    /// The metadata file for a data file, or the same path for a metadata file
+    pub fn meta_path(path: &Path) -> PathBuf {
+        path.with_extension(META_EXTENSION)
+    }
+
+    pub fn data_path(path: &Path) -> PathBuf {
+        path.with_extension(DATA_EXTENSION)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let text = |s: &str| Value::String(s.to_string());
+        let mut global = vec![
+            ("core:datatype".to_string(), text(self.format.sigmf_datatype())),
+            ("core:sample_rate".to_string(), Value::Number(self.sample_rate)),
+            ("core:version".to_string(), text(VERSION)),
+            ("core:recorder".to_string(), text(env!("CARGO_PKG_NAME"))),
+        ];
+        if let Some(description) = &self.description {
+            global.push(("core:description".to_string(), text(description)));
+        }
+        let mut capture = vec![
+            ("core:sample_start".to_string(), Value::Number(0.0)),
+            ("core:frequency".to_string(), Value::Number(self.frequency)),
+        ];
+        if let Some(datetime) = &self.datetime {
+            capture.push(("core:datetime".to_string(), text(datetime)));
+        }
+        let annotations = self
+            .annotations
+            .iter()
+            .map(|a| {
+                Value::Object(vec![
+                    ("core:sample_start".to_string(), Value::Number(a.sample_start as f64)),
+                    ("core:sample_count".to_string(), Value::Number(a.sample_count as f64)),
+                    ("core:label".to_string(), text(&a.label)),
+                ])
+            })
+            .collect();
+        Value::Object(vec![
+            ("global".to_string(), Value::Object(global)),
+            ("captures".to_string(), Value::Array(vec![Value::Object(capture)])),
+            ("annotations".to_string(), Value::Array(annotations)),
+        ])
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let root = Value::parse(text)?;
+        let global = root.get("global").ok_or("missing `global`")?;
+        let datatype = global
+            .get("core:datatype")
+            .and_then(Value::as_str)
+            .ok_or("missing `core:datatype`")?;
+        let format = SampleFormat::from_sigmf(datatype)
+            .ok_or_else(|| format!("unsupported datatype `{}`, expected cf32_le or ci16_le", datatype))?;
+        let sample_rate = global
+            .get("core:sample_rate")
+            .and_then(Value::as_f64)
+            .ok_or("missing `core:sample_rate`")?;
+        let capture = root
+            .get("captures")
+            .and_then(Value::as_array)
+            .and_then(|captures| captures.first());
+        let capture_field = |key: &str| capture.and_then(|c| c.get(key));
+        let annotations = root
+            .get("annotations")
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|a| {
+                Some(Annotation {
+                    sample_start: a.get("core:sample_start")?.as_f64()? as u64,
+                    sample_count: a.get("core:sample_count").and_then(Value::as_f64).unwrap_or(0.0) as u64,
+                    label: a
+                        .get("core:label")
+                        .or_else(|| a.get("core:comment"))
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                })
+            })
+            .collect();
+        Ok(Self {
+            format,
+            sample_rate,
+            frequency: capture_field("core:frequency").and_then(Value::as_f64).unwrap_or(0.0),
+            datetime: capture_field("core:datetime").and_then(Value::as_str).map(str::to_string),
+            description: global.get("core:description").and_then(Value::as_str).map(str::to_string),
+            annotations,
+        })
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let text = std::fs::read_to_string(Self::meta_path(path))?;
+        Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(Self::meta_path(path), format!("{}\n", self.to_json()))
+    }
+}
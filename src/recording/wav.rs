@@ -0,0 +1,267 @@
+//! Two-channel WAV IQ files as written by SDR#, HDSDR and SDRuno, with the
+//! `auxi` chunk carrying the recording time and centre frequency, and the
+//! 16-bit mono PCM of audio recordings.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::decoders::utc_date_time;
+
+use super::iq::SampleFormat;
+
+pub const EXTENSION: &str = "wav";
+
+/// Start time, stop time, nine DWORD fields and the 96-byte next-file name
+const AUXI_LEN: u32 = 164;
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+/// Most of a `fmt ` or `auxi` chunk read, far more than either holds, so a
+/// corrupt length cannot make a huge allocation
+const MAX_CHUNK_READ: u32 = 4096;
+/// Bytes before the samples in files from [`header`]
+const HEADER_LEN: u64 = 12 + 8 + 16 + 8 + AUXI_LEN as u64 + 8;
+/// Bytes before the samples in files from [`audio_header`]
+const AUDIO_HEADER_LEN: u64 = 12 + 8 + 16 + 8;
+
+/// Sample layout and metadata of a WAV IQ file
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavIq {
+    pub format: SampleFormat,
+    pub sample_rate: f64,
+    /// Zero when the file has no `auxi` chunk
+    pub center_freq: f64,
+    /// ISO 8601 UTC time from the `auxi` chunk
+    pub datetime: Option<String>,
+    pub data_len: u64,
+}
+
+/// File header up to and including the `data` chunk header. The centre
+/// frequency is stored as a 32-bit integer, so is limited to 4.29 GHz.
+pub fn header(
+    format: SampleFormat,
+    sample_rate: f64,
+    center_freq: f64,
+    start: SystemTime,
+    stop: SystemTime,
+    data_len: u64,
+) -> Vec<u8> {
+    let (tag, bits) = match format {
+        SampleFormat::Cf32 => (FORMAT_FLOAT, 32u16),
+        SampleFormat::Cs16 => (FORMAT_PCM, 16u16),
+    };
+    let rate = sample_rate.round() as u32;
+    let data_len = data_len.min((u32::MAX as u64) - HEADER_LEN) as u32;
+
+    let mut out = riff_fmt(HEADER_LEN, data_len, tag, 2, rate, bits);
+
+    out.extend_from_slice(b"auxi");
+    out.extend_from_slice(&AUXI_LEN.to_le_bytes());
+    out.extend_from_slice(&system_time(start));
+    out.extend_from_slice(&system_time(stop));
+    let auxi_fields = [
+        center_freq.round().clamp(0.0, u32::MAX as f64) as u32,
+        // AD frequency, IF frequency, bandwidth, IQ offset, dB offset, max value, unused
+        rate, 0, rate, 0, 0, 0, 0, 0,
+    ];
+    for field in auxi_fields {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+    out.extend_from_slice(&[0; 96]);
+
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out
+}
+
+/// Header of a 16-bit mono PCM file of `samples` samples, such as audio
+/// recordings and the cycles handed to external decoders
+pub fn audio_header(sample_rate: u32, samples: u64) -> Vec<u8> {
+    let data_len = samples.saturating_mul(2).min((u32::MAX as u64) - AUDIO_HEADER_LEN) as u32;
+    let mut out = riff_fmt(AUDIO_HEADER_LEN, data_len, FORMAT_PCM, 1, sample_rate, 16);
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out
+}
+
+/// RIFF header and `fmt ` chunk of a file with `header_len` bytes before
+/// `data_len` bytes of samples
+fn riff_fmt(header_len: u64, data_len: u32, tag: u16, channels: u16, rate: u32, bits: u16) -> Vec<u8> {
+    let block_align = channels * bits / 8;
+    let mut out = Vec::with_capacity(header_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(header_len as u32 - 8 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&tag.to_le_bytes());
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&rate.to_le_bytes());
+    out.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&bits.to_le_bytes());
+    out
+}
+
+/// Parse the chunks before the samples, leaving `input` at the first sample
+pub fn read_header(input: &mut (impl Read + Seek)) -> io::Result<WavIq> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    let mut riff = [0u8; 12];
+    input.read_exact(&mut riff)?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(invalid("not a RIFF WAVE file"));
+    }
+
+    let (mut format, mut sample_rate) = (None, 0.0);
+    let (mut center_freq, mut datetime) = (0.0, None);
+    loop {
+        let mut chunk = [0u8; 8];
+        input.read_exact(&mut chunk)?;
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        match &chunk[..4] {
+            b"fmt " => {
+                let body = read_body(input, len)?;
+                if body.len() < 16 {
+                    return Err(invalid("short fmt chunk"));
+                }
+                let field = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                let mut tag = field(0);
+                if tag == FORMAT_EXTENSIBLE && body.len() >= 26 {
+                    // First two bytes of the sub-format GUID
+                    tag = field(24);
+                }
+                if field(2) != 2 {
+                    return Err(invalid("WAV IQ needs two channels"));
+                }
+                sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]) as f64;
+                format = Some(match (tag, field(14)) {
+                    (FORMAT_FLOAT, 32) => SampleFormat::Cf32,
+                    (FORMAT_PCM, 16) => SampleFormat::Cs16,
+                    (_, bits) => return Err(invalid(&format!("unsupported {}-bit sample format {}", bits, tag))),
+                });
+            }
+            b"auxi" => {
+                let body = read_body(input, len)?;
+                if body.len() >= 36 {
+                    datetime = Some(iso_time(&body[..16]));
+                    center_freq = u32::from_le_bytes([body[32], body[33], body[34], body[35]]) as f64;
+                }
+            }
+            b"data" => {
+                let format = format.ok_or_else(|| invalid("data before fmt chunk"))?;
+                let data_offset = input.stream_position()?;
+                let end = input.seek(SeekFrom::End(0))?;
+                input.seek(SeekFrom::Start(data_offset))?;
+                // Recorders that were interrupted leave the length at zero
+                let data_len = match len as u64 {
+                    0 => end - data_offset,
+                    len => len.min(end - data_offset),
+                };
+                return Ok(WavIq {
+                    format,
+                    sample_rate,
+                    center_freq,
+                    datetime,
+                    data_len,
+                });
+            }
+            _ => {
+                input.seek(SeekFrom::Current(len as i64 + (len & 1) as i64))?;
+            }
+        }
+    }
+}
+
+/// A chunk's body, no more than [`MAX_CHUNK_READ`] of it, leaving `input`
+/// after the chunk
+fn read_body(input: &mut (impl Read + Seek), len: u32) -> io::Result<Vec<u8>> {
+    let kept = len.min(MAX_CHUNK_READ);
+    let mut body = vec![0u8; kept as usize];
+    input.read_exact(&mut body)?;
+    // The rest unread, and the pad byte after an odd length
+    input.seek(SeekFrom::Current((len - kept) as i64 + (len & 1) as i64))?;
+    Ok(body)
+}
+
+/// Windows SYSTEMTIME: year, month, day of week, day, hour, minute, second, millisecond
+fn system_time(time: SystemTime) -> [u8; 16] {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (date, clock) = utc_date_time(secs);
+    let number = |s: &str| s.parse::<u16>().unwrap_or_default();
+    let date: Vec<u16> = date.split('-').map(number).collect();
+    let clock: Vec<u16> = clock.split(':').map(number).collect();
+    // 1970-01-01 was a Thursday, with Sunday as day zero
+    let day_of_week = ((secs / 86_400 + 4) % 7) as u16;
+    let fields = [
+        date[0],
+        date[1],
+        day_of_week,
+        date[2],
+        clock[0],
+        clock[1],
+        clock[2],
+        since_epoch.subsec_millis() as u16,
+    ];
+    let mut out = [0u8; 16];
+    for (i, field) in fields.iter().enumerate() {
+        out[2 * i..2 * i + 2].copy_from_slice(&field.to_le_bytes());
+    }
+    out
+}
+
+fn iso_time(system_time: &[u8]) -> String {
+    let field = |i: usize| u16::from_le_bytes([system_time[2 * i], system_time[2 * i + 1]]);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        field(0),
+        field(1),
+        field(3),
+        field(4),
+        field(5),
+        field(6),
+        field(7)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn reads_back_the_header_it_writes() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut file = header(SampleFormat::Cs16, 2.4e6, 145.5e6, start, start, 8);
+        file.extend_from_slice(&[0; 8]);
+        let wav = read_header(&mut Cursor::new(file)).unwrap();
+        assert_eq!(wav.format, SampleFormat::Cs16);
+        assert_eq!((wav.sample_rate, wav.center_freq, wav.data_len), (2.4e6, 145.5e6, 8));
+        assert_eq!(wav.datetime.as_deref(), Some("2023-11-14T22:13:20.000Z"));
+    }
+
+    #[test]
+    fn audio_header_is_plain_mono_pcm() {
+        let header = audio_header(12_000, 100);
+        assert_eq!(header.len() as u64, AUDIO_HEADER_LEN);
+        assert_eq!(&header[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(header[4..8].try_into().unwrap()), 36 + 200);
+        assert_eq!(&header[8..16], b"WAVEfmt ");
+        // PCM, one channel, 12 kHz, 24 000 bytes a second, two-byte blocks of 16 bits
+        let fmt: Vec<u8> = [&1u16.to_le_bytes()[..], &1u16.to_le_bytes(), &12_000u32.to_le_bytes(), &24_000u32.to_le_bytes()]
+            .concat();
+        assert_eq!(&header[20..32], &fmt[..]);
+        assert_eq!(&header[32..36], &[2, 0, 16, 0]);
+        assert_eq!(&header[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(header[40..44].try_into().unwrap()), 200);
+    }
+
+    #[test]
+    fn refuses_a_huge_chunk_length_without_allocating_it() {
+        let mut file = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(&[0; 16]);
+        assert!(read_header(&mut Cursor::new(file)).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! The tokio runtime the engine's I/O runs on. Device links and network
+//! services are tasks on it, handing data to the receiver over bounded
+//! channels, so a slow network or device never holds up the caller, which
+//! only drains what has arrived.
+//!
+//! The runtime starts on first use and lives for the rest of the process.
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::JoinHandle;
+
+/// Threads running tasks, I/O waits far more than it computes
+const WORKER_THREADS: usize = 2;
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+pub fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        Builder::new_multi_thread()
+            .worker_threads(WORKER_THREADS)
+            .thread_name("rf_rust-io")
+            .enable_all()
+            .build()
+            .expect("cannot start the I/O runtime")
+    })
+}
+
+/// Run `future` on the runtime in the background
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    runtime().spawn(future)
+}
@@ -0,0 +1,244 @@
+//! Scanning a list of channels, a frequency range or the bookmarks, for
+//! activity. The scanner moves on from a quiet channel once its level has
+//! settled, stops on one whose level rises `threshold_db` above the noise
+//! floor, and stays while it remains active, plus `dwell` after it drops.
+//!
+//! The scanner only decides; the receiver tunes to the channels it returns
+//! and measures their levels.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::bookmarks::Bookmark;
+use crate::dsp::AudioMode;
+use crate::recording::iso_timestamp;
+
+/// Time for the spectrum to show a newly tuned channel
+const SETTLE: Duration = Duration::from_millis(150);
+/// An active channel stays active down to this much below the threshold
+const HYSTERESIS_DB: f32 = 3.0;
+const MAX_HITS: usize = 500;
+const CSV_HEADER: &str = "utc,frequency_hz,name,mode,level_db";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub name: String,
+    pub frequency: f64,
+    pub mode: AudioMode,
+    /// Width in Hz over which the level is measured
+    pub bandwidth: f64,
+}
+
+impl From<&Bookmark> for Channel {
+    fn from(bookmark: &Bookmark) -> Self {
+        Self {
+            name: bookmark.name.clone(),
+            frequency: bookmark.frequency,
+            mode: bookmark.mode,
+            bandwidth: bookmark.bandwidth,
+        }
+    }
+}
+
+/// Channels every `step` from `start` to `stop`, measured over the mode's
+/// passband or the step if narrower
+pub fn range(start: f64, stop: f64, step: f64, mode: AudioMode) -> Vec<Channel> {
+    if step <= 0.0 || stop < start {
+        return Vec::new();
+    }
+    let steps = ((stop - start) / step + 1e-9).floor() as usize;
+    (0..=steps)
+        .map(|i| {
+            let frequency = start + i as f64 * step;
+            Channel {
+                name: format!("{:.4} MHz", frequency / 1e6),
+                frequency,
+                mode,
+                bandwidth: mode.passband().min(step),
+            }
+        })
+        .collect()
+}
+
+/// Activity found on a channel
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub time: SystemTime,
+    pub channel: Channel,
+    /// Highest level above the noise floor while active, in dB
+    pub peak_db: f32,
+    /// How long the channel has been or was active
+    pub duration: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScanState {
+    Stopped,
+    /// Waiting for the level of the current channel to settle
+    Settling { until: Instant },
+    /// Stopped on an active channel, last heard at the time given
+    Active { since: Instant, heard: Instant },
+}
+
+pub struct Scanner {
+    pub channels: Vec<Channel>,
+    /// Channel being listened to
+    pub index: usize,
+    /// Level above the noise floor that stops the scan, in dB
+    pub threshold_db: f32,
+    /// Time to stay on a channel after its activity drops
+    pub dwell: Duration,
+    pub state: ScanState,
+    /// Hits, newest last
+    pub hits: Vec<Hit>,
+    /// Completed passes over the channels
+    pub sweeps: u64,
+    log: Option<File>,
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self {
+            channels: Vec::new(),
+            index: 0,
+            threshold_db: 10.0,
+            dwell: Duration::from_secs(2),
+            state: ScanState::Stopped,
+            hits: Vec::new(),
+            sweeps: 0,
+            log: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state != ScanState::Stopped
+    }
+
+    pub fn current(&self) -> Option<&Channel> {
+        self.channels.get(self.index).filter(|_| self.is_running())
+    }
+
+    /// Scan `channels` from the first, returning it to tune to
+    pub fn start(&mut self, channels: Vec<Channel>, now: Instant) -> Option<&Channel> {
+        self.channels = channels;
+        self.index = 0;
+        self.sweeps = 0;
+        self.state = match self.channels.is_empty() {
+            true => ScanState::Stopped,
+            false => ScanState::Settling { until: now + SETTLE },
+        };
+        self.current()
+    }
+
+    pub fn stop(&mut self) {
+        self.state = ScanState::Stopped;
+    }
+
+    /// Keep measuring the current channel until `until`, as after retuning
+    /// the hardware
+    pub fn hold(&mut self, until: Instant) {
+        if let ScanState::Settling { until: settle } = &mut self.state {
+            *settle = (*settle).max(until);
+        }
+    }
+
+    /// Leave the current channel whatever its level, returning the next
+    pub fn skip(&mut self, now: Instant) -> Option<&Channel> {
+        if !self.is_running() {
+            return None;
+        }
+        self.advance(now);
+        self.current()
+    }
+
+    /// Take the current channel's level above the noise floor, in dB,
+    /// returning the next channel when the scanner moves on
+    pub fn update(&mut self, level_db: f32, now: Instant) -> Option<&Channel> {
+        match self.state {
+            ScanState::Stopped => None,
+            ScanState::Settling { until } if now < until => None,
+            ScanState::Settling { .. } if level_db >= self.threshold_db => {
+                self.state = ScanState::Active { since: now, heard: now };
+                self.record_hit(level_db);
+                None
+            }
+            ScanState::Settling { .. } => {
+                self.advance(now);
+                self.current()
+            }
+            ScanState::Active { since, heard } => {
+                if level_db >= self.threshold_db - HYSTERESIS_DB {
+                    self.state = ScanState::Active { since, heard: now };
+                    if let Some(hit) = self.hits.last_mut() {
+                        hit.peak_db = hit.peak_db.max(level_db);
+                        hit.duration = now - since;
+                    }
+                    None
+                } else if now - heard >= self.dwell {
+                    self.advance(now);
+                    self.current()
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn advance(&mut self, now: Instant) {
+        self.index += 1;
+        if self.index >= self.channels.len() {
+            self.index = 0;
+            self.sweeps += 1;
+        }
+        self.state = ScanState::Settling { until: now + SETTLE };
+    }
+
+    fn record_hit(&mut self, level_db: f32) {
+        let Some(channel) = self.channels.get(self.index) else {
+            return;
+        };
+        let hit = Hit {
+            time: SystemTime::now(),
+            channel: channel.clone(),
+            peak_db: level_db,
+            duration: Duration::ZERO,
+        };
+        if let Some(log) = &mut self.log {
+            let name = hit.channel.name.replace(',', " ");
+            let time = iso_timestamp(hit.time);
+            if writeln!(log, "{},{:.0},{},{},{:.1}", time, channel.frequency, name, channel.mode, level_db).is_err() {
+                self.log = None;
+            }
+        }
+        if self.hits.len() == MAX_HITS {
+            self.hits.remove(0);
+        }
+        self.hits.push(hit);
+    }
+
+    /// Append each hit to the CSV file at `path`, or stop logging with `None`
+    pub fn set_log(&mut self, path: Option<&str>) -> io::Result<()> {
+        self.log = match path {
+            Some(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "{}", CSV_HEADER)?;
+                }
+                Some(file)
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,610 @@
+//! A small scripting language for automating the receiver, one command per
+//! line with `#` comments:
+//!
+//! ```text
+//! # Record whichever of these channels is active
+//! repeat
+//!   for f in 145.500M 145.525M 145.550M 145.575M 145.600M
+//!     tune $f
+//!     wait 0.3
+//!     if snr > 10
+//!       log active on $f at $power dBFS
+//!       record 30
+//!     end
+//!   end
+//! end
+//! ```
+//!
+//! Commands:
+//!
+//! - `tune HZ`, `mode fm|am|usb|lsb`, `gain DB`
+//! - `wait SECS`, `wait while CONDITION` and `wait event DECODER [SECS]`,
+//!   which sets `$event` to the event's JSON, or to nothing on a timeout
+//! - `record SECS`, an IQ recording of that length
+//! - `log TEXT`, `set NAME VALUE` and `stop`
+//! - `if CONDITION`, `else`, `repeat [N]` and `for NAME in VALUE...`, each
+//!   closed by `end`
+//!
+//! A condition compares two values with `<`, `<=`, `>`, `>=`, `==` or
+//! `!=`. `power` is the level of the active VFO's channel in dBFS and `snr`
+//! its level above the noise floor in dB. `$NAME` in any argument is the
+//! variable's value, and `$power` and `$snr` the levels. Quotes around a
+//! value are dropped, so `""` is nothing. Numbers take a `k`, `M` or `G`
+//! suffix.
+//!
+//! A script runs alongside the receiver, a step each update, so a wait
+//! never holds up the display.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::dsp::AudioMode;
+
+/// Commands run per step at most, so a loop without a wait cannot hang the receiver
+const MAX_OPS_PER_STEP: usize = 10_000;
+/// Decoder events kept for `wait event`
+const MAX_EVENTS: usize = 100;
+
+/// What a script controls
+pub trait Host {
+    fn tune(&mut self, hz: f64);
+    fn set_mode(&mut self, mode: AudioMode);
+    fn set_gain(&mut self, db: f64);
+    /// Level of the active VFO's channel in dBFS
+    fn power(&self) -> f32;
+    /// Level of the active VFO's channel above the noise floor in dB
+    fn snr(&self) -> f32;
+    fn start_recording(&mut self) -> Result<(), String>;
+    fn stop_recording(&mut self);
+    fn log(&mut self, text: &str);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Compare {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Clone, Debug)]
+struct Condition {
+    left: String,
+    compare: Compare,
+    right: String,
+}
+
+#[derive(Clone, Debug)]
+enum Loop {
+    Repeat(Option<String>),
+    For { name: String, values: Vec<String> },
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Tune(String),
+    Mode(String),
+    Gain(String),
+    Wait(String),
+    WaitWhile(Condition),
+    WaitEvent { decoder: String, timeout: Option<String> },
+    Record(bool),
+    Log(String),
+    Set(String, String),
+    Stop,
+    /// Continue at the op given when the condition is false
+    JumpUnless(Condition, usize),
+    Jump(usize),
+    /// Enter a loop, restarting its count
+    LoopStart(usize),
+    /// Take the loop's next pass, or leave it for the op given
+    Next { slot: usize, kind: Loop, exit: usize },
+}
+
+enum Waiting {
+    Until(Instant),
+    While(Condition),
+    Event { decoder: String, until: Option<Instant> },
+}
+
+/// Blocks open while parsing, with the op to patch when they close
+enum Open {
+    If(usize),
+    Else(usize),
+    Loop(usize),
+}
+
+pub struct Script {
+    ops: Vec<Op>,
+    /// Source line of each op, for errors
+    lines: Vec<usize>,
+    pc: usize,
+    /// Passes taken by each loop
+    passes: Vec<usize>,
+    vars: HashMap<String, String>,
+    waiting: Option<Waiting>,
+    events: VecDeque<(String, String)>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut ops = Vec::new();
+        let mut lines = Vec::new();
+        let mut open: Vec<(Open, usize)> = Vec::new();
+        let mut loops = 0;
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((&command, args)) = words.split_first() else {
+                continue;
+            };
+            let error = |message: &str| format!("line {}: {}", number, message);
+            let rest = line[command.len()..].trim().to_string();
+            let one = |what: &str| match args {
+                [arg] => Ok(arg.to_string()),
+                _ => Err(error(&format!("`{}` takes {}", command, what))),
+            };
+            let op = match command {
+                "tune" => Op::Tune(one("a frequency")?),
+                "mode" => Op::Mode(one("fm, am, usb or lsb")?),
+                "gain" => Op::Gain(one("a gain in dB")?),
+                "wait" => match args {
+                    [secs] => Op::Wait(secs.to_string()),
+                    ["while", condition @ ..] => Op::WaitWhile(parse_condition(condition).map_err(|e| error(&e))?),
+                    ["event", decoder] => Op::WaitEvent { decoder: decoder.to_string(), timeout: None },
+                    ["event", decoder, secs] => Op::WaitEvent { decoder: decoder.to_string(), timeout: Some(secs.to_string()) },
+                    _ => return Err(error("`wait` takes SECS, `while CONDITION` or `event DECODER [SECS]`")),
+                },
+                "record" => {
+                    let secs = one("a length in seconds")?;
+                    for op in [Op::Record(true), Op::Wait(secs), Op::Record(false)] {
+                        ops.push(op);
+                        lines.push(number);
+                    }
+                    continue;
+                }
+                "log" => Op::Log(rest),
+                "set" => match args {
+                    [name, ..] => Op::Set(name.to_string(), rest[name.len()..].trim().to_string()),
+                    [] => return Err(error("`set` takes a name and a value")),
+                },
+                "stop" => Op::Stop,
+                "if" => {
+                    open.push((Open::If(ops.len()), number));
+                    Op::JumpUnless(parse_condition(args).map_err(|e| error(&e))?, 0)
+                }
+                "else" => {
+                    let Some((Open::If(at), _)) = open.pop() else {
+                        return Err(error("`else` without `if`"));
+                    };
+                    let next = ops.len();
+                    open.push((Open::Else(next), number));
+                    patch(&mut ops, at, next + 1);
+                    Op::Jump(0)
+                }
+                "repeat" | "for" => {
+                    let kind = match (command, args) {
+                        ("repeat", []) => Loop::Repeat(None),
+                        ("repeat", [count]) => Loop::Repeat(Some(count.to_string())),
+                        ("for", [name, "in", values @ ..]) if !values.is_empty() => Loop::For {
+                            name: name.to_string(),
+                            values: values.iter().map(|v| v.to_string()).collect(),
+                        },
+                        ("repeat", _) => return Err(error("`repeat` takes a count or nothing to repeat forever")),
+                        _ => return Err(error("`for` takes `NAME in VALUE...`")),
+                    };
+                    ops.push(Op::LoopStart(loops));
+                    lines.push(number);
+                    open.push((Open::Loop(ops.len()), number));
+                    loops += 1;
+                    Op::Next { slot: loops - 1, kind, exit: 0 }
+                }
+                "end" => match open.pop() {
+                    Some((Open::If(at) | Open::Else(at), _)) => {
+                        let next = ops.len();
+                        patch(&mut ops, at, next);
+                        continue;
+                    }
+                    Some((Open::Loop(at), _)) => {
+                        let next = ops.len();
+                        patch(&mut ops, at, next + 1);
+                        Op::Jump(at)
+                    }
+                    None => return Err(error("`end` without a block to close")),
+                },
+                _ => return Err(error(&format!("unknown command `{}`", command))),
+            };
+            ops.push(op);
+            lines.push(number);
+        }
+        if let Some((_, line)) = open.pop() {
+            return Err(format!("line {}: block not closed with `end`", line));
+        }
+        Ok(Self {
+            ops,
+            lines,
+            pc: 0,
+            passes: vec![0; loops],
+            vars: HashMap::new(),
+            waiting: None,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pc >= self.ops.len()
+    }
+
+    /// Pass on an event from `decoder` for `wait event`
+    pub fn event(&mut self, decoder: &str, json: &str) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back((decoder.to_string(), json.to_string()));
+    }
+
+    /// Run until the script waits or ends, returning whether it is still
+    /// running. An error ends the script.
+    pub fn step(&mut self, host: &mut dyn Host, now: Instant) -> Result<bool, String> {
+        let result = self.run(host, now);
+        if result.is_err() {
+            self.pc = self.ops.len();
+        }
+        result.map(|()| !self.is_finished())
+    }
+
+    fn run(&mut self, host: &mut dyn Host, now: Instant) -> Result<(), String> {
+        if !self.still_waiting(host, now)? {
+            self.waiting = None;
+        } else {
+            return Ok(());
+        }
+        for _ in 0..MAX_OPS_PER_STEP {
+            let Some(op) = self.ops.get(self.pc).cloned() else {
+                return Ok(());
+            };
+            let line = self.lines[self.pc];
+            let error = |e: String| format!("line {}: {}", line, e);
+            self.pc += 1;
+            match op {
+                Op::Tune(hz) => host.tune(self.number(&hz, host).map_err(error)?),
+                Op::Mode(mode) => {
+                    let mode = self.expand(&mode, host);
+                    host.set_mode(AudioMode::parse(&mode).ok_or_else(|| error(format!("no mode `{}`", mode)))?);
+                }
+                Op::Gain(db) => host.set_gain(self.number(&db, host).map_err(error)?),
+                Op::Wait(secs) => {
+                    let secs = self.number(&secs, host).map_err(error)?;
+                    let secs = Some(secs).filter(|s| s.is_finite() && *s >= 0.0).ok_or_else(|| error("negative wait".to_string()))?;
+                    self.waiting = Some(Waiting::Until(now + Duration::from_secs_f64(secs)));
+                }
+                Op::WaitWhile(condition) => self.waiting = Some(Waiting::While(condition)),
+                Op::WaitEvent { decoder, timeout } => {
+                    let until = match timeout {
+                        Some(secs) => Some(now + Duration::from_secs_f64(self.number(&secs, host).map_err(error)?.max(0.0))),
+                        None => None,
+                    };
+                    self.events.clear();
+                    self.waiting = Some(Waiting::Event { decoder, until });
+                }
+                Op::Record(true) => host.start_recording().map_err(error)?,
+                Op::Record(false) => host.stop_recording(),
+                Op::Log(text) => host.log(&self.expand(&text, host)),
+                Op::Set(name, value) => {
+                    let value = self.expand(&value, host);
+                    self.vars.insert(name, value);
+                }
+                Op::Stop => self.pc = self.ops.len(),
+                Op::JumpUnless(condition, target) => {
+                    if !self.holds(&condition, host).map_err(error)? {
+                        self.pc = target;
+                    }
+                }
+                Op::Jump(target) => self.pc = target,
+                Op::LoopStart(slot) => self.passes[slot] = 0,
+                Op::Next { slot, kind, exit } => {
+                    let pass = self.passes[slot];
+                    let more = match kind {
+                        Loop::Repeat(None) => true,
+                        Loop::Repeat(Some(count)) => (pass as f64) < self.number(&count, host).map_err(error)?,
+                        Loop::For { name, values } => match values.get(pass) {
+                            Some(value) => {
+                                let value = self.expand(value, host);
+                                self.vars.insert(name, value);
+                                true
+                            }
+                            None => false,
+                        },
+                    };
+                    self.passes[slot] += 1;
+                    if !more {
+                        self.pc = exit;
+                    }
+                }
+            }
+            if self.waiting.is_some() {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn still_waiting(&mut self, host: &dyn Host, now: Instant) -> Result<bool, String> {
+        Ok(match &self.waiting {
+            None => false,
+            Some(Waiting::Until(until)) => now < *until,
+            Some(Waiting::While(condition)) => {
+                let condition = condition.clone();
+                self.holds(&condition, host)?
+            }
+            Some(Waiting::Event { decoder, until }) => {
+                if let Some((_, json)) = self.events.iter().find(|(name, _)| name == decoder) {
+                    self.vars.insert("event".to_string(), json.clone());
+                    false
+                } else if until.is_some_and(|until| now >= until) {
+                    self.vars.insert("event".to_string(), String::new());
+                    false
+                } else {
+                    true
+                }
+            }
+        })
+    }
+
+    /// `text` with each `$NAME` replaced by its value
+    fn expand(&self, text: &str, host: &dyn Host) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(at) = rest.find('$') {
+            out.push_str(&rest[..at]);
+            let name_len = rest[at + 1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - at - 1);
+            let name = &rest[at + 1..at + 1 + name_len];
+            match name {
+                "" => out.push('$'),
+                "power" => out.push_str(&format!("{:.1}", host.power())),
+                "snr" => out.push_str(&format!("{:.1}", host.snr())),
+                _ => out.push_str(self.vars.get(name).map(String::as_str).unwrap_or("")),
+            }
+            rest = &rest[at + 1 + name_len..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn value(&self, text: &str, host: &dyn Host) -> String {
+        match text {
+            "power" => host.power().to_string(),
+            "snr" => host.snr().to_string(),
+            _ => self.expand(text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text), host),
+        }
+    }
+
+    fn number(&self, text: &str, host: &dyn Host) -> Result<f64, String> {
+        let value = self.value(text, host);
+        parse_number(&value).ok_or_else(|| format!("`{}` is not a number", value))
+    }
+
+    fn holds(&self, condition: &Condition, host: &dyn Host) -> Result<bool, String> {
+        let (left, right) = (self.value(&condition.left, host), self.value(&condition.right, host));
+        Ok(match (parse_number(&left), parse_number(&right)) {
+            (Some(left), Some(right)) => match condition.compare {
+                Compare::Less => left < right,
+                Compare::LessOrEqual => left <= right,
+                Compare::Greater => left > right,
+                Compare::GreaterOrEqual => left >= right,
+                Compare::Equal => left == right,
+                Compare::NotEqual => left != right,
+            },
+            // Anything but a number compares as text, for equality only
+            _ => match condition.compare {
+                Compare::Equal => left == right,
+                Compare::NotEqual => left != right,
+                _ => return Err(format!("cannot compare `{}` with `{}`", left, right)),
+            },
+        })
+    }
+}
+
+fn parse_condition(words: &[&str]) -> Result<Condition, String> {
+    let [left, compare, right] = words else {
+        return Err("a condition is `VALUE OP VALUE`, such as `snr > 10`".to_string());
+    };
+    let compare = match *compare {
+        "<" => Compare::Less,
+        "<=" => Compare::LessOrEqual,
+        ">" => Compare::Greater,
+        ">=" => Compare::GreaterOrEqual,
+        "==" => Compare::Equal,
+        "!=" => Compare::NotEqual,
+        other => return Err(format!("no comparison `{}`", other)),
+    };
+    Ok(Condition { left: left.to_string(), compare, right: right.to_string() })
+}
+
+/// Point the jump of the op at `at`, which opened a block, at `target`
+fn patch(ops: &mut [Op], at: usize, target: usize) {
+    match &mut ops[at] {
+        Op::JumpUnless(_, to) | Op::Jump(to) | Op::Next { exit: to, .. } => *to = target,
+        _ => {}
+    }
+}
+
+/// A number with an optional `k`, `M` or `G` suffix
+fn parse_number(text: &str) -> Option<f64> {
+    let (number, scale) = match text.chars().last()? {
+        'k' | 'K' => (&text[..text.len() - 1], 1e3),
+        'M' => (&text[..text.len() - 1], 1e6),
+        'G' | 'g' => (&text[..text.len() - 1], 1e9),
+        _ => (text, 1.0),
+    };
+    number.parse::<f64>().ok().filter(|n| n.is_finite()).map(|n| n * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Radio {
+        calls: Vec<String>,
+        snr: f32,
+        recording: bool,
+    }
+
+    impl Host for Radio {
+        fn tune(&mut self, hz: f64) {
+            self.calls.push(format!("tune {}", hz));
+        }
+        fn set_mode(&mut self, mode: AudioMode) {
+            self.calls.push(format!("mode {:?}", mode));
+        }
+        fn set_gain(&mut self, db: f64) {
+            self.calls.push(format!("gain {}", db));
+        }
+        fn power(&self) -> f32 {
+            -40.0
+        }
+        fn snr(&self) -> f32 {
+            self.snr
+        }
+        fn start_recording(&mut self) -> Result<(), String> {
+            self.recording = true;
+            self.calls.push("record".to_string());
+            Ok(())
+        }
+        fn stop_recording(&mut self) {
+            self.recording = false;
+            self.calls.push("stop recording".to_string());
+        }
+        fn log(&mut self, text: &str) {
+            self.calls.push(format!("log {}", text));
+        }
+    }
+
+    /// Run `text` to its end or first wait
+    fn run(text: &str, radio: &mut Radio) -> Result<bool, String> {
+        Script::parse(text)?.step(radio, Instant::now())
+    }
+
+    #[test]
+    fn numbers_take_suffixes() {
+        assert_eq!(parse_number("145.5M"), Some(145.5e6));
+        assert_eq!(parse_number("12.5k"), Some(12.5e3));
+        assert_eq!(parse_number("1.2G"), Some(1.2e9));
+        assert_eq!(parse_number("-3"), Some(-3.0));
+        for bad in ["", "M", "1m", "inf", "NaN", "ten"] {
+            assert_eq!(parse_number(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn runs_commands_in_order() {
+        let mut radio = Radio::default();
+        let script = "# comment\ntune 145.5M  # trailing\nmode usb\ngain 20\nset who world\nlog hello $who at $power dBFS$\n";
+        assert_eq!(run(script, &mut radio), Ok(false));
+        assert_eq!(radio.calls, ["tune 145500000", "mode Usb", "gain 20", "log hello world at -40.0 dBFS$"]);
+    }
+
+    #[test]
+    fn blocks_nest_with_the_innermost_else() {
+        let script = "
+            for f in 1k 2k 3k
+              if $f == 2k
+                log two
+              else
+                if snr > 10
+                  log loud $f
+                else
+                  log quiet $f
+                end
+              end
+            end
+            log done";
+        let mut radio = Radio { snr: 20.0, ..Radio::default() };
+        assert_eq!(run(script, &mut radio), Ok(false));
+        assert_eq!(radio.calls, ["log loud 1k", "log two", "log loud 3k", "log done"]);
+
+        let mut radio = Radio::default();
+        run("repeat 2\n  repeat 3\n    log x\n  end\n  log y\nend", &mut radio).unwrap();
+        assert_eq!(radio.calls, ["log x", "log x", "log x", "log y", "log x", "log x", "log x", "log y"]);
+    }
+
+    #[test]
+    fn conditions_compare_numbers_then_text() {
+        let mut radio = Radio::default();
+        run("set a 1.0\nif $a == 1\nlog number\nend\nset b fm\nif $b != \"am\"\nlog text\nend\nif \"\" == $none\nlog empty\nend", &mut radio)
+            .unwrap();
+        assert_eq!(radio.calls, ["log number", "log text", "log empty"]);
+        assert_eq!(run("set b fm\nif $b > 1\nend", &mut radio), Err("line 2: cannot compare `fm` with `1`".to_string()));
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        let error = |text: &str| Script::parse(text).err().unwrap();
+        assert_eq!(error("tune\n"), "line 1: `tune` takes a frequency");
+        assert_eq!(error("log ok\nfly away"), "line 2: unknown command `fly`");
+        assert_eq!(error("else"), "line 1: `else` without `if`");
+        assert_eq!(error("log\nend"), "line 2: `end` without a block to close");
+        assert_eq!(error("if snr > 1\nrepeat\nlog x\nend"), "line 1: block not closed with `end`");
+        assert_eq!(error("if snr >\nend"), "line 1: a condition is `VALUE OP VALUE`, such as `snr > 10`");
+        assert_eq!(error("wait while snr => 3"), "line 1: no comparison `=>`");
+        assert_eq!(error("for f 1 2\nend"), "line 1: `for` takes `NAME in VALUE...`");
+
+        let mut radio = Radio::default();
+        let mut script = Script::parse("log a\ntune $nowhere\nlog b").unwrap();
+        assert_eq!(script.step(&mut radio, Instant::now()), Err("line 2: `` is not a number".to_string()));
+        assert!(script.is_finished());
+        assert_eq!(radio.calls, ["log a"]);
+        assert_eq!(run("mode cw", &mut radio), Err("line 1: no mode `cw`".to_string()));
+        assert_eq!(run("wait -1", &mut radio), Err("line 1: negative wait".to_string()));
+    }
+
+    #[test]
+    fn a_loop_without_a_wait_yields() {
+        let mut radio = Radio::default();
+        let mut script = Script::parse("repeat\n  set x 1\nend").unwrap();
+        assert_eq!(script.step(&mut radio, Instant::now()), Ok(true));
+        assert_eq!(script.step(&mut radio, Instant::now()), Ok(true));
+
+        let mut script = Script::parse("repeat\n  log x\n  stop\nend\nlog never").unwrap();
+        assert_eq!(script.step(&mut radio, Instant::now()), Ok(false));
+        assert_eq!(radio.calls, ["log x"]);
+    }
+
+    #[test]
+    fn waits_hold_until_their_time() {
+        let start = Instant::now();
+        let mut radio = Radio::default();
+        let mut script = Script::parse("record 2\nlog after").unwrap();
+        assert_eq!(script.step(&mut radio, start), Ok(true));
+        assert!(radio.recording);
+        assert_eq!(script.step(&mut radio, start + Duration::from_millis(1999)), Ok(true));
+        assert!(radio.recording);
+        assert_eq!(script.step(&mut radio, start + Duration::from_secs(2)), Ok(false));
+        assert_eq!(radio.calls, ["record", "stop recording", "log after"]);
+
+        let mut script = Script::parse("wait while snr < 10\nlog open").unwrap();
+        assert_eq!(script.step(&mut radio, start), Ok(true));
+        radio.snr = 12.0;
+        assert_eq!(script.step(&mut radio, start), Ok(false));
+        assert_eq!(radio.calls.last().unwrap(), "log open");
+    }
+
+    #[test]
+    fn waits_for_events_or_a_timeout() {
+        let start = Instant::now();
+        let mut radio = Radio::default();
+        let mut script = Script::parse("wait event ais 5\nlog got $event\nwait event ais 5\nlog got \"$event\"").unwrap();
+        assert_eq!(script.step(&mut radio, start), Ok(true));
+        script.event("adsb", "{\"icao\":1}");
+        assert_eq!(script.step(&mut radio, start), Ok(true));
+        script.event("ais", "{\"mmsi\":2}");
+        assert_eq!(script.step(&mut radio, start), Ok(true));
+        assert_eq!(script.step(&mut radio, start + Duration::from_secs(5)), Ok(false));
+        assert_eq!(radio.calls, ["log got {\"mmsi\":2}", "log got \"\""]);
+    }
+}
@@ -0,0 +1,253 @@
+//! The state of the TUI when it was last closed, kept in `session.toml`
+//! next to the config file so that the next launch picks up where it left
+//! off:
+//!
+//! ```text
+//! [receiver]
+//! device = "demo"
+//! frequency = 145.7e6
+//! sample_rate = 1e6
+//! gain = 30
+//! active_vfo = 1
+//!
+//! [vfo 1]
+//! mode = "fm"
+//! offset = 0
+//! route = "decoders"
+//!
+//! [layout]
+//! tab = 0
+//! view = "spectrum"
+//! full_screen = false
+//! zoom = 1
+//! pan = 0
+//! controls = 30
+//! samples = 20
+//! marker1 = 145.71e6
+//!
+//! [spectrum]
+//! fft_size = 2048
+//! window = "hann"
+//! averages = 4
+//! colormap = "theme"
+//! ```
+//!
+//! `device` is as in presets. `route` is where a VFO's audio goes, `muted`,
+//! `monitor` or `decoders`. `controls` and `samples` are the shares of
+//! the screen in percent of the controls panel and of the samples below
+//! the spectrum. A marker that is not set is left out. `colormap` is that
+//! of the waterfall, `theme` for the theme's own.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::dsp::AudioMode;
+use crate::dsp::measure::Window;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    /// rtl_tcp server, `None` for the demo source
+    pub device: Option<String>,
+    pub frequency: f64,
+    pub sample_rate: f64,
+    pub gain: f64,
+    pub vfos: Vec<SessionVfo>,
+    pub active_vfo: usize,
+    pub tab: usize,
+    /// Name of the view below the spectrum
+    pub view: String,
+    pub full_screen: bool,
+    pub zoom: f64,
+    pub pan_hz: f64,
+    pub markers: [Option<f64>; 2],
+    /// Width of the controls and height of the samples panel in percent,
+    /// `None` in sessions saved before they could be changed
+    pub controls_percent: Option<u16>,
+    pub samples_percent: Option<u16>,
+    /// Spectrum settings, `None` in sessions saved before they could be
+    /// changed
+    pub fft_size: Option<usize>,
+    pub window: Option<Window>,
+    pub averages: Option<usize>,
+    pub colormap: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionVfo {
+    pub mode: AudioMode,
+    /// Offset from the center frequency
+    pub offset_hz: f64,
+    /// Name of where its audio goes, `None` in sessions saved before it was
+    /// kept
+    pub route: Option<String>,
+}
+
+/// `session.toml` beside the config file
+pub fn path() -> Option<PathBuf> {
+    Some(Config::path()?.with_file_name("session.toml"))
+}
+
+/// The saved session, `None` when the file does not exist
+pub fn load(path: &Path) -> io::Result<Option<Session>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    parse(&text)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+pub fn parse(text: &str) -> Result<Session, String> {
+    let config = Config::parse(text)?;
+    let number = |key: &str| {
+        config
+            .get(key)
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| v.is_finite())
+            .ok_or(format!("{} must be a number", key))
+    };
+    let index = |key: &str| config.get(key).map_or(Ok(0), |v| v.parse::<usize>().map_err(|_| format!("{} must be a count", key)));
+    let vfos = config
+        .sections()
+        .iter()
+        .filter(|name| name.starts_with("vfo "))
+        .map(|name| {
+            let mode = config
+                .get(&format!("{}.mode", name))
+                .and_then(AudioMode::parse)
+                .ok_or(format!("[{}] mode must be fm, am, usb or lsb", name))?;
+            Ok(SessionVfo {
+                mode,
+                offset_hz: number(&format!("{}.offset", name))?,
+                route: config.get(&format!("{}.route", name)).map(String::from),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let marker = |key: &str| config.get(key).map(|_| number(key)).transpose();
+    let percent = |key: &str| {
+        config.get(key).map(|v| v.parse::<u16>().ok().filter(|p| *p <= 100).ok_or(format!("{} must be a percentage", key))).transpose()
+    };
+    let count = |key: &str| config.get(key).map(|v| v.parse::<usize>().map_err(|_| format!("{} must be a count", key))).transpose();
+    let window = config
+        .get("spectrum.window")
+        .map(|v| Window::parse(v).ok_or(format!("spectrum.window must be hann, rectangular, blackman-harris or flat-top, not `{}`", v)))
+        .transpose()?;
+    Ok(Session {
+        device: config.get("receiver.device").filter(|d| *d != "demo").map(String::from),
+        frequency: number("receiver.frequency")?,
+        sample_rate: number("receiver.sample_rate")?,
+        gain: number("receiver.gain")?,
+        vfos,
+        active_vfo: index("receiver.active_vfo")?.saturating_sub(1),
+        tab: index("layout.tab")?,
+        view: config.get("layout.view").unwrap_or("spectrum").to_string(),
+        full_screen: config.get("layout.full_screen") == Some("true"),
+        zoom: number("layout.zoom").unwrap_or(1.0),
+        pan_hz: number("layout.pan").unwrap_or(0.0),
+        markers: [marker("layout.marker1")?, marker("layout.marker2")?],
+        controls_percent: percent("layout.controls")?,
+        samples_percent: percent("layout.samples")?,
+        fft_size: count("spectrum.fft_size")?,
+        window,
+        averages: count("spectrum.averages")?,
+        colormap: config.get("spectrum.colormap").map(String::from),
+    })
+}
+
+/// Write `session` over the file at `path`, creating its directory
+pub fn save(path: &Path, session: &Session) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = format!(
+        "[receiver]\ndevice = \"{}\"\nfrequency = {}\nsample_rate = {}\ngain = {}\nactive_vfo = {}\n",
+        session.device.as_deref().unwrap_or("demo"),
+        session.frequency,
+        session.sample_rate,
+        session.gain,
+        session.active_vfo + 1
+    );
+    for (i, vfo) in session.vfos.iter().enumerate() {
+        text += &format!("\n[vfo {}]\nmode = \"{}\"\noffset = {}\n", i + 1, vfo.mode.to_string().to_lowercase(), vfo.offset_hz);
+        if let Some(route) = &vfo.route {
+            text += &format!("route = \"{}\"\n", route);
+        }
+    }
+    text += &format!(
+        "\n[layout]\ntab = {}\nview = \"{}\"\nfull_screen = {}\nzoom = {}\npan = {}\n",
+        session.tab, session.view, session.full_screen, session.zoom, session.pan_hz
+    );
+    if let Some(percent) = session.controls_percent {
+        text += &format!("controls = {}\n", percent);
+    }
+    if let Some(percent) = session.samples_percent {
+        text += &format!("samples = {}\n", percent);
+    }
+    for (i, marker) in session.markers.iter().enumerate() {
+        if let Some(hz) = marker {
+            text += &format!("marker{} = {}\n", i + 1, hz);
+        }
+    }
+    text += "\n[spectrum]\n";
+    if let Some(size) = session.fft_size {
+        text += &format!("fft_size = {}\n", size);
+    }
+    if let Some(window) = session.window {
+        text += &format!("window = \"{}\"\n", window.name());
+    }
+    if let Some(averages) = session.averages {
+        text += &format!("averages = {}\n", averages);
+    }
+    if let Some(colormap) = &session.colormap {
+        text += &format!("colormap = \"{}\"\n", colormap);
+    }
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_back() {
+        let session = Session {
+            device: Some("localhost:1234".to_string()),
+            frequency: 145.7e6,
+            sample_rate: 1e6,
+            gain: 30.0,
+            vfos: vec![
+                SessionVfo { mode: AudioMode::Fm, offset_hz: 0.0, route: Some("decoders".to_string()) },
+                SessionVfo { mode: AudioMode::Usb, offset_hz: -12.5e3, route: Some("monitor".to_string()) },
+            ],
+            active_vfo: 1,
+            tab: 2,
+            view: "scope".to_string(),
+            full_screen: true,
+            zoom: 4.0,
+            pan_hz: 1e3,
+            markers: [Some(145.71e6), None],
+            controls_percent: Some(30),
+            samples_percent: Some(20),
+            fft_size: Some(2048),
+            window: Some(Window::Hann),
+            averages: Some(4),
+            colormap: Some("theme".to_string()),
+        };
+        let path = std::env::temp_dir().join(format!("rf_rust_session_test_{}.toml", std::process::id()));
+        save(&path, &session).unwrap();
+        let loaded = load(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), Some(session));
+    }
+
+    #[test]
+    fn loads_sessions_from_before_routes() {
+        let session = parse("[receiver]\nfrequency = 1e8\nsample_rate = 1e6\ngain = 0\n\n[vfo 1]\nmode = \"am\"\noffset = 5e3\n").unwrap();
+        assert_eq!(session.device, None);
+        assert_eq!(session.vfos, [SessionVfo { mode: AudioMode::Am, offset_hz: 5e3, route: None }]);
+    }
+}
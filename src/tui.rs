@@ -1,432 +1,5097 @@
-use std::io::{self, stdout};
-use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+use std::io::{self, stdout, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent, MouseEventKind},
+    cursor::Show,
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
+    symbols::Marker,
+    text::{Line, Span, Text},
     widgets::{
-        Block, Borders, List, ListItem, Paragraph, Tabs, Wrap,
+        block::{Position, Title},
+        canvas::{self, Canvas, Points},
+        Axis, Block, Borders, Cell, Chart, Dataset, Gauge, GraphType, Clear, List, ListItem, Paragraph, Row, Sparkline,
+        Table, Tabs, Wrap,
     },
     Frame, Terminal,
 };
 
-// Using mock SDR functionality for demo
+use log::Level;
 use num_complex::Complex32;
 
-/// Application state
-pub struct App {
-    pub should_quit: bool,
-    pub current_tab: usize,
+mod ascii;
+mod bench;
+mod dialog;
+mod export;
+mod font;
+pub mod headless;
+mod keymap;
+mod palette;
+mod theme;
+
+use dialog::{Answer, Dialog, Outcome};
+use keymap::{ACTIONS, Action, CATEGORIES, Context, Key, Keymap};
+use palette::Palette;
+use theme::{COLORMAPS, Theme};
+use crate::args::{Options, parse_hz};
+use rf_rust::config::Config;
+use rf_rust::decoders::format_utc_time;
+use rf_rust::decoders::psk::PskMode;
+use rf_rust::decoders::same::Severity;
+use rf_rust::device::Device;
+use rf_rust::decoders::utc_date_time;
+use rf_rust::dsp::measure::{Window, S9_DBM};
+use rf_rust::dsp::{AudioMode, SampleHistogram, PskOrder};
+use rf_rust::engine::{
+    AIS_NMEA_LOG, AudioRoute, BURST_DIR, CONSTELLATION_BAUDS, Constellation, Engine, ISM_JSON_LOG, MAX_VFOS,
+    MEASURE_BANDWIDTHS, MEASURE_CSV, NAVTEX_LOG, POWER_HISTORY_SPANS, Vfo,
+};
+use rf_rust::error::RfError;
+use rf_rust::net::remote::RemoteSource;
+use rf_rust::presets::{self, Preset};
+use rf_rust::bandplan::{self, Region};
+use rf_rust::bookmarks::{self, Bookmark};
+use rf_rust::scanner::{self, Channel, ScanState};
+use rf_rust::logging::{self, Entry};
+use rf_rust::runtime;
+use rf_rust::session::{self, Session, SessionVfo};
+use rf_rust::net::rtp::AudioEncoding;
+use rf_rust::recording::{file_timestamp, format_size};
+use rf_rust::recording::iq::Container;
+use rf_rust::recording::playback::FilePlayer;
+use rf_rust::recording::schedule::JobState;
+
+/// Directory for spectrum and screen exports
+const EXPORT_DIR: &str = "exports";
+/// FFT sizes and spectra averaged the settings tab offers
+const FFT_SIZES: [usize; 6] = [256, 512, 1024, 2048, 4096, 8192];
+const SPECTRUM_AVERAGES: [usize; 5] = [1, 2, 4, 8, 16];
+/// Tabs of the controls panel, the settings last
+const TABS: usize = 5;
+const SETTINGS_TAB: usize = 4;
+/// Seek steps in seconds for the short and long transport keys
+const SEEK_SHORT_SECS: f64 = 10.0;
+const SEEK_LONG_SECS: f64 = 60.0;
+/// Scope window lengths in samples
+const SCOPE_TIMEBASES: [usize; 6] = [64, 128, 256, 512, 1024, 2048];
+const SCOPE_TRIGGER_STEP: f32 = 0.05;
+/// Bottom of the spectrum plot's dB axis
+const SPECTRUM_MIN_DB: f64 = -120.0;
+/// Waterfall lines kept for scrollback, and the bounds of those set in the
+/// config
+const WATERFALL_LINES: usize = 600;
+const WATERFALL_LINES_RANGE: RangeInclusive<usize> = 10..=100_000;
+/// Waterfall colour scale above the noise floor
+const WATERFALL_RANGE_DB: f32 = 60.0;
+/// Approximate spacing of frequency ticks on the spectrum, in columns
+const TICK_COLUMNS: u16 = 14;
+/// Past spectrum traces kept for the persistence display
+const PERSISTENCE_TRACES: usize = 12;
+/// Deepest spectrum zoom, as a fraction of the captured span
+const MAX_ZOOM: f64 = 64.0;
+/// Redraws per second, and the bounds of those set in the config or with
+/// `--fps`, low for slow links
+const DEFAULT_FPS: f64 = 20.0;
+pub const FPS: RangeInclusive<f64> = 1.0..=60.0;
+/// How long the status bar flags an overrun or underrun
+const LOSS_FLASH: Duration = Duration::from_secs(2);
+/// Parameter changes closer together than this undo as one, such as the
+/// steps of a held key
+const HISTORY_SETTLE: Duration = Duration::from_secs(1);
+/// Undo steps kept
+const MAX_HISTORY: usize = 100;
+/// Status messages kept for the messages view
+const MAX_MESSAGES: usize = 500;
+/// Toasts shown at once, the oldest giving way to a new one
+const MAX_TOASTS: usize = 4;
+/// How long a toast shows, errors twice as long
+const TOAST_SECS: Duration = Duration::from_secs(5);
+const TOAST_WIDTH: u16 = 48;
+/// Below this size the panels give way to a single column
+const COMPACT_WIDTH: u16 = 80;
+const COMPACT_HEIGHT: u16 = 24;
+/// Shares of the screen of the controls panel and of the samples below the
+/// spectrum, in percent, and how much each key press moves them
+const DEFAULT_CONTROLS_PERCENT: u16 = 30;
+const MIN_CONTROLS_PERCENT: u16 = 15;
+const MAX_CONTROLS_PERCENT: u16 = 60;
+const DEFAULT_SAMPLES_PERCENT: u16 = 20;
+const MAX_SAMPLES_PERCENT: u16 = 50;
+const LAYOUT_STEP_PERCENT: u16 = 5;
+/// Commands the palette lists at once
+const PALETTE_ROWS: usize = 12;
+/// Narrowest column of the help overlay
+const HELP_COLUMN_WIDTH: u16 = 44;
+/// Tuning steps, one per digit of the frequency readout from 1 Hz up
+const TUNING_STEPS: [f64; 8] = [1.0, 10.0, 100.0, 1e3, 1e4, 1e5, 1e6, 1e7];
+
+/// Content shown in the right-hand panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum View {
+    Spectrum,
+    Ais,
+    Pager,
+    Rtty,
+    Psk,
+    Wspr,
+    Ft8,
+    Dtmf,
+    Cw,
+    Ism,
+    Navtex,
+    /// Decoders registered from outside the crate
+    Plugins,
+    Constellation,
+    Bursts,
+    Measure,
+    Scope,
+    Histogram,
+    Schedule,
+    Presets,
+    Bookmarks,
+    Scanner,
+    /// What the source of samples reports of itself
+    Device,
+    Network,
+    Log,
+    /// Status messages of this session with their times
+    Messages,
+    /// Key bindings and their editor
+    Keys,
+}
+
+impl View {
+    const ALL: [View; 26] = [
+        View::Spectrum,
+        View::Ais,
+        View::Pager,
+        View::Rtty,
+        View::Psk,
+        View::Wspr,
+        View::Ft8,
+        View::Dtmf,
+        View::Cw,
+        View::Ism,
+        View::Navtex,
+        View::Plugins,
+        View::Constellation,
+        View::Bursts,
+        View::Measure,
+        View::Scope,
+        View::Histogram,
+        View::Schedule,
+        View::Presets,
+        View::Bookmarks,
+        View::Scanner,
+        View::Device,
+        View::Network,
+        View::Log,
+        View::Messages,
+        View::Keys,
+    ];
+
+    /// Lower-case name, as saved in the session
+    fn name(self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&v| v == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Signal shown in the scope view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScopeSource {
+    Iq,
+    Audio,
+}
+
+/// Sample history and settings of the time-domain scope
+pub struct Scope {
+    pub source: ScopeSource,
+    /// Index into [`SCOPE_TIMEBASES`]
+    pub timebase: usize,
+    /// Rising-edge trigger level on I or audio, free-running when `None`
+    pub trigger: Option<f32>,
+    iq: VecDeque<Complex32>,
+    audio: VecDeque<f32>,
+}
+
+impl Scope {
+    fn new() -> Self {
+        Self {
+            source: ScopeSource::Iq,
+            timebase: 2,
+            trigger: None,
+            iq: VecDeque::new(),
+            audio: VecDeque::new(),
+        }
+    }
+
+    fn window(&self) -> usize {
+        SCOPE_TIMEBASES[self.timebase]
+    }
+
+    fn push(&mut self, samples: &[Complex32], audio: &[f32]) {
+        let capacity = 2 * SCOPE_TIMEBASES[SCOPE_TIMEBASES.len() - 1];
+        self.iq.extend(samples);
+        self.audio.extend(audio);
+        let excess = self.iq.len().saturating_sub(capacity);
+        self.iq.drain(..excess);
+        let excess = self.audio.len().saturating_sub(capacity);
+        self.audio.drain(..excess);
+    }
+
+    /// The most recent full window as (I or audio, Q) pairs, starting at the last trigger
+    /// crossing if there is one, and whether the trigger fired
+    fn capture(&self) -> (Vec<(f32, f32)>, bool) {
+        let trace: Vec<(f32, f32)> = match self.source {
+            ScopeSource::Iq => self.iq.iter().map(|s| (s.re, s.im)).collect(),
+            ScopeSource::Audio => self.audio.iter().map(|&a| (a, 0.0)).collect(),
+        };
+        let window = self.window().min(trace.len());
+        let last_start = trace.len() - window;
+        let triggered = self.trigger.and_then(|level| {
+            (1..=last_start).rev().find(|&i| trace[i - 1].0 < level && trace[i].0 >= level)
+        });
+        let start = triggered.unwrap_or(last_start);
+        (trace[start..start + window].to_vec(), triggered.is_some())
+    }
+}
+
+/// Parameters that Ctrl+Z and Ctrl+Y step back and forth through
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuning {
     pub frequency: f64,
-    pub sample_rate: f64,
     pub gain: f64,
-    pub is_streaming: bool,
-    pub status_message: String,
-    pub spectrum_data: Vec<f32>,
-    pub sample_buffer: Vec<Complex32>,
+    /// Mode and offset of each VFO
+    pub vfos: Vec<(AudioMode, f64)>,
+}
+
+/// Past and undone [`Tuning`]s, oldest first
+#[derive(Default)]
+pub struct History {
+    pub undo: Vec<Tuning>,
+    pub redo: Vec<Tuning>,
+    /// When the user last changed the tuning
+    changed: Option<Instant>,
+    /// Whether an undo or redo set the tuning, which is not a step itself
+    restored: bool,
+}
+
+/// A log record shown for a while over the screen
+pub struct Toast {
+    pub level: Level,
+    pub message: String,
+    /// Times logged in a row
+    pub count: u32,
+    pub until: Instant,
+}
+
+/// A line of the settings tab
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    FftSize,
+    Window,
+    Averages,
+    Colormap,
+    /// Where the active VFO's audio goes
+    VfoRoute,
+}
+
+impl Setting {
+    const ALL: [Setting; 5] = [Setting::FftSize, Setting::Window, Setting::Averages, Setting::Colormap, Setting::VfoRoute];
+
+    fn label(self) -> &'static str {
+        match self {
+            Setting::FftSize => "FFT size",
+            Setting::Window => "Window",
+            Setting::Averages => "Averaging",
+            Setting::Colormap => "Colormap",
+            Setting::VfoRoute => "VFO audio",
+        }
+    }
+}
+
+/// The item after `current` in `items`, or before it, wrapping around
+fn cycle<T: Copy + PartialEq>(items: &[T], current: T, forward: bool) -> T {
+    let index = items.iter().position(|&item| item == current).unwrap_or(0);
+    let step = if forward { 1 } else { items.len() - 1 };
+    items[(index + step) % items.len()]
 }
 
-// Temporarily removed SdrConfig for testing
+/// What a [`Dialog`] was opened for
+pub enum Purpose {
+    /// Tune to the frequency typed
+    Tune,
+    /// Quit although a recording is running
+    Quit,
+    DeletePreset,
+    DeleteBookmark,
+    ClearHits,
+    /// Cancel the next pending scheduled recording
+    CancelJob,
+    /// Pick the demo signals, one of these rtl_tcp servers or another one
+    ChooseSource(Vec<String>),
+    /// Type the rtl_tcp server to connect to
+    Server,
+    /// Disconnect from the remote receiver and take samples from this one
+    Switch(Option<String>),
+}
+
+/// The terminal UI over the receiver
+pub struct App {
+    pub engine: Engine,
+    pub should_quit: bool,
+    pub current_tab: usize,
+    /// Recent spectrum traces, oldest first, while persistence is on
+    pub persistence: Option<VecDeque<Vec<f32>>>,
+    /// Past spectrum lines with the time they were taken, oldest first, and how many of them to keep
+    pub waterfall: VecDeque<(SystemTime, Vec<f32>)>,
+    pub waterfall_lines: usize,
+    /// Lines back from the newest while the waterfall is paused, `None` when live
+    pub waterfall_scroll: Option<usize>,
+    /// Area of the waterfall on screen, aligned with `spectrum_plot`
+    pub waterfall_area: Rect,
+    /// Show power in dBm instead of dBFS, needs `calibration_db`
+    pub show_dbm: bool,
+    /// Displayed fraction of the captured span, and the display centre relative to `frequency`
+    pub zoom: f64,
+    pub pan_hz: f64,
+    /// Plot area of the spectrum chart on screen, for mouse mapping
+    pub spectrum_plot: Rect,
+    /// Titles of the control tabs, the parameter panel and the VFO readout
+    /// on screen, where the mouse wheel and clicks work
+    pub tab_areas: [Rect; TABS],
+    pub parameter_area: Rect,
+    pub readout_area: Rect,
+    /// Width of the controls panel in percent of the screen
+    pub controls_percent: u16,
+    /// Height of the samples and VFO panels below the spectrum in percent
+    pub samples_percent: u16,
+    /// Column where the mouse button went down on the spectrum, and its last position
+    drag: Option<(u16, u16)>,
+    /// Clicking the spectrum retunes the hardware instead of the demodulator offset
+    pub click_tunes_lo: bool,
+    /// Spectrum and waterfall take the whole terminal
+    pub full_screen: bool,
+    /// Spectrum markers as absolute frequencies, and the one moved by the marker keys
+    pub markers: [Option<f64>; 2],
+    pub active_marker: usize,
+    pub view: View,
+    /// Named setups, recalled with F1 to F9 in order
+    pub presets: Vec<Preset>,
+    /// Preset the presets view acts on
+    pub preset_selected: usize,
+    pub bookmarks: Vec<Bookmark>,
+    /// Position in the bookmarks shown, those with `bookmark_tag` if set
+    pub bookmark_selected: usize,
+    pub bookmark_tag: Option<String>,
+    /// Decoder shown in the plugins view
+    pub plugin_selected: usize,
+    /// Least severe log entries shown in the log view
+    pub log_level: Level,
+    /// ITU region of the band plan named in the status bar
+    pub region: Region,
+    /// Start, stop and step of the range to scan, the span on screen if unset
+    pub scan_range: Option<(f64, f64, f64)>,
+    /// Scan the bookmarks shown instead of the range
+    pub scan_bookmarks: bool,
+    pub history: History,
+    /// Status messages as they changed, newest last
+    pub messages: VecDeque<(SystemTime, String)>,
+    /// Messages scrolled back from the newest in the messages view
+    pub message_scroll: usize,
+    /// Newest last
+    pub toasts: VecDeque<Toast>,
+    /// Id of the last log entry shown as a toast
+    toasted: u64,
+    pub keymap: Keymap,
+    /// Index into [`ACTIONS`] in the keys view
+    pub key_selected: usize,
+    /// Whether the next key pressed is bound to the selected action
+    pub key_capture: bool,
+    /// The dialog taking the keys, while one is open
+    pub dialog: Option<Dialog<Purpose>>,
+    /// Index into [`TUNING_STEPS`] of the step of ↑↓ on the frequency tab
+    pub tuning_step: usize,
+    /// Whether ←→ pick the digit of the frequency that ↑↓ change, as on a
+    /// rig, rather than the tab
+    pub digit_mode: bool,
+    /// Name in [`COLORMAPS`] of the waterfall colours, the theme's own if `None`
+    pub colormap: Option<&'static str>,
+    /// Index into [`Setting::ALL`] in the settings tab
+    pub setting_selected: usize,
+    /// First column of the help overlay shown, while it is open
+    pub help: Option<usize>,
+    pub palette: Option<Palette>,
+    /// Index into [`POWER_HISTORY_SPANS`]
+    pub power_span: usize,
+    /// SAME alerts already shown in the overlay
+    same_seen: u64,
+    pub alert_overlay: bool,
+    pub alert_bell: bool,
+    /// Plot with Braille dots, 2x4 points per cell, instead of one dot per cell
+    pub braille: bool,
+    pub theme: Theme,
+    /// Draw with ASCII only, for serial consoles and minimal terminals
+    pub ascii: bool,
+    /// Time between redraws, independent of the updates of the engine
+    pub frame_interval: Duration,
+    /// Save the next drawn frame as a screenshot
+    screenshot_pending: bool,
+    /// Fed with the samples and monitored audio of each update
+    pub scope: Scope,
+    pub histogram: SampleHistogram,
+}
 
 impl App {
     pub fn new() -> Self {
         Self {
+            engine: Engine::new(),
             should_quit: false,
             current_tab: 0,
-            frequency: 890e6,       // 100 MHz
-            sample_rate: 1e6,       // 1 MS/s
-            gain: 20.0,             // 30 dB
-            is_streaming: false,
-            status_message: "DEMO MODE - No USRP hardware detected".to_string(),
-            spectrum_data: vec![0.0; 512], // Half of FFT size
-            sample_buffer: Vec::new(),
+            persistence: None,
+            waterfall: VecDeque::new(),
+            waterfall_lines: WATERFALL_LINES,
+            waterfall_scroll: None,
+            waterfall_area: Rect::default(),
+            show_dbm: false,
+            zoom: 1.0,
+            pan_hz: 0.0,
+            spectrum_plot: Rect::default(),
+            tab_areas: [Rect::default(); TABS],
+            parameter_area: Rect::default(),
+            readout_area: Rect::default(),
+            controls_percent: DEFAULT_CONTROLS_PERCENT,
+            samples_percent: DEFAULT_SAMPLES_PERCENT,
+            drag: None,
+            click_tunes_lo: false,
+            full_screen: false,
+            markers: [None; 2],
+            active_marker: 0,
+            view: View::Spectrum,
+            presets: Vec::new(),
+            preset_selected: 0,
+            bookmarks: Vec::new(),
+            bookmark_selected: 0,
+            bookmark_tag: None,
+            region: Region::default(),
+            log_level: Level::Info,
+            plugin_selected: 0,
+            scan_range: None,
+            scan_bookmarks: false,
+            history: History::default(),
+            messages: VecDeque::new(),
+            message_scroll: 0,
+            toasts: VecDeque::new(),
+            toasted: logging::since(0).last().map_or(0, |entry| entry.id),
+            keymap: Keymap::default(),
+            key_selected: 0,
+            key_capture: false,
+            dialog: None,
+            tuning_step: 6,
+            digit_mode: false,
+            colormap: None,
+            setting_selected: 0,
+            help: None,
+            palette: None,
+            power_span: 1,
+            same_seen: 0,
+            alert_overlay: false,
+            alert_bell: false,
+            braille: false,
+            theme: Theme::builtin("dark").expect("dark theme is built in"),
+            ascii: false,
+            frame_interval: Duration::from_secs_f64(1.0 / DEFAULT_FPS),
+            screenshot_pending: false,
+            scope: Scope::new(),
+            histogram: SampleHistogram::new(),
+        }
+    }
+
+    /// Do what `key` is bound to in the current view. A key with Ctrl that
+    /// is not bound does what it does without.
+    pub fn on_key(&mut self, key: Key) {
+        self.by_user(|app| app.handle_key(key));
+    }
+
+    fn handle_key(&mut self, key: Key) {
+        if self.key_capture {
+            self.capture_key(key);
+            return;
+        }
+        if let Some(mut dialog) = self.dialog.take() {
+            match dialog.on_key(key) {
+                Outcome::Open => self.dialog = Some(dialog),
+                Outcome::Cancelled => {}
+                Outcome::Answered(answer) => self.answer(dialog, answer),
+            }
+            return;
+        }
+        if self.palette.is_some() {
+            self.edit_palette(key);
+            return;
+        }
+        if let Some(column) = &mut self.help {
+            match key.code {
+                KeyCode::Left | KeyCode::Up | KeyCode::PageUp => *column = column.saturating_sub(1),
+                KeyCode::Right | KeyCode::Down | KeyCode::PageDown => *column += 1,
+                _ => self.help = None,
+            }
+            return;
+        }
+        let enabled = |action| self.enabled(action);
+        let action = self
+            .keymap
+            .action(key, self.view, enabled)
+            .or_else(|| key.ctrl.then(|| self.keymap.action(Key::from(key.code), self.view, enabled)).flatten());
+        if let Some(action) = action {
+            self.perform(action);
+        }
+    }
+
+    /// Whether `action` can be done now, a key bound to several actions
+    /// doing the first that can
+    fn enabled(&self, action: Action) -> bool {
+        match action {
+            Action::DismissAlert => self.alert_overlay,
+            Action::DigitLeft | Action::DigitRight => self.digit_mode,
+            Action::SettingLess | Action::SettingMore => self.current_tab == SETTINGS_TAB,
+            Action::RecordFormat => !self.engine.recorder.lock().is_recording(),
+            Action::AudioFormat => !self.engine.audio_recorder.is_recording(),
+            Action::PlaybackPause
+            | Action::SeekBack
+            | Action::SeekForward
+            | Action::SeekBackLong
+            | Action::SeekForwardLong
+            | Action::PlaybackLoop
+            | Action::PlaybackSlower
+            | Action::PlaybackFaster => self.engine.player.is_some(),
+            _ => true,
         }
     }
 
-    pub fn on_key(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
-            KeyCode::Tab => {
-                self.current_tab = (self.current_tab + 1) % 3;
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Quit if self.engine.recorder.lock().is_recording() || self.engine.audio_recorder.is_recording() => {
+                self.dialog = Some(Dialog::confirm("Quit", "A recording is running. Stop it and quit?".to_string(), Purpose::Quit));
             }
-            KeyCode::Right => {
-                self.current_tab = (self.current_tab + 1) % 3;
+            Action::Quit => self.should_quit = true,
+            Action::NextTab => {
+                self.current_tab = (self.current_tab + 1) % TABS;
+                self.digit_mode = false;
             }
-            KeyCode::Left => {
-                self.current_tab = if self.current_tab == 0 { 2 } else { self.current_tab - 1 };
+            Action::PreviousTab => {
+                self.current_tab = (self.current_tab + TABS - 1) % TABS;
+                self.digit_mode = false;
             }
-            KeyCode::Char('c') => {
-                self.status_message = "MOCK USRP connected (demo mode)".to_string();
+            Action::DismissAlert => self.alert_overlay = false,
+            Action::AlertBell => {
+                self.alert_bell = !self.alert_bell;
+                self.engine.status_message = format!(
+                    "SAME alert bell {}",
+                    if self.alert_bell { "enabled" } else { "disabled" }
+                );
             }
-            KeyCode::Char('s') => {
-                if self.is_streaming {
-                    self.stop_streaming();
+            Action::NextView => self.view = self.view.next(),
+            Action::Record => self.engine.toggle_recording(),
+            Action::RecordFormat => {
+                let mut recorder = self.engine.recorder.lock();
+                recorder.next_format();
+                self.engine.status_message = format!("Recording format {}", recorder.format_label());
+            }
+            Action::Annotate => self.engine.annotate_recording(),
+            Action::ExportSpectrum => self.export_spectrum(),
+            Action::ExportWaterfall => self.export_waterfall(),
+            Action::Screenshot => self.screenshot_pending = true,
+            Action::Playback => self.engine.toggle_playback(),
+            Action::PlaybackPause => {
+                if let Some(player) = &mut self.engine.player {
+                    player.paused = !player.paused;
+                    self.engine.status_message = if player.paused { "Playback paused" } else { "Playback resumed" }.to_string();
+                }
+            }
+            Action::SeekBack => self.engine.seek_playback(-SEEK_SHORT_SECS),
+            Action::SeekForward => self.engine.seek_playback(SEEK_SHORT_SECS),
+            Action::SeekBackLong => self.engine.seek_playback(-SEEK_LONG_SECS),
+            Action::SeekForwardLong => self.engine.seek_playback(SEEK_LONG_SECS),
+            Action::PlaybackLoop => {
+                if let Some(player) = &mut self.engine.player {
+                    player.looping = !player.looping;
+                    self.engine.status_message = format!("Playback loop {}", if player.looping { "on" } else { "off" });
+                }
+            }
+            Action::PlaybackSlower => self.engine.step_playback_speed(false),
+            Action::PlaybackFaster => self.engine.step_playback_speed(true),
+            Action::RecordAudio => self.engine.toggle_audio_recording(),
+            Action::AudioFormat => {
+                self.engine.audio_recorder.format = self.engine.audio_recorder.format.next();
+                self.engine.status_message = format!("Audio recording format {}", self.engine.audio_recorder.format);
+            }
+            Action::SquelchGate => {
+                self.engine.audio_recorder.squelch_gated = !self.engine.audio_recorder.squelch_gated;
+                self.engine.status_message = if self.engine.audio_recorder.squelch_gated {
+                    format!("Audio recording squelch-gated at {:.0} dBFS, one file per transmission", self.engine.audio_recorder.squelch_db)
+                } else {
+                    "Audio recording continuous".to_string()
+                };
+            }
+            Action::Theme => {
+                self.theme = self.theme.next();
+                self.theme.set_colormap(self.colormap);
+                self.engine.status_message = format!("Theme: {}", self.theme.name);
+            }
+            Action::EnterFrequency => {
+                self.dialog = Some(Dialog::input(
+                    "Go to frequency",
+                    "Hz, or with k, M or G: 145.825M, 7.074MHz",
+                    String::new(),
+                    Purpose::Tune,
+                ));
+            }
+            Action::Help => self.help = Some(0),
+            Action::ControlsNarrower | Action::ControlsWider => {
+                self.controls_percent = match action {
+                    Action::ControlsNarrower => self.controls_percent.saturating_sub(LAYOUT_STEP_PERCENT).max(MIN_CONTROLS_PERCENT),
+                    _ => (self.controls_percent + LAYOUT_STEP_PERCENT).min(MAX_CONTROLS_PERCENT),
+                };
+                self.engine.status_message = format!("Controls {}% of the width", self.controls_percent);
+            }
+            Action::SamplesTaller | Action::SamplesShorter => {
+                self.samples_percent = match action {
+                    Action::SamplesTaller => (self.samples_percent + LAYOUT_STEP_PERCENT).min(MAX_SAMPLES_PERCENT),
+                    _ => self.samples_percent.saturating_sub(LAYOUT_STEP_PERCENT),
+                };
+                self.engine.status_message = format!("Samples {}% of the spectrum view", self.samples_percent);
+            }
+            Action::LayoutReset => {
+                self.controls_percent = DEFAULT_CONTROLS_PERCENT;
+                self.samples_percent = DEFAULT_SAMPLES_PERCENT;
+                self.engine.status_message = "Layout reset".to_string();
+            }
+            Action::Palette => self.palette = Some(Palette::default()),
+            Action::TuningStep => {
+                self.tuning_step = (self.tuning_step + 1) % TUNING_STEPS.len();
+                self.engine.status_message = format!("Tuning step {}", format_step(TUNING_STEPS[self.tuning_step]));
+            }
+            Action::DigitMode => {
+                self.digit_mode = !self.digit_mode;
+                if self.digit_mode {
+                    self.current_tab = 0;
+                }
+                self.engine.status_message = match self.digit_mode {
+                    true => "Digit mode: ←→ pick a digit, ↑↓ change it".to_string(),
+                    false => "Digit mode off".to_string(),
+                };
+            }
+            Action::SettingLess => self.change_setting(false),
+            Action::SettingMore => self.change_setting(true),
+            Action::DigitLeft => self.tuning_step = (self.tuning_step + 1).min(TUNING_STEPS.len() - 1),
+            Action::DigitRight => self.tuning_step = self.tuning_step.saturating_sub(1),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::ZoomIn => self.set_zoom(self.zoom * 2.0),
+            Action::ZoomOut => self.set_zoom(self.zoom / 2.0),
+            Action::ZoomReset => {
+                self.pan_hz = 0.0;
+                self.set_zoom(1.0);
+            }
+            Action::NextMarker => self.next_marker(),
+            Action::WaterfallPause => {
+                self.waterfall_scroll = match self.waterfall_scroll {
+                    Some(_) => None,
+                    None => Some(0),
+                };
+            }
+            Action::WaterfallBack => {
+                self.scroll_waterfall(self.waterfall_area.height.max(1) as isize);
+            }
+            Action::WaterfallForward => {
+                self.scroll_waterfall(-(self.waterfall_area.height.max(1) as isize));
+            }
+            Action::Persistence => {
+                self.persistence = match self.persistence {
+                    Some(_) => None,
+                    None => Some(VecDeque::with_capacity(PERSISTENCE_TRACES)),
+                };
+                self.engine.status_message = format!(
+                    "Spectrum persistence {}",
+                    if self.persistence.is_some() { "on" } else { "off" }
+                );
+            }
+            Action::FullScreen => {
+                self.full_screen = !self.full_screen;
+                self.engine.status_message =
+                    if self.full_screen { "Full-screen spectrum, [F] to restore" } else { "Spectrum layout restored" }
+                        .to_string();
+            }
+            Action::AddVfo => self.engine.add_vfo(),
+            Action::RemoveVfo => self.engine.remove_vfo(),
+            Action::RecallPreset(index) => self.recall_preset(index),
+            Action::Select => match self.view {
+                View::Presets => self.recall_preset(self.preset_selected),
+                View::Bookmarks => self.tune_bookmark(),
+                _ => self.toggle_scan(),
+            },
+            Action::Previous => match self.view {
+                View::Presets => self.preset_selected = self.preset_selected.saturating_sub(1),
+                View::Bookmarks => self.bookmark_selected = self.bookmark_selected.saturating_sub(1),
+                View::Plugins => self.plugin_selected = self.plugin_selected.saturating_sub(1),
+                View::Log => {
+                    self.log_level = match self.log_level {
+                        Level::Trace | Level::Debug | Level::Info => Level::Warn,
+                        Level::Warn | Level::Error => Level::Error,
+                    };
+                }
+                View::Scanner => self.engine.scanner.threshold_db = (self.engine.scanner.threshold_db - 1.0).max(3.0),
+                View::Bursts => self.engine.bursts.threshold_db = (self.engine.bursts.threshold_db - 1.0).max(3.0),
+                View::Scope => self.scope.timebase = self.scope.timebase.saturating_sub(1),
+                View::Messages => self.message_scroll = (self.message_scroll + 1).min(self.messages.len().saturating_sub(1)),
+                _ => self.key_selected = self.key_selected.saturating_sub(1),
+            },
+            Action::Next => match self.view {
+                View::Presets => self.preset_selected = (self.preset_selected + 1).min(self.presets.len().saturating_sub(1)),
+                View::Bookmarks => {
+                    self.bookmark_selected = (self.bookmark_selected + 1).min(self.shown_bookmarks().len().saturating_sub(1));
+                }
+                View::Plugins => self.plugin_selected = (self.plugin_selected + 1).min(self.engine.plugins.plugins.len().saturating_sub(1)),
+                View::Log => {
+                    self.log_level = match self.log_level {
+                        Level::Error => Level::Warn,
+                        _ => Level::Info,
+                    };
+                }
+                View::Scanner => self.engine.scanner.threshold_db = (self.engine.scanner.threshold_db + 1.0).min(40.0),
+                View::Bursts => self.engine.bursts.threshold_db = (self.engine.bursts.threshold_db + 1.0).min(40.0),
+                View::Scope => self.scope.timebase = (self.scope.timebase + 1).min(SCOPE_TIMEBASES.len() - 1),
+                View::Messages => self.message_scroll = self.message_scroll.saturating_sub(1),
+                _ => self.key_selected = (self.key_selected + 1).min(ACTIONS.len() - 1),
+            },
+            Action::Save => match self.view {
+                View::Presets => self.save_preset(),
+                View::Bookmarks => self.add_bookmark(),
+                _ => self.save_keymap(),
+            },
+            Action::Delete => match self.view {
+                View::Presets => {
+                    if let Some(preset) = self.presets.get(self.preset_selected) {
+                        let message = format!("Delete preset {}?", preset.name);
+                        self.dialog = Some(Dialog::confirm("Delete", message, Purpose::DeletePreset));
+                    }
+                }
+                View::Bookmarks => {
+                    if let Some(&index) = self.shown_bookmarks().get(self.bookmark_selected) {
+                        let message = format!("Delete bookmark {}?", self.bookmarks[index].name);
+                        self.dialog = Some(Dialog::confirm("Delete", message, Purpose::DeleteBookmark));
+                    }
+                }
+                View::Scanner if !self.engine.scanner.hits.is_empty() => {
+                    let message = format!("Clear the {} scanner hits?", self.engine.scanner.hits.len());
+                    self.dialog = Some(Dialog::confirm("Clear", message, Purpose::ClearHits));
+                }
+                View::Scanner => {}
+                View::Schedule => match self.engine.schedule.jobs.iter().find(|job| job.state == JobState::Pending) {
+                    Some(job) => {
+                        let message = format!("Cancel scheduled recording {}?", job.label);
+                        self.dialog = Some(Dialog::confirm("Cancel", message, Purpose::CancelJob));
+                    }
+                    None => self.engine.status_message = "No pending recordings".to_string(),
+                },
+                _ => {
+                    self.keymap.unbind(self.key_selected);
+                    self.engine.status_message = format!("{} unbound, [W] saves to the config file", ACTIONS[self.key_selected].1);
+                }
+            },
+            Action::BookmarkTag => self.next_bookmark_tag(),
+            Action::ScanBookmarks => {
+                self.scan_bookmarks = !self.scan_bookmarks;
+                self.engine.scanner.stop();
+                self.engine.status_message = match self.scan_bookmarks {
+                    true => "Scanning the bookmarks shown".to_string(),
+                    false => "Scanning the range".to_string(),
+                };
+            }
+            Action::SkipChannel => {
+                if let Some(channel) = self.engine.scanner.skip(Instant::now()).cloned() {
+                    self.engine.tune_scan_channel(&channel);
+                }
+            }
+            Action::ToggleLog => match self.view {
+                View::Scanner => self.engine.toggle_scan_log(),
+                View::Navtex => self.engine.toggle_navtex_log(),
+                _ => self.engine.toggle_measure_log(),
+            },
+            Action::SelectVfo(index) => {
+                if index < self.engine.vfos.len() {
+                    self.engine.active_vfo = index;
+                    self.engine.status_message = format!("VFO {} selected", index + 1);
+                }
+            }
+            Action::VfoMode => {
+                let demod = &mut self.engine.vfos[self.engine.active_vfo].demod;
+                demod.set_mode(demod.mode().next());
+                self.engine.status_message = format!("VFO {} mode {}", self.engine.active_vfo + 1, demod.mode());
+            }
+            Action::VfoRoute => {
+                let vfo = &mut self.engine.vfos[self.engine.active_vfo];
+                vfo.route = cycle(&AudioRoute::ALL, vfo.route, true);
+                self.engine.status_message = format!("VFO {} audio {}", self.engine.active_vfo + 1, vfo.route.label());
+            }
+            Action::ClickTunes => {
+                self.click_tunes_lo = !self.click_tunes_lo;
+                self.engine.status_message = format!(
+                    "Click tunes the {}",
+                    if self.click_tunes_lo { "hardware LO" } else { "demodulator" }
+                );
+            }
+            Action::ClearMarkers => {
+                self.markers = [None; 2];
+                self.active_marker = 0;
+            }
+            Action::MarkerLeft => self.move_marker(-1.0),
+            Action::MarkerRight => self.move_marker(1.0),
+            Action::PanLeft => self.pan(-self.engine.sample_rate / self.zoom / 10.0),
+            Action::PanRight => self.pan(self.engine.sample_rate / self.zoom / 10.0),
+            Action::PowerUnit => {
+                if self.engine.calibration_db.is_none() {
+                    self.engine.status_message = "Set offset_db under [calibration DRIVER SERIAL] in the config file to display dBm".to_string();
+                } else {
+                    self.show_dbm = !self.show_dbm;
+                    self.engine.status_message = format!("Power shown in {}", self.power_unit().1);
+                }
+            }
+            Action::Braille => {
+                self.braille = !self.braille;
+                self.engine.status_message = format!(
+                    "{} plotting",
+                    if self.braille { "Braille high-resolution" } else { "Block" }
+                );
+            }
+            Action::NmeaLog => self.engine.toggle_nmea_log(),
+            Action::IsmLog => self.engine.toggle_ism_log(),
+            Action::RttyShift => self.engine.cycle_rtty(true),
+            Action::RttyBaud => self.engine.cycle_rtty(false),
+            Action::PskMode => {
+                let mut psk = self.engine.psk.lock();
+                let mode = match psk.mode {
+                    PskMode::Psk31 => PskMode::Psk63,
+                    PskMode::Psk63 => PskMode::Psk31,
+                };
+                psk.set_mode(mode);
+                self.engine.status_message = format!("PSK mode {}", mode.name());
+            }
+            Action::PskOrder => {
+                let mut config = self.engine.constellation.lock().demod.config().clone();
+                config.order = match config.order {
+                    PskOrder::Bpsk => PskOrder::Qpsk,
+                    PskOrder::Qpsk => PskOrder::Bpsk,
+                };
+                self.engine.reconfigure_psk_demod(config);
+            }
+            Action::PskBaud => {
+                let mut config = self.engine.constellation.lock().demod.config().clone();
+                let index = CONSTELLATION_BAUDS.iter().position(|&b| b == config.baud).unwrap_or(0);
+                config.baud = CONSTELLATION_BAUDS[(index + 1) % CONSTELLATION_BAUDS.len()];
+                self.engine.reconfigure_psk_demod(config);
+            }
+            Action::BurstCapture => {
+                self.engine.burst_capture = !self.engine.burst_capture;
+                self.engine.status_message = if self.engine.burst_capture {
+                    format!("Burst capture armed, saving to {}/", BURST_DIR)
+                } else {
+                    "Burst capture disarmed".to_string()
+                };
+            }
+            Action::ScopeSource => {
+                self.scope.source = match self.scope.source {
+                    ScopeSource::Iq => ScopeSource::Audio,
+                    ScopeSource::Audio => ScopeSource::Iq,
+                };
+            }
+            Action::ScopeTrigger => {
+                self.scope.trigger = match self.scope.trigger {
+                    Some(_) => None,
+                    None => Some(0.0),
+                };
+            }
+            Action::TriggerUp => {
+                if let Some(level) = &mut self.scope.trigger {
+                    *level += SCOPE_TRIGGER_STEP;
+                }
+            }
+            Action::TriggerDown => {
+                if let Some(level) = &mut self.scope.trigger {
+                    *level -= SCOPE_TRIGGER_STEP;
+                }
+            }
+            Action::WsprUpload => self.engine.toggle_wspr_upload(),
+            Action::MeasureBandwidth => {
+                let index = MEASURE_BANDWIDTHS.iter().position(|&b| b == self.engine.meter.bandwidth_hz).unwrap_or(0);
+                self.engine.meter.bandwidth_hz = MEASURE_BANDWIDTHS[(index + 1) % MEASURE_BANDWIDTHS.len()];
+                self.engine.status_message = format!("Measurement passband {:.1} kHz", self.engine.meter.bandwidth_hz / 1e3);
+            }
+            Action::PowerSpan => {
+                self.power_span = (self.power_span + 1) % POWER_HISTORY_SPANS.len();
+                self.engine.status_message = format!("Power history over {} s", POWER_HISTORY_SPANS[self.power_span]);
+            }
+            Action::EditKey => {
+                self.key_capture = true;
+                self.engine.status_message = format!("Press the key for {}, [Esc] to cancel", ACTIONS[self.key_selected].1);
+            }
+            Action::Connect => {
+                // rtl_tcp servers of the presets and the one in use
+                let mut servers: Vec<String> = self.presets.iter().filter_map(|preset| preset.device.clone()).collect();
+                servers.extend(self.engine.remote.as_ref().map(|remote| remote.server.clone()));
+                servers.sort();
+                servers.dedup();
+                let mut options = vec!["Demo signals".to_string()];
+                options.extend(servers.iter().map(|server| format!("rtl_tcp {}", server)));
+                options.push("Other rtl_tcp server…".to_string());
+                self.dialog = Some(Dialog::select("Connect", "Source of samples", options, Purpose::ChooseSource(servers)));
+            }
+            Action::Stream => {
+                if self.engine.is_streaming {
+                    self.engine.stop_streaming();
                 } else {
-                    self.start_streaming();
+                    self.engine.start_streaming();
                 }
             }
             // Parameter adjustments
-            KeyCode::Up => self.adjust_parameter(true),
-            KeyCode::Down => self.adjust_parameter(false),
-            _ => {}
+            Action::Increase => self.adjust_parameter(true),
+            Action::Decrease => self.adjust_parameter(false),
         }
     }
 
-    fn mock_stream_samples(&mut self) {
-        // Generate mock IQ samples
-        self.sample_buffer.clear();
-        for _ in 0..100 { // Limit for display
-            // Generate some realistic-looking complex samples
-            let phase = rand::random::<f32>() * std::f32::consts::PI * 2.0;
-            let magnitude = 0.5 + rand::random::<f32>() * 0.5; // 0.5 to 1.0
-            let real = magnitude * phase.cos();
-            let imag = magnitude * phase.sin();
-            self.sample_buffer.push(Complex32::new(real, imag));
+    /// Do what `dialog` was opened for, opening it again with the reason
+    /// when `answer` will not do
+    fn answer(&mut self, mut dialog: Dialog<Purpose>, answer: Answer) {
+        match (&dialog.then, answer) {
+            (Purpose::Tune, Answer::Text(text)) => match parse_hz(&text) {
+                Ok(hz) if (1e6..=6e9).contains(&hz) => {
+                    self.engine.frequency = hz;
+                    self.engine.vfos[self.engine.active_vfo].demod.set_offset(0.0);
+                    self.engine.status_message = format!("Tuned to {:.6} MHz", hz / 1e6);
+                }
+                Ok(hz) => {
+                    dialog.error = Some(format!("{:.0} Hz is outside 1 MHz to 6 GHz", hz));
+                    self.dialog = Some(dialog);
+                }
+                Err(e) => {
+                    dialog.error = Some(e);
+                    self.dialog = Some(dialog);
+                }
+            },
+            (Purpose::Quit, _) => self.should_quit = true,
+            (Purpose::DeletePreset, _) => self.delete_preset(),
+            (Purpose::DeleteBookmark, _) => self.delete_bookmark(),
+            (Purpose::ClearHits, _) => {
+                self.engine.scanner.hits.clear();
+                self.engine.status_message = "Scanner hits cleared".to_string();
+            }
+            (Purpose::CancelJob, _) => {
+                if let Some(label) = self.engine.schedule.cancel_next() {
+                    self.engine.status_message = format!("Cancelled scheduled recording {}", label);
+                }
+            }
+            (Purpose::ChooseSource(_), Answer::Choice(0)) => self.switch_source(None),
+            (Purpose::ChooseSource(servers), Answer::Choice(i)) if i <= servers.len() => {
+                let server = servers[i - 1].clone();
+                self.switch_source(Some(server));
+            }
+            (Purpose::ChooseSource(_), _) => {
+                let server = self.engine.remote.as_ref().map_or("localhost:1234".to_string(), |remote| remote.server.clone());
+                self.dialog = Some(Dialog::input("Connect", "rtl_tcp server as HOST:PORT", server, Purpose::Server));
+            }
+            (Purpose::Server, Answer::Text(server)) if server.is_empty() => {
+                dialog.error = Some("Type the HOST:PORT of the server".to_string());
+                self.dialog = Some(dialog);
+            }
+            (Purpose::Server, Answer::Text(server)) => self.switch_source(Some(server)),
+            (Purpose::Switch(source), _) => {
+                let source = source.clone();
+                self.use_source(source);
+            }
+            _ => {}
         }
     }
 
-    fn start_streaming(&mut self) {
-        self.is_streaming = true;
-        self.status_message = "Mock streaming started (demo mode)".to_string();
-        self.mock_stream_samples();
+    /// Take samples from the rtl_tcp server `source`, or the demo signals,
+    /// asking first when that disconnects a remote receiver
+    fn switch_source(&mut self, source: Option<String>) {
+        match &self.engine.remote {
+            Some(remote) if source.as_ref() == Some(&remote.server) => {
+                self.engine.status_message = format!("Connected to {} already", remote.server);
+            }
+            Some(remote) => {
+                let message = match &source {
+                    Some(server) => format!("Disconnect from {} and connect to {}?", remote.server, server),
+                    None => format!("Disconnect from {} and use the demo signals?", remote.server),
+                };
+                self.dialog = Some(Dialog::confirm("Disconnect", message, Purpose::Switch(source)));
+            }
+            None => self.use_source(source),
+        }
     }
 
-    fn stop_streaming(&mut self) {
-        self.is_streaming = false;
-        self.status_message = "Streaming stopped".to_string();
+    fn use_source(&mut self, source: Option<String>) {
+        self.set_remote(source.as_deref().map(RemoteSource::connect));
+        self.engine.status_message = match &self.engine.remote {
+            Some(remote) => format!("Connecting to {}", remote.server),
+            None => "Taking samples from the demo signals".to_string(),
+        };
     }
 
-    fn adjust_parameter(&mut self, increase: bool) {
-        let delta = if increase { 1.0 } else { -1.0 };
+    /// The commands of the palette that can be run here and now
+    fn palette_commands(&self) -> Vec<(palette::Command, String)> {
+        palette::commands()
+            .into_iter()
+            .filter(|&(command, _)| match command {
+                palette::Command::Action(Action::Palette) => false,
+                palette::Command::Action(action) => {
+                    ACTIONS.iter().any(|&(a, _, context, _)| a == action && context.applies(self.view)) && self.enabled(action)
+                }
+                palette::Command::Mode(_) | palette::Command::View(_) => true,
+            })
+            .collect()
+    }
 
-        match self.current_tab {
-            0 => { // Frequency tab
-                let step = 1e6; // 1 MHz steps
-                self.frequency = (self.frequency + delta * step).max(1e6).min(6e9);
+    /// Type `key` into the command palette, running the selected command
+    /// on Enter
+    fn edit_palette(&mut self, key: Key) {
+        let commands = self.palette_commands();
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        let count = palette.matches(commands.clone()).len();
+        match key.code {
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
             }
-            1 => { // Gain tab
-                let step = 1.0; // 1 dB steps
-                self.gain = (self.gain + delta * step).max(0.0).min(60.0);
+            KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Down => palette.selected = (palette.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Char(c) if !key.ctrl => {
+                palette.query.push(c);
+                palette.selected = 0;
             }
-            2 => { // Sample rate tab
-                let step = 0.1e6; // 0.1 MS/s steps
-                self.sample_rate = (self.sample_rate + delta * step).max(0.1e6).min(10e6);
+            KeyCode::Enter => {
+                let command = palette.matches(commands).get(palette.selected).map(|&(command, _)| command);
+                self.palette = None;
+                match command {
+                    Some(palette::Command::Action(action)) => self.perform(action),
+                    Some(palette::Command::Mode(mode)) => {
+                        self.engine.vfos[self.engine.active_vfo].demod.set_mode(mode);
+                        self.engine.status_message = format!("VFO {} mode {}", self.engine.active_vfo + 1, mode);
+                    }
+                    Some(palette::Command::View(view)) => self.view = view,
+                    None => {}
+                }
             }
             _ => {}
         }
     }
-}
 
-/// Run the TUI application
-pub fn run_tui() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    /// Bind `key` to the action being edited in the keys view
+    fn capture_key(&mut self, key: Key) {
+        self.key_capture = false;
+        let name = ACTIONS[self.key_selected].1;
+        if key == Key::from(KeyCode::Esc) {
+            self.engine.status_message = format!("{} left as it was", name);
+            return;
+        }
+        match self.keymap.conflict(self.key_selected, key) {
+            Some(other) => log::warn!("{} is bound to {} already, unbind it first", key, other),
+            None => {
+                self.keymap.unbind(self.key_selected);
+                // Cannot conflict, checked above
+                let _ = self.keymap.bind(self.key_selected, key);
+                self.engine.status_message = format!("{} bound to {}, [W] saves to the config file", name, key);
+            }
+        }
+    }
 
-    // Create app and run it
-    let mut app = App::new();
-    let res = run_app(&mut terminal, &mut app);
+    fn save_keymap(&mut self) {
+        let Some(path) = Config::path() else {
+            log::warn!("Keys not saved: no config directory");
+            return;
+        };
+        match self.keymap.save(&path) {
+            Ok(()) => self.engine.status_message = format!("Keys saved to {}", path.display()),
+            Err(e) => log::warn!("Keys not saved: {}", e),
+        }
+    }
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    fn load_presets(&mut self) {
+        let Some(path) = presets::path() else {
+            return;
+        };
+        match presets::load(&path) {
+            Ok(presets) => self.presets = presets,
+            Err(e) => log::warn!("Presets not loaded: {}", e),
+        }
+    }
+
+    fn store_presets(&self) -> Result<PathBuf, String> {
+        let path = presets::path().ok_or("no config directory for presets")?;
+        presets::save(&path, &self.presets).map_err(|e| format!("cannot save {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    fn recall_preset(&mut self, index: usize) {
+        let Some(preset) = self.presets.get(index).cloned() else {
+            self.engine.status_message = format!("No preset {}, save one in the presets view", index + 1);
+            return;
+        };
+        self.preset_selected = index;
+        self.engine.frequency = preset.frequency.clamp(1e6, 6e9);
+        self.engine.sample_rate = preset.sample_rate.clamp(0.1e6, 10e6);
+        self.engine.gain = preset.gain.clamp(0.0, 60.0);
+        self.engine.vfos[self.engine.active_vfo].demod.set_mode(preset.mode);
+        let server = self.engine.remote.as_ref().map(|remote| remote.server.as_str());
+        if preset.device.as_deref() != server {
+            self.set_remote(preset.device.as_deref().map(RemoteSource::connect));
+        }
+        self.engine.status_message = format!(
+            "Preset {}: {:.4} MHz {} from {}",
+            preset.name,
+            self.engine.frequency / 1e6,
+            preset.mode,
+            preset.device.as_deref().unwrap_or("demo")
+        );
+    }
+
+    /// Add the current setup as a preset named after its frequency and mode,
+    /// which can be renamed in the file
+    fn save_preset(&mut self) {
+        let mode = self.engine.vfo().demod.mode();
+        let preset = Preset {
+            name: format!("{:.4} MHz {}", self.engine.frequency / 1e6, mode),
+            frequency: self.engine.frequency,
+            sample_rate: self.engine.sample_rate,
+            gain: self.engine.gain,
+            mode,
+            device: self.engine.remote.as_ref().map(|remote| remote.server.clone()),
+        };
+        let name = preset.name.clone();
+        match self.presets.iter().position(|p| p.name == name) {
+            Some(index) => self.presets[index] = preset,
+            None => self.presets.push(preset),
+        }
+        self.preset_selected = self.presets.iter().position(|p| p.name == name).unwrap_or(0);
+        match self.store_presets() {
+            Ok(path) => self.engine.status_message = format!("Preset {} saved to {}", name, path.display()),
+            Err(e) => log::warn!("Preset {} not saved: {}", name, e),
+        }
+    }
+
+    fn delete_preset(&mut self) {
+        if self.preset_selected >= self.presets.len() {
+            return;
+        }
+        let preset = self.presets.remove(self.preset_selected);
+        self.preset_selected = self.preset_selected.min(self.presets.len().saturating_sub(1));
+        match self.store_presets() {
+            Ok(_) => self.engine.status_message = format!("Preset {} deleted", preset.name),
+            Err(e) => log::warn!("Preset {} deleted for this session only: {}", preset.name, e),
+        }
+    }
+
+    fn load_bookmarks(&mut self) {
+        let Some(path) = bookmarks::path() else {
+            return;
+        };
+        match bookmarks::load(&path) {
+            Ok(bookmarks) => self.bookmarks = bookmarks,
+            Err(e) => log::warn!("Bookmarks not loaded: {}", e),
+        }
+    }
+
+    fn store_bookmarks(&self) -> Result<PathBuf, String> {
+        let path = bookmarks::path().ok_or("no config directory for bookmarks")?;
+        bookmarks::save(&path, &self.bookmarks).map_err(|e| format!("cannot save {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Pick up where the last session left off, reconnecting to its device
+    /// unless `with_source` is false for a source given on the command line
+    fn restore_session(&mut self, with_source: bool) {
+        let Some(path) = session::path() else {
+            return;
+        };
+        let session = match session::load(&path) {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Session not restored: {}", e);
+                return;
+            }
+        };
+        self.apply_session(session, with_source);
+    }
+
+    fn apply_session(&mut self, session: Session, with_source: bool) {
+        self.engine.frequency = session.frequency.clamp(1e6, 6e9);
+        self.engine.sample_rate = session.sample_rate.clamp(0.1e6, 10e6);
+        self.engine.gain = session.gain.clamp(0.0, 60.0);
+        if with_source && let Some(server) = &session.device {
+            self.set_remote(Some(RemoteSource::connect(server)));
+        }
+        if !session.vfos.is_empty() {
+            let limit = self.engine.sample_rate / 2.0;
+            self.engine.vfos = session
+                .vfos
+                .iter()
+                .take(MAX_VFOS)
+                .enumerate()
+                .map(|(i, vfo)| {
+                    let route = vfo.route.as_deref().and_then(AudioRoute::parse).unwrap_or(match i {
+                        0 => AudioRoute::Decoders,
+                        _ => AudioRoute::Muted,
+                    });
+                    Vfo::new(vfo.mode, vfo.offset_hz.clamp(-limit, limit), route)
+                })
+                .collect();
+        }
+        self.engine.active_vfo = session.active_vfo.min(self.engine.vfos.len() - 1);
+        self.current_tab = session.tab % TABS;
+        self.view = View::ALL.into_iter().find(|view| view.name() == session.view).unwrap_or(View::Spectrum);
+        self.full_screen = session.full_screen;
+        self.zoom = session.zoom.clamp(1.0, MAX_ZOOM);
+        self.pan_hz = 0.0;
+        self.pan(session.pan_hz);
+        self.markers = session.markers;
+        if let Some(percent) = session.controls_percent {
+            self.controls_percent = percent.clamp(MIN_CONTROLS_PERCENT, MAX_CONTROLS_PERCENT);
+        }
+        if let Some(percent) = session.samples_percent {
+            self.samples_percent = percent.min(MAX_SAMPLES_PERCENT);
+        }
+        self.engine.fft_size = session.fft_size.filter(|size| FFT_SIZES.contains(size)).unwrap_or(self.engine.fft_size);
+        self.engine.fft_window = session.window.unwrap_or(self.engine.fft_window);
+        self.engine.fft_averages = session.averages.filter(|n| SPECTRUM_AVERAGES.contains(n)).unwrap_or(self.engine.fft_averages);
+        self.reset_spectrum();
+        self.colormap = COLORMAPS.iter().map(|&(name, _)| name).find(|&name| session.colormap.as_deref() == Some(name));
+        self.theme.set_colormap(self.colormap);
+        self.engine.status_message = format!("Session restored, {:.4} MHz", self.engine.frequency / 1e6);
+    }
+
+    /// Keep the receiver's state for the next launch
+    fn save_session(&self) -> io::Result<()> {
+        let path = session::path().ok_or_else(|| io::Error::other("no config directory"))?;
+        let session = Session {
+            device: self.engine.remote.as_ref().map(|remote| remote.server.clone()),
+            frequency: self.engine.frequency,
+            sample_rate: self.engine.sample_rate,
+            gain: self.engine.gain,
+            vfos: self
+                .engine.vfos
+                .iter()
+                .map(|vfo| SessionVfo {
+                    mode: vfo.demod.mode(),
+                    offset_hz: vfo.demod.offset_hz(),
+                    route: Some(vfo.route.label().to_string()),
+                })
+                .collect(),
+            active_vfo: self.engine.active_vfo,
+            tab: self.current_tab,
+            view: self.view.name(),
+            full_screen: self.full_screen,
+            zoom: self.zoom,
+            pan_hz: self.pan_hz,
+            markers: self.markers,
+            controls_percent: Some(self.controls_percent),
+            samples_percent: Some(self.samples_percent),
+            fft_size: Some(self.engine.fft_size),
+            window: Some(self.engine.fft_window),
+            averages: Some(self.engine.fft_averages),
+            colormap: Some(self.colormap.unwrap_or("theme").to_string()),
+        };
+        session::save(&path, &session)
+    }
+
+    /// Indices of the bookmarks with the tag being browsed, or of all of them
+    pub fn shown_bookmarks(&self) -> Vec<usize> {
+        (0..self.bookmarks.len())
+            .filter(|&i| self.bookmark_tag.as_ref().is_none_or(|tag| self.bookmarks[i].tags.contains(tag)))
+            .collect()
+    }
+
+    /// Move the active VFO to the selected bookmark, retuning the hardware
+    /// only when the channel is outside the captured span
+    fn tune_bookmark(&mut self) {
+        let Some(&index) = self.shown_bookmarks().get(self.bookmark_selected) else {
+            return;
+        };
+        let bookmark = self.bookmarks[index].clone();
+        let offset = bookmark.frequency - self.engine.frequency;
+        if offset.abs() + bookmark.bandwidth / 2.0 > self.engine.sample_rate / 2.0 {
+            self.engine.frequency = bookmark.frequency.clamp(1e6, 6e9);
+            self.engine.vfos[self.engine.active_vfo].demod.set_offset(0.0);
+        } else {
+            self.engine.vfos[self.engine.active_vfo].demod.set_offset(offset);
+        }
+        self.engine.vfos[self.engine.active_vfo].demod.set_mode(bookmark.mode);
+        self.engine.status_message = format!("{}: {:.4} MHz {}", bookmark.name, bookmark.frequency / 1e6, bookmark.mode);
+    }
+
+    /// Scan the bookmarks shown or the range, or stop scanning
+    fn toggle_scan(&mut self) {
+        if self.engine.scanner.is_running() {
+            self.engine.scanner.stop();
+            self.engine.status_message = "Scan stopped".to_string();
+            return;
+        }
+        let channels: Vec<Channel> = if self.scan_bookmarks {
+            self.shown_bookmarks().into_iter().map(|i| Channel::from(&self.bookmarks[i])).collect()
+        } else {
+            let mode = self.engine.vfo().demod.mode();
+            let (start, stop, step) = self.scan_range.unwrap_or_else(|| {
+                let step = mode.passband();
+                let half = self.engine.sample_rate / 2.0 - step;
+                (self.engine.frequency - half, self.engine.frequency + half, step)
+            });
+            scanner::range(start, stop, step, mode)
+        };
+        let count = channels.len();
+        let Some(channel) = self.engine.scanner.start(channels, Instant::now()).cloned() else {
+            self.engine.status_message = "Nothing to scan".to_string();
+            return;
+        };
+        if !self.engine.is_streaming {
+            self.engine.start_streaming();
+        }
+        self.engine.tune_scan_channel(&channel);
+        self.engine.status_message = format!("Scanning {} channels for +{:.0} dB", count, self.engine.scanner.threshold_db);
+    }
+
+    /// Bookmark the active VFO, tagged with the tag being browsed
+    fn add_bookmark(&mut self) {
+        let frequency = self.engine.vfo_frequency(self.engine.vfo());
+        let mode = self.engine.vfo().demod.mode();
+        let bookmark = Bookmark {
+            name: format!("{:.4} MHz {}", frequency / 1e6, mode),
+            frequency,
+            mode,
+            bandwidth: mode.passband(),
+            tags: self.bookmark_tag.iter().cloned().collect(),
+        };
+        let name = bookmark.name.clone();
+        match self.bookmarks.iter().position(|b| b.name == name) {
+            Some(index) => self.bookmarks[index] = bookmark,
+            None => self.bookmarks.push(bookmark),
+        }
+        let index = self.bookmarks.iter().position(|b| b.name == name).unwrap_or(0);
+        self.bookmark_selected = self.shown_bookmarks().iter().position(|&i| i == index).unwrap_or(0);
+        match self.store_bookmarks() {
+            Ok(path) => self.engine.status_message = format!("Bookmarked {} in {}", name, path.display()),
+            Err(e) => log::warn!("Bookmark {} not saved: {}", name, e),
+        }
+    }
+
+    fn delete_bookmark(&mut self) {
+        let Some(&index) = self.shown_bookmarks().get(self.bookmark_selected) else {
+            return;
+        };
+        let bookmark = self.bookmarks.remove(index);
+        self.bookmark_selected = self.bookmark_selected.min(self.shown_bookmarks().len().saturating_sub(1));
+        match self.store_bookmarks() {
+            Ok(_) => self.engine.status_message = format!("Bookmark {} deleted", bookmark.name),
+            Err(e) => log::warn!("Bookmark {} deleted for this session only: {}", bookmark.name, e),
+        }
+    }
+
+    /// Browse the bookmarks with the next tag, then all of them again
+    fn next_bookmark_tag(&mut self) {
+        let mut tags: Vec<&String> = self.bookmarks.iter().flat_map(|b| &b.tags).collect();
+        tags.sort();
+        tags.dedup();
+        let next = match &self.bookmark_tag {
+            None => tags.first(),
+            Some(tag) => tags.iter().position(|t| *t == tag).and_then(|i| tags.get(i + 1)),
+        };
+        self.bookmark_tag = next.map(|tag| tag.to_string());
+        self.bookmark_selected = 0;
+        self.engine.status_message = match &self.bookmark_tag {
+            Some(tag) => format!("Bookmarks tagged {}", tag),
+            None => "All bookmarks".to_string(),
+        };
+    }
+
+    /// Save the latest spectrum and its average over the waterfall history as CSV
+    fn export_spectrum(&mut self) {
+        let path = Path::new(EXPORT_DIR).join(format!(
+            "spectrum_{}_{:.0}Hz.csv",
+            file_timestamp(SystemTime::now()),
+            self.engine.frequency
+        ));
+        let (offset, unit) = self.power_unit();
+        let history: Vec<&[f32]> = self.waterfall.iter().map(|(_, line)| line.as_slice()).collect();
+        let result = std::fs::create_dir_all(EXPORT_DIR).and_then(|()| {
+            export::spectrum_csv(
+                &path,
+                self.engine.frequency - self.engine.sample_rate / 2.0,
+                self.engine.bin_hz(),
+                &self.engine.spectrum_data,
+                &history,
+                offset,
+                unit,
+            )
+        });
+        match result {
+            Ok(()) => self.engine.status_message = format!(
+                "Spectrum saved to {} ({} bins, average of {} lines)",
+                path.display(),
+                self.engine.spectrum_data.len(),
+                history.len()
+            ),
+            Err(e) => log::warn!("Cannot save spectrum to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Save the waterfall history as an image
+    fn export_waterfall(&mut self) {
+        let path = Path::new(EXPORT_DIR).join(format!(
+            "waterfall_{}_{:.0}Hz.png",
+            file_timestamp(SystemTime::now()),
+            self.engine.frequency
+        ));
+        let (first_bin_hz, bin_hz) = (self.engine.frequency - self.engine.sample_rate / 2.0, self.engine.bin_hz());
+        let lines = self.waterfall.make_contiguous();
+        let title = format!(
+            "{:.6} MHz  {:.3} MS/s  {} lines",
+            self.engine.frequency / 1e6,
+            self.engine.sample_rate / 1e6,
+            lines.len()
+        );
+        let floor = self.engine.noise_floor;
+        let result = std::fs::create_dir_all(EXPORT_DIR).and_then(|()| {
+            export::waterfall_png(
+                &path,
+                lines,
+                first_bin_hz,
+                bin_hz,
+                &title,
+                &self.theme,
+                |level| (level - floor) / WATERFALL_RANGE_DB + 0.1,
+            )
+        });
+        match result {
+            Ok(()) => self.engine.status_message = format!("Waterfall saved to {}", path.display()),
+            Err(e) => log::warn!("Cannot save waterfall to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Save a drawn frame as ANSI text and as an image
+    fn export_screen(&mut self, buffer: &Buffer) {
+        self.screenshot_pending = false;
+        let stem = Path::new(EXPORT_DIR).join(format!("screen_{}", file_timestamp(SystemTime::now())));
+        let (ansi, png) = (stem.with_extension("ans"), stem.with_extension("png"));
+        let result = std::fs::create_dir_all(EXPORT_DIR)
+            .and_then(|()| std::fs::write(&ansi, export::screen_ansi(buffer)))
+            .and_then(|()| std::fs::write(&png, export::screen_png(buffer, &self.theme)));
+        match result {
+            Ok(()) => self.engine.status_message = format!("Screenshot saved to {} and {}", ansi.display(), png.display()),
+            Err(e) => log::warn!("Cannot save screenshot in {}/: {}", EXPORT_DIR, e),
+        }
+    }
+
+    /// Offset from dBFS to the displayed power unit, with its name
+    pub fn power_unit(&self) -> (f32, &'static str) {
+        match self.engine.calibration_db {
+            Some(calibration) if self.show_dbm => (calibration - self.engine.gain as f32, "dBm"),
+            _ => (0.0, "dBFS"),
+        }
+    }
+
+    /// Lowest and highest displayed frequency, kept inside the captured span
+    pub fn visible_span(&self) -> (f64, f64) {
+        let half = self.engine.sample_rate / self.zoom / 2.0;
+        let limit = self.engine.sample_rate / 2.0 - half;
+        let centre = self.engine.frequency + self.pan_hz.clamp(-limit, limit);
+        (centre - half, centre + half)
+    }
+
+    /// Select the next marker, placing it mid-screen if it is not shown yet
+    fn next_marker(&mut self) {
+        if self.markers[self.active_marker].is_some() {
+            self.active_marker = (self.active_marker + 1) % self.markers.len();
+        }
+        if self.markers[self.active_marker].is_none() {
+            let (low, high) = self.visible_span();
+            self.markers[self.active_marker] = Some((low + high) / 2.0);
+        }
+        self.engine.status_message = format!("Marker {} selected", self.active_marker + 1);
+    }
+
+    fn move_marker(&mut self, bins: f64) {
+        let (low, high) = (self.engine.frequency - self.engine.sample_rate / 2.0, self.engine.frequency + self.engine.sample_rate / 2.0);
+        let step = bins * self.engine.bin_hz();
+        if let Some(hz) = &mut self.markers[self.active_marker] {
+            *hz = (*hz + step).clamp(low, high);
+        }
+    }
+
+    fn set_zoom(&mut self, zoom: f64) {
+        self.zoom = zoom.clamp(1.0, MAX_ZOOM);
+        let limit = self.engine.sample_rate / 2.0 * (1.0 - 1.0 / self.zoom);
+        self.pan_hz = self.pan_hz.clamp(-limit, limit);
+        self.engine.status_message = format!("Spectrum zoom x{}", self.zoom);
+    }
+
+    fn pan(&mut self, hz: f64) {
+        let limit = self.engine.sample_rate / 2.0 * (1.0 - 1.0 / self.zoom);
+        self.pan_hz = (self.pan_hz + hz).clamp(-limit, limit);
+    }
+
+    pub fn on_mouse(&mut self, mouse: MouseEvent) {
+        self.by_user(|app| app.handle_mouse(mouse));
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        // Popups and overlays take the keyboard only
+        if self.dialog.is_some() || self.palette.is_some() || self.help.is_some() {
+            return;
+        }
+        let up = match mouse.kind {
+            MouseEventKind::ScrollUp => Some(true),
+            MouseEventKind::ScrollDown => Some(false),
+            _ => None,
+        };
+        if let Some(up) = up
+            && hit(self.readout_area, &mouse)
+        {
+            let step = if up { TUNING_STEPS[self.tuning_step] } else { -TUNING_STEPS[self.tuning_step] };
+            let mode = self.engine.vfo().demod.mode();
+            self.engine.tune_vfo(self.engine.vfo_frequency(self.engine.vfo()) + step, mode.passband());
+            return;
+        }
+        if let Some(up) = up
+            && hit(self.parameter_area, &mouse)
+        {
+            self.adjust_parameter(up);
+            return;
+        }
+        if mouse.kind == MouseEventKind::Down(MouseButton::Left)
+            && let Some(tab) = self.tab_areas.iter().position(|&area| hit(area, &mouse))
+        {
+            self.current_tab = tab;
+            self.digit_mode = false;
+            return;
+        }
+        if self.view != View::Spectrum {
+            return;
+        }
+        let plot = self.spectrum_plot;
+        let waterfall = self.waterfall_area;
+        let inside = mouse.column >= plot.left()
+            && mouse.column < plot.right()
+            && ((mouse.row >= plot.top() && mouse.row < plot.bottom())
+                || (mouse.row >= waterfall.top() && mouse.row < waterfall.bottom()));
+        let (low, high) = self.visible_span();
+        let hz_per_column = (high - low) / plot.width.max(1) as f64;
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) if inside => self.drag = Some((mouse.column, mouse.column)),
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some((start, last)) = self.drag {
+                    self.pan(-(mouse.column as f64 - last as f64) * hz_per_column);
+                    self.drag = Some((start, mouse.column));
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                // A click without dragging tunes to the frequency under the pointer
+                if let Some((start, _)) = self.drag.take()
+                    && start == mouse.column
+                {
+                    self.tune_to(low + mouse.column.saturating_sub(plot.left()) as f64 * hz_per_column + hz_per_column / 2.0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tune_to(&mut self, hz: f64) {
+        if self.click_tunes_lo {
+            self.engine.frequency = hz.clamp(1e6, 6e9);
+            self.engine.vfos[self.engine.active_vfo].demod.set_offset(0.0);
+            self.engine.status_message = format!("Tuned to {:.4} MHz", self.engine.frequency / 1e6);
+        } else {
+            self.engine.vfos[self.engine.active_vfo].demod.set_offset(hz - self.engine.frequency);
+            self.engine.status_message = format!(
+                "VFO {} at {:.4} MHz ({:+.1} kHz)",
+                self.engine.active_vfo + 1,
+                hz / 1e6,
+                self.engine.vfo().demod.offset_hz() / 1e3
+            );
+        }
+    }
+
+    /// Take the settings the TUI understands from the config file
+    pub fn apply_config(&mut self, config: &Config) {
+        self.engine.apply_config(config);
+        match Theme::from_config(config) {
+            Ok(theme) => self.theme = theme,
+            Err(e) => log::warn!("Config: {}", e),
+        }
+        let (keymap, errors) = Keymap::from_config(config);
+        self.keymap = keymap;
+        for e in errors {
+            log::warn!("Config: {}", e);
+        }
+        match config.get("ui.ascii") {
+            Some("true") => self.ascii = true,
+            Some("false") | None => {}
+            Some(other) => log::warn!("Config: ui.ascii must be true or false, not `{}`", other),
+        }
+        if let Some(value) = config.get("ui.fps") {
+            match value.parse::<f64>() {
+                Ok(fps) if FPS.contains(&fps) => self.frame_interval = Duration::from_secs_f64(1.0 / fps),
+                _ => log::warn!("Config: ui.fps must be {} to {} frames a second, not `{}`", FPS.start(), FPS.end(), value),
+            }
+        }
+        if let Some(value) = config.get("ui.waterfall_lines") {
+            match value.parse::<usize>() {
+                Ok(lines) if WATERFALL_LINES_RANGE.contains(&lines) => {
+                    self.waterfall_lines = lines;
+                    while self.waterfall.len() > lines {
+                        self.waterfall.pop_front();
+                    }
+                }
+                _ => log::warn!(
+                    "Config: ui.waterfall_lines must be {} to {}, not `{}`",
+                    WATERFALL_LINES_RANGE.start(),
+                    WATERFALL_LINES_RANGE.end(),
+                    value
+                ),
+            }
+        }
+        if let Some(value) = config.get("bandplan.region") {
+            match Region::parse(value) {
+                Some(region) => self.region = region,
+                None => log::warn!("Config: bandplan.region must be 1, 2 or 3, not `{}`", value),
+            }
+        }
+        if let Some(value) = config.get("scanner.range") {
+            let parts: Option<Vec<f64>> = value.split_whitespace().map(|v| parse_hz(v).ok()).collect();
+            match parts.as_deref() {
+                Some(&[start, stop, step]) if step > 0.0 && stop >= start => self.scan_range = Some((start, stop, step)),
+                _ => log::warn!("Config: scanner.range must be `START STOP STEP`, such as `144M 146M 25k`, not `{}`", value),
+            }
+        }
+        if self.engine.calibration_db.is_none() {
+            self.show_dbm = false;
+        }
+    }
+
+    /// Settings from the command line, over those of the config file
+    pub fn apply_options(&mut self, options: &Options) {
+        options.apply(&mut self.engine);
+        if let Some(fps) = options.fps {
+            self.frame_interval = Duration::from_secs_f64(1.0 / fps);
+        }
+        if self.engine.calibration_db.is_none() {
+            self.show_dbm = false;
+        }
+    }
+
+    /// Update the engine, then what the TUI keeps of its samples and decodes
+    fn tick(&mut self) {
+        if self.engine.tick() {
+            self.remember_trace();
+            self.scope.push(&self.engine.sample_buffer, &self.engine.audio_buffer);
+            self.histogram.process(&self.engine.sample_buffer, self.engine.sample_rate);
+        }
+        self.track_alerts();
+        self.track_messages(SystemTime::now());
+        self.track_toasts(Instant::now());
+    }
+
+    /// Take samples from `remote`, or the demo signals for `None`, showing
+    /// power in dBFS unless that device is calibrated
+    fn set_remote(&mut self, remote: Option<RemoteSource>) {
+        self.engine.set_remote(remote);
+        if self.engine.calibration_db.is_none() {
+            self.show_dbm = false;
+        }
+    }
+
+    /// Show the overlay, and ring the bell if enabled, for a new SAME alert
+    fn track_alerts(&mut self) {
+        if self.engine.same.alert_count == self.same_seen {
+            return;
+        }
+        self.same_seen = self.engine.same.alert_count;
+        self.alert_overlay = true;
+        if self.alert_bell {
+            let mut out = stdout();
+            let _ = out.write_all(b"\x07").and_then(|_| out.flush());
+        }
+    }
+
+    fn remember_trace(&mut self) {
+        if let Some(traces) = &mut self.persistence {
+            if traces.len() == PERSISTENCE_TRACES {
+                traces.pop_front();
+            }
+            traces.push_back(self.engine.spectrum_data.clone());
+        }
+
+        if self.waterfall.len() >= self.waterfall_lines {
+            self.waterfall.pop_front();
+        }
+        self.waterfall.push_back((SystemTime::now(), self.engine.spectrum_data.clone()));
+        // Keep a paused view on the same lines as new ones arrive
+        if let Some(scroll) = &mut self.waterfall_scroll {
+            *scroll = (*scroll + 1).min(self.waterfall.len().saturating_sub(1));
+        }
+    }
+
+    fn scroll_waterfall(&mut self, lines: isize) {
+        let newest = self.waterfall.len().saturating_sub(1);
+        let scroll = self.waterfall_scroll.unwrap_or(0) as isize + lines;
+        self.waterfall_scroll = Some(scroll.clamp(0, newest as isize) as usize);
+    }
+
+    /// Keep the status message when it changed since the last update
+    fn track_messages(&mut self, now: SystemTime) {
+        if self.engine.status_message.is_empty() || self.messages.back().is_some_and(|(_, last)| *last == self.engine.status_message) {
+            return;
+        }
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((now, self.engine.status_message.clone()));
+        // Those scrolled back to stay in place
+        if self.message_scroll > 0 {
+            self.message_scroll = (self.message_scroll + 1).min(self.messages.len() - 1);
+        }
+    }
+
+    /// Show what was logged since the last update as toasts, a repeat
+    /// refreshing the toast it repeats, and drop those shown long enough
+    fn track_toasts(&mut self, now: Instant) {
+        for entry in logging::since(self.toasted) {
+            self.toasted = entry.id;
+            let until = now + if entry.level == Level::Error { 2 * TOAST_SECS } else { TOAST_SECS };
+            match self.toasts.iter_mut().find(|toast| toast.level == entry.level && toast.message == entry.message) {
+                Some(toast) => (toast.count, toast.until) = (entry.count, until),
+                None => {
+                    if self.toasts.len() == MAX_TOASTS {
+                        self.toasts.pop_front();
+                    }
+                    self.toasts.push_back(Toast { level: entry.level, message: entry.message, count: entry.count, until });
+                }
+            }
+        }
+        self.toasts.retain(|toast| toast.until > now);
+    }
+
+    fn tuning(&self) -> Tuning {
+        Tuning {
+            frequency: self.engine.frequency,
+            gain: self.engine.gain,
+            vfos: self.engine.vfos.iter().map(|vfo| (vfo.demod.mode(), vfo.demod.offset_hz())).collect(),
+        }
+    }
+
+    /// Do `act` for the user, noting a change of tuning it makes as a step
+    /// to undo. Changes by the scanner, a script or remote control are not
+    /// steps, so undo goes back to what the user last chose.
+    fn by_user(&mut self, act: impl FnOnce(&mut Self)) {
+        let before = self.tuning();
+        self.history.restored = false;
+        act(self);
+        if !self.history.restored {
+            self.track_history(before, Instant::now());
+        }
+    }
+
+    /// Note a change of tuning from `before` as a step to undo, joining it
+    /// to the step before if that was under [`HISTORY_SETTLE`] ago
+    fn track_history(&mut self, before: Tuning, now: Instant) {
+        if before == self.tuning() {
+            return;
+        }
+        let history = &mut self.history;
+        if history.changed.is_none_or(|at| now.duration_since(at) >= HISTORY_SETTLE) {
+            history.undo.push(before);
+            if history.undo.len() > MAX_HISTORY {
+                history.undo.remove(0);
+            }
+        }
+        history.redo.clear();
+        history.changed = Some(now);
+    }
+
+    fn undo(&mut self) {
+        match self.history.undo.pop() {
+            Some(tuning) => {
+                self.history.redo.push(self.tuning());
+                self.restore_tuning(tuning);
+                self.engine.status_message = format!("Undone, {}", self.engine.status_message);
+            }
+            None => self.engine.status_message = "Nothing to undo".to_string(),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.history.redo.pop() {
+            Some(tuning) => {
+                self.history.undo.push(self.tuning());
+                self.restore_tuning(tuning);
+                self.engine.status_message = format!("Redone, {}", self.engine.status_message);
+            }
+            None => self.engine.status_message = "Nothing to redo".to_string(),
+        }
+    }
+
+    /// Go back to `tuning` without it counting as a change
+    fn restore_tuning(&mut self, tuning: Tuning) {
+        self.engine.frequency = tuning.frequency;
+        self.engine.gain = tuning.gain;
+        for (vfo, &(mode, offset)) in self.engine.vfos.iter_mut().zip(&tuning.vfos) {
+            vfo.demod.set_mode(mode);
+            vfo.demod.set_offset(offset);
+        }
+        self.history.changed = None;
+        self.history.restored = true;
+        self.engine.status_message = format!("{:.4} MHz, gain {:.0} dB, {}", self.engine.frequency / 1e6, self.engine.gain, self.engine.vfo().demod.mode());
+    }
+
+    /// Step the selected setting of the settings tab to its next value, or
+    /// its previous one
+    fn change_setting(&mut self, forward: bool) {
+        match Setting::ALL[self.setting_selected] {
+            Setting::FftSize => {
+                self.engine.fft_size = cycle(&FFT_SIZES, self.engine.fft_size, forward);
+                self.reset_spectrum();
+                self.engine.status_message = format!("FFT size {}, {:.0} Hz per bin", self.engine.fft_size, self.engine.sample_rate / self.engine.fft_size as f64);
+            }
+            Setting::Window => {
+                self.engine.fft_window = cycle(&Window::ALL, self.engine.fft_window, forward);
+                self.reset_spectrum();
+                self.engine.status_message = format!("Window {}", self.engine.fft_window.name());
+            }
+            Setting::Averages => {
+                self.engine.fft_averages = cycle(&SPECTRUM_AVERAGES, self.engine.fft_averages, forward);
+                self.reset_spectrum();
+                self.engine.status_message = format!("Averaging {} spectra", self.engine.fft_averages);
+            }
+            Setting::Colormap => {
+                let names: Vec<Option<&'static str>> = std::iter::once(None).chain(COLORMAPS.iter().map(|&(name, _)| Some(name))).collect();
+                self.colormap = cycle(&names, self.colormap, forward);
+                self.theme.set_colormap(self.colormap);
+                self.engine.status_message = format!("Colormap {}", self.colormap.unwrap_or("of the theme"));
+            }
+            Setting::VfoRoute => {
+                let vfo = &mut self.engine.vfos[self.engine.active_vfo];
+                vfo.route = cycle(&AudioRoute::ALL, vfo.route, forward);
+                self.engine.status_message = format!("VFO {} audio {}", self.engine.active_vfo + 1, vfo.route.label());
+            }
+        }
+    }
+
+    /// Measure spectra with the settings of the settings tab from the next
+    /// block, dropping those drawn with the old ones
+    fn reset_spectrum(&mut self) {
+        self.engine.reset_spectrum();
+        self.waterfall.clear();
+        self.waterfall_scroll = None;
+        if let Some(traces) = &mut self.persistence {
+            traces.clear();
+        }
+    }
+
+    fn adjust_parameter(&mut self, increase: bool) {
+        let delta = if increase { 1.0 } else { -1.0 };
+
+        match self.current_tab {
+            0 => { // Frequency tab
+                let step = TUNING_STEPS[self.tuning_step];
+                self.engine.frequency = (self.engine.frequency + delta * step).clamp(1e6, 6e9);
+            }
+            1 => { // Gain tab
+                let step = 1.0; // 1 dB steps
+                self.engine.gain = (self.engine.gain + delta * step).clamp(0.0, 60.0);
+            }
+            2 => { // Sample rate tab
+                let step = 0.1e6; // 0.1 MS/s steps
+                self.engine.sample_rate = (self.engine.sample_rate + delta * step).clamp(0.1e6, 10e6);
+            }
+            3 => { // AF gain tab
+                self.engine.af_gain_db = (self.engine.af_gain_db + delta as f32).clamp(-20.0, 40.0);
+            }
+            SETTINGS_TAB => { // Settings listed top down
+                let last = Setting::ALL.len() - 1;
+                self.setting_selected = if increase { self.setting_selected.saturating_sub(1) } else { (self.setting_selected + 1).min(last) };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Run the TUI application with the command-line `options`
+pub fn run_tui(options: &Options) -> Result<(), RfError> {
+    // Setup terminal
+    let panic_hook = restore_terminal_on_panic();
+    quit_on_signals();
+    enable_raw_mode().map_err(terminal_failed)?;
+    let mut stdout = stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture).map_err(terminal_failed)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend).map_err(terminal_failed)?;
+
+    // Create app and run it
+    let mut app = App::new();
+    match Config::load() {
+        Ok(config) => app.apply_config(&config),
+        Err(e) => log::warn!("Config not loaded: {}", e),
+    }
+    app.load_presets();
+    app.load_bookmarks();
+    if !options.fresh {
+        app.restore_session(options.source.is_none());
+    }
+    app.apply_options(options);
+    if app.engine.remote.is_some() {
+        app.engine.start_streaming();
+    }
+    let res = run_app(&mut terminal, &mut app);
+
+    // Restore terminal
+    disable_raw_mode().map_err(terminal_failed)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .map_err(terminal_failed)?;
+    terminal.show_cursor().map_err(terminal_failed)?;
+    restore_panic_hook(panic_hook);
+
+    res.map_err(terminal_failed)?;
+    if let Err(e) = app.save_session() {
+        eprintln!("Session not saved: {}", e);
+    }
+    Ok(())
+}
+
+/// Set by SIGINT or SIGTERM, quitting as the quit key does
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// The panic hook in place before the TUI's, called by it and put back after
+type PanicHook = Arc<dyn Fn(&std::panic::PanicHookInfo<'_>) + Send + Sync>;
+
+/// Put the terminal back before a panic of the TUI thread is reported, so
+/// the shell is left usable. Panics of other threads leave the TUI running.
+/// Returns the hook it wraps, for [`restore_panic_hook`].
+fn restore_terminal_on_panic() -> PanicHook {
+    let tui = std::thread::current().id();
+    let previous: PanicHook = Arc::from(std::panic::take_hook());
+    let report = Arc::clone(&previous);
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().id() == tui {
+            let _ = disable_raw_mode();
+            let _ = execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture, Show);
+        }
+        report(info);
+    }));
+    previous
+}
+
+/// Reinstate the hook [`restore_terminal_on_panic`] wrapped, once the
+/// terminal is back to normal
+fn restore_panic_hook(previous: PanicHook) {
+    std::panic::set_hook(Box::new(move |info| previous(info)));
+}
+
+/// Quit cleanly on SIGINT, which in raw mode only comes from another
+/// process, and on SIGTERM
+fn quit_on_signals() {
+    runtime::spawn(async {
+        // A signal that cannot be watched never comes
+        let interrupt = async {
+            if tokio::signal::ctrl_c().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{SignalKind, signal};
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(_) => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        tokio::select! {
+            _ = interrupt => {}
+            _ = terminate => {}
+        }
+        SIGNALLED.store(true, Ordering::Relaxed);
+    });
+}
+
+/// The error for a terminal that can no longer be drawn on or read
+fn terminal_failed(e: io::Error) -> RfError {
+    RfError::Ui(format!("Terminal failed: {}", e))
+}
+
+fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+) -> io::Result<()> {
+    let mut next_tick = Instant::now();
+    let mut next_frame = Instant::now();
+
+    loop {
+        // Redrawn at its own rate, slow links keeping up without slowing the
+        // updates behind the spectrum and decoders
+        if Instant::now() >= next_frame {
+            let frame = terminal.draw(|f| {
+                ui(f, app);
+                if app.ascii {
+                    ascii::to_ascii(f.buffer_mut());
+                }
+            })?;
+            if app.screenshot_pending {
+                app.export_screen(frame.buffer);
+            }
+            next_frame = (next_frame + app.frame_interval).max(Instant::now());
+        }
+
+        // Input is handled as it comes while waiting for the next tick or
+        // frame, the tick taking whatever the sources queued since the last
+        let timeout = next_tick.min(next_frame).saturating_duration_since(Instant::now());
+        if crossterm::event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) => app.on_key(Key::from(key)),
+                Event::Mouse(mouse) => app.on_mouse(mouse),
+                _ => {}
+            }
+        }
+        if Instant::now() >= next_tick {
+            app.tick();
+            next_tick = (next_tick + app.engine.tick_interval).max(Instant::now());
+        }
+
+        if app.should_quit || SIGNALLED.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn ui(f: &mut Frame, app: &mut App) {
+    let size = f.size();
+    f.render_widget(
+        Block::default().style(Style::default().fg(app.theme.text).bg(app.theme.background)),
+        size,
+    );
+
+    if app.full_screen && app.view == View::Spectrum {
+        (app.tab_areas, app.parameter_area, app.readout_area) = ([Rect::default(); TABS], Rect::default(), Rect::default());
+        (app.spectrum_plot, app.waterfall_area) = draw_spectrum_panel(f, size, app);
+        draw_overlays(f, size, app);
+        return;
+    }
+    if size.width < COMPACT_WIDTH || size.height < COMPACT_HEIGHT {
+        draw_compact(f, size, app);
+        draw_overlays(f, size, app);
+        return;
+    }
+
+    // Main layout
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Title bar
+            Constraint::Min(10),    // Main content
+            Constraint::Length(if app.engine.player.is_some() { 3 } else { 0 }),  // Playback transport
+            Constraint::Length(3),  // Status bar
+        ])
+        .split(size);
+
+    let header = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(app.controls_percent),
+            Constraint::Percentage((100 - app.controls_percent) * 4 / 7),
+            Constraint::Min(0),
+        ])
+        .split(chunks[0]);
+
+    // Demodulator frequency readout and S-meter either side of the title
+    let readout = Paragraph::new(format!(
+        "📻 VFO{} {} {:.6} MHz",
+        app.engine.active_vfo + 1,
+        app.engine.vfo().demod.mode(),
+        app.engine.vfo_frequency(app.engine.vfo()) / 1e6
+    ))
+        .style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.secondary))
+                .style(Style::default().bg(app.theme.background)),
+        );
+    f.render_widget(readout, header[0]);
+    app.readout_area = header[0];
+
+    let s_meter = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.secondary))
+                .title(if app.engine.calibration_db.is_some() { "S" } else { "S (uncal)" })
+                .style(Style::default().bg(app.theme.background)),
+        )
+        .gauge_style(Style::default().fg(if app.engine.s_meter.level_dbm() > S9_DBM { app.theme.alert } else { app.theme.good }))
+        .ratio(app.engine.s_meter.ratio() as f64)
+        .label(format!("{} {:.0} dBm", app.engine.s_meter.reading(), app.engine.s_meter.level_dbm()));
+    f.render_widget(s_meter, header[2]);
+
+    // Title bar with futuristic styling, the UTC time for satellite passes
+    // and digital mode time slots, and how long the receiver has streamed
+    let clock = Title::from(format!(" {} UTC ", format_utc_time(SystemTime::now()))).alignment(Alignment::Right);
+    let elapsed = match app.engine.streaming_since {
+        Some(since) => {
+            let secs = since.elapsed().as_secs();
+            format!(" ● {:02}:{:02}:{:02} ", secs / 3600, secs / 60 % 60, secs % 60)
+        }
+        None => " ○ --:--:-- ".to_string(),
+    };
+    let title = Paragraph::new("🛰️  SDR CONTROL TERMINAL  🛰️")
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.secondary))
+                .title(Title::from(elapsed).position(Position::Bottom).alignment(Alignment::Left))
+                .title(clock.position(Position::Bottom))
+                .title_style(Style::default().fg(app.theme.dim))
+                .style(Style::default().bg(app.theme.background)),
+        );
+    f.render_widget(title, header[1]);
+
+    // Main content area
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(app.controls_percent), Constraint::Percentage(100 - app.controls_percent)])
+        .split(chunks[1]);
+
+    // Left panel - Controls
+    (app.tab_areas, app.parameter_area) = draw_controls_panel(f, main_chunks[0], app);
+
+    // Right panel - Spectrum and data, or a decoder view
+    draw_view(f, main_chunks[1], app);
+
+    if let Some(player) = &app.engine.player {
+        draw_transport(f, chunks[2], player, app);
+    }
+
+    // Status bar
+    draw_status_bar(f, chunks[3], app);
+    draw_overlays(f, size, app);
+}
+
+/// A single column for small terminals: a line for the demodulator, the
+/// tabs and parameter without borders, the view, and a line of status
+fn draw_compact(f: &mut Frame, size: Rect, app: &mut App) {
+    let parameter = parameter_text(app);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),  // Demodulator and time
+            Constraint::Length(1),  // Tabs
+            Constraint::Length(parameter.height().min(7) as u16),  // Parameter
+            Constraint::Min(3),     // View
+            Constraint::Length(if app.engine.player.is_some() { 3 } else { 0 }),  // Playback transport
+            Constraint::Length(1),  // Status
+        ])
+        .split(size);
+
+    let elapsed = match app.engine.streaming_since {
+        Some(since) => {
+            let secs = since.elapsed().as_secs();
+            format!("● {:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        }
+        None => "○ --:--:--".to_string(),
+    };
+    let header = Line::from(vec![
+        Span::styled(
+            format!(
+                " VFO{} {} {:.6} MHz ",
+                app.engine.active_vfo + 1,
+                app.engine.vfo().demod.mode(),
+                app.engine.vfo_frequency(app.engine.vfo()) / 1e6
+            ),
+            Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!("{} {:.0} dBm ", app.engine.s_meter.reading(), app.engine.s_meter.level_dbm()),
+            Style::default().fg(if app.engine.s_meter.level_dbm() > S9_DBM { app.theme.alert } else { app.theme.good }),
+        ),
+        Span::styled(elapsed, Style::default().fg(app.theme.dim)),
+    ]);
+    f.render_widget(Paragraph::new(header).style(Style::default().bg(app.theme.background)), chunks[0]);
+    app.readout_area = chunks[0];
+
+    let (tabs, tab_areas) = control_tabs(app, chunks[1]);
+    f.render_widget(tabs, chunks[1]);
+    app.tab_areas = tab_areas;
+
+    let params = Paragraph::new(parameter).style(Style::default().fg(app.theme.text)).wrap(Wrap { trim: true });
+    f.render_widget(params, chunks[2]);
+    app.parameter_area = chunks[2];
+
+    draw_view(f, chunks[3], app);
+
+    if let Some(player) = &app.engine.player {
+        draw_transport(f, chunks[4], player, app);
+    }
+
+    let status = Paragraph::new(status_line(app)).style(Style::default().fg(app.theme.text).bg(app.theme.info));
+    f.render_widget(status, chunks[5]);
+}
+
+/// Draw the current view, the spectrum or a decoder, log or table
+fn draw_view(f: &mut Frame, area: Rect, app: &mut App) {
+    match app.view {
+        View::Spectrum => (app.spectrum_plot, app.waterfall_area) = draw_spectrum_panel(f, area, app),
+        View::Ais => draw_ais_panel(f, area, app),
+        View::Pager => draw_pager_panel(f, area, app),
+        View::Rtty => draw_rtty_panel(f, area, app),
+        View::Psk => draw_psk_panel(f, area, app),
+        View::Wspr => draw_wspr_panel(f, area, app),
+        View::Ft8 => draw_ft8_panel(f, area, app),
+        View::Dtmf => draw_dtmf_panel(f, area, app),
+        View::Cw => draw_cw_panel(f, area, app),
+        View::Ism => draw_ism_panel(f, area, app),
+        View::Navtex => draw_navtex_panel(f, area, app),
+        View::Plugins => draw_plugins_panel(f, area, app),
+        View::Constellation => draw_constellation_panel(f, area, app),
+        View::Bursts => draw_bursts_panel(f, area, app),
+        View::Measure => draw_measure_panel(f, area, app),
+        View::Scope => draw_scope_panel(f, area, app),
+        View::Histogram => draw_histogram_panel(f, area, app),
+        View::Schedule => draw_schedule_panel(f, area, app),
+        View::Presets => draw_presets_panel(f, area, app),
+        View::Bookmarks => draw_bookmarks_panel(f, area, app),
+        View::Scanner => draw_scanner_panel(f, area, app),
+        View::Log => draw_log_panel(f, area, app),
+        View::Messages => draw_messages_panel(f, area, app),
+        View::Device => draw_device_panel(f, area, app),
+        View::Network => draw_network_panel(f, area, app),
+        View::Keys => draw_keys_panel(f, area, app),
+    }
+}
+
+/// Draw what floats over the panels, alerts, toasts, the palette, a dialog
+/// and help
+fn draw_overlays(f: &mut Frame, size: Rect, app: &mut App) {
+    if app.alert_overlay {
+        draw_alert_overlay(f, size, app);
+    }
+    draw_toasts(f, size, app);
+    draw_palette(f, size, app);
+    if let Some(dialog) = &app.dialog {
+        dialog.draw(f, size, &app.theme);
+    }
+    draw_help_overlay(f, size, app);
+}
+
+/// Whether the mouse is over `area`
+fn hit(area: Rect, mouse: &MouseEvent) -> bool {
+    (area.left()..area.right()).contains(&mouse.column) && (area.top()..area.bottom()).contains(&mouse.row)
+}
+
+/// The frequency in MHz down to the hertz, the digit of the tuning step
+/// underlined, and lit in digit mode
+fn frequency_readout(app: &App) -> Text<'static> {
+    // A leading zero for a 10 MHz step below 10 MHz
+    let digits = format!("{:01$.6}", app.engine.frequency / 1e6, if app.tuning_step == 7 { 9 } else { 8 });
+    // Digits from the end, skipping the point between MHz and kHz
+    let selected = digits.len() - 1 - app.tuning_step - usize::from(app.tuning_step >= 6);
+    let style = match app.digit_mode {
+        true => Style::default().fg(app.theme.background).bg(app.theme.highlight),
+        false => Style::default().add_modifier(Modifier::UNDERLINED),
+    };
+    let hint = match app.digit_mode {
+        true => "←→ digit, ↑↓ change, [D] done",
+        false => "↑↓ adjust, [G] type, [D] digits",
+    };
+    Text::from(vec![
+        Line::from(vec![
+            Span::raw("Frequency: "),
+            Span::raw(digits[..selected].to_string()),
+            Span::styled(digits[selected..=selected].to_string(), style.add_modifier(Modifier::BOLD)),
+            Span::raw(digits[selected + 1..].to_string()),
+            Span::raw(" MHz"),
+        ]),
+        Line::from(""),
+        Line::from(hint),
+        Line::from(format!("Step: {}, [Z] to change", format_step(TUNING_STEPS[app.tuning_step]))),
+    ])
+}
+
+/// A power of ten of hertz as `1 kHz`, `10 MHz` and so on
+fn format_step(hz: f64) -> String {
+    match hz {
+        hz if hz >= 1e6 => format!("{} MHz", hz / 1e6),
+        hz if hz >= 1e3 => format!("{} kHz", hz / 1e3),
+        hz => format!("{} Hz", hz),
+    }
+}
+
+/// The tab titles fitted to `area`, the row they are drawn on, and where
+/// each title is
+fn control_tabs(app: &App, area: Rect) -> (Tabs<'static>, [Rect; TABS]) {
+    // Without the icons, then without the spaces, when they do not all fit
+    let (names, pad) = [(["📡 FREQ", "⚡ GAIN", "📊 RATE", "🔊 AF", "⚙ SET"], 1), (["FREQ", "GAIN", "RATE", "AF", "SET"], 1), (["FREQ", "GAIN", "RATE", "AF", "SET"], 0)]
+        .into_iter()
+        .find(|(names, pad)| names.iter().map(|t| Line::from(*t).width() as u16 + 2 * pad + 1).sum::<u16>() <= area.width + 1)
+        .unwrap_or((["FREQ", "GAIN", "RATE", "AF", "SET"], 0));
+    let titles: Vec<Line> = names
+        .iter()
+        .map(|t| Line::from(Span::styled(*t, Style::default().fg(app.theme.good))))
+        .collect();
+    // Each title with its padding, between the dividers
+    let mut tab_areas = [Rect::default(); TABS];
+    let mut x = area.x;
+    for (tab, title) in tab_areas.iter_mut().zip(&titles) {
+        let width = title.width() as u16 + 2 * pad;
+        *tab = Rect::new(x, area.y, width, 1).intersection(area);
+        x += width + 1;
+    }
+    let tabs = Tabs::new(titles)
+        .select(app.current_tab)
+        .padding(" ".repeat(pad as usize), " ".repeat(pad as usize))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
+    (tabs, tab_areas)
+}
+
+/// What the selected tab shows and adjusts
+fn parameter_text(app: &App) -> Text<'static> {
+    match app.current_tab {
+        0 => frequency_readout(app),
+        1 => Text::from(format!("Gain: {:.1} dB\n\nUse ↑↓ to adjust\nStep: 1 dB", app.engine.gain)),
+        2 => Text::from(format!("Sample Rate: {:.1} MS/s\n\nUse ↑↓ to adjust\nStep: 0.1 MS/s", app.engine.sample_rate / 1e6)),
+        3 => Text::from(format!("AF Gain: {:+.0} dB\n\nUse ↑↓ to adjust\nStep: 1 dB", app.engine.af_gain_db)),
+        SETTINGS_TAB => settings_list(app),
+        _ => Text::from("Unknown parameter"),
+    }
+}
+
+/// Draw the tabs, the parameter of the selected tab, the audio level and
+/// the actions, returning where each tab title and the parameter are
+fn draw_controls_panel(f: &mut Frame, area: Rect, app: &App) -> ([Rect; TABS], Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Tabs
+            Constraint::Min(5),     // Parameters
+            Constraint::Length(3),  // Audio level
+            Constraint::Length(10), // Actions
+        ])
+        .split(area);
+
+    // Tabs
+    let (tabs, tab_areas) = control_tabs(app, chunks[0].inner(&Margin::new(1, 1)));
+    let tabs = tabs.block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.info))
+            .title("CONTROLS")
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(tabs, chunks[0]);
+
+    // Parameter display
+    let param_text = parameter_text(app);
+
+    let params = Paragraph::new(param_text)
+        .style(Style::default().fg(app.theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.good))
+                .title("PARAMETER")
+                .title_style(Style::default().fg(app.theme.good)),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(params, chunks[1]);
+
+    // Audio VU meter over -60..0 dBFS
+    let level = app.engine.vu.level_db();
+    let clipping = app.engine.vu.is_clipping();
+    let vu = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(if clipping { app.theme.alert } else { app.theme.good }))
+                .title(if clipping { "AUDIO ■ CLIP" } else { "AUDIO" })
+                .title_style(Style::default().fg(if clipping { app.theme.alert } else { app.theme.good })),
+        )
+        .gauge_style(Style::default().fg(match level {
+            l if l > -6.0 => app.theme.alert,
+            l if l > -18.0 => app.theme.highlight,
+            _ => app.theme.good,
+        }))
+        .ratio(((level + 60.0) / 60.0).clamp(0.0, 1.0) as f64)
+        .label(format!("{:.1} dBFS", level));
+    f.render_widget(vu, chunks[2]);
+
+    // Action buttons
+    let streaming_action = format!(" [S] {} Streaming ", if app.engine.is_streaming { "Stop" } else { "Start" });
+    let recorder = app.engine.recorder.lock();
+    let recording_action = if recorder.is_recording() && recorder.container == Container::Sigmf {
+        " [R] Stop Recording (Shift+N mark) ".to_string()
+    } else if recorder.is_recording() {
+        " [R] Stop Recording ".to_string()
+    } else {
+        format!(" [R] Record IQ ({}, Shift+R) ", recorder.format_label())
+    };
+    drop(recorder);
+    let actions = [
+        " [C] Connect ".to_string(),
+        streaming_action,
+        recording_action,
+        if app.engine.player.is_some() { " [P] Stop Playback " } else { " [P] Play Recording " }.to_string(),
+        if app.engine.audio_recorder.is_recording() {
+            " [A] Stop Audio Recording ".to_string()
+        } else {
+            format!(" [A] Record Audio ({}, Shift+W) ", app.engine.audio_recorder.format)
+        },
+        " [V] Cycle View ".to_string(),
+        " Shift+X/I/S Save CSV/PNG/Screen ".to_string(),
+        " Ctrl+Z/Y Undo/Redo Tuning ".to_string(),
+        " [Q] Quit ".to_string(),
+    ];
+
+    let action_items: Vec<ListItem> = actions
+        .iter()
+        .map(|action| {
+            ListItem::new(Line::from(vec![Span::styled(
+                action.clone(),
+                Style::default().fg(if action.starts_with(" [S]") {
+                    if app.engine.is_streaming { app.theme.good } else { app.theme.highlight }
+                } else {
+                    app.theme.primary
+                }),
+            )]))
+        })
+        .collect();
+
+    let actions_list = List::new(action_items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.secondary))
+                .title("ACTIONS")
+                .title_style(Style::default().fg(app.theme.secondary)),
+        );
+    f.render_widget(actions_list, chunks[3]);
+
+    (tab_areas, chunks[1])
+}
+
+/// Returns the plot area of the spectrum chart, empty when not streaming
+fn draw_spectrum_panel(f: &mut Frame, area: Rect, app: &App) -> (Rect, Rect) {
+    // The spectrum takes a little more than the waterfall of what is left
+    let (spectrum, samples) = match app.full_screen {
+        true => (55, 0),
+        false => ((100 - app.samples_percent) * 9 / 16, app.samples_percent),
+    };
+    let constraints = [
+        Constraint::Percentage(spectrum),
+        Constraint::Percentage(100 - spectrum - samples),
+        Constraint::Percentage(samples),
+    ];
+    let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints).split(area);
+
+    let (mut plot, mut waterfall) = (Rect::default(), Rect::default());
+    if app.engine.is_streaming {
+        plot = draw_spectrum_chart(f, chunks[0], app);
+        waterfall = draw_waterfall(f, chunks[1], plot, app);
+    } else {
+        let idle = Paragraph::new("SPECTRUM ANALYSIS\n\nNot streaming...\nPress 'S' to start")
+            .style(Style::default().fg(app.theme.good))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(app.theme.primary))
+                    .title("SPECTRUM")
+                    .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+            );
+        f.render_widget(idle, chunks[0]);
+    }
+    if app.full_screen {
+        return (plot, waterfall);
+    }
+
+    // Sample data display
+    let sample_text = if !app.engine.sample_buffer.is_empty() {
+        let mut display = String::new();
+        if let Some(player) = &app.engine.player {
+            display.push_str(&playback_summary(player));
+        }
+        display.push_str("RECENT SAMPLES\n\n");
+
+        for (i, sample) in app.engine.sample_buffer.iter().take(5).enumerate() {
+            display.push_str(&format!("{:2}: {:.4} + j{:.4}\n", i, sample.re, sample.im));
+        }
+
+        // Basic stats
+        let total_power: f32 = app.engine.sample_buffer.iter().map(|s| s.norm_sqr()).sum();
+        let avg_power = total_power / app.engine.sample_buffer.len() as f32;
+        display.push_str(&format!("\nAvg Power: {:.6}", avg_power));
+
+        if let Some(c) = &app.engine.classifier.lock().latest {
+            display.push_str(&format!(
+                "\nSignal: {}  BW {:.1} kHz  SNR {:.1} dB\n  sidebands {:+.0} dB  env CV {:.2}  carrier {:.0}%  f-kurtosis {:.1}  PSK line {:.0}",
+                c.class,
+                c.bandwidth_hz / 1e3,
+                c.snr_db,
+                c.asymmetry_db,
+                c.envelope_cv,
+                c.carrier_fraction * 100.0,
+                c.frequency_kurtosis,
+                c.psk_line
+            ));
+        }
+
+        display
+    } else {
+        "RECENT SAMPLES\n\nNo data available".to_string()
+    };
+
+    let samples = Paragraph::new(sample_text)
+        .style(Style::default().fg(app.theme.highlight))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.highlight))
+                .title("SAMPLES")
+                .title_style(Style::default().fg(app.theme.highlight)),
+        )
+        .wrap(Wrap { trim: true });
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[2]);
+    f.render_widget(samples, bottom[0]);
+    draw_vfo_list(f, bottom[1], app);
+    (plot, waterfall)
+}
+
+/// Source file, its metadata and position for the samples panel
+fn playback_summary(player: &FilePlayer) -> String {
+    let meta = &player.meta;
+    let (position, duration) = player.progress();
+    let name = player.path().file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let mut summary = format!(
+        "PLAYBACK {} {:.3} MS/s @ {:.6} MHz | {:.1}/{:.1} s\n",
+        meta.format.sigmf_datatype(),
+        meta.sample_rate / 1e6,
+        meta.frequency / 1e6,
+        position,
+        duration
+    );
+    if !meta.annotations.is_empty() {
+        let labels: Vec<String> = meta
+            .annotations
+            .iter()
+            .map(|a| {
+                format!(
+                    "{} @ {:.1} s ({:.1} s)",
+                    a.label,
+                    a.sample_start as f64 / meta.sample_rate,
+                    a.sample_count as f64 / meta.sample_rate
+                )
+            })
+            .collect();
+        summary.push_str(&format!("Annotations: {}\n", labels.join(", ")));
+    }
+    if let Some(datetime) = &meta.datetime {
+        summary.push_str(&format!("Recorded {}\n", datetime));
+    }
+    if let Some(description) = &meta.description {
+        summary.push_str(&format!("{}\n", description));
+    }
+    summary.push_str(&format!("{}\n", name));
+    summary.push('\n');
+    summary
+}
+
+fn draw_vfo_list(f: &mut Frame, area: Rect, app: &App) {
+    let items: Vec<ListItem> = app
+        .engine.vfos
+        .iter()
+        .enumerate()
+        .map(|(i, vfo)| {
+            let power = 10.0 * vfo.demod.channel_power().max(1e-20).log10();
+            let style = Style::default().fg(app.theme.vfos[i % app.theme.vfos.len()]);
+            ListItem::new(format!(
+                "{}{} {:<3} {:.4} MHz {:+.1}k {:.0} dBFS {}",
+                if i == app.engine.active_vfo { "▶" } else { " " },
+                i + 1,
+                vfo.demod.mode(),
+                app.engine.vfo_frequency(vfo) / 1e6,
+                vfo.demod.offset_hz() / 1e3,
+                power,
+                vfo.route.label()
+            ))
+            .style(if i == app.engine.active_vfo { style.add_modifier(Modifier::BOLD) } else { style })
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.highlight))
+            .title("VFOS | [N] add [X] del [1-9] [U] mode [H] audio")
+            .title_style(Style::default().fg(app.theme.highlight)),
+    );
+    f.render_widget(list, area);
+}
+
+/// Waterfall of past spectrum lines, newest at the top, with columns lined up under the
+/// spectrum plot. Returns the area the lines are drawn in.
+fn draw_waterfall(f: &mut Frame, area: Rect, plot: Rect, app: &App) -> Rect {
+    let state = match app.waterfall_scroll {
+        None => "LIVE".to_string(),
+        Some(0) => "PAUSED".to_string(),
+        Some(back) => format!("PAUSED -{} lines", back),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if app.waterfall_scroll.is_some() { app.theme.highlight } else { app.theme.primary }))
+        .title(format!(
+            "WATERFALL {} | {}/{} lines | [Space] pause  [PgUp/PgDn] scroll",
+            state,
+            app.waterfall.len(),
+            app.waterfall_lines
+        ))
+        .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let lines_area = Rect::new(plot.x, inner.y, plot.width.min(inner.right().saturating_sub(plot.x)), inner.height);
+
+    let (low, high) = app.visible_span();
+    let first_bin = app.engine.frequency - app.engine.sample_rate / 2.0;
+    let bin_hz = app.engine.sample_rate / app.engine.spectrum_data.len() as f64;
+    let column_hz = (high - low) / lines_area.width.max(1) as f64;
+    // Bins covered by each column, at least the nearest one when zoomed in
+    let columns: Vec<(usize, usize)> = (0..lines_area.width)
+        .map(|c| {
+            let from = ((low + c as f64 * column_hz - first_bin) / bin_hz).floor().max(0.0) as usize;
+            let to = ((low + (c + 1) as f64 * column_hz - first_bin) / bin_hz).ceil() as usize;
+            (from, to.max(from + 1))
+        })
+        .collect();
+
+    let skip = app.waterfall_scroll.unwrap_or(0);
+    let lines: Vec<Line> = app
+        .waterfall
+        .iter()
+        .rev()
+        .skip(skip)
+        .take(lines_area.height as usize)
+        .map(|(_, data)| {
+            Line::from(
+                columns
+                    .iter()
+                    .map(|&(from, to)| {
+                        let level = data
+                            .get(from..to.min(data.len()))
+                            .and_then(|bins| bins.iter().copied().reduce(f32::max))
+                            .unwrap_or(app.engine.noise_floor);
+                        let t = (level - app.engine.noise_floor) / WATERFALL_RANGE_DB + 0.1;
+                        Span::styled(" ", Style::default().bg(app.theme.waterfall_color(t)))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), lines_area);
+    lines_area
+}
+
+fn plot_marker(app: &App) -> Marker {
+    if app.braille { Marker::Braille } else { Marker::Dot }
+}
+
+/// Spectrum line plot over the visible span with the noise floor as a reference line,
+/// returning the area the trace is drawn in
+fn draw_spectrum_chart(f: &mut Frame, area: Rect, app: &App) -> Rect {
+    let (offset, unit) = app.power_unit();
+    let to_db = |dbfs: f32| (dbfs + offset) as f64;
+    let (bottom, top) = (SPECTRUM_MIN_DB + offset as f64, offset as f64);
+    let (low, high) = app.visible_span();
+    let (start, stop) = (low / 1e6, high / 1e6);
+    let first_bin = app.engine.frequency - app.engine.sample_rate / 2.0;
+    let bin_hz = app.engine.sample_rate / app.engine.spectrum_data.len() as f64;
+
+    // Only the bins in view, plus one either side so the trace reaches the edges
+    let points = |data: &[f32]| -> Vec<(f64, f64)> {
+        data.iter()
+            .enumerate()
+            .map(|(i, &level)| ((first_bin + i as f64 * bin_hz) / 1e6, to_db(level)))
+            .filter(|&(mhz, _)| mhz >= start - bin_hz / 1e6 && mhz <= stop + bin_hz / 1e6)
+            .collect()
+    };
+    let trace = points(&app.engine.spectrum_data);
+    // Older traces fade towards black; the newest one is `trace` itself
+    let history: Vec<&Vec<f32>> = app.persistence.iter().flatten().collect();
+    let faded: Vec<(Color, Vec<(f64, f64)>)> = history
+        .iter()
+        .rev()
+        .skip(1)
+        .enumerate()
+        .rev()
+        .map(|(age, data)| {
+            (app.theme.faded(age as f32 / PERSISTENCE_TRACES as f32), points(data))
+        })
+        .collect();
+    let noise_floor_db = to_db(app.engine.noise_floor);
+    let floor = [(start, noise_floor_db), (stop, noise_floor_db)];
+
+    let marker_lines: Vec<[(f64, f64); 2]> = app
+        .markers
+        .iter()
+        .map(|m| m.map_or([(0.0, 0.0); 2], |hz| [(hz / 1e6, bottom), (hz / 1e6, top)]))
+        .collect();
+
+    let vfo_lines: Vec<[(f64, f64); 2]> = app
+        .engine.vfos
+        .iter()
+        .map(|vfo| {
+            let mhz = app.engine.vfo_frequency(vfo) / 1e6;
+            [(mhz, bottom), (mhz, top)]
+        })
+        .collect();
+
+    let marker = plot_marker(app);
+    let mut datasets: Vec<Dataset> = faded
+        .iter()
+        .map(|(color, data)| {
+            Dataset::default()
+                .marker(marker)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(*color))
+                .data(data)
+        })
+        .collect();
+    datasets.extend(vfo_lines.iter().enumerate().map(|(i, line)| {
+        Dataset::default()
+            .marker(marker)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.vfos[i % app.theme.vfos.len()]))
+            .data(line)
+    }));
+    datasets.extend([
+        Dataset::default()
+            .marker(marker)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.dim))
+            .data(&floor),
+        Dataset::default()
+            .marker(marker)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.trace))
+            .data(&trace),
+    ]);
+    for (i, line) in marker_lines.iter().enumerate() {
+        if app.markers[i].is_some() {
+            datasets.push(
+                Dataset::default()
+                    .marker(marker)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(app.theme.markers[i]))
+                    .data(line),
+            );
+        }
+    }
+
+    // Marker readouts and the delta between them
+    let readings: Vec<Option<(f64, f32)>> = app
+        .markers
+        .iter()
+        .map(|m| m.and_then(|hz| Some((hz, app.engine.power_at(hz)? + offset))))
+        .collect();
+    let mut readout: Vec<Span> = Vec::new();
+    for (i, reading) in readings.iter().enumerate() {
+        if let Some((hz, db)) = reading {
+            let active = if i == app.active_marker { "*" } else { " " };
+            readout.push(Span::styled(
+                format!(" {}M{} {:.4} MHz {:.1} {} ", active, i + 1, hz / 1e6, db, unit),
+                Style::default().fg(app.theme.markers[i]),
+            ));
+        }
+    }
+    if let (Some((f1, p1)), Some((f2, p2))) = (readings[0], readings[1]) {
+        readout.push(Span::styled(
+            format!(" Δ {:+.3} kHz {:+.1} dB ", (f2 - f1) / 1e3, p2 - p1),
+            Style::default().fg(app.theme.text).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    let mut modes = if app.zoom > 1.0 { format!(" | zoom x{}", app.zoom) } else { String::new() };
+    if app.persistence.is_some() {
+        modes.push_str(" | persistence");
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.primary))
+        .title(format!(
+            "SPECTRUM | floor {:.1} {}{} | [P] {} [D] unit [+-0] zoom [,.] pan [M<>] markers [E] persist [O] {}",
+            noise_floor_db,
+            unit,
+            modes,
+            if app.braille { "braille" } else { "dots" },
+            if app.click_tunes_lo { "LO" } else { "demod" }
+        ))
+        .title(Title::from(Line::from(readout)).position(Position::Bottom))
+        .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height < 4 {
+        return Rect::default();
+    }
+
+    // The chart leaves the bottom two rows for our own frequency axis, since its X labels
+    // are spread evenly rather than placed at round frequencies
+    let chart_area = Rect::new(inner.x, inner.y, inner.width, inner.height - 2);
+    let y_labels = [
+        format!("{:.0}", bottom),
+        format!("{:.0}", (bottom + top) / 2.0),
+        format!("{:.0}", top),
+    ];
+    // The chart puts the Y axis right of its widest label
+    let label_width = y_labels.iter().map(|l| l.len() as u16).max().unwrap_or(0).min(inner.width / 3);
+    let plot = Rect::new(
+        inner.x + label_width + 1,
+        inner.y,
+        inner.width.saturating_sub(label_width + 1),
+        chart_area.height,
+    );
+
+    let (step, decimals) = tick_step(stop - start, plot.width);
+    let ticks: Vec<f64> = ((start / step).ceil() as i64..=(stop / step).floor() as i64)
+        .map(|i| i as f64 * step)
+        .collect();
+    let rows = (plot.height as usize).max(1);
+    let grid: Vec<(f64, f64)> = ticks
+        .iter()
+        .flat_map(|&x| (0..rows).step_by(2).map(move |k| (x, bottom + (top - bottom) * k as f64 / rows as f64)))
+        .collect();
+    let centre_mhz = app.engine.frequency / 1e6;
+    let centre_line = [(centre_mhz, bottom), (centre_mhz, top)];
+    datasets.splice(
+        0..0,
+        [
+            Dataset::default()
+                .marker(Marker::Dot)
+                .graph_type(GraphType::Scatter)
+                .style(Style::default().fg(app.theme.dim))
+                .data(&grid),
+            Dataset::default()
+                .marker(marker)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(app.theme.info))
+                .data(&centre_line),
+        ],
+    );
+
+    let label = |text: &String| Span::styled(text.clone(), Style::default().fg(app.theme.dim));
+    let chart = Chart::new(datasets)
+        .x_axis(Axis::default().bounds([start, stop]))
+        .y_axis(
+            Axis::default()
+                .title(unit)
+                .style(Style::default().fg(app.theme.label))
+                .bounds([bottom, top])
+                .labels(y_labels.iter().map(label).collect()),
+        );
+    f.render_widget(chart, chart_area);
+
+    // Axis line with a tick under each grid line, the centre frequency marked, and labels
+    let column = |mhz: f64| ((mhz - start) / (stop - start) * plot.width as f64) as usize;
+    let mut axis: Vec<char> = vec!['─'; plot.width as usize];
+    let mut labels: Vec<char> = vec![' '; plot.width as usize];
+    for &tick in &ticks {
+        let c = column(tick).min(axis.len().saturating_sub(1));
+        axis[c] = '┴';
+        let text = format!("{:.*}", decimals, tick);
+        let from = c.saturating_sub(text.len() / 2);
+        // Skip labels that would run into their neighbour or off the edge
+        if from + text.len() <= labels.len() && labels[from.saturating_sub(1)..from + text.len()].iter().all(|&ch| ch == ' ') {
+            labels.splice(from..from + text.len(), text.chars());
+        }
+    }
+    if (start..stop).contains(&centre_mhz) {
+        axis[column(centre_mhz)] = '▲';
+    }
+    let axis_line = Line::from(vec![
+        Span::styled("└", Style::default().fg(app.theme.label)),
+        Span::styled(axis.into_iter().collect::<String>(), Style::default().fg(app.theme.label)),
+    ]);
+    let label_line = Line::from(vec![
+        Span::styled(format!("{:>width$}", "MHz", width = label_width as usize + 1), Style::default().fg(app.theme.label)),
+        Span::styled(labels.into_iter().collect::<String>(), Style::default().fg(app.theme.dim)),
+    ]);
+    f.render_widget(
+        Paragraph::new(vec![axis_line, label_line]),
+        Rect::new(plot.x - 1, inner.y + chart_area.height, plot.width + 1, 2).intersection(inner),
+    );
+    plot
+}
+
+/// Round tick spacing in MHz giving ticks at least [`TICK_COLUMNS`] apart, and the decimals
+/// needed to print it
+fn tick_step(span_mhz: f64, columns: u16) -> (f64, usize) {
+    let min_step = span_mhz * TICK_COLUMNS as f64 / columns.max(1) as f64;
+    let magnitude = 10f64.powf(min_step.log10().floor());
+    let step = [1.0, 2.0, 2.5, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= min_step)
+        .unwrap_or(10.0 * magnitude);
+    let mut decimals = (-step.log10().floor()).max(0.0) as usize;
+    if (step / 10f64.powi(-(decimals as i32))).fract() > 1e-6 {
+        decimals += 1;
+    }
+    (step, decimals.min(6))
+}
+
+fn draw_ais_panel(f: &mut Frame, area: Rect, app: &App) {
+    let ais = app.engine.ais.lock();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+        .split(area);
+
+    let header = Row::new(["MMSI", "NAME", "SOG", "LAT", "LON", "AGE"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+
+    let mut vessels: Vec<_> = ais.vessels.values().collect();
+    vessels.sort_by_key(|v| std::cmp::Reverse(v.last_seen));
+
+    let rows: Vec<Row> = vessels
+        .iter()
+        .map(|v| {
+            let opt = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+            Row::new([
+                Cell::from(format!("{:09}", v.mmsi)),
+                Cell::from(opt(v.name.clone())),
+                Cell::from(opt(v.sog.map(|s| format!("{:.1} kn", s)))),
+                Cell::from(opt(v.lat.map(|l| format!("{:.5}", l)))),
+                Cell::from(opt(v.lon.map(|l| format!("{:.5}", l)))),
+                Cell::from(format!("{}s", v.last_seen.elapsed().as_secs())),
+            ])
+        })
+        .collect();
+
+    let title = format!(
+        "AIS VESSELS ({}) | channels in band: {} | frames ok/bad: {}/{}",
+        vessels.len(),
+        ais.active_channels(),
+        ais.frames_ok,
+        ais.frames_bad
+    );
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(10),
+            Constraint::Min(12),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Length(11),
+            Constraint::Length(6),
+        ],
+    )
+    .header(header)
+    .style(Style::default().fg(app.theme.text))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, chunks[0]);
+
+    let visible = chunks[1].height.saturating_sub(2) as usize;
+    let nmea_lines: Vec<ListItem> = ais
+        .nmea
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+
+    let nmea_title = format!(
+        "NMEA [N] log to {}: {}",
+        AIS_NMEA_LOG,
+        if ais.is_logging_nmea() { "ON" } else { "OFF" }
+    );
+    let nmea = List::new(nmea_lines)
+        .style(Style::default().fg(app.theme.good))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.good))
+                .title(nmea_title)
+                .title_style(Style::default().fg(app.theme.good)),
+        );
+    f.render_widget(nmea, chunks[1]);
+}
+
+fn draw_pager_panel(f: &mut Frame, area: Rect, app: &App) {
+    let pager = app.engine.pager.lock();
+    // Newest messages at the bottom, scrolling up as more arrive
+    let visible = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = pager
+        .messages
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|msg| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format_utc_time(msg.received), Style::default().fg(app.theme.dim)),
+                Span::styled(format!(" {:<10}", msg.protocol.to_string()), Style::default().fg(app.theme.secondary)),
+                Span::styled(format!("{:>8}/{} ", msg.address, msg.function), Style::default().fg(app.theme.primary)),
+                Span::styled(msg.text.clone(), Style::default().fg(app.theme.text)),
+            ]))
+        })
+        .collect();
+
+    let title = format!("PAGER POCSAG 512/1200/2400 + FLEX 1600 ({} messages)", pager.messages.len());
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.secondary))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.secondary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(list, area);
+}
+
+/// Keep only the tail of `text` that fits in a wrapped pane of the given size
+fn tail_for_area(text: &str, area: Rect) -> String {
+    let width = area.width.saturating_sub(2).max(1) as usize;
+    let height = area.height.saturating_sub(2) as usize;
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(chars.chunks(width).map(|c| c.iter().collect()));
+    }
+    let skip = lines.len().saturating_sub(height);
+    lines[skip..].join("\n")
+}
+
+fn draw_rtty_panel(f: &mut Frame, area: Rect, app: &App) {
+    let rtty = app.engine.rtty.lock();
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(20), Constraint::Length(30)])
+        .split(area);
+
+    let text_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.good))
+        .title(format!(
+            "RTTY {} Hz / {} Bd  [H] shift [B] baud",
+            rtty.shift, rtty.baud
+        ))
+        .title_style(Style::default().fg(app.theme.good).add_modifier(Modifier::BOLD));
+    let text = Paragraph::new(tail_for_area(&rtty.text, chunks[0]))
+        .style(Style::default().fg(app.theme.text))
+        .block(text_block);
+    f.render_widget(text, chunks[0]);
+
+    // Crossed-bananas: mark filter on X, space filter on Y
+    let peak = rtty
+        .scope
+        .iter()
+        .map(|&(x, y)| x.abs().max(y.abs()))
+        .fold(1e-6f32, f32::max) as f64;
+    let points: Vec<(f64, f64)> = rtty
+        .scope
+        .iter()
+        .map(|&(x, y)| (x as f64 / peak, y as f64 / peak))
+        .collect();
+    let bananas = Canvas::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.highlight))
+                .title("TUNING")
+                .title_style(Style::default().fg(app.theme.highlight)),
+        )
+        .x_bounds([-1.0, 1.0])
+        .y_bounds([-1.0, 1.0])
+        .paint(move |ctx| {
+            ctx.draw(&Points {
+                coords: &points,
+                color: app.theme.highlight,
+            });
+        });
+    f.render_widget(bananas, chunks[1]);
+}
+
+fn draw_psk_panel(f: &mut Frame, area: Rect, app: &App) {
+    let psk = app.engine.psk.lock();
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(20), Constraint::Length(30)])
+        .split(area);
+
+    let text_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.good))
+        .title(format!(
+            "{}  AFC {:+.1} Hz  [M] mode",
+            psk.mode.name(),
+            psk.afc_hz
+        ))
+        .title_style(Style::default().fg(app.theme.good).add_modifier(Modifier::BOLD));
+    let text = Paragraph::new(tail_for_area(&psk.text, chunks[0]))
+        .style(Style::default().fg(app.theme.text))
+        .block(text_block);
+    f.render_widget(text, chunks[0]);
+
+    // Phase scope: a clean signal shows two dots on the horizontal axis
+    let points: Vec<(f64, f64)> = psk
+        .scope
+        .iter()
+        .map(|p| (p.re as f64, p.im as f64))
+        .collect();
+    let scope = Canvas::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.highlight))
+                .title("PHASE")
+                .title_style(Style::default().fg(app.theme.highlight)),
+        )
+        .x_bounds([-1.2, 1.2])
+        .y_bounds([-1.2, 1.2])
+        .paint(move |ctx| {
+            ctx.draw(&Points {
+                coords: &[(0.0, 0.0)],
+                color: app.theme.dim,
+            });
+            ctx.draw(&Points {
+                coords: &points,
+                color: app.theme.highlight,
+            });
+        });
+    f.render_widget(scope, chunks[1]);
+}
+
+fn draw_wspr_panel(f: &mut Frame, area: Rect, app: &App) {
+    let wspr = app.engine.wspr.lock();
+    let header = Row::new(["UTC", "CALL", "GRID", "dBm", "SNR", "DT", "FREQ MHz"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+
+    let visible = area.height.saturating_sub(3) as usize;
+    let rows: Vec<Row> = wspr
+        .spots
+        .iter()
+        .rev()
+        .take(visible)
+        .map(|spot| {
+            let (_, time) = utc_date_time(spot.window_start);
+            Row::new([
+                Cell::from(time[..5].to_string()),
+                Cell::from(spot.callsign.clone()),
+                Cell::from(spot.grid.clone()),
+                Cell::from(spot.dbm.to_string()),
+                Cell::from(format!("{:.0}", spot.snr)),
+                Cell::from(format!("{:.1}", spot.dt)),
+                Cell::from(format!("{:.6}", spot.frequency / 1e6)),
+            ])
+        })
+        .collect();
+
+    let band = match wspr.dial {
+        Some(dial) => format!(
+            "{:.4} MHz | window {:.0}%{}",
+            dial / 1e6,
+            wspr.window_progress() * 100.0,
+            if wspr.decoding { " | decoding" } else { "" }
+        ),
+        None => "not on a WSPR sub-band".to_string(),
+    };
+    let upload = match (&wspr.last_upload, wspr.upload) {
+        (Some(status), true) => status.clone(),
+        (None, true) => "upload ON".to_string(),
+        _ => "[U] upload OFF".to_string(),
+    };
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(6),
+            Constraint::Min(8),
+            Constraint::Length(5),
+            Constraint::Length(4),
+            Constraint::Length(4),
+            Constraint::Length(5),
+            Constraint::Length(11),
+        ],
+    )
+    .header(header)
+    .style(Style::default().fg(app.theme.text))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(format!("WSPR {} | {}", band, upload))
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
+
+fn draw_ft8_panel(f: &mut Frame, area: Rect, app: &App) {
+    let ft8 = app.engine.ft8.lock();
+    let header = Row::new(["UTC", "dB", "DT", "FREQ", "CALL", "GRID", "MESSAGE"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+
+    let visible = area.height.saturating_sub(3) as usize;
+    let rows: Vec<Row> = ft8
+        .decodes
+        .iter()
+        .rev()
+        .take(visible)
+        .map(|decode| {
+            let (_, time) = utc_date_time(decode.cycle_start);
+            let style = if decode.message.starts_with("CQ ") {
+                Style::default().fg(app.theme.good)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            Row::new([
+                Cell::from(time.replace(':', "")),
+                Cell::from(format!("{:+}", decode.snr)),
+                Cell::from(format!("{:.1}", decode.dt)),
+                Cell::from(decode.audio_hz.to_string()),
+                Cell::from(decode.callsign.clone().unwrap_or_default()),
+                Cell::from(decode.grid.clone().unwrap_or_default()),
+                Cell::from(decode.message.clone()),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let status = match (ft8.dial, &ft8.last_error) {
+        (None, _) => "not on an FT8 sub-band".to_string(),
+        (Some(_), Some(error)) => error.clone(),
+        (Some(dial), None) => format!(
+            "{:.3} MHz | cycle {:.0}%{}",
+            dial / 1e6,
+            ft8.cycle_progress() * 100.0,
+            if ft8.decoding { " | decoding" } else { "" }
+        ),
+    };
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(7),
+            Constraint::Length(4),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Min(20),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(format!("FT8 {}", status))
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
+
+fn draw_dtmf_panel(f: &mut Frame, area: Rect, app: &App) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = app
+        .engine.dtmf
+        .sequences
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|sequence| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format_utc_time(sequence.started), Style::default().fg(app.theme.dim)),
+                Span::raw("  "),
+                Span::styled(
+                    sequence.digits.clone(),
+                    Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD),
+                ),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.highlight))
+            .title(format!("DTMF (NFM audio) | {} sequences", app.engine.dtmf.sequences.len()))
+            .title_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(list, area);
+}
+
+fn draw_plugins_panel(f: &mut Frame, area: Rect, app: &App) {
+    let Some(plugin) = app.engine.plugins.plugins.get(app.plugin_selected) else {
+        let help = Paragraph::new("No decoder plugins. Name their libraries under [plugins] in the config file, such as\n\n[plugins]\nmydecoder = \"/usr/local/lib/libmydecoder.so\"")
+            .style(Style::default().fg(app.theme.dim))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(app.theme.highlight))
+                    .title("PLUGINS")
+                    .title_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)),
+            );
+        f.render_widget(help, area);
+        return;
+    };
+
+    let visible = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = plugin
+        .messages
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|message| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format_utc_time(message.received), Style::default().fg(app.theme.dim)),
+                Span::raw("  "),
+                Span::styled(message.text.clone(), Style::default().fg(app.theme.text)),
+            ]))
+        })
+        .collect();
+
+    let status = plugin.decoder.status();
+    let title = format!(
+        "{} ({} of {}) | {} messages{}{} | [[ ]] decoder",
+        plugin.decoder.name().to_uppercase(),
+        app.plugin_selected + 1,
+        app.engine.plugins.plugins.len(),
+        plugin.total,
+        if status.is_empty() { "" } else { " | " },
+        status
+    );
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.highlight))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(list, area);
+}
+
+fn draw_cw_panel(f: &mut Frame, area: Rect, app: &App) {
+    let cw = app.engine.cw.lock();
+    let key = if cw.key_down {
+        Span::styled(" KEY ", Style::default().fg(app.theme.background).bg(app.theme.good))
+    } else {
+        Span::styled(" KEY ", Style::default().fg(app.theme.dim))
+    };
+    let title = Line::from(vec![
+        Span::styled(
+            format!("CW {:.0} WPM ", cw.wpm()),
+            Style::default().fg(app.theme.good).add_modifier(Modifier::BOLD),
+        ),
+        key,
+    ]);
+
+    let text = Paragraph::new(tail_for_area(&cw.text, area))
+        .style(Style::default().fg(app.theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.good))
+                .title(title),
+        );
+    f.render_widget(text, area);
+}
+
+fn draw_ism_panel(f: &mut Frame, area: Rect, app: &App) {
+    let ism = app.engine.ism.lock();
+    let visible = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = ism
+        .records
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|record| ListItem::new(record.to_json()))
+        .collect();
+
+    let title = format!(
+        "ISM {} | packets {} | [J] log to {}: {}",
+        ism.protocol_names().join(", "),
+        ism.packets,
+        ISM_JSON_LOG,
+        if ism.is_logging() { "ON" } else { "OFF" }
+    );
+    let list = List::new(items)
+        .style(Style::default().fg(app.theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.primary))
+                .title(title)
+                .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+        );
+    f.render_widget(list, area);
+}
+
+fn draw_navtex_panel(f: &mut Frame, area: Rect, app: &App) {
+    let navtex = app.engine.navtex.lock();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(area);
+
+    let visible = chunks[0].height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = navtex
+        .messages
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|message| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format_utc_time(message.received), Style::default().fg(app.theme.dim)),
+                Span::raw("  "),
+                Span::styled(
+                    format!("{}{}{}", message.station, message.subject, message.number),
+                    Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw("  "),
+                Span::styled(message.subject_name(), Style::default().fg(app.theme.primary)),
+                Span::raw("  "),
+                Span::raw(message.text.lines().next().unwrap_or_default().to_string()),
+            ]))
+        })
+        .collect();
+
+    let title = match navtex.carrier {
+        Some(carrier) => format!(
+            "NAVTEX {:.1} kHz | {} | offset {:+.0} Hz | {} messages | [L] log to {}: {}",
+            carrier / 1e3,
+            if navtex.is_locked() { "LOCKED" } else { "PHASING" },
+            navtex.offset_hz(),
+            navtex.messages.len(),
+            NAVTEX_LOG,
+            if navtex.is_logging() { "ON" } else { "OFF" }
+        ),
+        None => "NAVTEX | tune near 490 kHz, 518 kHz or 4209.5 kHz".to_string(),
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.highlight))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(list, chunks[0]);
+
+    let text = Paragraph::new(tail_for_area(&navtex.text, chunks[1]))
+        .style(Style::default().fg(app.theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.highlight))
+                .title(format!("SITOR-B text | {} uncorrectable", navtex.errors)),
+        );
+    f.render_widget(text, chunks[1]);
+}
+
+fn draw_constellation_panel(f: &mut Frame, area: Rect, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(8), Constraint::Length(3)])
+        .split(area);
+
+    let constellation = app.engine.constellation.lock();
+    let demod = &constellation.demod;
+    let config = demod.config();
+    let points: Vec<(f64, f64)> = constellation.points.iter().map(|p| (p.re as f64, p.im as f64)).collect();
+    let ideal: Vec<(f64, f64)> = match config.order {
+        PskOrder::Bpsk => vec![(-1.0, 0.0), (1.0, 0.0)],
+        PskOrder::Qpsk => {
+            let a = std::f64::consts::FRAC_1_SQRT_2;
+            vec![(a, a), (-a, a), (-a, -a), (a, -a)]
+        }
+    };
+    let title = format!(
+        "{} {} Bd | carrier {:+.1} Hz | EVM {:.0}%",
+        config.order.name(),
+        config.baud,
+        demod.frequency_offset(),
+        demod.evm() * 100.0
+    );
+    let plots = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[0]);
+    let canvas = Canvas::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.secondary))
+                .title(title)
+                .title_style(Style::default().fg(app.theme.secondary).add_modifier(Modifier::BOLD)),
+        )
+        .x_bounds([-1.5, 1.5])
+        .y_bounds([-1.5, 1.5])
+        .paint(move |ctx| {
+            ctx.draw(&Points {
+                coords: &points,
+                color: app.theme.highlight,
+            });
+            ctx.draw(&Points {
+                coords: &ideal,
+                color: app.theme.alert,
+            });
+        });
+    f.render_widget(canvas, plots[0]);
+    draw_eye_diagram(f, plots[1], app, &constellation);
+
+    // Hard decisions of the most recent symbols
+    let width = chunks[1].width.saturating_sub(2) as usize;
+    let per_symbol = config.order.bits_per_symbol() + 1;
+    let decisions: Vec<String> = constellation
+        .points
+        .iter()
+        .rev()
+        .take(width / per_symbol)
+        .rev()
+        .map(|&s| format!("{:0width$b}", demod.decide(s), width = config.order.bits_per_symbol()))
+        .collect();
+    let bits = Paragraph::new(decisions.join(" "))
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title("SYMBOLS | [M] order  [B] baud  [P] braille"));
+    f.render_widget(bits, chunks[1]);
+}
+
+/// Channel samples folded on the recovered symbol clock, two symbol periods wide
+fn draw_eye_diagram(f: &mut Frame, area: Rect, app: &App, constellation: &Constellation) {
+    // Each sample is drawn at its offset from the nearest symbol centre and one period later
+    let fold = |component: fn(&Complex32) -> f32| -> Vec<(f64, f64)> {
+        constellation.eye
+            .iter()
+            .flat_map(|(t, x)| [(*t as f64, component(x) as f64), (*t as f64 + 1.0, component(x) as f64)])
+            .collect()
+    };
+    let in_phase = fold(|x| x.re);
+    let quadrature = match constellation.demod.config().order {
+        PskOrder::Bpsk => Vec::new(),
+        PskOrder::Qpsk => fold(|x| x.im),
+    };
+    let title = match constellation.demod.config().order {
+        PskOrder::Bpsk => "EYE I",
+        PskOrder::Qpsk => "EYE I (yellow) / Q (cyan)",
+    };
+    let canvas = Canvas::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.secondary))
+                .title(title)
+                .title_style(Style::default().fg(app.theme.secondary).add_modifier(Modifier::BOLD)),
+        )
+        .marker(plot_marker(app))
+        .x_bounds([-0.5, 1.5])
+        .y_bounds([-1.5, 1.5])
+        .paint(move |ctx| {
+            ctx.draw(&Points {
+                coords: &quadrature,
+                color: app.theme.primary,
+            });
+            ctx.draw(&Points {
+                coords: &in_phase,
+                color: app.theme.highlight,
+            });
+            // Symbol centres, where the eye should be widest open
+            for t in [0.0, 1.0] {
+                ctx.draw(&canvas::Line {
+                    x1: t,
+                    y1: -1.5,
+                    x2: t,
+                    y2: 1.5,
+                    color: app.theme.dim,
+                });
+            }
+        });
+    f.render_widget(canvas, area);
+}
+
+fn draw_bursts_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["UTC", "LENGTH", "PEAK", "FREQ MHz", "FILE"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+
+    let visible = area.height.saturating_sub(3) as usize;
+    let rows: Vec<Row> = app
+        .engine.bursts
+        .bursts
+        .iter()
+        .rev()
+        .take(visible)
+        .map(|burst| {
+            Row::new(vec![
+                Cell::from(format_utc_time(burst.started)),
+                Cell::from(format!("{:.1} ms", burst.duration.as_secs_f64() * 1e3)),
+                Cell::from(format!("{:+.1} dB", burst.peak_db)),
+                Cell::from(format!("{:.4}", burst.center_freq / 1e6)),
+                Cell::from(
+                    burst
+                        .path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                ),
+            ])
+        })
+        .collect();
+
+    let state = match (app.engine.burst_capture, app.engine.bursts.is_triggered()) {
+        (false, _) => "OFF",
+        (true, false) => "ARMED",
+        (true, true) => "CAPTURING",
+    };
+    let title = match &app.engine.bursts.last_error {
+        Some(e) => format!("BURSTS {} | {}", state, e),
+        None => format!(
+            "BURSTS {} | floor {:.1} dB | trigger +{:.0} dB | {} in {}/ | [K] arm  [ / ] trigger",
+            state,
+            app.engine.bursts.noise_floor_db(),
+            app.engine.bursts.threshold_db,
+            app.engine.bursts.bursts.len(),
+            app.engine.bursts.dir().display()
+        ),
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(11),
+            Constraint::Min(20),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(if app.engine.bursts.is_triggered() { app.theme.alert } else { app.theme.primary }))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
+
+fn draw_scope_panel(f: &mut Frame, area: Rect, app: &App) {
+    let (trace, triggered) = app.scope.capture();
+    let (rate, limit, source) = match app.scope.source {
+        ScopeSource::Iq => (app.engine.sample_rate, 1.0, "I/Q"),
+        ScopeSource::Audio => (app.engine.vfo().demod.rate(), std::f64::consts::PI, "audio"),
+    };
+    let span_ms = app.scope.window() as f64 / rate * 1e3;
+    let time = |i: usize| i as f64 / rate * 1e3;
+
+    let first: Vec<(f64, f64)> = trace.iter().enumerate().map(|(i, &(a, _))| (time(i), a as f64)).collect();
+    let second: Vec<(f64, f64)> = trace.iter().enumerate().map(|(i, &(_, b))| (time(i), b as f64)).collect();
+    let level: Vec<(f64, f64)> = app
+        .scope
+        .trigger
+        .map(|level| vec![(0.0, level as f64), (span_ms, level as f64)])
+        .unwrap_or_default();
+
+    let marker = plot_marker(app);
+    let mut datasets = vec![Dataset::default()
+        .marker(marker)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(app.theme.dim))
+        .data(&level)];
+    if app.scope.source == ScopeSource::Iq {
+        datasets.push(
+            Dataset::default()
+                .name("Q")
+                .marker(marker)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(app.theme.secondary))
+                .data(&second),
+        );
+    }
+    datasets.push(
+        Dataset::default()
+            .name(if app.scope.source == ScopeSource::Iq { "I" } else { "audio" })
+            .marker(marker)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.primary))
+            .data(&first),
+    );
+
+    let trigger = match app.scope.trigger {
+        Some(level) => format!("trigger {:+.2} {}", level, if triggered { "TRIG'D" } else { "WAIT" }),
+        None => "trigger off".to_string(),
+    };
+    let label = |text: String| Span::styled(text, Style::default().fg(app.theme.dim));
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.primary))
+                .title(format!(
+                    "SCOPE {} | {} samples | {} | [I] source  [ / ] timebase  [T] trigger  [+/-] level",
+                    source,
+                    app.scope.window(),
+                    trigger
+                ))
+                .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+        )
+        .x_axis(
+            Axis::default()
+                .title("ms")
+                .style(Style::default().fg(app.theme.label))
+                .bounds([0.0, span_ms])
+                .labels(vec![
+                    label("0".to_string()),
+                    label(format!("{:.2}", span_ms / 2.0)),
+                    label(format!("{:.2}", span_ms)),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(app.theme.label))
+                .bounds([-limit, limit])
+                .labels(vec![
+                    label(format!("{:.1}", -limit)),
+                    label("0".to_string()),
+                    label(format!("{:.1}", limit)),
+                ]),
+        );
+    f.render_widget(chart, area);
+}
+
+fn draw_histogram_panel(f: &mut Frame, area: Rect, app: &App) {
+    let (i, q) = app.histogram.bins();
+    let largest = i.iter().chain(q).fold(1.0f32, |m, &c| m.max(c));
+    let points = |counts: &[f32]| -> Vec<(f64, f64)> {
+        counts
+            .iter()
+            .enumerate()
+            .map(|(k, &c)| ((k as f64 + 0.5) / counts.len() as f64 * 2.0 - 1.0, (c / largest * 100.0) as f64))
+            .collect()
+    };
+    let (i_points, q_points) = (points(i), points(q));
+    let rails = [[(-1.0, 0.0), (-1.0, 100.0)], [(1.0, 0.0), (1.0, 100.0)]];
+
+    let overloaded = app.histogram.is_overloaded();
+    let rail_color = if overloaded { app.theme.alert } else { app.theme.dim };
+    let marker = plot_marker(app);
+    let mut datasets: Vec<Dataset> = rails
+        .iter()
+        .map(|rail| {
+            Dataset::default()
+                .marker(marker)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(rail_color))
+                .data(rail)
+        })
+        .collect();
+    datasets.extend([
+        Dataset::default()
+            .name("Q")
+            .marker(marker)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.secondary))
+            .data(&q_points),
+        Dataset::default()
+            .name("I")
+            .marker(marker)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.primary))
+            .data(&i_points),
+    ]);
+
+    let title = format!(
+        "HISTOGRAM | peak {:.1} dBFS | {} of {} bins used{}",
+        app.histogram.peak_dbfs(),
+        app.histogram.occupied_bins(),
+        i.len(),
+        if overloaded { " | ■ OVERLOAD, reduce gain" } else { "" }
+    );
+    let border = if overloaded { app.theme.alert } else { app.theme.primary };
+    let label = |text: &str| Span::styled(text.to_string(), Style::default().fg(app.theme.dim));
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border))
+                .title(title)
+                .title_style(Style::default().fg(border).add_modifier(Modifier::BOLD)),
+        )
+        .x_axis(
+            Axis::default()
+                .title("full scale")
+                .style(Style::default().fg(app.theme.label))
+                .bounds([-1.0, 1.0])
+                .labels(vec![label("-1"), label("0"), label("1")]),
+        )
+        .y_axis(
+            Axis::default()
+                .title("%")
+                .style(Style::default().fg(app.theme.label))
+                .bounds([0.0, 100.0])
+                .labels(vec![label("0"), label("50"), label("100")]),
+        );
+    f.render_widget(chart, area);
+}
 
-    if let Err(err) = res {
-        println!("{:?}", err);
+/// `1h02m`, `12m05s` or `40s`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, _) => format!("{}h{:02}m", h, m),
     }
-
-    Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(
-    terminal: &mut Terminal<B>,
-    app: &mut App,
-) -> io::Result<()> {
-    let mut last_tick = Instant::now();
+fn draw_schedule_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["JOB", "START UTC", "LENGTH", "FREQ MHz", "MODE", "STATE"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
 
-    loop {
-        terminal.draw(|f| ui(f, app))?;
+    let now = SystemTime::now();
+    let rows: Vec<Row> = app
+        .engine.schedule
+        .jobs
+        .iter()
+        .map(|job| {
+            let since_epoch = job.start.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            let (date, clock) = utc_date_time(since_epoch.as_secs());
+            let (state, color) = match &job.state {
+                JobState::Pending => (
+                    format!("in {}", format_duration(job.start.duration_since(now).unwrap_or_default())),
+                    app.theme.text,
+                ),
+                JobState::Recording => (
+                    format!("● REC {} left", format_duration(job.end().duration_since(now).unwrap_or_default())),
+                    app.theme.alert,
+                ),
+                JobState::Done { path, bytes } => (
+                    format!(
+                        "done {} ({})",
+                        path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+                        format_size(*bytes)
+                    ),
+                    app.theme.good,
+                ),
+                JobState::Missed => ("missed".to_string(), app.theme.dim),
+                JobState::Cancelled => ("cancelled".to_string(), app.theme.dim),
+                JobState::Failed(e) => (format!("failed: {}", e), app.theme.alert),
+            };
+            Row::new(vec![
+                Cell::from(job.label.clone()),
+                Cell::from(format!("{} {}", date, clock)),
+                Cell::from(format_duration(job.duration)),
+                Cell::from(format!("{:.4}", job.frequency / 1e6)),
+                Cell::from(job.mode.to_string()),
+                Cell::from(state),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
 
-        let timeout = Duration::from_millis(100);
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                app.on_key(key.code);
-            }
-        }
+    let title = if app.engine.schedule.jobs.is_empty() {
+        "SCHEDULE | no jobs, add `label = \"03:12 12m 137.1M fm\"` under [schedule] in the config file".to_string()
+    } else {
+        format!(
+            "SCHEDULE | {} pending | recording {} | [X] cancel next",
+            app.engine.schedule.pending(),
+            app.engine.recorder.lock().format_label()
+        )
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(12),
+            Constraint::Length(20),
+            Constraint::Length(8),
+            Constraint::Length(11),
+            Constraint::Length(5),
+            Constraint::Min(20),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
 
-        // Handle streaming logic
-        if app.is_streaming {
-            // Continuously update mock data for demo
-            simulate_streaming_data(app);
-        }
+fn draw_presets_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["KEY", "NAME", "FREQ MHz", "RATE MS/s", "GAIN", "MODE", "DEVICE"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
 
-        if app.should_quit {
-            break;
-        }
+    let rows: Vec<Row> = app
+        .presets
+        .iter()
+        .enumerate()
+        .map(|(i, preset)| {
+            let style = if i == app.preset_selected {
+                Style::default().fg(app.theme.background).bg(app.theme.primary)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            Row::new(vec![
+                Cell::from(if i < 9 { format!("F{}", i + 1) } else { String::new() }),
+                Cell::from(preset.name.clone()),
+                Cell::from(format!("{:.4}", preset.frequency / 1e6)),
+                Cell::from(format!("{:.3}", preset.sample_rate / 1e6)),
+                Cell::from(format!("{:.1}", preset.gain)),
+                Cell::from(preset.mode.to_string()),
+                Cell::from(preset.device.clone().unwrap_or_else(|| "demo".to_string())),
+            ])
+            .style(style)
+        })
+        .collect();
 
-        // Throttle updates
-        let elapsed = last_tick.elapsed();
-        if elapsed < Duration::from_millis(50) {
-            std::thread::sleep(Duration::from_millis(50) - elapsed);
-        }
-        last_tick = Instant::now();
-    }
+    let title = if app.presets.is_empty() {
+        "PRESETS | none yet, [W] saves the current setup".to_string()
+    } else {
+        format!("PRESETS | {} | [Enter] or F1-F9 recall | [[ ]] select | [W] save current | [X] delete", app.presets.len())
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(4),
+            Constraint::Min(16),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(5),
+            Constraint::Length(22),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
 
-    Ok(())
+fn draw_keys_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["ACTION", "KEYS", "WHERE"]).style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
+
+    // Keep the selection in view
+    let visible = area.height.saturating_sub(3) as usize;
+    let first = (app.key_selected + 1).saturating_sub(visible);
+    let rows: Vec<Row> = ACTIONS
+        .iter()
+        .enumerate()
+        .skip(first)
+        .map(|(i, &(_, name, context, _))| {
+            let style = if i == app.key_selected {
+                Style::default().fg(app.theme.background).bg(if app.key_capture { app.theme.highlight } else { app.theme.primary })
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            let keys: Vec<String> = app.keymap.keys(i).iter().map(Key::to_string).collect();
+            Row::new(vec![Cell::from(name), Cell::from(keys.join(" ")), Cell::from(context.label())]).style(style)
+        })
+        .collect();
+
+    let title = if app.key_capture {
+        format!("KEYS | press the key for {}, [Esc] to cancel", ACTIONS[app.key_selected].1)
+    } else {
+        "KEYS | [Enter] rebind | [[ ]] select | [X] unbind | [W] save to the config file".to_string()
+    };
+    let table = Table::new(rows, [Constraint::Length(20), Constraint::Length(16), Constraint::Min(16)])
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.primary))
+                .title(title)
+                .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+        );
+    f.render_widget(table, area);
 }
 
-fn simulate_streaming_data(app: &mut App) {
-    if !app.is_streaming {
-        return;
-    }
+fn draw_bookmarks_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["NAME", "FREQ MHz", "MODE", "BW kHz", "TAGS"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
 
-    // Simulate some spectrum data for demo purposes
-    for i in 0..app.spectrum_data.len() {
-        let freq = i as f32 / app.spectrum_data.len() as f32;
-        let noise = (rand::random::<f32>() - 0.5) * 0.05; // Reduced noise
+    let shown = app.shown_bookmarks();
+    // Keep the selection in view on long lists
+    let visible = area.height.saturating_sub(3) as usize;
+    let first = (app.bookmark_selected + 1).saturating_sub(visible);
+    let rows: Vec<Row> = shown
+        .iter()
+        .enumerate()
+        .skip(first)
+        .map(|(position, &i)| {
+            let bookmark = &app.bookmarks[i];
+            let style = if position == app.bookmark_selected {
+                Style::default().fg(app.theme.background).bg(app.theme.primary)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            Row::new(vec![
+                Cell::from(bookmark.name.clone()),
+                Cell::from(format!("{:.4}", bookmark.frequency / 1e6)),
+                Cell::from(bookmark.mode.to_string()),
+                Cell::from(format!("{:.1}", bookmark.bandwidth / 1e3)),
+                Cell::from(bookmark.tags.join(", ")),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let title = if app.bookmarks.is_empty() {
+        "BOOKMARKS | none yet, [W] bookmarks the active VFO".to_string()
+    } else {
+        format!(
+            "BOOKMARKS | {} of {} | tag {} | [Enter] tune | [[ ]] select | [T] tag | [W] add VFO | [X] delete",
+            shown.len(),
+            app.bookmarks.len(),
+            app.bookmark_tag.as_deref().unwrap_or("any")
+        )
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(16),
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Length(7),
+            Constraint::Min(16),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
 
-        // Create multiple signal peaks based on frequency settings
-        let center_freq = (app.frequency / 1e9) as f32; // Normalize to 0-1 range (assuming 0-1GHz)
-        let signal1 = if (freq - center_freq).abs() < 0.05 { 0.7 } else { 0.0 };
-        let signal2 = if (freq - (center_freq + 0.1)).abs() < 0.03 { 0.5 } else { 0.0 };
-        let signal3 = if (freq - 0.8).abs() < 0.02 { 0.3 } else { 0.0 }; // Background signal
+fn draw_scanner_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["UTC", "FREQ MHz", "NAME", "MODE", "PEAK", "ACTIVE"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
 
-        let signal = signal1 + signal2 + signal3;
-        app.spectrum_data[i] = (signal + noise).max(0.0).min(1.0);
-    }
+    let visible = area.height.saturating_sub(3) as usize;
+    let rows: Vec<Row> = app
+        .engine.scanner
+        .hits
+        .iter()
+        .rev()
+        .take(visible)
+        .map(|hit| {
+            Row::new(vec![
+                Cell::from(format_utc_time(hit.time)),
+                Cell::from(format!("{:.4}", hit.channel.frequency / 1e6)),
+                Cell::from(hit.channel.name.clone()),
+                Cell::from(hit.channel.mode.to_string()),
+                Cell::from(format!("{:+.1} dB", hit.peak_db)),
+                Cell::from(format!("{:.1} s", hit.duration.as_secs_f64())),
+            ])
+        })
+        .collect();
 
-    // Simulate some sample data with realistic IQ characteristics
-    app.sample_buffer.clear();
-    for _ in 0..20 { // Show more samples
-        // Generate IQ samples with some correlation (realistic SDR behavior)
-        let phase_noise = rand::random::<f32>() * 0.1;
-        let amplitude_noise = rand::random::<f32>() * 0.2;
+    let source = if app.scan_bookmarks { "bookmarks" } else { "range" };
+    let state = match (app.engine.scanner.state, app.engine.scanner.current()) {
+        (ScanState::Active { .. }, Some(channel)) => format!("STOPPED ON {}", channel.name),
+        (ScanState::Settling { .. }, Some(channel)) => {
+            format!("{} {}/{}", channel.name, app.engine.scanner.index + 1, app.engine.scanner.channels.len())
+        }
+        _ => format!("OFF, {}", source),
+    };
+    let title = format!(
+        "SCANNER {} | +{:.0} dB | {} hits{} | [Enter] scan [B] {} [N] skip [[ ]] threshold [L] log [X] clear",
+        state,
+        app.engine.scanner.threshold_db,
+        app.engine.scanner.hits.len(),
+        if app.engine.scanner.is_logging() { " logged" } else { "" },
+        if app.scan_bookmarks { "range" } else { "bookmarks" }
+    );
+    let active = matches!(app.engine.scanner.state, ScanState::Active { .. });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Min(16),
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Length(8),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(if active { app.theme.alert } else { app.theme.primary }))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
 
-        let base_amplitude = 0.8 + amplitude_noise;
-        let phase = rand::random::<f32>() * std::f32::consts::PI * 2.0 + phase_noise;
+fn draw_log_panel(f: &mut Frame, area: Rect, app: &App) {
+    let entries: Vec<Entry> = logging::since(0).into_iter().filter(|entry| entry.level <= app.log_level).collect();
+    let visible = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = entries
+        .iter()
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|entry| {
+            let colour = match entry.level {
+                Level::Error => app.theme.alert,
+                Level::Warn => app.theme.highlight,
+                _ => app.theme.text,
+            };
+            let repeats = if entry.count > 1 { format!(" (x{})", entry.count) } else { String::new() };
+            ListItem::new(Line::from(vec![
+                Span::styled(format_utc_time(entry.time), Style::default().fg(app.theme.dim)),
+                Span::raw("  "),
+                Span::styled(format!("{:<5}", entry.level), Style::default().fg(colour).add_modifier(Modifier::BOLD)),
+                Span::raw("  "),
+                Span::styled(entry.target.clone(), Style::default().fg(app.theme.dim)),
+                Span::raw("  "),
+                Span::styled(format!("{}{}", entry.message, repeats), Style::default().fg(colour)),
+            ]))
+        })
+        .collect();
 
-        let re = base_amplitude * phase.cos();
-        let im = base_amplitude * phase.sin();
-        app.sample_buffer.push(Complex32::new(re, im));
-    }
+    let shown = match app.log_level {
+        Level::Error => "errors",
+        Level::Warn => "warnings and errors",
+        _ => "everything",
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(format!("LOG | {} | {} entries | [[ ]] level", shown, entries.len()))
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(list, area);
 }
 
-fn ui(f: &mut Frame, app: &mut App) {
-    let size = f.size();
+fn draw_messages_panel(f: &mut Frame, area: Rect, app: &App) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let shown = app.messages.len() - app.message_scroll.min(app.messages.len());
+    let items: Vec<ListItem> = app
+        .messages
+        .iter()
+        .take(shown)
+        .rev()
+        .take(visible)
+        .rev()
+        .map(|(time, message)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format_utc_time(*time), Style::default().fg(app.theme.dim)),
+                Span::raw("  "),
+                Span::styled(message.clone(), Style::default().fg(app.theme.text)),
+            ]))
+        })
+        .collect();
 
-    // Main layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // Title bar
-            Constraint::Min(10),    // Main content
-            Constraint::Length(3),  // Status bar
-        ])
-        .split(size);
+    let position = match app.message_scroll {
+        0 => "newest".to_string(),
+        back => format!("{} back", back),
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(format!("MESSAGES | {} | {} | [[ ]] scroll", app.messages.len(), position))
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(list, area);
+}
 
-    // Title bar with futuristic styling
-    let title = Paragraph::new("🛰️  SDR CONTROL TERMINAL  🛰️")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Magenta))
-                .style(Style::default().bg(Color::Black)),
-        );
-    f.render_widget(title, chunks[0]);
+fn draw_device_panel(f: &mut Frame, area: Rect, app: &App) {
+    let range = |range: Option<(f64, f64)>, unit: &str, scale: f64| match range {
+        Some((low, high)) => format!("{} to {} {}", low / scale, high / scale, unit),
+        None => "not reported".to_string(),
+    };
+    let mut rows: Vec<(String, String)> = Vec::new();
+    if let Some(player) = &app.engine.player {
+        let (_, total) = player.progress();
+        rows.push(("Source".to_string(), format!("recording {}", player.path().display())));
+        rows.push(("Format".to_string(), player.meta.format.sigmf_datatype().to_string()));
+        rows.push(("Recorded at".to_string(), player.meta.datetime.clone().unwrap_or_else(|| "not reported".to_string())));
+        rows.push(("Frequency".to_string(), format!("{:.6} MHz", player.meta.frequency / 1e6)));
+        rows.push(("Sample rate".to_string(), format!("{} MS/s", player.meta.sample_rate / 1e6)));
+        rows.push(("Length".to_string(), format!("{:.1} s", total)));
+    } else {
+        let device: &dyn Device = match &app.engine.remote {
+            Some(remote) => remote,
+            None => &app.engine.demo,
+        };
+        let info = device.info();
+        let or_not = |value: &Option<String>| value.clone().unwrap_or_else(|| "not reported".to_string());
+        rows.push(("Source".to_string(), device.name().to_string()));
+        rows.push(("Driver".to_string(), info.driver));
+        if let Some(remote) = &app.engine.remote {
+            rows.push(("Connection".to_string(), remote.status.clone()));
+        }
+        rows.push(("Serial".to_string(), or_not(&info.serial)));
+        rows.push(("Firmware".to_string(), or_not(&info.firmware)));
+        rows.push(("Tuner".to_string(), or_not(&info.tuner)));
+        rows.push(("Frequency range".to_string(), range(info.frequency_range, "MHz", 1e6)));
+        rows.push(("Sample rate range".to_string(), range(info.sample_rate_range, "MS/s", 1e6)));
+        rows.push(("Gain range".to_string(), range(info.gain_range, "dB", 1.0)));
+        match info.sensors.is_empty() {
+            true => rows.push(("Sensors".to_string(), "none reported".to_string())),
+            false => rows.extend(info.sensors),
+        }
+    }
 
-    // Main content area
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-        .split(chunks[1]);
+    let label = Style::default().fg(app.theme.dim);
+    let table = Table::new(
+        rows.into_iter().map(|(name, value)| Row::new(vec![Cell::from(name).style(label), Cell::from(value)])),
+        [Constraint::Length(18), Constraint::Min(0)],
+    )
+    .style(Style::default().fg(app.theme.text))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title("DEVICE")
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
+}
 
-    // Left panel - Controls
-    draw_controls_panel(f, main_chunks[0], app);
+fn draw_network_panel(f: &mut Frame, area: Rect, app: &App) {
+    let header = Row::new(["SERVICE", "ADDRESS", "CLIENTS", "DETAIL"])
+        .style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD));
 
-    // Right panel - Spectrum and data
-    draw_spectrum_panel(f, main_chunks[1], app);
+    let mut rows = Vec::new();
+    if let Some(server) = &app.engine.rtl_tcp {
+        rows.push(Row::new(vec![
+            Cell::from("rtl_tcp"),
+            Cell::from(server.local_addr().to_string()),
+            Cell::from(server.clients().to_string()),
+            Cell::from(format!("8-bit IQ, {} blocks dropped", server.dropped())),
+        ]));
+    }
+    if let Some(publisher) = &app.engine.zmq {
+        rows.push(Row::new(vec![
+            Cell::from("zmq pub"),
+            Cell::from(publisher.local_addr().to_string()),
+            Cell::from(publisher.clients().to_string()),
+            Cell::from(format!("cf32 for GNU Radio, {} blocks dropped", publisher.dropped())),
+        ]));
+    }
+    if let Some(server) = &app.engine.rest {
+        rows.push(Row::new(vec![
+            Cell::from("rest"),
+            Cell::from(server.local_addr().to_string()),
+            Cell::from("-"),
+            Cell::from("GET /api/status, GET or PUT /api/<setting>"),
+        ]));
+    }
+    if let Some(server) = &app.engine.websocket {
+        rows.push(Row::new(vec![
+            Cell::from("websocket"),
+            Cell::from(server.local_addr().to_string()),
+            Cell::from(server.clients().to_string()),
+            Cell::from(format!("spectrum as JSON, {} frames dropped", server.dropped())),
+        ]));
+    }
+    if let Some(server) = &app.engine.rigctl {
+        rows.push(Row::new(vec![
+            Cell::from("rigctld"),
+            Cell::from(server.local_addr().to_string()),
+            Cell::from("-"),
+            Cell::from("Hamlib NET rigctl, frequency and mode"),
+        ]));
+    }
+    if let Some(server) = &app.engine.gqrx {
+        rows.push(Row::new(vec![
+            Cell::from("gqrx remote"),
+            Cell::from(server.local_addr().to_string()),
+            Cell::from("-"),
+            Cell::from("frequency, mode, signal, squelch and recording"),
+        ]));
+    }
+    if let Some(mqtt) = &app.engine.mqtt {
+        rows.push(Row::new(vec![
+            Cell::from("mqtt"),
+            Cell::from(mqtt.config.broker.clone()),
+            Cell::from("-"),
+            Cell::from(format!(
+                "{}, {} published, {} dropped",
+                mqtt.status, mqtt.published, mqtt.dropped
+            )),
+        ]));
+    }
+    if let Some(sender) = &app.engine.audio_out {
+        let format = sender.encoding.map_or("16-bit PCM", AudioEncoding::name);
+        rows.push(Row::new(vec![
+            Cell::from("audio out"),
+            Cell::from(sender.url.clone()),
+            Cell::from("-"),
+            Cell::from(match &sender.error {
+                Some(e) => format!("{} 8 kHz, {}", format, e),
+                None => format!("{} 8 kHz, {} packets", format, sender.packets),
+            }),
+        ]));
+    }
+    if let Some(source) = &app.engine.icecast {
+        rows.push(Row::new(vec![
+            Cell::from("icecast"),
+            Cell::from(source.config.server.clone()),
+            Cell::from("-"),
+            Cell::from(format!("Ogg FLAC 8 kHz, {}, {} blocks dropped", source.status, source.dropped)),
+        ]));
+    }
+    if let Some(sender) = &app.engine.vita49 {
+        rows.push(Row::new(vec![
+            Cell::from("vita 49"),
+            Cell::from(sender.url.clone()),
+            Cell::from("-"),
+            Cell::from(match &sender.error {
+                Some(e) => format!("stream {:#x}, {}", sender.stream_id, e),
+                None => format!("stream {:#x}, 16-bit IQ, {} packets", sender.stream_id, sender.packets),
+            }),
+        ]));
+    }
+    if let Some(sender) = &app.engine.nmea_out {
+        rows.push(Row::new(vec![
+            Cell::from("nmea out"),
+            Cell::from(sender.url.clone()),
+            Cell::from("-"),
+            Cell::from(match &sender.error {
+                Some(e) => format!("AIS over UDP, {}", e),
+                None => format!("AIS over UDP, {} sentences", sender.sentences),
+            }),
+        ]));
+    }
+    if let Some(remote) = &app.engine.remote {
+        rows.push(Row::new(vec![
+            Cell::from("remote rx"),
+            Cell::from(remote.server.clone()),
+            Cell::from("-"),
+            Cell::from(format!("rtl_tcp source, {}, {} samples dropped", remote.status, remote.dropped)),
+        ]));
+    }
+    if let Some(stream) = &app.engine.iq_stream {
+        rows.push(Row::new(vec![
+            Cell::from("iq out"),
+            Cell::from(stream.url.clone()),
+            Cell::from("-"),
+            Cell::from(format!(
+                "{}, {}, {} blocks dropped",
+                stream.format.extension(),
+                stream.status,
+                stream.dropped
+            )),
+        ]));
+    }
 
-    // Status bar
-    draw_status_bar(f, chunks[2], app);
+    let title = if rows.is_empty() {
+        "NETWORK | no services, add `rtl_tcp = \"0.0.0.0:1234\"` under [network] in the config file".to_string()
+    } else {
+        format!("NETWORK | {} running", rows.len())
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(12),
+            Constraint::Length(22),
+            Constraint::Length(8),
+            Constraint::Min(20),
+        ],
+    )
+    .header(header)
+    .style(Style::default().fg(app.theme.text))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.primary))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
+    );
+    f.render_widget(table, area);
 }
 
-fn draw_controls_panel(f: &mut Frame, area: Rect, app: &App) {
+fn draw_measure_panel(f: &mut Frame, area: Rect, app: &App) {
+    let label = |text: &str| Span::styled(format!("{:<18}", text), Style::default().fg(app.theme.dim));
+    let value = |text: String| Span::styled(text, Style::default().fg(app.theme.text).add_modifier(Modifier::BOLD));
+
+    let (offset, unit) = app.power_unit();
+    let lines = match &app.engine.meter.latest {
+        Some(m) => vec![
+            Line::from(vec![label("Measured at"), value(format_utc_time(m.time))]),
+            Line::from(vec![
+                label("Passband"),
+                value(format!("{:.6} MHz ± {:.2} kHz", m.center_freq / 1e6, m.bandwidth_hz / 2e3)),
+            ]),
+            Line::from(""),
+            Line::from(vec![label("Channel power"), value(format!("{:.1} {}", m.channel_power_db + offset, unit))]),
+            Line::from(vec![label("Noise in channel"), value(format!("{:.1} {}", m.noise_power_db + offset, unit))]),
+            Line::from(vec![
+                label("SNR"),
+                Span::styled(
+                    format!("{:.1} dB", m.snr_db),
+                    Style::default()
+                        .fg(if m.snr_db >= 10.0 { app.theme.good } else if m.snr_db >= 3.0 { app.theme.highlight } else { app.theme.alert })
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ]),
+            Line::from(vec![
+                label("Occupied BW (99%)"),
+                value(format!("{:.2} kHz", m.occupied_bandwidth_hz / 1e3)),
+            ]),
+        ],
+        None => vec![Line::from(Span::styled(
+            "Waiting for samples...",
+            Style::default().fg(app.theme.dim),
+        ))],
+    };
+
+    let title = format!(
+        "MEASURE | {:.1} kHz passband | [W] width  [D] dBFS/dBm  [T] history  [L] log to {}: {}",
+        app.engine.meter.bandwidth_hz / 1e3,
+        MEASURE_CSV,
+        if app.engine.meter.is_logging() { "ON" } else { "OFF" }
+    );
+    let panel = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.good))
+            .title(title)
+            .title_style(Style::default().fg(app.theme.good).add_modifier(Modifier::BOLD)),
+    );
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // Tabs
-            Constraint::Min(5),     // Parameters
-            Constraint::Length(4),  // Actions
-        ])
+        .constraints([Constraint::Min(9), Constraint::Length(8)])
         .split(area);
+    f.render_widget(panel, chunks[0]);
+    draw_power_sparkline(f, chunks[1], app);
+}
 
-    // Tabs
-    let titles: Vec<Line> = ["📡 FREQUENCY", "⚡ GAIN", "📊 SAMPLE RATE"]
+/// Demodulator channel power over the selected span, one column per time slot
+fn draw_power_sparkline(f: &mut Frame, area: Rect, app: &App) {
+    let span = Duration::from_secs(POWER_HISTORY_SPANS[app.power_span]);
+    let columns = area.width.saturating_sub(2).max(1) as usize;
+    let now = Instant::now();
+
+    // Strongest block in each slot so short bursts still show up
+    let mut slots: Vec<Option<f32>> = vec![None; columns];
+    for &(time, db) in &app.engine.power_history {
+        let age = now - time;
+        if age >= span {
+            continue;
+        }
+        let slot = columns - 1 - (age.as_secs_f64() / span.as_secs_f64() * columns as f64) as usize;
+        slots[slot] = Some(slots[slot].map_or(db, |peak| peak.max(db)));
+    }
+    let (low, high) = slots
         .iter()
-        .map(|t| Line::from(Span::styled(*t, Style::default().fg(Color::Green))))
+        .flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &db| (low.min(db), high.max(db)));
+    // Bars start one step above empty slots so the weakest reading remains visible
+    let data: Vec<u64> = slots
+        .iter()
+        .map(|slot| slot.map_or(0, |db| ((db - low) * 10.0) as u64 + 1))
         .collect();
 
-    let tabs = Tabs::new(titles)
+    let (offset, unit) = app.power_unit();
+    let range = if low.is_finite() {
+        format!("{:.1} .. {:.1} {}", low + offset, high + offset, unit)
+    } else {
+        "no data".to_string()
+    };
+    let sparkline = Sparkline::default()
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Blue))
-                .title("CONTROLS")
-                .title_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                .border_style(Style::default().fg(app.theme.good))
+                .title(format!("CHANNEL POWER | last {} s | {}", span.as_secs(), range))
+                .title_style(Style::default().fg(app.theme.good).add_modifier(Modifier::BOLD)),
         )
-        .select(app.current_tab)
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
-    f.render_widget(tabs, chunks[0]);
+        .style(Style::default().fg(app.theme.highlight))
+        .data(&data);
+    f.render_widget(sparkline, area);
+}
 
-    // Parameter display
-    let param_text = match app.current_tab {
-        0 => format!("Frequency: {:.3} MHz\n\nUse ↑↓ to adjust\nStep: 1 MHz", app.frequency / 1e6),
-        1 => format!("Gain: {:.1} dB\n\nUse ↑↓ to adjust\nStep: 1 dB", app.gain),
-        2 => format!("Sample Rate: {:.1} MS/s\n\nUse ↑↓ to adjust\nStep: 0.1 MS/s", app.sample_rate / 1e6),
-        _ => "Unknown parameter".to_string(),
+/// Pop-up with the most recent SAME alert, dismissed with Enter
+fn draw_alert_overlay(f: &mut Frame, area: Rect, app: &App) {
+    let Some(alert) = app.engine.same.alerts.last() else {
+        return;
+    };
+    let color = match alert.severity() {
+        Severity::Warning => app.theme.alert,
+        Severity::Watch => app.theme.highlight,
+        Severity::Statement => app.theme.primary,
+        Severity::Test => app.theme.good,
     };
+    let label = |text: &str| Span::styled(format!("{:<10}", text), Style::default().fg(app.theme.dim));
+    let minutes = alert.purge.as_secs() / 60;
 
-    let params = Paragraph::new(param_text)
-        .style(Style::default().fg(Color::White))
+    let lines = vec![
+        Line::from(Span::styled(
+            alert.event_name().to_uppercase(),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(vec![label("From"), Span::raw(alert.originator_name().to_string())]),
+        Line::from(vec![label("Issued"), Span::raw(alert.issued_text())]),
+        Line::from(vec![
+            label("Duration"),
+            Span::raw(format!(
+                "{}h{:02}m (until {} UTC)",
+                minutes / 60,
+                minutes % 60,
+                format_utc_time(alert.expires())
+            )),
+        ]),
+        Line::from(vec![label("Sender"), Span::raw(alert.sender.clone())]),
+        Line::from(vec![label("Areas"), Span::raw(alert.location_names().join(", "))]),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("[Enter] dismiss  [A] bell: {}", if app.alert_bell { "ON" } else { "OFF" }),
+            Style::default().fg(app.theme.dim),
+        )),
+    ];
+
+    let width = (area.width * 3 / 5).max(40).min(area.width);
+    let height = 13.min(area.height);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    let paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(app.theme.text).bg(app.theme.background))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Green))
-                .title("PARAMETER")
-                .title_style(Style::default().fg(Color::Green)),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(params, chunks[1]);
+                .border_style(Style::default().fg(color).add_modifier(Modifier::BOLD))
+                .title(format!(" SAME ALERT {}-{} ", alert.originator, alert.event))
+                .title_style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
+        );
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
 
-    // Action buttons
-    let streaming_action = format!(" [S] {} Streaming ", if app.is_streaming { "Stop" } else { "Start" });
-    let actions = vec![
-        " [C] Connect USRP ".to_string(),
-        streaming_action,
-        " [Q] Quit ".to_string(),
-    ];
+/// Toasts stacked down the top right corner of `area`, newest at the top
+fn draw_toasts(f: &mut Frame, area: Rect, app: &App) {
+    let width = TOAST_WIDTH.min(area.width);
+    let mut y = area.y + 1;
+    for toast in app.toasts.iter().rev() {
+        let colour = match toast.level {
+            Level::Error => app.theme.alert,
+            Level::Warn => app.theme.highlight,
+            _ => app.theme.primary,
+        };
+        let repeats = if toast.count > 1 { format!(" (x{})", toast.count) } else { String::new() };
+        let text = format!("{}{}", toast.message, repeats);
+        // Wrapped onto at most three lines
+        let lines = text.chars().count().div_ceil(width.saturating_sub(2).max(1) as usize).clamp(1, 3) as u16;
+        let paragraph = Paragraph::new(text)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(app.theme.text).bg(app.theme.background))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(colour))
+                    .title(format!(" {} ", toast.level))
+                    .title_style(Style::default().fg(colour).add_modifier(Modifier::BOLD)),
+            );
+        let height = (lines + 2).min(area.bottom().saturating_sub(y));
+        if height < 3 {
+            break;
+        }
+        let rect = Rect::new(area.right() - width, y, width, height);
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+        y += height;
+    }
+}
 
-    let action_items: Vec<ListItem> = actions
+/// The settings tab, the selected one highlighted
+fn settings_list(app: &App) -> Text<'static> {
+    let mut lines: Vec<Line> = Setting::ALL
         .iter()
-        .map(|action| {
-            ListItem::new(Line::from(vec![Span::styled(
-                action.clone(),
-                Style::default().fg(if action.contains('S') {
-                    if app.is_streaming { Color::Green } else { Color::Yellow }
-                } else {
-                    Color::Cyan
-                }),
-            )]))
+        .enumerate()
+        .map(|(i, &setting)| {
+            let value = match setting {
+                Setting::FftSize => app.engine.fft_size.to_string(),
+                Setting::Window => app.engine.fft_window.name().to_string(),
+                Setting::Averages => app.engine.fft_averages.to_string(),
+                Setting::Colormap => app.colormap.unwrap_or("theme").to_string(),
+                Setting::VfoRoute => format!("VFO {} {}", app.engine.active_vfo + 1, app.engine.vfo().route.label()),
+            };
+            let style = match i == app.setting_selected {
+                true => Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD),
+                false => Style::default().fg(app.theme.text),
+            };
+            Line::styled(format!("{:<10} ◂ {} ▸", setting.label(), value), style)
         })
         .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::styled("↑↓ select, ←→ change, Tab leaves", Style::default().fg(app.theme.dim)));
+    Text::from(lines)
+}
 
-    let actions_list = List::new(action_items)
+/// The command palette, when it is open: the query and the commands it
+/// matches with their keys
+fn draw_palette(f: &mut Frame, area: Rect, app: &App) {
+    let Some(palette) = &app.palette else {
+        return;
+    };
+    let matches = palette.matches(app.palette_commands());
+    let width = 60.min(area.width);
+    let height = (PALETTE_ROWS as u16 + 4).min(area.height);
+    let popup = Rect::new(area.x + (area.width - width) / 2, area.y + area.height / 6, width, height);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(":", Style::default().fg(app.theme.dim)),
+            Span::styled(palette.query.clone(), Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)),
+            Span::styled("█", Style::default().fg(app.theme.highlight)),
+        ]),
+        Line::from(""),
+    ];
+    // Keep the selection in view
+    let first = (palette.selected + 1).saturating_sub(PALETTE_ROWS);
+    for (i, (command, name)) in matches.iter().enumerate().skip(first).take(PALETTE_ROWS) {
+        let keys = match command {
+            palette::Command::Action(action) => ACTIONS
+                .iter()
+                .position(|&(a, _, _, _)| a == *action)
+                .map(|index| app.keymap.keys(index).iter().map(Key::to_string).collect::<Vec<_>>().join(" "))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        let style = match i == palette.selected {
+            true => Style::default().fg(app.theme.background).bg(app.theme.primary),
+            false => Style::default().fg(app.theme.text),
+        };
+        let inner = width.saturating_sub(2) as usize;
+        lines.push(Line::styled(format!("{:<width$}{}", name, keys, width = inner.saturating_sub(keys.len())), style));
+    }
+    if matches.is_empty() {
+        lines.push(Line::styled("No command matches", Style::default().fg(app.theme.dim)));
+    }
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text).bg(app.theme.background))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Magenta))
-                .title("ACTIONS")
-                .title_style(Style::default().fg(Color::Magenta)),
+                .border_style(Style::default().fg(app.theme.primary))
+                .title(" COMMAND | [↑↓] select [Enter] run [Esc] close ")
+                .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD)),
         );
-    f.render_widget(actions_list, chunks[2]);
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
 }
 
-fn draw_spectrum_panel(f: &mut Frame, area: Rect, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
-        .split(area);
-
-    // Spectrum display (simplified)
-    let spectrum_text = if app.is_streaming {
-        let mut display = String::new();
-        display.push_str("SPECTRUM ANALYSIS\n\n");
-
-        // Simple ASCII spectrum visualization
-        for (i, &power) in app.spectrum_data.iter().enumerate() {
-            if i % 16 == 0 { // Show every 16th point for readability
-                let bar_len = (power * 20.0) as usize;
-                let bar = "█".repeat(bar_len);
-                display.push_str(&format!("{:.1}: {}\n", i as f32 / app.spectrum_data.len() as f32, bar));
+/// Every action with its keys from the keymap, by category, in columns
+/// over the whole screen
+fn draw_help_overlay(f: &mut Frame, area: Rect, app: &mut App) {
+    let Some(first) = app.help else {
+        return;
+    };
+    let key_style = Style::default().fg(app.theme.highlight);
+    let dim = Style::default().fg(app.theme.dim);
+    let mut sections = Vec::new();
+    for &category in CATEGORIES {
+        let mut lines = vec![Line::from(Span::styled(
+            category.to_uppercase(),
+            Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD),
+        ))];
+        for (i, &(action, name, context, _)) in ACTIONS.iter().enumerate() {
+            if action.category() != category {
+                continue;
+            }
+            let keys: Vec<String> = app.keymap.keys(i).iter().map(Key::to_string).collect();
+            let mut line = vec![
+                match keys.is_empty() {
+                    true => Span::styled(format!("{:<12}", "unbound"), dim),
+                    false => Span::styled(format!("{:<12}", keys.join(" ")), key_style),
+                },
+                Span::raw(name.replace('_', " ")),
+            ];
+            if context != Context::Global {
+                line.push(Span::styled(format!("  {}", context.label()), dim));
             }
+            lines.push(Line::from(line));
         }
-        display
-    } else {
-        "SPECTRUM ANALYSIS\n\nNot streaming...\nPress 'S' to start".to_string()
-    };
-
-    let spectrum = Paragraph::new(spectrum_text)
-        .style(Style::default().fg(Color::Green))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan))
-                .title("SPECTRUM")
-                .title_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(spectrum, chunks[0]);
+        sections.push(lines);
+    }
 
-    // Sample data display
-    let sample_text = if !app.sample_buffer.is_empty() {
-        let mut display = String::new();
-        display.push_str("RECENT SAMPLES\n\n");
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.primary))
+        .title(" HELP | [←→] more columns, any other key closes ")
+        .title_style(Style::default().fg(app.theme.primary).add_modifier(Modifier::BOLD))
+        .style(Style::default().fg(app.theme.text).bg(app.theme.background));
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    if inner.height == 0 {
+        return;
+    }
 
-        for (i, sample) in app.sample_buffer.iter().take(5).enumerate() {
-            display.push_str(&format!("{:2}: {:.4} + j{:.4}\n", i, sample.re, sample.im));
+    // A category starts a new column unless it fits under the one before
+    let rows = inner.height as usize;
+    let mut columns: Vec<Vec<Line>> = vec![Vec::new()];
+    for section in sections {
+        let column = columns.last_mut().expect("one column at least");
+        if column.is_empty() {
+            column.extend(section);
+        } else if column.len() + 1 + section.len() > rows {
+            columns.push(section);
+        } else {
+            column.push(Line::from(""));
+            column.extend(section);
         }
+    }
+    // A category taller than the screen goes on in the next column
+    let columns: Vec<Vec<Line>> = columns
+        .into_iter()
+        .flat_map(|column| column.chunks(rows).map(<[Line]>::to_vec).collect::<Vec<_>>())
+        .collect();
 
-        // Basic stats
-        let total_power: f32 = app.sample_buffer.iter().map(|s| s.norm_sqr()).sum();
-        let avg_power = total_power / app.sample_buffer.len() as f32;
-        display.push_str(&format!("\nAvg Power: {:.6}", avg_power));
+    let shown = (inner.width / HELP_COLUMN_WIDTH).max(1) as usize;
+    let first = first.min(columns.len().saturating_sub(shown));
+    app.help = Some(first);
+    let width = inner.width / shown as u16;
+    for (i, column) in columns.into_iter().skip(first).take(shown).enumerate() {
+        let rect = Rect::new(inner.x + i as u16 * width, inner.y, width.saturating_sub(1), inner.height);
+        f.render_widget(Paragraph::new(column), rect);
+    }
+}
 
-        display
-    } else {
-        "RECENT SAMPLES\n\nNo data available".to_string()
-    };
+/// `mm:ss` position of a recording
+fn format_position(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
 
-    let samples = Paragraph::new(sample_text)
-        .style(Style::default().fg(Color::Yellow))
+fn draw_transport(f: &mut Frame, area: Rect, player: &FilePlayer, app: &App) {
+    let (position, duration) = player.progress();
+    let label = format!(
+        "{} {} / {}  ×{}{}",
+        if player.paused { "PAUSED" } else { "▶" },
+        format_position(position),
+        format_position(duration),
+        player.speed,
+        if player.looping { "  LOOP" } else { "" }
+    );
+    let name = player.path().file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let gauge = Gauge::default()
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Yellow))
-                .title("SAMPLES")
-                .title_style(Style::default().fg(Color::Yellow)),
+                .border_style(Style::default().fg(app.theme.good))
+                .title(format!(
+                    "PLAYBACK {} | [K] pause [J/L] ±10 s [{{/}}] ±1 min [O] loop [( )] speed",
+                    name
+                ))
+                .title_style(Style::default().fg(app.theme.good)),
         )
-        .wrap(Wrap { trim: true });
-    f.render_widget(samples, chunks[1]);
+        .gauge_style(Style::default().fg(app.theme.good).bg(app.theme.background))
+        .ratio(if duration > 0.0 { (position / duration).clamp(0.0, 1.0) } else { 0.0 })
+        .label(label);
+    f.render_widget(gauge, area);
 }
 
-fn draw_status_bar(f: &mut Frame, area: Rect, app: &App) {
+/// Recording, mode, band and the last message, led by any sample losses
+fn status_line(app: &App) -> Line<'static> {
+    let recorder = app.engine.recorder.lock();
+    let recording = match recorder.progress() {
+        Some((elapsed, bytes)) => format!(
+            "● REC {:02}:{:02} {}{} | ",
+            elapsed.as_secs() / 60,
+            elapsed.as_secs() % 60,
+            format_size(bytes),
+            recorder.part().map(|part| format!(" part {}", part)).unwrap_or_default()
+        ),
+        None => String::new(),
+    };
+    drop(recorder);
+    let audio = match app.engine.audio_recorder.segment_duration() {
+        _ if !app.engine.audio_recorder.is_recording() => String::new(),
+        Some(length) => format!(
+            "● AUDIO {:02}:{:02} | ",
+            length.as_secs() / 60,
+            length.as_secs() % 60
+        ),
+        None => format!("○ AUDIO squelched, {} files | ", app.engine.audio_recorder.files),
+    };
+    let band = match bandplan::lookup(app.engine.vfo_frequency(app.engine.vfo()), app.region) {
+        Some(band) => format!("BAND: {} | ", band.name),
+        None => String::new(),
+    };
     let status = format!(
-        " MODE: DEMO | Streaming: {} | {}",
-        if app.is_streaming { "ACTIVE" } else { "INACTIVE" },
-        app.status_message
+        "{}{}MODE: {} | Streaming: {} | {}{}",
+        recording,
+        audio,
+        match (&app.engine.player, &app.engine.remote) {
+            (Some(_), _) => "FILE",
+            (None, Some(_)) => "REMOTE",
+            (None, None) => "DEMO",
+        },
+        if app.engine.is_streaming { "ACTIVE" } else { "INACTIVE" },
+        band,
+        app.engine.status_message
     );
+    // Flagged for a while after each loss, the counts stay
+    let recent = |at: Option<Instant>| at.is_some_and(|t| t.elapsed() < LOSS_FLASH);
+    let flag = Style::default().fg(app.theme.alert).add_modifier(Modifier::BOLD | Modifier::REVERSED);
+    let mut spans = vec![Span::raw(" ")];
+    if recent(app.engine.losses.last_overrun) {
+        spans.push(Span::styled("▲ OVERRUN", flag));
+        spans.push(Span::raw(" "));
+    }
+    if recent(app.engine.losses.last_underrun) {
+        spans.push(Span::styled("▼ UNDERRUN", flag));
+        spans.push(Span::raw(" "));
+    }
+    if app.engine.losses.dropped > 0 || app.engine.losses.underruns > 0 {
+        spans.push(Span::raw(format!(
+            "DROPS: {} samples, {} underruns | ",
+            app.engine.losses.dropped, app.engine.losses.underruns
+        )));
+    }
+    spans.push(Span::raw(status));
+    Line::from(spans)
+}
 
-    let status_bar = Paragraph::new(status)
-        .style(Style::default().fg(Color::White).bg(Color::Blue))
+fn draw_status_bar(f: &mut Frame, area: Rect, app: &App) {
+    let status_bar = Paragraph::new(status_line(app))
+        .style(Style::default().fg(app.theme.text).bg(app.theme.info))
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(status_bar, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::args::Source;
+
+    /// The demo receiver after a second of streaming
+    fn streaming() -> App {
+        let mut app = App::new();
+        app.engine.start_streaming();
+        for _ in 0..20 {
+            app.tick();
+        }
+        app.engine.wait_for_workers();
+        app.tick();
+        app
+    }
+
+    #[test]
+    fn draws_the_demo_receiver() {
+        let mut app = streaming();
+        let mut terminal = Terminal::new(TestBackend::new(140, 40)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = (0..40).flat_map(|y| (0..140).map(move |x| (x, y))).map(|(x, y)| buffer.get(x, y).symbol().to_string()).collect();
+        assert!(text.contains("890.000000 MHz"));
+        assert!(text.contains("Streaming: ACTIVE"));
+    }
+
+    #[test]
+    fn undo_steps_through_user_changes_only() {
+        let mut app = App::new();
+        let start = app.engine.frequency;
+        let key = |text: &str| Key::parse(text).unwrap();
+        // Held keys make one step
+        app.on_key(key("Up"));
+        app.on_key(key("Up"));
+        let tuned = app.engine.frequency;
+        assert!(tuned > start);
+        // As the scanner or a script would
+        app.engine.frequency = 100e6;
+        app.tick();
+        app.on_key(key("Ctrl+z"));
+        assert_eq!(app.engine.frequency, start);
+        app.on_key(key("Ctrl+z"));
+        assert_eq!(app.engine.frequency, start);
+        assert_eq!(app.engine.status_message, "Nothing to undo");
+        app.on_key(key("Ctrl+y"));
+        assert_eq!(app.engine.frequency, 100e6);
+        // A change after an undo is a step of its own and drops the redo
+        app.on_key(key("Ctrl+z"));
+        app.on_key(key("Down"));
+        assert_eq!(app.history.undo.len(), 1);
+        assert!(app.history.redo.is_empty());
+    }
+
+    #[test]
+    fn sessions_keep_routes_and_yield_to_the_command_line() {
+        let mut app = App::new();
+        app.perform(Action::AddVfo);
+        app.perform(Action::VfoRoute);
+        app.engine.vfos[0].route = AudioRoute::Monitor;
+        let mut session = session::parse("[receiver]\nfrequency = 1e8\nsample_rate = 1e6\ngain = 0").unwrap();
+        session.device = Some("127.0.0.1:1".to_string());
+        session.vfos = app
+            .engine.vfos
+            .iter()
+            .map(|vfo| SessionVfo { mode: vfo.demod.mode(), offset_hz: vfo.demod.offset_hz(), route: Some(vfo.route.label().to_string()) })
+            .collect();
+        let routes = [AudioRoute::Monitor, AudioRoute::Monitor];
+        assert_eq!(app.engine.vfos.iter().map(|vfo| vfo.route).collect::<Vec<_>>(), routes);
+
+        let mut restored = App::new();
+        restored.apply_session(session.clone(), false);
+        assert!(restored.engine.remote.is_none());
+        assert_eq!(restored.engine.vfos.iter().map(|vfo| vfo.route).collect::<Vec<_>>(), routes);
+
+        // The session's device unless the command line names a source
+        let mut restored = App::new();
+        restored.apply_session(session, true);
+        assert_eq!(restored.engine.remote.as_ref().map(|remote| remote.server.as_str()), Some("127.0.0.1:1"));
+        restored.apply_options(&Options { source: Some(Source::Demo), ..Options::default() });
+        assert!(restored.engine.remote.is_none());
+    }
+
+    #[test]
+    fn vfo_audio_setting_steps_both_ways() {
+        let mut app = App::new();
+        app.current_tab = SETTINGS_TAB;
+        app.setting_selected = Setting::ALL.iter().position(|&s| s == Setting::VfoRoute).unwrap();
+        assert_eq!(app.engine.vfo().route, AudioRoute::Decoders);
+        app.perform(Action::SettingLess);
+        assert_eq!(app.engine.vfo().route, AudioRoute::Monitor);
+        app.perform(Action::SettingMore);
+        app.perform(Action::SettingMore);
+        assert_eq!(app.engine.vfo().route, AudioRoute::Muted);
+    }
+
+    #[test]
+    fn calibration_follows_the_device() {
+        let mut app = App::new();
+        let config = Config::parse(
+            "[calibration mock]\noffset_db = -20\n\n[calibration rtl_tcp 127.0.0.1:1]\noffset_db = -5.5\n\n[calibration rtl_tcp]\noffset_db = -8",
+        )
+        .unwrap();
+        app.apply_config(&config);
+        assert_eq!(app.engine.calibration_db, Some(-20.0));
+        app.use_source(Some("127.0.0.1:1".to_string()));
+        assert_eq!(app.engine.calibration_db, Some(-5.5));
+        app.show_dbm = true;
+        app.use_source(Some("127.0.0.1:2".to_string()));
+        assert_eq!(app.engine.calibration_db, Some(-8.0));
+        assert!(app.show_dbm);
+
+        let mut app = App::new();
+        app.apply_config(&Config::parse("[calibration rtl_tcp]\noffset_db = -8").unwrap());
+        assert_eq!(app.engine.calibration_db, None);
+        app.use_source(Some("127.0.0.1:1".to_string()));
+        app.show_dbm = true;
+        app.use_source(None);
+        assert_eq!(app.engine.calibration_db, None);
+        assert!(!app.show_dbm);
+    }
+
+    #[test]
+    fn waterfall_history_comes_from_the_config() {
+        let mut app = App::new();
+        assert_eq!(app.waterfall_lines, WATERFALL_LINES);
+        app.waterfall.extend((0..50).map(|_| (SystemTime::now(), Vec::new())));
+        app.apply_config(&Config::parse("[ui]\nwaterfall_lines = 20").unwrap());
+        assert_eq!((app.waterfall_lines, app.waterfall.len()), (20, 20));
+        app.apply_config(&Config::parse("[ui]\nwaterfall_lines = 0").unwrap());
+        assert_eq!(app.waterfall_lines, 20);
+    }
+
+    #[test]
+    fn panic_hook_calls_and_puts_back_the_previous_one() {
+        static CALLS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        std::panic::set_hook(Box::new(|_| {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+        }));
+        // Panics away from the TUI thread, which leave the terminal alone
+        let previous = restore_terminal_on_panic();
+        let _ = std::thread::spawn(|| panic!("with the TUI hook")).join();
+        restore_panic_hook(previous);
+        let _ = std::thread::spawn(|| panic!("after it")).join();
+        drop(std::panic::take_hook());
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn draws_a_single_column_on_a_small_terminal() {
+        let mut app = streaming();
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = (0..20).flat_map(|y| (0..60).map(move |x| (x, y))).map(|(x, y)| buffer.get(x, y).symbol().to_string()).collect();
+        assert!(text.starts_with(" VFO1 FM 890.000000 MHz"));
+        assert!(!text.contains("CONTROLS"));
+        assert!(text.contains("Streaming: ACTIVE"));
+    }
+}
@@ -0,0 +1,43 @@
+//! Plain-ASCII rendering for serial consoles and terminals without Unicode fonts.
+
+use ratatui::buffer::Buffer;
+
+/// Replace every non-ASCII cell of a drawn frame with a printable ASCII stand-in
+pub fn to_ascii(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        if !cell.symbol().is_ascii() {
+            let replacement = ascii_symbol(cell.symbol());
+            cell.set_symbol(replacement);
+        }
+    }
+}
+
+pub fn ascii_symbol(symbol: &str) -> &'static str {
+    let Some(c) = symbol.chars().next() else {
+        return " ";
+    };
+    match c {
+        // Box drawing: straight runs keep their direction, everything else is a corner
+        '─' | '━' | '┄' | '┅' | '┈' | '┉' | '═' | '╌' | '╍' => "-",
+        '│' | '┃' | '┆' | '┇' | '┊' | '┋' | '║' | '╎' | '╏' => "|",
+        '\u{2500}'..='\u{257f}' => "+",
+        // Gauges and waterfall blocks
+        '\u{2580}'..='\u{259f}' => "#",
+        // Braille plots, the empty pattern stays blank
+        '\u{2800}' => " ",
+        '\u{2801}'..='\u{28ff}' => "*",
+        '•' | '·' | '●' | '○' => "*",
+        '■' | '□' | '▪' => "#",
+        '▶' | '►' | '→' => ">",
+        '◀' | '◄' | '←' => "<",
+        '▲' | '↑' => "^",
+        '▼' | '↓' => "v",
+        '±' => "+",
+        '°' => "o",
+        'Δ' => "d",
+        'µ' | 'μ' => "u",
+        '…' => ".",
+        // Emoji and anything else without an ASCII equivalent
+        _ => "*",
+    }
+}
@@ -0,0 +1,98 @@
+//! `rf_rust bench`: how fast this machine runs the DSP, stage by stage and
+//! through the whole receiver, to tell which sample rates it keeps up with
+//! before samples are dropped. Every measurement takes the same demo
+//! signals from [`MockSdr`], so runs on different machines compare.
+
+use std::time::Instant;
+
+use rf_rust::device::Device;
+use rf_rust::device::mock::MockSdr;
+use rf_rust::dsp::audio::AUDIO_RATE;
+use rf_rust::dsp::measure::SpectrumEstimator;
+use rf_rust::dsp::{AudioMode, DecimatingFir, simd};
+use rf_rust::engine::{AudioRoute, Engine, TICK, Vfo};
+use rf_rust::error::Result;
+
+/// Time spent on each measurement
+const BENCH_SECS: f64 = 0.5;
+/// Input rate of the filters and the receiver, that of a typical RTL-SDR
+const RATE: f64 = 2.4e6;
+const FFT_SIZES: [usize; 4] = [512, 2048, 8192, 65536];
+/// Share of the measured throughput a stream can take while leaving room
+/// for the terminal, the network and the rest of the machine
+const HEADROOM: f64 = 0.8;
+/// Demodulators of each receiver configuration, one VFO per mode
+const PIPELINES: [(&str, &[AudioMode]); 3] = [
+    ("1 VFO, FM", &[AudioMode::Fm]),
+    ("2 VFOs, FM and AM", &[AudioMode::Fm, AudioMode::Am]),
+    ("4 VFOs, FM, AM, USB and LSB", &[AudioMode::Fm, AudioMode::Am, AudioMode::Usb, AudioMode::Lsb]),
+];
+/// Spacing of the VFOs of a configuration
+const VFO_SPACING_HZ: f64 = 100e3;
+
+pub fn run() -> Result<()> {
+    let mut sdr = MockSdr::demo();
+    sdr.tune(890.1e6, RATE, 20.0);
+    let mut iq = Vec::new();
+    sdr.generate((RATE * TICK.as_secs_f64()) as usize, &mut iq);
+    println!("DSP kernels: {}, blocks of {} samples at {}", simd::level().name(), iq.len(), rate(RATE));
+
+    println!("\n{:<36}{:>14}", "Stage", "Throughput");
+    for size in FFT_SIZES {
+        let mut estimator = SpectrumEstimator::new(size, 1);
+        let samples = throughput(iq.len(), || {
+            estimator.push(&iq, RATE);
+        });
+        println!("{:<36}{:>14}", format!("FFT {} with window and power", size), rate(samples));
+    }
+    for (target, bandwidth) in [(AUDIO_RATE, 12.5e3), (240e3, 200e3)] {
+        let (mut fir, output) = DecimatingFir::for_rates(RATE, target, bandwidth);
+        let samples = throughput(iq.len(), || {
+            for &s in &iq {
+                std::hint::black_box(fir.push(s));
+            }
+        });
+        println!("{:<36}{:>14}", format!("Filter {} to {}", rate(RATE), rate(output)), rate(samples));
+    }
+
+    // The whole receiver but the terminal, waiting for every worker after
+    // each block, so no block is dropped and the workers get no head start
+    println!("\n{:<36}{:>14}{:>18}", "Receiver", "Throughput", "Max sample rate");
+    for (name, modes) in PIPELINES {
+        let mut engine = Engine::new();
+        engine.frequency = sdr.frequency();
+        engine.sample_rate = RATE;
+        engine.vfos = modes
+            .iter()
+            .enumerate()
+            .map(|(i, &mode)| Vfo::new(mode, i as f64 * VFO_SPACING_HZ, AudioRoute::Decoders))
+            .collect();
+        let samples = throughput(iq.len(), || {
+            engine.sample_buffer.clone_from(&iq);
+            engine.process_samples();
+            engine.wait_for_workers();
+        });
+        println!("{:<36}{:>14}{:>18}", name, rate(samples), rate(samples * HEADROOM));
+    }
+    Ok(())
+}
+
+/// Samples per second through `run`, which takes `samples` each call,
+/// after one call to warm up
+fn throughput(samples: usize, mut run: impl FnMut()) -> f64 {
+    run();
+    let started = Instant::now();
+    let mut calls = 0;
+    while calls == 0 || started.elapsed().as_secs_f64() < BENCH_SECS {
+        run();
+        calls += 1;
+    }
+    (calls * samples) as f64 / started.elapsed().as_secs_f64()
+}
+
+fn rate(samples_per_sec: f64) -> String {
+    match samples_per_sec {
+        r if r >= 1e6 => format!("{:.1} MS/s", r / 1e6),
+        r => format!("{:.1} kS/s", r / 1e3),
+    }
+}
@@ -0,0 +1,155 @@
+//! Modal dialogs over the TUI: a question to confirm, a line of text to
+//! type or one of a list to pick. While a dialog is open it takes every
+//! key, and what it was opened for is carried in it until it is answered.
+
+use crossterm::event::KeyCode;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+
+use super::keymap::Key;
+use super::theme::Theme;
+
+/// Characters an input dialog takes
+const MAX_INPUT: usize = 64;
+const WIDTH: u16 = 56;
+
+pub enum Kind {
+    Confirm,
+    Input(String),
+    Select { options: Vec<String>, selected: usize },
+}
+
+/// What the dialog was answered with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    Text(String),
+    Choice(usize),
+}
+
+pub enum Outcome {
+    Open,
+    Cancelled,
+    Answered(Answer),
+}
+
+/// A dialog opened for `then`
+pub struct Dialog<T> {
+    pub title: String,
+    pub message: String,
+    pub kind: Kind,
+    /// Why the last answer was not taken, shown until the next key
+    pub error: Option<String>,
+    pub then: T,
+}
+
+impl<T> Dialog<T> {
+    pub fn confirm(title: &str, message: String, then: T) -> Self {
+        Self { title: title.to_string(), message, kind: Kind::Confirm, error: None, then }
+    }
+
+    /// A line of text, starting as `text`
+    pub fn input(title: &str, message: &str, text: String, then: T) -> Self {
+        Self { title: title.to_string(), message: message.to_string(), kind: Kind::Input(text), error: None, then }
+    }
+
+    pub fn select(title: &str, message: &str, options: Vec<String>, then: T) -> Self {
+        Self {
+            title: title.to_string(),
+            message: message.to_string(),
+            kind: Kind::Select { options, selected: 0 },
+            error: None,
+            then,
+        }
+    }
+
+    /// Answer with `key` or go on waiting. Esc cancels any dialog.
+    pub fn on_key(&mut self, key: Key) -> Outcome {
+        self.error = None;
+        if key.code == KeyCode::Esc {
+            return Outcome::Cancelled;
+        }
+        match &mut self.kind {
+            Kind::Confirm => match key.code {
+                KeyCode::Enter | KeyCode::Char('y' | 'Y') => Outcome::Answered(Answer::Yes),
+                KeyCode::Char('n' | 'N') => Outcome::Cancelled,
+                _ => Outcome::Open,
+            },
+            Kind::Input(text) => {
+                match key.code {
+                    KeyCode::Enter => return Outcome::Answered(Answer::Text(text.trim().to_string())),
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Char(c) if !key.ctrl && text.chars().count() < MAX_INPUT => text.push(c),
+                    _ => {}
+                }
+                Outcome::Open
+            }
+            Kind::Select { options, selected } => {
+                match key.code {
+                    KeyCode::Enter if !options.is_empty() => return Outcome::Answered(Answer::Choice(*selected)),
+                    KeyCode::Up => *selected = selected.saturating_sub(1),
+                    KeyCode::Down => *selected = (*selected + 1).min(options.len().saturating_sub(1)),
+                    // Digits pick the first nine directly
+                    KeyCode::Char(c @ '1'..='9') if (c as usize - '1' as usize) < options.len() => {
+                        return Outcome::Answered(Answer::Choice(c as usize - '1' as usize));
+                    }
+                    _ => {}
+                }
+                Outcome::Open
+            }
+        }
+    }
+
+    /// Draw the dialog in the middle of `area`
+    pub fn draw(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let dim = Style::default().fg(theme.dim);
+        let mut lines = vec![Line::from(self.message.clone()), Line::from("")];
+        let hint = match &self.kind {
+            Kind::Confirm => "[Y/Enter] yes  [N/Esc] no",
+            Kind::Input(text) => {
+                lines.push(Line::from(vec![
+                    Span::styled(text.clone(), Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+                    Span::styled("█", Style::default().fg(theme.highlight)),
+                ]));
+                "[Enter] done  [Esc] cancel"
+            }
+            Kind::Select { options, selected } => {
+                for (i, option) in options.iter().enumerate() {
+                    let style = match i == *selected {
+                        true => Style::default().fg(theme.background).bg(theme.primary),
+                        false => Style::default().fg(theme.text),
+                    };
+                    lines.push(Line::styled(format!("{} {}", i + 1, option), style));
+                }
+                "[↑↓] select  [Enter] choose  [Esc] cancel"
+            }
+        };
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.clone(), Style::default().fg(theme.alert)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(hint, dim));
+
+        let width = WIDTH.min(area.width);
+        // The message may wrap onto a second line
+        let height = (lines.len() as u16 + 3).min(area.height);
+        let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .style(Style::default().fg(theme.text).bg(theme.background))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.primary))
+                    .title(format!(" {} ", self.title.to_uppercase()))
+                    .title_style(Style::default().fg(theme.primary).add_modifier(Modifier::BOLD)),
+            );
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+}
@@ -0,0 +1,432 @@
+//! Saving what is on screen to files for use in other tools.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::buffer::{Buffer, Cell};
+use ratatui::style::{Color, Modifier};
+use unicode_width::UnicodeWidthStr;
+
+use rf_rust::decoders::utc_date_time;
+use rf_rust::png;
+
+use super::ascii::ascii_symbol;
+use super::font;
+use super::theme::Theme;
+
+/// Space left of the waterfall for time labels, and above and below it for the
+/// title and frequency labels
+const MARGIN_LEFT: usize = 60;
+const MARGIN_TOP: usize = 16;
+const MARGIN_BOTTOM: usize = 22;
+const MARGIN_RIGHT: usize = 12;
+/// Lines and bins are repeated until the waterfall is at least this large
+const MIN_PLOT_WIDTH: usize = 512;
+const MIN_PLOT_HEIGHT: usize = 256;
+/// Rough spacing of axis labels in pixels
+const TICK_SPACING: f64 = 100.0;
+const TIME_LABEL_SPACING: usize = 40;
+/// Pixels per terminal cell in screenshots
+const CELL_WIDTH: usize = 6;
+const CELL_HEIGHT: usize = 12;
+
+/// Spectrum bins as `frequency_hz,power,average` rows, with levels shifted by
+/// `offset` into `unit`. The average is the mean power of `history`.
+pub fn spectrum_csv(
+    path: &Path,
+    first_bin_hz: f64,
+    bin_hz: f64,
+    spectrum: &[f32],
+    history: &[&[f32]],
+    offset: f32,
+    unit: &str,
+) -> io::Result<()> {
+    let average = average_db(spectrum.len(), history);
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "frequency_hz,power_{0},average_{0}", unit.to_ascii_lowercase())?;
+    for (i, &level) in spectrum.iter().enumerate() {
+        write!(out, "{:.1},{:.2},", first_bin_hz + i as f64 * bin_hz, level + offset)?;
+        match average.get(i) {
+            Some(mean) => writeln!(out, "{:.2}", mean + offset)?,
+            None => writeln!(out)?,
+        }
+    }
+    out.flush()
+}
+
+/// Mean of the lines in dB, averaged as power, skipping lines of another length
+fn average_db(bins: usize, lines: &[&[f32]]) -> Vec<f32> {
+    let lines: Vec<&&[f32]> = lines.iter().filter(|line| line.len() == bins).collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    (0..bins)
+        .map(|i| {
+            let power: f32 = lines.iter().map(|line| 10f32.powf(line[i] / 10.0)).sum();
+            10.0 * (power / lines.len() as f32).max(1e-20).log10()
+        })
+        .collect()
+}
+
+/// Waterfall lines, oldest first, as a PNG with the newest line at the top,
+/// UTC times down the left and frequencies along the bottom. `scale` maps a
+/// level to the position on the theme's waterfall scale.
+pub fn waterfall_png(
+    path: &Path,
+    lines: &[(SystemTime, Vec<f32>)],
+    first_bin_hz: f64,
+    bin_hz: f64,
+    title: &str,
+    theme: &Theme,
+    scale: impl Fn(f32) -> f32,
+) -> io::Result<()> {
+    let text = theme.text;
+    let bins = lines.iter().map(|(_, line)| line.len()).max().unwrap_or(0);
+    if bins == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the waterfall is empty"));
+    }
+    let column_px = MIN_PLOT_WIDTH.div_ceil(bins).max(1);
+    let row_px = MIN_PLOT_HEIGHT.div_ceil(lines.len()).max(1);
+    let (plot_width, plot_height) = (bins * column_px, lines.len() * row_px);
+    let mut image = Image::new(
+        MARGIN_LEFT + plot_width + MARGIN_RIGHT,
+        MARGIN_TOP + plot_height + MARGIN_BOTTOM,
+        theme.background,
+    );
+    image.text(MARGIN_LEFT, 4, title, text);
+
+    for (row, (_, line)) in lines.iter().rev().enumerate() {
+        for (bin, &level) in line.iter().enumerate() {
+            image.fill(
+                MARGIN_LEFT + bin * column_px,
+                MARGIN_TOP + row * row_px,
+                column_px,
+                row_px,
+                theme.waterfall_color(scale(level)),
+            );
+        }
+    }
+
+    // Times of every few lines, down from the newest
+    image.text(MARGIN_LEFT - 4 - 3 * CHAR_ADVANCE, MARGIN_TOP + plot_height + 4, "UTC", text);
+    let every = TIME_LABEL_SPACING.div_ceil(row_px).max(1);
+    for (row, (time, _)) in lines.iter().rev().enumerate().step_by(every) {
+        let y = MARGIN_TOP + row * row_px;
+        if y + font::HEIGHT > MARGIN_TOP + plot_height {
+            break;
+        }
+        let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        image.fill(MARGIN_LEFT - 3, y, 3, 1, text);
+        image.text(MARGIN_LEFT - 6 - 8 * CHAR_ADVANCE, y, &utc_date_time(secs).1, text);
+    }
+
+    // Frequencies at round steps across the span
+    let span = bins as f64 * bin_hz;
+    let step = round_step(span * TICK_SPACING / plot_width as f64);
+    let decimals = (-(step / 1e6).log10()).ceil().max(0.0) as usize;
+    let y = MARGIN_TOP + plot_height;
+    let mut tick = (first_bin_hz / step).ceil() * step;
+    while tick <= first_bin_hz + span {
+        let x = MARGIN_LEFT + ((tick - first_bin_hz) / bin_hz * column_px as f64) as usize;
+        image.fill(x.min(MARGIN_LEFT + plot_width - 1), y, 1, 4, text);
+        let label = format!("{:.*}", decimals, tick / 1e6);
+        let width = label.len() * CHAR_ADVANCE;
+        let left = x.saturating_sub(width / 2).min(image.width - width);
+        image.text(left, y + 6, &label, text);
+        tick += step;
+    }
+    image.text(image.width - MARGIN_RIGHT - 3 * CHAR_ADVANCE, y + 6 + font::HEIGHT + 2, "MHz", text);
+
+    std::fs::write(path, image.png())
+}
+
+/// A drawn frame as text with ANSI colour escapes, one line per row
+pub fn screen_ansi(buffer: &Buffer) -> String {
+    let mut out = String::new();
+    for y in 0..buffer.area.height {
+        let mut style = None;
+        // Cells covered by the right half of a wide character
+        let mut covered = 0;
+        for x in 0..buffer.area.width {
+            if covered > 0 {
+                covered -= 1;
+                continue;
+            }
+            let cell = buffer.get(buffer.area.x + x, buffer.area.y + y);
+            let current = (cell.fg, cell.bg, cell.modifier);
+            if style != Some(current) {
+                out.push_str(&sgr(cell));
+                style = Some(current);
+            }
+            out.push_str(cell.symbol());
+            covered = cell.symbol().width().saturating_sub(1);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// Escape sequence setting the colours and weight of `cell`
+fn sgr(cell: &Cell) -> String {
+    let mut codes = vec!["0".to_string()];
+    if cell.modifier.contains(Modifier::BOLD) {
+        codes.push("1".to_string());
+    }
+    if cell.modifier.contains(Modifier::REVERSED) {
+        codes.push("7".to_string());
+    }
+    for (color, base) in [(cell.fg, 30), (cell.bg, 40)] {
+        let code = match color {
+            Color::Reset => continue,
+            Color::Black => base.to_string(),
+            Color::Red => (base + 1).to_string(),
+            Color::Green => (base + 2).to_string(),
+            Color::Yellow => (base + 3).to_string(),
+            Color::Blue => (base + 4).to_string(),
+            Color::Magenta => (base + 5).to_string(),
+            Color::Cyan => (base + 6).to_string(),
+            Color::Gray => (base + 7).to_string(),
+            Color::DarkGray => (base + 60).to_string(),
+            Color::LightRed => (base + 61).to_string(),
+            Color::LightGreen => (base + 62).to_string(),
+            Color::LightYellow => (base + 63).to_string(),
+            Color::LightBlue => (base + 64).to_string(),
+            Color::LightMagenta => (base + 65).to_string(),
+            Color::LightCyan => (base + 66).to_string(),
+            Color::White => (base + 67).to_string(),
+            Color::Indexed(i) => format!("{};5;{}", base + 8, i),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+        };
+        codes.push(code);
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// A drawn frame as a PNG, with default colours taken from `theme`
+pub fn screen_png(buffer: &Buffer, theme: &Theme) -> Vec<u8> {
+    let (columns, rows) = (buffer.area.width as usize, buffer.area.height as usize);
+    let mut image = Image::new(columns * CELL_WIDTH, rows * CELL_HEIGHT, theme.background);
+    for row in 0..rows {
+        for column in 0..columns {
+            let cell = buffer.get(buffer.area.x + column as u16, buffer.area.y + row as u16);
+            let mut fg = if cell.fg == Color::Reset { theme.text } else { cell.fg };
+            let mut bg = if cell.bg == Color::Reset { theme.background } else { cell.bg };
+            if cell.modifier.contains(Modifier::REVERSED) {
+                (fg, bg) = (bg, fg);
+            }
+            let (x, y) = (column * CELL_WIDTH, row * CELL_HEIGHT);
+            image.fill(x, y, CELL_WIDTH, CELL_HEIGHT, bg);
+            image.symbol(x, y, cell.symbol(), fg);
+        }
+    }
+    image.png()
+}
+
+/// 1, 2 or 5 times a power of ten, at least `hz`
+fn round_step(hz: f64) -> f64 {
+    let magnitude = 10f64.powf(hz.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= hz)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Character cell width in pixels, the glyph and a column of spacing
+const CHAR_ADVANCE: usize = font::WIDTH + 1;
+
+/// RGB pixels drawn in terminal colours
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: Color) -> Self {
+        let mut image = Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        };
+        image.fill(0, 0, width, height, background);
+        image
+    }
+
+    /// Fill a rectangle, clipped to the image
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        let (r, g, b) = rgb(color);
+        for row in y.min(self.height)..(y + height).min(self.height) {
+            for column in x.min(self.width)..(x + width).min(self.width) {
+                let i = (row * self.width + column) * 3;
+                self.pixels[i..i + 3].copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+
+    /// One line of text with its top left corner at `x`, `y`
+    pub fn text(&mut self, x: usize, y: usize, text: &str, color: Color) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i * CHAR_ADVANCE;
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..font::WIDTH {
+                    if bits & (0x10 >> column) != 0 {
+                        self.fill(left + column, y + row, 1, 1, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// One terminal cell's symbol in a `CELL_WIDTH` by `CELL_HEIGHT` cell,
+    /// drawing lines, blocks and Braille as shapes and other text with the font
+    fn symbol(&mut self, x: usize, y: usize, symbol: &str, color: Color) {
+        let Some(c) = symbol.chars().next() else {
+            return;
+        };
+        let (middle_x, middle_y) = (x + CELL_WIDTH / 2 - 1, y + CELL_HEIGHT / 2);
+        if let Some((up, down, left, right)) = box_arms(c) {
+            if up {
+                self.fill(middle_x, y, 1, middle_y - y + 1, color);
+            }
+            if down {
+                self.fill(middle_x, middle_y, 1, y + CELL_HEIGHT - middle_y, color);
+            }
+            if left {
+                self.fill(x, middle_y, middle_x - x + 1, 1, color);
+            }
+            if right {
+                self.fill(middle_x, middle_y, x + CELL_WIDTH - middle_x, 1, color);
+            }
+            return;
+        }
+        match c {
+            ' ' => {}
+            '▀' => self.fill(x, y, CELL_WIDTH, CELL_HEIGHT / 2, color),
+            // Lower eighths up to the full block
+            '▁'..='█' => {
+                let height = CELL_HEIGHT * (c as usize - '▀' as usize) / 8;
+                self.fill(x, y + CELL_HEIGHT - height, CELL_WIDTH, height, color);
+            }
+            // Left eighths from seven down to one
+            '▉'..='▏' => {
+                let width = (CELL_WIDTH * ('█' as usize + 8 - c as usize) / 8).max(1);
+                self.fill(x, y, width, CELL_HEIGHT, color);
+            }
+            '▐' => self.fill(x + CELL_WIDTH / 2, y, CELL_WIDTH / 2, CELL_HEIGHT, color),
+            // Shades as every fourth, second or three in four pixels
+            '░' | '▒' | '▓' => {
+                for dy in 0..CELL_HEIGHT {
+                    for dx in 0..CELL_WIDTH {
+                        let on = match c {
+                            '░' => dx % 2 == 0 && dy % 2 == 0,
+                            '▒' => (dx + dy) % 2 == 0,
+                            _ => dx % 2 == 0 || dy % 2 == 0,
+                        };
+                        if on {
+                            self.fill(x + dx, y + dy, 1, 1, color);
+                        }
+                    }
+                }
+            }
+            // Braille dots 1-3 and 7 down the left, 4-6 and 8 down the right
+            '\u{2800}'..='\u{28ff}' => {
+                let dots = c as u32 - 0x2800;
+                const POSITIONS: [(usize, usize); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+                for (bit, (column, row)) in POSITIONS.iter().enumerate() {
+                    if dots & (1 << bit) != 0 {
+                        self.fill(x + 1 + 3 * column, y + 1 + 3 * row, 2, 2, color);
+                    }
+                }
+            }
+            '•' | '●' => self.fill(x + 1, middle_y - 2, 4, 4, color),
+            '·' => self.fill(middle_x, middle_y, 2, 2, color),
+            _ => {
+                let text = if c.is_ascii() { symbol } else { ascii_symbol(symbol) };
+                self.text(x, y + (CELL_HEIGHT - font::HEIGHT) / 2, text, color);
+            }
+        }
+    }
+
+    pub fn png(&self) -> Vec<u8> {
+        png::encode(self.width as u32, self.height as u32, &self.pixels)
+    }
+}
+
+/// Whether a box-drawing character reaches up, down, left and right
+fn box_arms(c: char) -> Option<(bool, bool, bool, bool)> {
+    Some(match c {
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' => (false, false, true, true),
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' => (true, true, false, false),
+        '┌' | '┏' | '╔' | '╭' => (false, true, false, true),
+        '┐' | '┓' | '╗' | '╮' => (false, true, true, false),
+        '└' | '┗' | '╚' | '╰' => (true, false, false, true),
+        '┘' | '┛' | '╝' | '╯' => (true, false, true, false),
+        '├' | '┣' | '╠' => (true, true, false, true),
+        '┤' | '┫' | '╣' => (true, true, true, false),
+        '┬' | '┳' | '╦' => (false, true, true, true),
+        '┴' | '┻' | '╩' => (true, false, true, true),
+        '┼' | '╋' | '╬' => (true, true, true, true),
+        _ => return None,
+    })
+}
+
+/// RGB value of a terminal colour, named ones as the xterm defaults
+pub fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black | Color::Reset => (0, 0, 0),
+        Color::Red => (205, 0, 0),
+        Color::Green => (0, 205, 0),
+        Color::Yellow => (205, 205, 0),
+        Color::Blue => (0, 0, 238),
+        Color::Magenta => (205, 0, 205),
+        Color::Cyan => (0, 205, 205),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        Color::LightRed => (255, 0, 0),
+        Color::LightGreen => (0, 255, 0),
+        Color::LightYellow => (255, 255, 0),
+        Color::LightBlue => (92, 92, 255),
+        Color::LightMagenta => (255, 0, 255),
+        Color::LightCyan => (0, 255, 255),
+        Color::White => (255, 255, 255),
+        Color::Indexed(i) => indexed_rgb(i),
+    }
+}
+
+/// The xterm 256-colour palette: 16 system colours, a 6x6x6 cube and a grey ramp
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    const SYSTEM: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    let level = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+    match index {
+        0..=15 => rgb(SYSTEM[index as usize]),
+        16..=231 => {
+            let i = index - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let grey = 8 + 10 * (index - 232);
+            (grey, grey, grey)
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Subcommands running the receiver without the TUI, for scripts, SSH
+//! sessions and boxes without a terminal. Each drives the same [`Engine`]
+//! as the TUI through [`Engine::tick`], so sources, decoders and the config
+//! file behave exactly as they do on screen.
+
+use std::io::{self, Write, stdout};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rf_rust::config::Config;
+use rf_rust::error::{Result, RfError};
+use rf_rust::logging;
+use rf_rust::dsp::measure::median;
+use rf_rust::dsp::simd;
+
+use rf_rust::engine::{DecodeMarks, Engine, RECORDING_DIR};
+
+use super::bench;
+use crate::args::{OPTIONS_USAGE, Options, parse_hz};
+
+pub const USAGE: &str = "\
+usage: rf_rust                              the TUI, in a terminal
+       rf_rust connect HOST:PORT            the TUI for a receiver running `serve`
+       rf_rust serve                        the receiver and its network services
+       rf_rust record SECONDS               record IQ to recordings/
+       rf_rust scan START STOP STEP         strongest signal at each step, in Hz
+                                            or with a k, M or G suffix
+       rf_rust decode DECODER [FILE]        print decodes as they arrive, from a
+                                            recording if given: ais prints NMEA,
+                                            the others JSON lines
+       rf_rust run SCRIPT                   run a receiver script to its end, printing
+                                            what it logs
+       rf_rust info                         version, config and decoders
+       rf_rust bench                        DSP throughput on this machine and the
+                                            sample rates it keeps up with";
+
+/// Decoders `decode` can print
+const DECODERS: [&str; 7] = ["ais", "ft8", "ism", "navtex", "pager", "same", "wspr"];
+/// Updates at each scan step, enough for a remote receiver to retune and
+/// the spectrum to settle
+const SCAN_TICKS: usize = 6;
+
+/// Run the subcommand in `args` with the command-line `options`, or
+/// return the usage as the error
+pub fn run(args: &[&str], options: &Options) -> Result<()> {
+    match *args {
+        ["serve"] => serve(options),
+        ["record", secs] => record(secs, options),
+        ["scan", start, stop, step] => scan(start, stop, step, options),
+        ["decode", decoder] => decode(decoder, None, options),
+        ["decode", decoder, file] => decode(decoder, Some(Path::new(file)), options),
+        ["run", script] => run_script(Path::new(script), options),
+        ["info"] => info(),
+        ["bench"] => bench::run(),
+        _ => Err(RfError::Config(format!("{}\n{}", USAGE, OPTIONS_USAGE))),
+    }
+}
+
+/// The receiver with the config file and `options` applied, streaming
+fn receiver(options: &Options) -> Result<Engine> {
+    let mut engine = Engine::new();
+    engine.apply_config(&Config::load()?);
+    // Config errors, all of them rather than the last in the status
+    print_log(&mut 0);
+    options.apply(&mut engine);
+    engine.start_streaming();
+    Ok(engine)
+}
+
+/// Print the log entries after `seen` to stderr, moving `seen` up to the
+/// newest, and return the last message printed
+fn print_log(seen: &mut u64) -> Option<String> {
+    let entries = logging::since(*seen);
+    for entry in &entries {
+        match entry.count {
+            1 => eprintln!("{}: {}", entry.level.as_str().to_lowercase(), entry.message),
+            count => eprintln!("{}: {} (x{})", entry.level.as_str().to_lowercase(), entry.message, count),
+        }
+    }
+    let last = entries.last()?;
+    *seen = last.id;
+    Some(last.message.clone())
+}
+
+/// One update, then the rest of the update interval
+fn tick(engine: &mut Engine) {
+    let started = Instant::now();
+    engine.tick();
+    if let Some(wait) = engine.tick_interval.checked_sub(started.elapsed()) {
+        thread::sleep(wait);
+    }
+}
+
+/// Run the receiver and its configured network services, printing status
+/// changes, until interrupted. Remote TUIs tune it and take its samples
+/// through `rtl_tcp` under `[network]`.
+fn serve(options: &Options) -> Result<()> {
+    let mut engine = receiver(options)?;
+    if engine.rtl_tcp.is_none() {
+        eprintln!("No rtl_tcp server for remote TUIs, add `rtl_tcp = \"0.0.0.0:1234\"` under [network] in the config file");
+    }
+    let mut reported = engine.status_message.clone();
+    let mut seen = logging::since(0).last().map_or(0, |entry| entry.id);
+    loop {
+        tick(&mut engine);
+        let logged = print_log(&mut seen);
+        if engine.status_message != reported && logged.as_ref() != Some(&engine.status_message) {
+            eprintln!("{}", engine.status_message);
+        }
+        reported = engine.status_message.clone();
+    }
+}
+
+fn run_script(path: &Path, options: &Options) -> Result<()> {
+    let mut engine = receiver(options)?;
+    engine.load_script(path)?;
+    let mut reported = engine.status_message.clone();
+    let mut seen = logging::since(0).last().map_or(0, |entry| entry.id);
+    let mut failed = None;
+    while engine.script.is_some() {
+        tick(&mut engine);
+        let logged = engine.script_log.last().cloned();
+        for line in engine.script_log.drain(..) {
+            println!("{}", line);
+        }
+        failed = logging::since(seen).into_iter().find_map(|entry| entry.message.strip_prefix("Script failed: ").map(str::to_string));
+        // Warnings and events, such as scheduled recordings, on stderr
+        if failed.is_none() {
+            print_log(&mut seen);
+        }
+        // Other status changes too, such as recordings starting and stopping
+        if engine.status_message != reported && logged.as_ref() != Some(&engine.status_message) && engine.script.is_some() {
+            println!("{}", engine.status_message);
+        }
+        reported = engine.status_message.clone();
+    }
+    for line in engine.script_log.drain(..) {
+        println!("{}", line);
+    }
+    match failed {
+        Some(e) => Err(RfError::Config(e)),
+        None => Ok(()),
+    }
+}
+
+fn record(secs: &str, options: &Options) -> Result<()> {
+    let secs: f64 = secs.parse().ok().filter(|s: &f64| *s > 0.0 && s.is_finite()).ok_or_else(|| RfError::Config("SECONDS must be a positive number".to_string()))?;
+    let mut engine = receiver(options)?;
+    engine.toggle_recording();
+    if !engine.recorder.lock().is_recording() {
+        let error = logging::since(0).pop().map_or_else(|| "recording not started".to_string(), |entry| entry.message);
+        return Err(RfError::Io(io::Error::other(error)));
+    }
+    eprintln!("{}", engine.status_message);
+    let until = Instant::now() + Duration::from_secs_f64(secs);
+    while Instant::now() < until {
+        tick(&mut engine);
+    }
+    engine.toggle_recording();
+    println!("{}", engine.status_message);
+    Ok(())
+}
+
+fn scan(start: &str, stop: &str, step: &str, options: &Options) -> Result<()> {
+    let hz = |text| parse_hz(text).map_err(RfError::Config);
+    let (start, stop, step) = (hz(start)?, hz(stop)?, hz(step)?);
+    if step <= 0.0 || stop < start {
+        return Err(RfError::Config("STEP must be positive and STOP at least START".to_string()));
+    }
+    let mut engine = receiver(options)?;
+    println!("frequency_hz\tpeak_hz\tpeak_dbfs\tfloor_dbfs");
+    let steps = ((stop - start) / step).floor() as usize;
+    for i in 0..=steps {
+        engine.frequency = start + i as f64 * step;
+        for _ in 0..SCAN_TICKS {
+            tick(&mut engine);
+        }
+        let bins = &engine.spectrum_data;
+        let (peak, &db) = bins
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .ok_or_else(|| RfError::Dsp("no spectrum".to_string()))?;
+        let peak_hz = engine.frequency + (peak as f64 / bins.len() as f64 - 0.5) * engine.sample_rate;
+        println!("{:.0}\t{:.0}\t{:.1}\t{:.1}", engine.frequency, peak_hz, db, median(bins));
+        stdout().flush()?;
+    }
+    Ok(())
+}
+
+fn decode(decoder: &str, file: Option<&Path>, options: &Options) -> Result<()> {
+    if !DECODERS.contains(&decoder) {
+        return Err(RfError::Config(format!("no decoder `{}`, there are {}", decoder, DECODERS.join(", "))));
+    }
+    let mut engine = receiver(options)?;
+    if let Some(file) = file {
+        engine.open_playback(file)?;
+    }
+    let mut marks = DecodeMarks::now();
+    let mut sentences = engine.ais.lock().sentences;
+    let mut out = stdout();
+    loop {
+        tick(&mut engine);
+        // The last blocks of a recording are still with the decoders
+        let finished = file.is_some() && engine.player.is_none();
+        if finished {
+            engine.wait_for_workers();
+        }
+        if decoder == "ais" {
+            let ais = engine.ais.lock();
+            let new = (ais.sentences - sentences).min(ais.nmea.len() as u64) as usize;
+            for sentence in &ais.nmea[ais.nmea.len() - new..] {
+                writeln!(out, "{}", sentence)?;
+            }
+            sentences = ais.sentences;
+        } else {
+            for (_, json) in engine.decode_events(&mut marks).into_iter().filter(|(name, _)| *name == decoder) {
+                writeln!(out, "{}", json)?;
+            }
+        }
+        out.flush()?;
+        if finished {
+            return Ok(());
+        }
+    }
+}
+
+fn info() -> Result<()> {
+    println!("rf_rust {}", env!("CARGO_PKG_VERSION"));
+    println!("Source: demo signals, recordings in {}/, or an rtl_tcp server with --driver rtl_tcp", RECORDING_DIR);
+    println!("Decoders: {}", DECODERS.join(", "));
+    println!("DSP kernels: {}", simd::level().name());
+    match Config::path() {
+        Some(path) if path.exists() => println!("Config: {}", path.display()),
+        Some(path) => println!("Config: none at {}", path.display()),
+        None => println!("Config: none, no home directory"),
+    }
+    let config = Config::load()?;
+    for section in ["network", "mqtt", "icecast"] {
+        let mut keys: Vec<&str> = config.keys(section).collect();
+        keys.sort_unstable();
+        for key in keys {
+            let name = format!("{}.{}", section, key);
+            let value = if key == "password" { "***" } else { config.get(&name).unwrap_or("") };
+            println!("  {} = {}", name, value);
+        }
+    }
+    Ok(())
+}
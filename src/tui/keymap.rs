@@ -0,0 +1,749 @@
+//! Key bindings, set under `[keys]` in the config file as the action's
+//! name and its keys separated by spaces, such as
+//!
+//! ```text
+//! [keys]
+//! quit = "q Esc"
+//! zoom_in = "+ ="
+//! undo = "Ctrl+z"
+//! ```
+//!
+//! Keys are a character or one of Esc, Enter, Tab, Space, Backspace,
+//! Delete, Insert, Home, End, PageUp, PageDown, Up, Down, Left, Right and
+//! F1 to F12, with `Ctrl+` in front for the Control key. An action bound
+//! in a view shares its keys with actions of other views but not with
+//! those of the same view or of every view.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use rf_rust::config::Config;
+
+use super::View;
+
+/// A key as bound, Shift being part of the character
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Self {
+        Self { code, ctrl: false }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self { code: event.code, ctrl: event.modifiers.contains(KeyModifiers::CONTROL) }
+    }
+}
+
+impl Key {
+    pub fn parse(text: &str) -> Option<Self> {
+        let (ctrl, name) = match text.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("ctrl+") && text.len() > 5 => (true, &text[5..]),
+            _ => (false, text),
+        };
+        let code = match name {
+            "Esc" => KeyCode::Esc,
+            "Enter" => KeyCode::Enter,
+            "Tab" => KeyCode::Tab,
+            "Space" => KeyCode::Char(' '),
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Insert" => KeyCode::Insert,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            _ => match name.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => {
+                    let mut chars = name.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => return None,
+                    }
+                }
+            },
+        };
+        Some(Self { code, ctrl })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            // The names Key::parse takes
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Where an action's keys work
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Context {
+    Global,
+    /// While the SAME alert is shown over the other views
+    Alert,
+    /// While ←→ pick a digit of the frequency
+    Digits,
+    /// While the settings tab is shown, where ←→ change a setting
+    Settings,
+    Views(&'static [View]),
+}
+
+impl Context {
+    /// Whether a key can do an action of each in some view
+    fn overlaps(self, other: Context) -> bool {
+        match (self, other) {
+            (Context::Alert, other) | (other, Context::Alert) => other == Context::Alert,
+            (Context::Digits, other) | (other, Context::Digits) => other == Context::Digits,
+            (Context::Settings, other) | (other, Context::Settings) => other == Context::Settings,
+            (Context::Global, _) | (_, Context::Global) => true,
+            (Context::Views(a), Context::Views(b)) => a.iter().any(|view| b.contains(view)),
+        }
+    }
+
+    pub fn applies(self, view: View) -> bool {
+        match self {
+            Context::Global | Context::Alert | Context::Digits | Context::Settings => true,
+            Context::Views(views) => views.contains(&view),
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Context::Global => "all views".to_string(),
+            Context::Alert => "alert".to_string(),
+            Context::Digits => "digit mode".to_string(),
+            Context::Settings => "settings tab".to_string(),
+            Context::Views(views) => views.iter().map(|view| view.name()).collect::<Vec<_>>().join(", "),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    NextTab,
+    PreviousTab,
+    AlertBell,
+    DismissAlert,
+    NextView,
+    Record,
+    RecordFormat,
+    Annotate,
+    ExportSpectrum,
+    ExportWaterfall,
+    Screenshot,
+    Playback,
+    PlaybackPause,
+    SeekBack,
+    SeekForward,
+    SeekBackLong,
+    SeekForwardLong,
+    PlaybackLoop,
+    PlaybackSlower,
+    PlaybackFaster,
+    RecordAudio,
+    AudioFormat,
+    SquelchGate,
+    Theme,
+    Connect,
+    Stream,
+    Increase,
+    Decrease,
+    EnterFrequency,
+    TuningStep,
+    DigitMode,
+    DigitLeft,
+    DigitRight,
+    SettingLess,
+    SettingMore,
+    Undo,
+    Redo,
+    /// Preset by index
+    RecallPreset(usize),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    NextMarker,
+    ClearMarkers,
+    MarkerLeft,
+    MarkerRight,
+    PanLeft,
+    PanRight,
+    WaterfallPause,
+    WaterfallBack,
+    WaterfallForward,
+    Persistence,
+    FullScreen,
+    AddVfo,
+    RemoveVfo,
+    /// VFO by index
+    SelectVfo(usize),
+    VfoMode,
+    VfoRoute,
+    ClickTunes,
+    PowerUnit,
+    Braille,
+    /// The selection or setting before in a list view
+    Previous,
+    Next,
+    /// Act on the selected entry
+    Select,
+    Save,
+    Delete,
+    BookmarkTag,
+    ScanBookmarks,
+    SkipChannel,
+    ToggleLog,
+    NmeaLog,
+    IsmLog,
+    RttyShift,
+    RttyBaud,
+    PskMode,
+    PskOrder,
+    PskBaud,
+    BurstCapture,
+    ScopeSource,
+    ScopeTrigger,
+    TriggerUp,
+    TriggerDown,
+    WsprUpload,
+    MeasureBandwidth,
+    PowerSpan,
+    EditKey,
+    Help,
+    Palette,
+    ControlsNarrower,
+    ControlsWider,
+    SamplesTaller,
+    SamplesShorter,
+    LayoutReset,
+}
+
+/// Headings of the help overlay, in the order shown
+pub const CATEGORIES: &[&str] = &[
+    "General",
+    "Tuning",
+    "Spectrum",
+    "Layout",
+    "VFOs and audio",
+    "Recording",
+    "Playback",
+    "Lists",
+    "Decoders",
+    "Measurement",
+];
+
+impl Action {
+    /// Which of [`CATEGORIES`] the action is listed under in the help
+    pub fn category(self) -> &'static str {
+        match self {
+            Action::Quit
+            | Action::NextTab
+            | Action::PreviousTab
+            | Action::NextView
+            | Action::Connect
+            | Action::Stream
+            | Action::Undo
+            | Action::Redo
+            | Action::Theme
+            | Action::Screenshot
+            | Action::Help
+            | Action::Palette
+            | Action::SettingLess
+            | Action::SettingMore => "General",
+            Action::Increase
+            | Action::Decrease
+            | Action::EnterFrequency
+            | Action::TuningStep
+            | Action::DigitMode
+            | Action::DigitLeft
+            | Action::DigitRight
+            | Action::RecallPreset(_) => "Tuning",
+            Action::ZoomIn
+            | Action::ZoomOut
+            | Action::ZoomReset
+            | Action::NextMarker
+            | Action::ClearMarkers
+            | Action::MarkerLeft
+            | Action::MarkerRight
+            | Action::PanLeft
+            | Action::PanRight
+            | Action::WaterfallPause
+            | Action::WaterfallBack
+            | Action::WaterfallForward
+            | Action::Persistence
+            | Action::FullScreen
+            | Action::ClickTunes
+            | Action::PowerUnit
+            | Action::Braille => "Spectrum",
+            Action::ControlsNarrower
+            | Action::ControlsWider
+            | Action::SamplesTaller
+            | Action::SamplesShorter
+            | Action::LayoutReset => "Layout",
+            Action::AddVfo
+            | Action::RemoveVfo
+            | Action::SelectVfo(_)
+            | Action::VfoMode
+            | Action::VfoRoute
+            | Action::SquelchGate => "VFOs and audio",
+            Action::Record
+            | Action::RecordFormat
+            | Action::Annotate
+            | Action::RecordAudio
+            | Action::AudioFormat
+            | Action::ExportSpectrum
+            | Action::ExportWaterfall => "Recording",
+            Action::Playback
+            | Action::PlaybackPause
+            | Action::SeekBack
+            | Action::SeekForward
+            | Action::SeekBackLong
+            | Action::SeekForwardLong
+            | Action::PlaybackLoop
+            | Action::PlaybackSlower
+            | Action::PlaybackFaster => "Playback",
+            Action::Previous
+            | Action::Next
+            | Action::Select
+            | Action::Save
+            | Action::Delete
+            | Action::BookmarkTag
+            | Action::ScanBookmarks
+            | Action::SkipChannel
+            | Action::EditKey => "Lists",
+            Action::AlertBell
+            | Action::DismissAlert
+            | Action::ToggleLog
+            | Action::NmeaLog
+            | Action::IsmLog
+            | Action::RttyShift
+            | Action::RttyBaud
+            | Action::PskMode
+            | Action::PskOrder
+            | Action::PskBaud
+            | Action::BurstCapture
+            | Action::WsprUpload => "Decoders",
+            Action::ScopeSource
+            | Action::ScopeTrigger
+            | Action::TriggerUp
+            | Action::TriggerDown
+            | Action::MeasureBandwidth
+            | Action::PowerSpan => "Measurement",
+        }
+    }
+}
+
+const SPECTRUM: &[View] = &[View::Spectrum];
+
+/// Every action with its name in the config file, where it works and its
+/// default keys
+pub const ACTIONS: &[(Action, &str, Context, &str)] = &[
+    (Action::Quit, "quit", Context::Global, "q Esc"),
+    (Action::NextTab, "next_tab", Context::Global, "Tab Right"),
+    (Action::PreviousTab, "previous_tab", Context::Global, "Left"),
+    (Action::Increase, "increase", Context::Global, "Up"),
+    (Action::Decrease, "decrease", Context::Global, "Down"),
+    (Action::NextView, "next_view", Context::Global, "v"),
+    (Action::Help, "help", Context::Global, "?"),
+    (Action::Palette, "palette", Context::Global, ":"),
+    (Action::ControlsNarrower, "controls_narrower", Context::Global, "Ctrl+Left"),
+    (Action::ControlsWider, "controls_wider", Context::Global, "Ctrl+Right"),
+    (Action::SamplesTaller, "samples_taller", Context::Global, "Ctrl+Up"),
+    (Action::SamplesShorter, "samples_shorter", Context::Global, "Ctrl+Down"),
+    (Action::LayoutReset, "layout_reset", Context::Global, "Ctrl+0"),
+    (Action::Connect, "connect", Context::Global, "c"),
+    (Action::Stream, "stream", Context::Global, "s"),
+    (Action::EnterFrequency, "enter_frequency", Context::Global, "g"),
+    (Action::TuningStep, "tuning_step", Context::Global, "z"),
+    (Action::DigitMode, "digit_mode", Context::Global, "D"),
+    (Action::DigitLeft, "digit_left", Context::Digits, "Left"),
+    (Action::DigitRight, "digit_right", Context::Digits, "Right"),
+    (Action::SettingLess, "setting_less", Context::Settings, "Left"),
+    (Action::SettingMore, "setting_more", Context::Settings, "Right"),
+    (Action::Undo, "undo", Context::Global, "Ctrl+z"),
+    (Action::Redo, "redo", Context::Global, "Ctrl+y"),
+    (Action::AlertBell, "alert_bell", Context::Global, "a"),
+    (Action::DismissAlert, "dismiss_alert", Context::Alert, "Enter"),
+    (Action::Record, "record", Context::Global, "r"),
+    (Action::RecordFormat, "record_format", Context::Global, "R"),
+    (Action::Annotate, "annotate", Context::Global, "N"),
+    (Action::RecordAudio, "record_audio", Context::Global, "A"),
+    (Action::AudioFormat, "audio_format", Context::Global, "W"),
+    (Action::SquelchGate, "squelch_gate", Context::Global, "G"),
+    (Action::ExportSpectrum, "export_spectrum", Context::Global, "X"),
+    (Action::ExportWaterfall, "export_waterfall", Context::Global, "I"),
+    (Action::Screenshot, "screenshot", Context::Global, "S"),
+    (Action::Theme, "theme", Context::Global, "T"),
+    (Action::Playback, "playback", Context::Global, "P"),
+    (Action::PlaybackPause, "playback_pause", Context::Global, "K"),
+    (Action::SeekBack, "seek_back", Context::Global, "J"),
+    (Action::SeekForward, "seek_forward", Context::Global, "L"),
+    (Action::SeekBackLong, "seek_back_long", Context::Global, "{"),
+    (Action::SeekForwardLong, "seek_forward_long", Context::Global, "}"),
+    (Action::PlaybackLoop, "playback_loop", Context::Global, "O"),
+    (Action::PlaybackSlower, "playback_slower", Context::Global, "("),
+    (Action::PlaybackFaster, "playback_faster", Context::Global, ")"),
+    (Action::RecallPreset(0), "preset1", Context::Global, "F1"),
+    (Action::RecallPreset(1), "preset2", Context::Global, "F2"),
+    (Action::RecallPreset(2), "preset3", Context::Global, "F3"),
+    (Action::RecallPreset(3), "preset4", Context::Global, "F4"),
+    (Action::RecallPreset(4), "preset5", Context::Global, "F5"),
+    (Action::RecallPreset(5), "preset6", Context::Global, "F6"),
+    (Action::RecallPreset(6), "preset7", Context::Global, "F7"),
+    (Action::RecallPreset(7), "preset8", Context::Global, "F8"),
+    (Action::RecallPreset(8), "preset9", Context::Global, "F9"),
+    (Action::ZoomIn, "zoom_in", Context::Views(SPECTRUM), "+ ="),
+    (Action::ZoomOut, "zoom_out", Context::Views(SPECTRUM), "-"),
+    (Action::ZoomReset, "zoom_reset", Context::Views(SPECTRUM), "0"),
+    (Action::PanLeft, "pan_left", Context::Views(SPECTRUM), ","),
+    (Action::PanRight, "pan_right", Context::Views(SPECTRUM), "."),
+    (Action::NextMarker, "next_marker", Context::Views(SPECTRUM), "m"),
+    (Action::ClearMarkers, "clear_markers", Context::Views(SPECTRUM), "M"),
+    (Action::MarkerLeft, "marker_left", Context::Views(SPECTRUM), "<"),
+    (Action::MarkerRight, "marker_right", Context::Views(SPECTRUM), ">"),
+    (Action::WaterfallPause, "waterfall_pause", Context::Views(SPECTRUM), "Space"),
+    (Action::WaterfallBack, "waterfall_back", Context::Views(SPECTRUM), "PageUp"),
+    (Action::WaterfallForward, "waterfall_forward", Context::Views(SPECTRUM), "PageDown"),
+    (Action::Persistence, "persistence", Context::Views(SPECTRUM), "e"),
+    (Action::FullScreen, "full_screen", Context::Views(SPECTRUM), "f"),
+    (Action::AddVfo, "add_vfo", Context::Views(SPECTRUM), "n"),
+    (Action::RemoveVfo, "remove_vfo", Context::Views(SPECTRUM), "x"),
+    (Action::SelectVfo(0), "vfo1", Context::Views(SPECTRUM), "1"),
+    (Action::SelectVfo(1), "vfo2", Context::Views(SPECTRUM), "2"),
+    (Action::SelectVfo(2), "vfo3", Context::Views(SPECTRUM), "3"),
+    (Action::SelectVfo(3), "vfo4", Context::Views(SPECTRUM), "4"),
+    (Action::SelectVfo(4), "vfo5", Context::Views(SPECTRUM), "5"),
+    (Action::SelectVfo(5), "vfo6", Context::Views(SPECTRUM), "6"),
+    (Action::SelectVfo(6), "vfo7", Context::Views(SPECTRUM), "7"),
+    (Action::SelectVfo(7), "vfo8", Context::Views(SPECTRUM), "8"),
+    (Action::SelectVfo(8), "vfo9", Context::Views(SPECTRUM), "9"),
+    (Action::VfoMode, "vfo_mode", Context::Views(SPECTRUM), "u"),
+    (Action::VfoRoute, "vfo_route", Context::Views(SPECTRUM), "h"),
+    (Action::ClickTunes, "click_tunes", Context::Views(SPECTRUM), "o"),
+    (Action::PowerUnit, "power_unit", Context::Views(&[View::Spectrum, View::Measure]), "d"),
+    (Action::Braille, "braille", Context::Views(&[View::Spectrum, View::Scope, View::Constellation]), "p"),
+    (
+        Action::Previous,
+        "previous",
+        Context::Views(&[
+            View::Presets,
+            View::Bookmarks,
+            View::Plugins,
+            View::Log,
+            View::Messages,
+            View::Scanner,
+            View::Bursts,
+            View::Scope,
+            View::Keys,
+        ]),
+        "[",
+    ),
+    (
+        Action::Next,
+        "next",
+        Context::Views(&[
+            View::Presets,
+            View::Bookmarks,
+            View::Plugins,
+            View::Log,
+            View::Messages,
+            View::Scanner,
+            View::Bursts,
+            View::Scope,
+            View::Keys,
+        ]),
+        "]",
+    ),
+    (Action::Select, "select", Context::Views(&[View::Presets, View::Bookmarks, View::Scanner]), "Enter"),
+    (Action::Save, "save", Context::Views(&[View::Presets, View::Bookmarks, View::Keys]), "w"),
+    (Action::Delete, "delete", Context::Views(&[View::Presets, View::Bookmarks, View::Scanner, View::Schedule, View::Keys]), "x"),
+    (Action::BookmarkTag, "bookmark_tag", Context::Views(&[View::Bookmarks]), "t"),
+    (Action::ScanBookmarks, "scan_bookmarks", Context::Views(&[View::Scanner]), "b"),
+    (Action::SkipChannel, "skip_channel", Context::Views(&[View::Scanner]), "n"),
+    (Action::ToggleLog, "toggle_log", Context::Views(&[View::Scanner, View::Navtex, View::Measure]), "l"),
+    (Action::NmeaLog, "nmea_log", Context::Views(&[View::Ais]), "n"),
+    (Action::IsmLog, "ism_log", Context::Views(&[View::Ism]), "j"),
+    (Action::RttyShift, "rtty_shift", Context::Views(&[View::Rtty]), "h"),
+    (Action::RttyBaud, "rtty_baud", Context::Views(&[View::Rtty]), "b"),
+    (Action::PskMode, "psk_mode", Context::Views(&[View::Psk]), "m"),
+    (Action::PskOrder, "psk_order", Context::Views(&[View::Constellation]), "m"),
+    (Action::PskBaud, "psk_baud", Context::Views(&[View::Constellation]), "b"),
+    (Action::BurstCapture, "burst_capture", Context::Views(&[View::Bursts]), "k"),
+    (Action::ScopeSource, "scope_source", Context::Views(&[View::Scope]), "i"),
+    (Action::ScopeTrigger, "scope_trigger", Context::Views(&[View::Scope]), "t"),
+    (Action::TriggerUp, "trigger_up", Context::Views(&[View::Scope]), "+"),
+    (Action::TriggerDown, "trigger_down", Context::Views(&[View::Scope]), "-"),
+    (Action::WsprUpload, "wspr_upload", Context::Views(&[View::Wspr]), "u"),
+    (Action::MeasureBandwidth, "measure_bandwidth", Context::Views(&[View::Measure]), "w"),
+    (Action::PowerSpan, "power_span", Context::Views(&[View::Measure]), "t"),
+    (Action::EditKey, "edit_key", Context::Views(&[View::Keys]), "Enter"),
+];
+
+/// The keys of each of [`ACTIONS`], in the same order
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    keys: Vec<Vec<Key>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self { keys: ACTIONS.iter().map(|&(_, _, _, keys)| parse_keys(keys).unwrap_or_default()).collect() }
+    }
+}
+
+fn parse_keys(text: &str) -> Option<Vec<Key>> {
+    text.split_whitespace().map(Key::parse).collect()
+}
+
+impl Keymap {
+    /// The default bindings with those under `[keys]` in place of them,
+    /// and a message for each binding left out
+    pub fn from_config(config: &Config) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+        let mut bound = Vec::new();
+        let mut names: Vec<&str> = config.keys("keys").collect();
+        names.sort_unstable();
+        for name in names {
+            let value = config.get(&format!("keys.{}", name)).unwrap_or_default();
+            match (ACTIONS.iter().position(|&(_, n, _, _)| n == name), parse_keys(value)) {
+                (Some(index), Some(keys)) => {
+                    keymap.keys[index].clear();
+                    bound.push((index, keys));
+                }
+                (None, _) => errors.push(format!("keys.{}: no such action", name)),
+                (_, None) => errors.push(format!("keys.{}: cannot read `{}` as keys", name, value)),
+            }
+        }
+        // Every binding from the file is cleared first, so that two actions
+        // can swap keys
+        for (index, keys) in bound {
+            for key in keys {
+                if let Err(e) = keymap.bind(index, key) {
+                    errors.push(format!("keys.{}: {}", ACTIONS[index].1, e));
+                }
+            }
+        }
+        (keymap, errors)
+    }
+
+    pub fn keys(&self, index: usize) -> &[Key] {
+        &self.keys[index]
+    }
+
+    /// The action bound to `key` that works in `view`, the first that
+    /// `enabled` takes when several do. Those of the alert and digit mode
+    /// come before the rest while they are on.
+    pub fn action(&self, key: Key, view: View, enabled: impl Fn(Action) -> bool) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .zip(&self.keys)
+            .filter(|((action, _, context, _), keys)| context.applies(view) && keys.contains(&key) && enabled(*action))
+            .min_by_key(|((_, _, context, _), _)| matches!(context, Context::Global | Context::Views(_)))
+            .map(|(&(action, _, _, _), _)| action)
+    }
+
+    /// The action other than the one at `index` that `key` already does
+    /// where that one works
+    pub fn conflict(&self, index: usize, key: Key) -> Option<&'static str> {
+        let context = ACTIONS[index].2;
+        ACTIONS
+            .iter()
+            .zip(&self.keys)
+            .enumerate()
+            .find(|&(i, ((_, _, other, _), keys))| i != index && context.overlaps(*other) && keys.contains(&key))
+            .map(|(_, ((_, name, _, _), _))| *name)
+    }
+
+    /// Add `key` to the action at `index` unless it conflicts
+    pub fn bind(&mut self, index: usize, key: Key) -> Result<(), String> {
+        if let Some(name) = self.conflict(index, key) {
+            return Err(format!("{} is bound to {} already", key, name));
+        }
+        if !self.keys[index].contains(&key) {
+            self.keys[index].push(key);
+        }
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, index: usize) {
+        self.keys[index].clear();
+    }
+
+    /// Put the bindings that differ from the defaults under `[keys]` in
+    /// the config file at `path`, leaving the rest of the file as it was
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let defaults = Self::default();
+        let section: Vec<String> = ACTIONS
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.keys[i] != defaults.keys[i])
+            .map(|(i, (_, name, _, _))| {
+                let keys: Vec<String> = self.keys[i].iter().map(Key::to_string).collect();
+                format!("{} = \"{}\"", name, keys.join(" "))
+            })
+            .collect();
+        // The file without its [keys] section, which goes at the end
+        let mut lines = Vec::new();
+        let mut in_keys = false;
+        for line in text.lines() {
+            let header = line.trim().strip_prefix('[').and_then(|l| l.split_once(']')).map(|(name, _)| name.trim());
+            if let Some(name) = header {
+                in_keys = name == "keys";
+            }
+            if !in_keys {
+                lines.push(line);
+            }
+        }
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        let mut out = lines.join("\n");
+        if !section.is_empty() {
+            if !out.is_empty() {
+                out += "\n\n";
+            }
+            out += &format!("[keys]\n{}", section.join("\n"));
+        }
+        out.push('\n');
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(name: &str) -> usize {
+        ACTIONS.iter().position(|&(_, n, _, _)| n == name).unwrap()
+    }
+
+    fn key(text: &str) -> Key {
+        Key::parse(text).unwrap()
+    }
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        let keymap = Keymap::default();
+        for (i, &(_, name, _, text)) in ACTIONS.iter().enumerate() {
+            assert_eq!(keymap.keys(i).len(), text.split_whitespace().count(), "{} has a key that does not parse", name);
+            for &key in keymap.keys(i) {
+                assert_eq!(keymap.conflict(i, key), None, "{} of {}", key, name);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_read_back_as_written() {
+        for text in ["q", "Ctrl+z", "Space", "F12", "PageUp", "Esc", "+"] {
+            assert_eq!(key(text).to_string(), text);
+        }
+        assert_eq!(key("ctrl+Z"), Key { code: KeyCode::Char('Z'), ctrl: true });
+        for bad in ["", "F13", "Ctrl+", "qq", "Escape"] {
+            assert_eq!(Key::parse(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn config_replaces_defaults_and_reports_the_rest() {
+        let config = Config::parse(
+            "[keys]\nquit = \"Q Ctrl+c\"\nundo = \"Ctrl+u\"\nfly = \"f\"\nredo = \"Ctrl+\"\nzoom_out = \"Ctrl+u\"",
+        )
+        .unwrap();
+        let (keymap, errors) = Keymap::from_config(&config);
+        assert_eq!(keymap.keys(index("quit")), [key("Q"), key("Ctrl+c")]);
+        assert_eq!(keymap.keys(index("undo")), [key("Ctrl+u")]);
+        // Left as they were
+        assert_eq!(keymap.keys(index("redo")), [key("Ctrl+y")]);
+        assert_eq!(keymap.keys(index("zoom_in")), [key("+"), key("=")]);
+        assert_eq!(keymap.action(key("Q"), View::Spectrum, |_| true), Some(Action::Quit));
+        assert_eq!(keymap.action(key("q"), View::Spectrum, |_| true), None);
+        assert_eq!(
+            errors,
+            [
+                "keys.fly: no such action",
+                "keys.redo: cannot read `Ctrl+` as keys",
+                "keys.zoom_out: Ctrl+u is bound to undo already",
+            ]
+        );
+    }
+
+    #[test]
+    fn actions_swap_keys() {
+        let config = Config::parse("[keys]\nundo = \"Ctrl+y\"\nredo = \"Ctrl+z\"").unwrap();
+        let (keymap, errors) = Keymap::from_config(&config);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(keymap.action(key("Ctrl+z"), View::Spectrum, |_| true), Some(Action::Redo));
+        assert_eq!(keymap.action(key("Ctrl+y"), View::Spectrum, |_| true), Some(Action::Undo));
+    }
+
+    #[test]
+    fn conflicts_follow_the_views() {
+        let mut keymap = Keymap::default();
+        // A global key is taken everywhere
+        assert_eq!(keymap.conflict(index("zoom_out"), key("r")), Some("record"));
+        // Views that never meet share keys
+        assert_eq!(keymap.conflict(index("nmea_log"), key("x")), None);
+        assert_eq!(keymap.conflict(index("zoom_out"), key("n")), Some("add_vfo"));
+        assert!(keymap.bind(index("zoom_out"), key("n")).is_err());
+        keymap.unbind(index("add_vfo"));
+        assert_eq!(keymap.bind(index("zoom_out"), key("n")), Ok(()));
+        assert_eq!(keymap.keys(index("zoom_out")), [key("-"), key("n")]);
+    }
+
+    #[test]
+    fn saved_bindings_load_back() {
+        let dir = std::env::temp_dir().join(format!("rf_rust_keymap_test_{}", std::process::id()));
+        let path = dir.join("config.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "[radio]\nfrequency = 100e6\n\n[keys]\nquit = \"x\"\n\n[ui]\ntick_ms = 50\n").unwrap();
+
+        let mut keymap = Keymap::default();
+        keymap.unbind(index("undo"));
+        keymap.bind(index("undo"), key("Ctrl+u")).unwrap();
+        keymap.unbind(index("screenshot"));
+        keymap.save(&path).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            text,
+            "[radio]\nfrequency = 100e6\n\n[ui]\ntick_ms = 50\n\n[keys]\nundo = \"Ctrl+u\"\nscreenshot = \"\"\n"
+        );
+        let (loaded, errors) = Keymap::from_config(&Config::parse(&text).unwrap());
+        assert!(errors.is_empty(), "{:?}", errors);
+        for (i, (_, name, _, _)) in ACTIONS.iter().enumerate() {
+            assert_eq!(loaded.keys(i), keymap.keys(i), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! The command palette: every action of the keymap, every demodulator and
+//! every view by name, found by typing a few letters of it in order.
+
+use rf_rust::dsp::AudioMode;
+
+use super::View;
+use super::keymap::{ACTIONS, Action};
+
+/// What a line of the palette does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Action(Action),
+    Mode(AudioMode),
+    View(View),
+}
+
+/// Every command with its name, actions first in keymap order
+pub fn commands() -> Vec<(Command, String)> {
+    let actions = ACTIONS.iter().map(|&(action, name, _, _)| (Command::Action(action), name.replace('_', " ")));
+    let modes = [AudioMode::Fm, AudioMode::Am, AudioMode::Usb, AudioMode::Lsb]
+        .into_iter()
+        .map(|mode| (Command::Mode(mode), format!("set mode {}", mode)));
+    let views = View::ALL.into_iter().map(|view| (Command::View(view), format!("open {}", view.name())));
+    actions.chain(modes).chain(views).collect()
+}
+
+/// How well `query` matches `name`, `None` unless every character of the
+/// query is in the name in the same order, ignoring case. Runs of
+/// characters and those at the start of a word count for more.
+pub fn score(query: &str, name: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let name: Vec<char> = name.chars().flat_map(char::to_lowercase).collect();
+    let mut from = 0;
+    for c in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let found = from + name[from..].iter().position(|&n| n == c)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || name[found - 1] == ' ' {
+            score += 8;
+        }
+        previous = Some(found);
+        from = found + 1;
+    }
+    Some(score)
+}
+
+/// The palette while it is open
+#[derive(Clone, Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    /// Index into [`Palette::matches`]
+    pub selected: usize,
+}
+
+impl Palette {
+    /// Those of `commands` the query matches, best first
+    pub fn matches(&self, commands: Vec<(Command, String)>) -> Vec<(Command, String)> {
+        let mut scored: Vec<(i32, (Command, String))> = commands
+            .into_iter()
+            .filter_map(|(command, name)| Some((score(&self.query, &name)?, (command, name))))
+            .collect();
+        // Stable, so equal scores keep the order of the commands
+        scored.sort_by_key(|&(score, _)| -score);
+        scored.into_iter().map(|(_, command)| command).collect()
+    }
+}
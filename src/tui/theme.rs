@@ -0,0 +1,210 @@
+//! Colour schemes for the TUI.
+
+use std::str::FromStr;
+
+use ratatui::style::Color;
+
+use rf_rust::config::Config;
+
+type Rgb = (u8, u8, u8);
+
+/// Every colour the interface draws with, by role
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Color,
+    pub text: Color,
+    /// Axis lines and secondary text
+    pub label: Color,
+    /// Timestamps, grid and other de-emphasised detail
+    pub dim: Color,
+    /// Main panel borders and titles
+    pub primary: Color,
+    /// Header, controls and list accents
+    pub secondary: Color,
+    pub highlight: Color,
+    pub good: Color,
+    pub alert: Color,
+    pub info: Color,
+    /// Live spectrum trace
+    pub trace: Color,
+    pub markers: [Color; 2],
+    /// Tuning indicators, repeating after the last
+    pub vfos: [Color; 4],
+    /// Waterfall scale from the noise floor to the strongest signal
+    pub waterfall: [Rgb; 5],
+    /// Newest and oldest persistence traces
+    pub persistence: (Rgb, Rgb),
+}
+
+/// Waterfall colour maps that replace a theme's own, from the noise floor up
+pub const COLORMAPS: [(&str, [Rgb; 5]); 4] = [
+    ("viridis", [(68, 1, 84), (59, 82, 139), (33, 145, 140), (94, 201, 98), (253, 231, 37)]),
+    ("inferno", [(0, 0, 4), (87, 16, 110), (188, 55, 84), (249, 142, 9), (252, 255, 164)]),
+    ("grey", [(0, 0, 0), (64, 64, 64), (128, 128, 128), (192, 192, 192), (255, 255, 255)]),
+    ("classic", [(0, 0, 0), (0, 0, 255), (0, 255, 255), (255, 255, 0), (255, 0, 0)]),
+];
+
+impl Theme {
+    pub const NAMES: [&'static str; 4] = ["dark", "light", "green-phosphor", "high-contrast"];
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        Some(match name {
+            "dark" => Self::dark(),
+            "light" => Self::light(),
+            "green-phosphor" => Self::green_phosphor(),
+            "high-contrast" => Self::high_contrast(),
+            _ => return None,
+        })
+    }
+
+    /// The next built-in theme after this one
+    pub fn next(&self) -> Self {
+        let index = Self::NAMES.iter().position(|&n| n == self.name).unwrap_or(0);
+        Self::builtin(Self::NAMES[(index + 1) % Self::NAMES.len()]).unwrap_or_else(Self::dark)
+    }
+
+    /// The built-in theme named by `theme.name`, with any single colours such as
+    /// `theme.alert = "#ff8000"` overriding it
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let name = config.get("theme.name").unwrap_or("dark");
+        let mut theme = Self::builtin(name)
+            .ok_or_else(|| format!("unknown theme `{}`, expected one of {}", name, Self::NAMES.join(", ")))?;
+        for key in config.keys("theme").filter(|&key| key != "name") {
+            let value = config.get(&format!("theme.{}", key)).unwrap_or_default();
+            let color = Color::from_str(value).map_err(|_| format!("theme.{}: invalid colour `{}`", key, value))?;
+            let slot = match key {
+                "background" => &mut theme.background,
+                "text" => &mut theme.text,
+                "label" => &mut theme.label,
+                "dim" => &mut theme.dim,
+                "primary" => &mut theme.primary,
+                "secondary" => &mut theme.secondary,
+                "highlight" => &mut theme.highlight,
+                "good" => &mut theme.good,
+                "alert" => &mut theme.alert,
+                "info" => &mut theme.info,
+                "trace" => &mut theme.trace,
+                _ => return Err(format!("theme.{}: unknown colour", key)),
+            };
+            *slot = color;
+        }
+        Ok(theme)
+    }
+
+    /// Draw the waterfall with the colour map `name` from [`COLORMAPS`], or
+    /// with the theme's own for `None`
+    pub fn set_colormap(&mut self, name: Option<&str>) {
+        let colours = match name {
+            Some(name) => COLORMAPS.iter().find(|&&(n, _)| n == name).map(|&(_, colours)| colours),
+            None => Self::builtin(self.name).map(|theme| theme.waterfall),
+        };
+        if let Some(colours) = colours {
+            self.waterfall = colours;
+        }
+    }
+
+        /// Waterfall colour for `t` in 0..1
+    pub fn waterfall_color(&self, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0) * (self.waterfall.len() - 1) as f32;
+        let index = (t as usize).min(self.waterfall.len() - 2);
+        blend(self.waterfall[index], self.waterfall[index + 1], t - index as f32)
+    }
+
+    /// Persistence trace colour for `age` from 0 (newest) to 1 (oldest)
+    pub fn faded(&self, age: f32) -> Color {
+        blend(self.persistence.0, self.persistence.1, age.clamp(0.0, 1.0))
+    }
+
+    fn dark() -> Self {
+        Self {
+            name: "dark",
+            background: Color::Black,
+            text: Color::White,
+            label: Color::Gray,
+            dim: Color::DarkGray,
+            primary: Color::Cyan,
+            secondary: Color::Magenta,
+            highlight: Color::Yellow,
+            good: Color::Green,
+            alert: Color::Red,
+            info: Color::Blue,
+            trace: Color::Green,
+            markers: [Color::Yellow, Color::Magenta],
+            vfos: [Color::Red, Color::LightCyan, Color::White, Color::LightRed],
+            waterfall: [(0, 0, 0), (0, 0, 255), (0, 255, 255), (255, 255, 0), (255, 0, 0)],
+            persistence: ((0, 160, 53), (0, 20, 6)),
+        }
+    }
+
+    fn light() -> Self {
+        Self {
+            name: "light",
+            background: Color::White,
+            text: Color::Black,
+            label: Color::DarkGray,
+            dim: Color::Gray,
+            primary: Color::Blue,
+            secondary: Color::Magenta,
+            highlight: Color::Rgb(175, 95, 0),
+            good: Color::Rgb(0, 135, 0),
+            alert: Color::Red,
+            info: Color::Rgb(0, 95, 175),
+            trace: Color::Rgb(0, 110, 0),
+            markers: [Color::Rgb(175, 95, 0), Color::Magenta],
+            vfos: [Color::Red, Color::Blue, Color::Black, Color::Rgb(135, 0, 0)],
+            waterfall: [(255, 255, 255), (160, 160, 255), (0, 0, 255), (135, 0, 135), (215, 0, 0)],
+            persistence: ((0, 120, 40), (215, 235, 220)),
+        }
+    }
+
+    fn green_phosphor() -> Self {
+        let bright = Color::Rgb(51, 255, 102);
+        let mid = Color::Rgb(0, 200, 60);
+        Self {
+            name: "green-phosphor",
+            background: Color::Black,
+            text: bright,
+            label: mid,
+            dim: Color::Rgb(0, 110, 30),
+            primary: bright,
+            secondary: mid,
+            highlight: Color::Rgb(180, 255, 180),
+            good: bright,
+            // No red on a phosphor tube, alerts are the brightest green
+            alert: Color::Rgb(225, 255, 225),
+            info: Color::Rgb(0, 160, 50),
+            trace: Color::Rgb(120, 255, 140),
+            markers: [Color::Rgb(200, 255, 200), mid],
+            vfos: [Color::Rgb(225, 255, 225), Color::Rgb(0, 255, 100), Color::Rgb(120, 200, 120), Color::Rgb(0, 140, 40)],
+            waterfall: [(0, 0, 0), (0, 50, 15), (0, 120, 35), (50, 220, 90), (200, 255, 210)],
+            persistence: ((0, 200, 60), (0, 25, 8)),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast",
+            background: Color::Black,
+            text: Color::White,
+            label: Color::White,
+            dim: Color::Gray,
+            primary: Color::White,
+            secondary: Color::LightCyan,
+            highlight: Color::LightYellow,
+            good: Color::LightGreen,
+            alert: Color::LightRed,
+            info: Color::LightBlue,
+            trace: Color::LightYellow,
+            markers: [Color::LightGreen, Color::LightMagenta],
+            vfos: [Color::LightRed, Color::LightCyan, Color::White, Color::LightMagenta],
+            waterfall: [(0, 0, 0), (0, 0, 255), (0, 255, 255), (255, 255, 0), (255, 0, 0)],
+            persistence: ((0, 255, 85), (0, 60, 20)),
+        }
+    }
+}
+
+fn blend(from: Rgb, to: Rgb, t: f32) -> Color {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
@@ -0,0 +1,41 @@
+//! The VU meter and the audio recorder agreeing on full scale.
+
+use std::f32::consts::{PI, TAU};
+
+use rf_rust::dsp::{VuMeter, af_gain};
+use rf_rust::recording::audio::AudioRecorder;
+
+const RATE: f64 = 8e3;
+
+/// A 1 kHz tone at `peak` in the units of the demodulator output
+fn tone(peak: f32) -> Vec<f32> {
+    (0..RATE as usize).map(|i| peak * (TAU * 1e3 * i as f32 / RATE as f32).sin()).collect()
+}
+
+#[test]
+fn audio_the_meter_shows_below_clip_is_written_unclipped() {
+    let gain_db = 6.0;
+    // Just below where the meter lights CLIP at this gain
+    let audio = tone(0.97 * PI / 10f32.powf(gain_db / 20.0));
+    let mut vu = VuMeter::new();
+    vu.process(&audio, gain_db, RATE);
+    assert!(!vu.is_clipping());
+
+    let dir = std::env::temp_dir().join(format!("rf_rust_audio_test_{}", std::process::id()));
+    let mut recorder = AudioRecorder::new(&dir);
+    recorder.start().unwrap();
+    let scaled: Vec<f32> = audio.iter().map(|a| a * af_gain(gain_db)).collect();
+    recorder.process(&scaled, RATE, 0.0, 100e6, "FM").unwrap();
+    recorder.stop().unwrap();
+
+    let path = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    let wav = std::fs::read(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let pcm: Vec<i16> = wav[44..].chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+    assert_eq!(pcm.len(), audio.len());
+    let peak = pcm.iter().map(|s| s.unsigned_abs()).max().unwrap();
+    let full = i16::MAX as f32;
+    assert!((peak as f32 - 0.97 * full).abs() < 0.01 * full, "peak {}", peak);
+    // Nothing reached the clamp at full scale
+    assert!(pcm.iter().all(|s| s.unsigned_abs() < i16::MAX as u16));
+}
@@ -0,0 +1,116 @@
+//! The DSP driven by MockSdr, with signals where they are known to be.
+
+use num_complex::Complex32;
+use rf_rust::device::Device;
+use rf_rust::device::mock::{MockSdr, Shape};
+use rf_rust::dsp::measure::SpectrumEstimator;
+use rf_rust::dsp::{AudioDemod, AudioMode};
+
+const CENTER: f64 = 100e6;
+const RATE: f64 = 1e6;
+const FFT_SIZE: usize = 1024;
+
+fn sdr(seed: u64) -> MockSdr {
+    let mut sdr = MockSdr::new(seed);
+    sdr.noise_db = -60.0;
+    sdr.tune(CENTER, RATE, 20.0);
+    sdr
+}
+
+fn samples(sdr: &mut MockSdr, count: usize) -> Vec<Complex32> {
+    let mut out = Vec::new();
+    sdr.generate(count, &mut out);
+    out
+}
+
+/// Power per bin in dBFS, averaged over `count` samples
+fn spectrum(sdr: &mut MockSdr, count: usize) -> Vec<f32> {
+    let mut estimator = SpectrumEstimator::new(FFT_SIZE, count / FFT_SIZE);
+    let spectrum = estimator.push(&samples(sdr, count), RATE).expect("enough samples for a spectrum");
+    spectrum.bins.iter().map(|p| 10.0 * p.log10()).collect()
+}
+
+fn bin(frequency: f64) -> usize {
+    ((frequency - CENTER) / RATE * FFT_SIZE as f64 + FFT_SIZE as f64 / 2.0).round() as usize
+}
+
+fn peak(bins: &[f32]) -> usize {
+    (0..bins.len()).max_by(|&a, &b| bins[a].total_cmp(&bins[b])).unwrap()
+}
+
+#[test]
+fn same_seed_same_samples() {
+    let (mut a, mut b, mut c) = (sdr(7), sdr(7), sdr(8));
+    for sdr in [&mut a, &mut b, &mut c] {
+        sdr.add(CENTER + 100e3, -20.0, Shape::Tone);
+    }
+    let first = samples(&mut a, 4096);
+    assert_eq!(first, samples(&mut b, 4096));
+    assert_ne!(first, samples(&mut c, 4096));
+}
+
+#[test]
+fn tone_at_its_frequency_and_power() {
+    let mut sdr = sdr(1);
+    sdr.add(CENTER + 125e3, -20.0, Shape::Tone);
+    let bins = spectrum(&mut sdr, 64 * FFT_SIZE);
+    assert_eq!(peak(&bins), bin(CENTER + 125e3));
+    // The window spreads the tone over three bins
+    let power: f32 = bins[peak(&bins) - 1..=peak(&bins) + 1].iter().map(|db| 10f32.powf(db / 10.0)).sum();
+    assert!((10.0 * power.log10() + 20.0).abs() < 1.0, "tone at {:.1} dBFS", 10.0 * power.log10());
+    // Noise spread over every bin
+    let floor = bins[bin(CENTER - 300e3)];
+    let expected = -60.0 - 10.0 * (FFT_SIZE as f32).log10();
+    assert!((floor - expected).abs() < 3.0, "floor at {:.1} dBFS, expected {:.1}", floor, expected);
+}
+
+#[test]
+fn signals_outside_the_span_are_filtered() {
+    let mut sdr = sdr(2);
+    sdr.add(CENTER + 700e3, -10.0, Shape::Tone);
+    let bins = spectrum(&mut sdr, 32 * FFT_SIZE);
+    assert!(bins.iter().all(|&db| db < -80.0));
+}
+
+#[test]
+fn retuning_moves_the_signal() {
+    let mut sdr = sdr(3);
+    sdr.add(CENTER + 200e3, -20.0, Shape::Tone);
+    sdr.tune(CENTER + 100e3, RATE, 20.0);
+    let bins = spectrum(&mut sdr, 32 * FFT_SIZE);
+    assert_eq!(peak(&bins), bin(CENTER + 100e3));
+}
+
+#[test]
+fn bursts_follow_their_period() {
+    let mut sdr = sdr(4);
+    sdr.block_secs = 0.1;
+    sdr.add(CENTER, -20.0, Shape::Burst { period_secs: 0.4, on_secs: 0.1 });
+    let mut block = Vec::new();
+    let powers: Vec<f32> = (0..8)
+        .map(|_| {
+            assert!(sdr.receive(&mut block));
+            10.0 * (block.iter().map(|s| s.norm_sqr()).sum::<f32>() / block.len() as f32).log10()
+        })
+        .collect();
+    for (i, power) in powers.iter().enumerate() {
+        let expected = if i % 4 == 0 { -20.0 } else { -60.0 };
+        assert!((power - expected).abs() < 1.0, "block {} at {:.1} dBFS", i, power);
+    }
+}
+
+#[test]
+fn fm_demodulates_to_its_tone() {
+    let mut sdr = sdr(5);
+    sdr.add(CENTER + 50e3, -20.0, Shape::Fm { deviation_hz: 3e3, tone_hz: 1e3 });
+    let mut demod = AudioDemod::new(AudioMode::Fm);
+    demod.set_offset(50e3);
+    let mut audio = Vec::new();
+    demod.process(&samples(&mut sdr, 500_000), RATE, &mut audio);
+    // The second half, after the filters have settled
+    let settled = &audio[audio.len() / 2..];
+    let mean = settled.iter().sum::<f32>() / settled.len() as f32;
+    let crossings = settled.windows(2).filter(|w| (w[0] - mean).signum() != (w[1] - mean).signum()).count();
+    let tone = crossings as f64 / 2.0 / (settled.len() as f64 / demod.rate());
+    assert!((tone - 1e3).abs() < 20.0, "tone at {:.0} Hz", tone);
+}
@@ -0,0 +1,165 @@
+//! The network services answering clients over loopback.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use num_complex::Complex32;
+use rf_rust::device::Device;
+use rf_rust::dsp::AudioMode;
+use rf_rust::net::remote::RemoteSource;
+use rf_rust::net::rest::RestServer;
+use rf_rust::net::rigctl::{Dialect, RigctlServer};
+use rf_rust::net::rtl_tcp::RtlTcpServer;
+use rf_rust::net::websocket::WebSocketServer;
+use rf_rust::net::{Command, ReceiverState};
+
+fn state() -> ReceiverState {
+    ReceiverState {
+        frequency: 100e6,
+        vfo_frequency: 100e6,
+        sample_rate: 2.4e6,
+        gain: 20.0,
+        mode: AudioMode::Fm,
+        streaming: true,
+        signal_db: -50.0,
+        squelch_db: -60.0,
+        recording: false,
+    }
+}
+
+/// Send one HTTP request and read the whole response
+fn http(server: &RestServer, method: &str, path: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    write!(stream, "{} {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", method, path, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn rest_reports_and_changes_settings() {
+    let mut server = RestServer::bind("127.0.0.1:0").unwrap();
+    server.update(&state());
+    let response = http(&server, "GET", "/api/frequency", "");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("100000000"), "{}", response);
+
+    let response = http(&server, "PUT", "/api/mode", "usb");
+    assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+    assert_eq!(server.commands().collect::<Vec<_>>(), [Command::Mode(AudioMode::Usb)]);
+}
+
+#[test]
+fn rest_refuses_deeply_nested_bodies_and_keeps_serving() {
+    let mut server = RestServer::bind("127.0.0.1:0").unwrap();
+    server.update(&state());
+    let response = http(&server, "PUT", "/api/frequency", &"[".repeat(60_000));
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+    assert!(response.contains("nested too deeply"), "{}", response);
+    assert_eq!(server.commands().count(), 0);
+    assert!(http(&server, "GET", "/api/status", "").starts_with("HTTP/1.1 200"));
+}
+
+/// A masked text frame as browsers send them
+fn client_frame(payload: &[u8]) -> Vec<u8> {
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let mut out = vec![0x81];
+    match payload.len() {
+        len @ 0..=125 => out.push(0x80 | len as u8),
+        len => {
+            out.push(0x80 | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(&mask);
+    out.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    out
+}
+
+#[test]
+fn websocket_skips_deeply_nested_messages() {
+    let mut server = WebSocketServer::bind("127.0.0.1:0").unwrap();
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    write!(
+        stream,
+        "GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n"
+    )
+    .unwrap();
+    // The response head, up to the blank line
+    let mut head = Vec::new();
+    let mut byte = [0u8];
+    while !head.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    assert!(head.starts_with("HTTP/1.1 101"), "{}", head);
+    assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="), "{}", head);
+
+    stream.write_all(&client_frame(r#"{"a":"#.repeat(12_000).as_bytes())).unwrap();
+    stream.write_all(&client_frame(br#"{"frequency": 145.5e6}"#)).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut commands = Vec::new();
+    while commands.is_empty() && Instant::now() < deadline {
+        commands.extend(server.commands());
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(commands, [Command::Frequency(145.5e6)]);
+}
+
+/// Commands `take` returns within a few seconds
+fn wait_for_commands(mut take: impl FnMut() -> Vec<Command>, count: usize) -> Vec<Command> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut commands = Vec::new();
+    while commands.len() < count && Instant::now() < deadline {
+        commands.extend(take());
+        thread::sleep(Duration::from_millis(10));
+    }
+    commands
+}
+
+#[test]
+fn rigctl_answers_queries_and_takes_changes() {
+    let mut server = RigctlServer::bind("127.0.0.1:0", Dialect::Hamlib).unwrap();
+    server.update(&state());
+    let stream = TcpStream::connect(server.local_addr()).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut line = String::new();
+
+    writer.write_all(b"f\n").unwrap();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "100000000\n");
+
+    line.clear();
+    writer.write_all(b"F 145500000\n").unwrap();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "RPRT 0\n");
+    assert_eq!(wait_for_commands(|| server.commands().collect(), 1), [Command::Frequency(145.5e6)]);
+}
+
+#[test]
+fn remote_source_tunes_an_rtl_tcp_server_and_takes_its_samples() {
+    let mut server = RtlTcpServer::bind("127.0.0.1:0").unwrap();
+    let mut source = RemoteSource::connect(&server.local_addr().to_string());
+    source.tune(433.92e6, 1.024e6, 20.0);
+    let commands = wait_for_commands(|| server.commands().collect(), 3);
+    assert_eq!(commands, [Command::Frequency(433.92e6), Command::SampleRate(1.024e6), Command::Gain(20.0)]);
+
+    // Only what changed goes out again
+    source.tune(433.92e6, 1.024e6, 30.0);
+    assert_eq!(wait_for_commands(|| server.commands().collect(), 1), [Command::Gain(30.0)]);
+
+    let block = vec![Complex32::new(0.5, -0.5); 1000];
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut samples = Vec::new();
+    while samples.is_empty() && Instant::now() < deadline {
+        server.send(&block);
+        thread::sleep(Duration::from_millis(10));
+        source.receive(&mut samples);
+    }
+    assert!(!samples.is_empty());
+    assert!((samples[0].re - 0.5).abs() < 0.01 && (samples[0].im + 0.5).abs() < 0.01, "{:?}", samples[0]);
+}